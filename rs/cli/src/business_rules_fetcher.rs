@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use decentralization::business_rules::BusinessRulesPolicy;
+use futures::future::BoxFuture;
+use mockall::automock;
+
+use crate::github_file_fetcher::GithubFileFetcher;
+
+#[automock]
+pub trait BusinessRulesFetcher: Sync + Send {
+    fn fetch(&self) -> BoxFuture<'_, anyhow::Result<BusinessRulesPolicy>>;
+}
+
+pub struct BusinessRulesFetcherImpl {
+    file: GithubFileFetcher,
+}
+
+impl BusinessRulesFetcherImpl {
+    pub fn new(local_copy: PathBuf, use_local_file: bool) -> anyhow::Result<Self> {
+        Ok(Self {
+            file: GithubFileFetcher::new("business_rules.yaml", "business rules", local_copy, use_local_file)?,
+        })
+    }
+
+    fn parse(&self, contents: &[u8]) -> anyhow::Result<BusinessRulesPolicy> {
+        self.file.parse_or_default(contents, BusinessRulesPolicy::parse)
+    }
+
+    #[cfg(test)]
+    fn fetch_from_file(&self) -> anyhow::Result<BusinessRulesPolicy> {
+        self.file.fetch_from_file(|contents| self.parse(contents))
+    }
+}
+
+impl BusinessRulesFetcher for BusinessRulesFetcherImpl {
+    fn fetch(&self) -> BoxFuture<'_, anyhow::Result<BusinessRulesPolicy>> {
+        Box::pin(self.file.fetch(|contents| self.parse(contents)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn empty_file_gives_default_policy() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let fetcher = BusinessRulesFetcherImpl::new(temp_file.path().to_path_buf(), true).unwrap();
+
+        assert_eq!(fetcher.fetch_from_file().unwrap(), BusinessRulesPolicy::default());
+    }
+
+    #[test]
+    fn file_overrides_default_policy() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(
+            temp_file,
+            r#"
+version: 1
+dfinity_node_provider: bvcsg-3od6r-jnydw-eysln-aql7w-td5zn-ay5m6-sibd2-jzojt-anwag-mqe
+defaults:
+  dfinity_owned_nodes: 2
+  max_nodes_per_node_provider: 1
+  max_nodes_per_data_center: 1
+  max_nodes_per_data_center_owner: 1
+  max_nodes_per_country: 3
+"#
+        )
+        .unwrap();
+        let fetcher = BusinessRulesFetcherImpl::new(temp_file.path().to_path_buf(), true).unwrap();
+
+        let policy = fetcher.fetch_from_file().unwrap();
+        assert_eq!(policy.defaults.dfinity_owned_nodes, Some(2));
+        assert!(policy.subnets.is_empty());
+    }
+
+    #[test]
+    fn invalid_file_is_rejected() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "features: []").unwrap();
+        let fetcher = BusinessRulesFetcherImpl::new(temp_file.path().to_path_buf(), true).unwrap();
+
+        assert!(fetcher.fetch_from_file().is_err());
+    }
+
+    #[test]
+    fn missing_file_is_rejected() {
+        let fetcher = BusinessRulesFetcherImpl::new(PathBuf::from("non_existent_file.yaml"), true).unwrap();
+
+        assert!(fetcher.fetch_from_file().is_err());
+    }
+}
@@ -36,7 +36,7 @@ impl ExecutableCommand for Add {
     async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
         Submitter::from(&self.submission_parameters)
             .propose_and_print(
-                ctx.ic_admin_executor().await?.execution(ic_admin::IcAdminProposal::new(
+                ctx.proposal_execution(ic_admin::IcAdminProposal::new(
                     ic_admin::IcAdminProposalCommand::AddApiBoundaryNodes {
                         nodes: self.nodes.to_vec(),
                         version: self.version.clone(),
@@ -46,7 +46,8 @@ impl ExecutableCommand for Add {
                         summary: Some(format!("Add {} API boundary node(s)", self.nodes.len())),
                         motivation: self.motivation.clone(),
                     },
-                )),
+                ))
+                .await?,
                 ForumPostKind::Generic,
             )
             .await
@@ -32,14 +32,15 @@ impl ExecutableCommand for Remove {
     async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
         Submitter::from(&self.submission_parameters)
             .propose_and_print(
-                ctx.ic_admin_executor().await?.execution(ic_admin::IcAdminProposal::new(
+                ctx.proposal_execution(ic_admin::IcAdminProposal::new(
                     ic_admin::IcAdminProposalCommand::RemoveApiBoundaryNodes { nodes: self.nodes.to_vec() },
                     ic_admin::IcAdminProposalOptions {
                         title: Some(format!("Remove {} API boundary node(s)", self.nodes.len())),
                         summary: Some(format!("Remove {} API boundary node(s)", self.nodes.len())),
                         motivation: self.motivation.clone(),
                     },
-                )),
+                ))
+                .await?,
                 ForumPostKind::Generic,
             )
             .await
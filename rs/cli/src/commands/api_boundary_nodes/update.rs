@@ -35,7 +35,7 @@ impl ExecutableCommand for Update {
     async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
         Submitter::from(&self.submission_parameters)
             .propose_and_print(
-                ctx.ic_admin_executor().await?.execution(ic_admin::IcAdminProposal::new(
+                ctx.proposal_execution(ic_admin::IcAdminProposal::new(
                     ic_admin::IcAdminProposalCommand::DeployGuestosToSomeApiBoundaryNodes {
                         nodes: self.nodes.to_vec(),
                         version: self.version.to_string(),
@@ -45,7 +45,8 @@ impl ExecutableCommand for Update {
                         summary: Some(format!("Update {} API boundary node(s) to {}", self.nodes.len(), &self.version)),
                         motivation: self.motivation.clone(),
                     },
-                )),
+                ))
+                .await?,
                 ForumPostKind::Generic,
             )
             .await
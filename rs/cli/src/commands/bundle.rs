@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use ic_canisters::governance::GovernanceCanisterWrapper;
+use ic_management_backend::lazy_registry::LazyRegistryFamilyEntries;
+use log::{info, warn};
+
+use crate::{
+    auth::AuthRequirement,
+    ctx::DreContext,
+    exe::{ExecutableCommand, args::GlobalArgs},
+    proposal_bundle::{ProposalBundle, SIGNATURE_VALIDITY},
+    proposal_executors::ProposalResponseWithId,
+    util::yesno,
+};
+
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre subnet replace --id <subnet> --neuron-id 40 --prepare-only bundle.json   # On the online machine
+    dre --offline sign-bundle bundle.json --hsm-slot 0 --hsm-key-id 1            # On the offline machine
+    dre submit-bundle bundle.json                                                 # On the online machine
+
+  The IC rejects signed calls after 5 minutes, so the signed bundle has to be submitted within that time.
+"#)]
+pub struct SignBundle {
+    /// Proposal bundle written with `--prepare-only`
+    pub bundle: PathBuf,
+
+    /// Where to write the signed bundle, by default the input bundle is overwritten
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Sign even if the registry changed since the proposal was prepared
+    #[clap(long)]
+    pub allow_registry_changes: bool,
+
+    /// Sign without asking for confirmation
+    #[clap(long)]
+    pub yes: bool,
+}
+
+impl ExecutableCommand for SignBundle {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Signer
+    }
+
+    async fn execute(&self, ctx: DreContext) -> anyhow::Result<()> {
+        let bundle = ProposalBundle::read(&self.bundle)?;
+        check_network(&bundle, &ctx)?;
+        if bundle.signature.is_some() {
+            warn!("Proposal bundle is already signed, the signature will be replaced");
+        }
+        if ctx.is_offline() {
+            warn!(
+                "Cannot check whether registry version {} is still current while offline, it is checked again on submission",
+                bundle.content.registry_version
+            );
+        } else {
+            bundle.check_registry_version(ctx.registry().await.get_latest_version().get(), self.allow_registry_changes)?;
+        }
+
+        println!("{}", bundle);
+        if !self.yes && !yesno("Do you want to sign this proposal?", false).await?? {
+            return Ok(());
+        }
+
+        let (_, client) = ctx.create_ic_agent_canister_client().await?;
+        let signed = bundle.sign(&GovernanceCanisterWrapper::from(client))?;
+        let output = self.output.as_ref().unwrap_or(&self.bundle);
+        signed.write(output)?;
+        info!(
+            "Signed proposal bundle written to {}. Submit it with `dre submit-bundle` within {}.",
+            output.display(),
+            humantime::format_duration(SIGNATURE_VALIDITY)
+        );
+        Ok(())
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+}
+
+#[derive(Args, Debug)]
+pub struct SubmitBundle {
+    /// Proposal bundle signed with `dre sign-bundle`
+    pub bundle: PathBuf,
+
+    /// Submit even if the registry changed since the proposal was prepared
+    #[clap(long)]
+    pub allow_registry_changes: bool,
+}
+
+impl ExecutableCommand for SubmitBundle {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Anonymous
+    }
+
+    async fn execute(&self, ctx: DreContext) -> anyhow::Result<()> {
+        let bundle = ProposalBundle::read(&self.bundle)?;
+        check_network(&bundle, &ctx)?;
+        if bundle.signature.is_none() {
+            anyhow::bail!("Proposal bundle is not signed, sign it with `dre sign-bundle` first");
+        }
+        if bundle.signature_validity_left().is_none() {
+            anyhow::bail!("The signature of the proposal bundle expired, sign it again with `dre sign-bundle`");
+        }
+        bundle.check_registry_version(ctx.registry().await.get_latest_version().get(), self.allow_registry_changes)?;
+
+        info!("Submitting the proposal bundle with content SHA-256 {}", bundle.content_sha256);
+        // The call is already signed, so it can be sent by an anonymous agent
+        let (_, client) = ctx.create_ic_agent_canister_client().await?;
+        let response = GovernanceCanisterWrapper::from(client)
+            .submit_signed_manage_neuron(&bundle.signed_manage_neuron()?)
+            .await?;
+        let proposal: ProposalResponseWithId = GovernanceCanisterWrapper::make_proposal_response(response)?.try_into()?;
+        if let Some(forum_post_link) = &bundle.content.forum_post_link {
+            info!("Remember to link the {} in the forum post {}", proposal, forum_post_link);
+        }
+        println!("{}", proposal);
+        Ok(())
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+}
+
+fn check_network(bundle: &ProposalBundle, ctx: &DreContext) -> anyhow::Result<()> {
+    if bundle.content.network != ctx.network().name {
+        anyhow::bail!(
+            "Proposal bundle was prepared for network `{}` but the target network is `{}`, pass `--network {}`",
+            bundle.content.network,
+            ctx.network().name,
+            bundle.content.network
+        );
+    }
+    Ok(())
+}
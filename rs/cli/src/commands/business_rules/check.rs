@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use decentralization::{
+    business_rules::{BusinessRules, BusinessRulesPolicy},
+    network::DecentralizedSubnet,
+};
+use itertools::Itertools;
+use log::info;
+
+use crate::{auth::AuthRequirement, exe::ExecutableCommand, exe::args::GlobalArgs};
+
+/// Validate a proposed business rules policy and check every live subnet against it,
+/// comparing the penalties with the ones from the currently active policy.
+#[derive(Args, Debug)]
+pub struct Check {
+    /// Path to the proposed policy file, in YAML or JSON
+    #[clap(long, visible_alias = "file")]
+    pub policy_file: PathBuf,
+
+    /// Print the business rules messages for all subnets, not only for the changed ones
+    #[clap(long)]
+    pub all: bool,
+}
+
+impl ExecutableCommand for Check {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Anonymous
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        let contents = fs_err::read(&self.policy_file)?;
+        let subnets = ctx.registry().await.subnets().await?;
+        let policy = BusinessRulesPolicy::parse(&contents)?.with_subnet_types(subnets.values());
+        info!("Policy file `{}` is valid", self.policy_file.display());
+        let active = ctx.business_rules().await?;
+        let proposed = BusinessRules::new(policy, active.provider_clusters().clone());
+
+        let mut table = tabular::Table::new("{:<}    {:<}    {:>}    {:>}");
+        table.add_row(
+            tabular::Row::new()
+                .with_cell("Subnet")
+                .with_cell("Name")
+                .with_cell("Penalty (active)")
+                .with_cell("Penalty (proposed)"),
+        );
+        let mut details = vec![];
+        let mut newly_violating = 0;
+        for subnet in subnets.values() {
+            let (penalty_active, checks_active) =
+                DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&subnet.principal, &subnet.nodes, &active)?;
+            let (penalty_proposed, checks_proposed) =
+                DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&subnet.principal, &subnet.nodes, &proposed)?;
+            if penalty_active == 0 && penalty_proposed > 0 {
+                newly_violating += 1;
+            }
+            table.add_row(
+                tabular::Row::new()
+                    .with_cell(subnet.principal)
+                    .with_cell(&subnet.metadata.name)
+                    .with_cell(penalty_active)
+                    .with_cell(penalty_proposed),
+            );
+            if self.all || checks_active != checks_proposed {
+                details.push(format!(
+                    "Subnet {} ({}):\n{}",
+                    subnet.principal,
+                    subnet.metadata.name,
+                    checks_proposed.iter().map(|c| format!("  - {}", c)).join("\n")
+                ));
+            }
+        }
+
+        println!("{}", table);
+        if !details.is_empty() {
+            println!("Business rules check results with the proposed policy:\n\n{}", details.join("\n\n"));
+        }
+        println!(
+            "\n{} subnet(s) would start violating the business rules with the proposed policy",
+            newly_violating
+        );
+        Ok(())
+    }
+}
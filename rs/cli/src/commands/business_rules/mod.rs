@@ -0,0 +1,17 @@
+use check::Check;
+use clap::Args;
+use show::Show;
+
+use crate::exe::impl_executable_command_for_enums;
+
+mod check;
+mod show;
+
+/// Inspect and validate the business rules policy (target topology)
+#[derive(Args, Debug)]
+pub struct BusinessRules {
+    #[clap(subcommand)]
+    pub subcommands: Subcommands,
+}
+
+impl_executable_command_for_enums! { BusinessRules, Check, Show }
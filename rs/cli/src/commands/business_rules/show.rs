@@ -0,0 +1,21 @@
+use clap::Args;
+
+use crate::{auth::AuthRequirement, exe::ExecutableCommand, exe::args::GlobalArgs};
+
+/// Print the business rules policy that `dre` currently uses
+#[derive(Args, Debug)]
+pub struct Show {}
+
+impl ExecutableCommand for Show {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Anonymous
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        let policy = ctx.business_rules_policy().await?;
+        print!("{}", policy.to_yaml()?);
+        Ok(())
+    }
+}
@@ -188,6 +188,12 @@ struct FirewallModifyCommand {
 impl RunnableViaIcAdmin for FirewallModifyCommand {
     type Output = ProposalResponseWithId;
     fn to_ic_admin_arguments(&self) -> anyhow::Result<Vec<String>> {
+        self.ic_admin_proposal().to_ic_admin_arguments()
+    }
+}
+
+impl FirewallModifyCommand {
+    fn ic_admin_proposal(&self) -> IcAdminProposal {
         // Uses nearly the same arguments as the test argv.
         let mut final_args = self.test_command.args.clone();
         // Remove --test from head of args.
@@ -203,7 +209,6 @@ impl RunnableViaIcAdmin for FirewallModifyCommand {
                 motivation: None,
             },
         )
-        .to_ic_admin_arguments()
     }
 }
 
@@ -239,16 +244,18 @@ impl Firewall {
         // this is called in a loop by the caller of create_proposal.  Perhaps
         // we should summarize the proposals that were submitted, or the errors
         // that were returned.
+        let command = FirewallModifyCommand {
+            test_command,
+            hash,
+            summary,
+            title,
+        };
+        let proposal = command.ic_admin_proposal();
+        let execution = ctx
+            .recorded_execution(ctx.ic_admin_executor().await?.execution(command), &proposal)
+            .await?;
         Submitter::from(submission_parameters)
-            .propose_and_print(
-                ctx.ic_admin_executor().await?.execution(FirewallModifyCommand {
-                    test_command,
-                    hash,
-                    summary,
-                    title,
-                }),
-                ForumPostKind::Generic,
-            )
+            .propose_and_print(execution, ForumPostKind::Generic)
             .await
     }
 }
@@ -9,6 +9,7 @@ use crate::{
     exe::ExecutableCommand,
     exe::args::GlobalArgs,
     forum::ForumPostKind,
+    ic_admin::{IcAdminProposal, IcAdminProposalCommand, IcAdminProposalOptions},
     submitter::{SubmissionParameters, Submitter},
     util::{extract_title_and_text, utf8},
 };
@@ -73,16 +74,28 @@ impl ExecutableCommand for Motion {
             (None, None) => "Please refer to the summary of the proposal for the contents of the motion.".to_string(),
         };
 
+        // Motions have no ic-admin command, the history records them under `motion`
+        let record = IcAdminProposal::new(
+            IcAdminProposalCommand::Raw(vec!["motion".to_string(), motion_text.clone()]),
+            IcAdminProposalOptions {
+                title: title.clone(),
+                summary: Some(summary.clone()),
+                motivation: None,
+            },
+        );
         let request = MakeProposalRequest {
             title: title.clone(),
             summary: summary.clone(),
             url: "<to be supplied or generated later>".into(),
             action: Some(ProposalActionRequest::Motion(MotionPayload { motion_text })),
         };
+        let execution = ctx
+            .recorded_execution(ctx.governance_executor().await?.execution(request), &record)
+            .await?;
 
         Submitter::from(&self.submission_parameters)
             .propose_and_print(
-                ctx.governance_executor().await?.execution(request),
+                execution,
                 ForumPostKind::Motion {
                     title: title.clone(),
                     summary: summary.clone(),
@@ -0,0 +1,86 @@
+use clap::Args;
+use ic_types::PrincipalId;
+use itertools::Itertools;
+
+use crate::{
+    auth::AuthRequirement,
+    ctx::DreContext,
+    exe::{ExecutableCommand, args::GlobalArgs},
+    proposal_history::ProposalOutcome,
+};
+
+#[derive(Args, Debug)]
+pub struct History {
+    /// Number of most recent records to show
+    #[clap(long, default_value = "20")]
+    pub limit: usize,
+
+    /// Only show records with these outcomes
+    #[clap(long, num_args(1..))]
+    pub outcome: Vec<ProposalOutcome>,
+
+    /// Only show proposals changing this subnet or node
+    #[clap(long)]
+    pub target: Option<PrincipalId>,
+
+    /// Print the records as JSON
+    #[clap(long)]
+    pub json: bool,
+}
+
+impl ExecutableCommand for History {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Anonymous
+    }
+
+    async fn execute(&self, ctx: DreContext) -> anyhow::Result<()> {
+        let history = ctx.proposal_history()?;
+        let records = history
+            .records()?
+            .into_iter()
+            .rev()
+            .filter(|r| self.outcome.is_empty() || self.outcome.contains(&r.outcome))
+            .filter(|r| self.target.is_none_or(|target| r.targets.contains(&target)))
+            .take(self.limit)
+            .collect_vec();
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&records)?);
+            return Ok(());
+        }
+        if records.is_empty() {
+            println!("No proposals recorded in {}", history.path().display());
+            return Ok(());
+        }
+
+        let mut table = tabular::Table::new("{:<}    {:<}    {:<}    {:<}    {:>}    {:<}");
+        table.add_row(
+            tabular::Row::new()
+                .with_cell("Recorded at")
+                .with_cell("Outcome")
+                .with_cell("Command")
+                .with_cell("Targets")
+                .with_cell("Proposal")
+                .with_cell("Forum post"),
+        );
+        for record in &records {
+            table.add_row(
+                tabular::Row::new()
+                    .with_cell(record.recorded_at.format("%Y-%m-%d %H:%M:%S"))
+                    .with_cell(record.outcome)
+                    .with_cell(&record.command)
+                    .with_cell(match record.targets.len() {
+                        0 => "-".to_string(),
+                        1 => record.targets[0].to_string(),
+                        n => format!("{} (+{})", record.targets[0], n - 1),
+                    })
+                    .with_cell(record.proposal_id.map(|id| id.to_string()).unwrap_or("-".to_string()))
+                    .with_cell(record.forum_url.as_ref().map(|u| u.to_string()).unwrap_or("-".to_string())),
+            );
+        }
+        println!("{}", table);
+        Ok(())
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+}
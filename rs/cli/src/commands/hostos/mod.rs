@@ -1,11 +1,13 @@
 use clap::Args;
 use rollout::Rollout;
 use rollout_from_node_group::RolloutFromNodeGroup;
+use simulate_campaign::SimulateCampaign;
 
 use crate::exe::impl_executable_command_for_enums;
 
 mod rollout;
 pub mod rollout_from_node_group;
+mod simulate_campaign;
 
 #[derive(Args, Debug)]
 pub struct HostOs {
@@ -13,4 +15,4 @@ pub struct HostOs {
     pub subcommands: Subcommands,
 }
 
-impl_executable_command_for_enums! { HostOs, Rollout, RolloutFromNodeGroup }
+impl_executable_command_for_enums! { HostOs, Rollout, RolloutFromNodeGroup, SimulateCampaign }
@@ -31,7 +31,7 @@ impl ExecutableCommand for Rollout {
     async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
         let runner_proposal = ctx.runner().await?.hostos_rollout(self.nodes.clone(), &self.version, None)?;
         Submitter::from(&self.submission_parameters)
-            .propose_and_print(ctx.ic_admin_executor().await?.execution(runner_proposal), ForumPostKind::Generic)
+            .propose_and_print(ctx.proposal_execution(runner_proposal).await?, ForumPostKind::Generic)
             .await
     }
 
@@ -1,13 +1,17 @@
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 
-use clap::{Args, ValueEnum};
+use clap::{Args, ValueEnum, error::ErrorKind};
+use humantime::parse_duration;
 
 use crate::{
     auth::AuthRequirement,
     exe::ExecutableCommand,
     exe::args::GlobalArgs,
     forum::ForumPostKind,
-    operations::hostos_rollout::{NodeGroupUpdate, NumberOfNodes},
+    operations::{
+        hostos_rollout::{NodeGroupUpdate, NumberOfNodes},
+        hostos_rollout_gates::HostosRolloutGates,
+    },
     submitter::{SubmissionParameters, Submitter},
 };
 
@@ -78,6 +82,22 @@ supported values are absolute numbers (10) or percentage (10%)"#
     )]
     pub nodes_in_group: String,
 
+    /// How long the nodes already on the version must have been running it
+    /// before more nodes are selected. No bake time by default
+    #[clap(long, default_value = "0s", value_parser = parse_duration)]
+    pub bake_time: Duration,
+
+    /// Select no more nodes if more than this fraction of the nodes already on
+    /// the version are failing. Nodes with unknown health are not counted as failing.
+    /// No limit by default
+    #[clap(long, default_value_t = 1.0)]
+    pub max_failure_ratio: f64,
+
+    /// Also require Prometheus to report the hosts of the nodes already on the
+    /// version as up
+    #[clap(long)]
+    pub check_prometheus: bool,
+
     #[clap(flatten)]
     pub submission_parameters: SubmissionParameters,
 }
@@ -92,7 +112,7 @@ impl ExecutableCommand for RolloutFromNodeGroup {
         let runner = ctx.runner().await?;
 
         let (nodes_to_update, summary) = match runner
-            .hostos_rollout_nodes(update_group, &self.version, &self.only, &self.exclude)
+            .hostos_rollout_nodes(update_group, &self.version, &self.only, &self.exclude, self.gates(&ctx))
             .await?
         {
             Some(s) => s,
@@ -101,9 +121,23 @@ impl ExecutableCommand for RolloutFromNodeGroup {
 
         let runner_proposal = runner.hostos_rollout(nodes_to_update, &self.version, Some(summary))?;
         Submitter::from(&self.submission_parameters)
-            .propose_and_print(ctx.ic_admin_executor().await?.execution(runner_proposal), ForumPostKind::Generic)
+            .propose_and_print(ctx.proposal_execution(runner_proposal).await?, ForumPostKind::Generic)
             .await
     }
 
-    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+    fn validate(&self, _args: &GlobalArgs, cmd: &mut clap::Command) {
+        if !(0.0..=1.0).contains(&self.max_failure_ratio) {
+            cmd.error(ErrorKind::InvalidValue, "The failure ratio must be between 0 and 1").exit()
+        }
+    }
+}
+
+impl RolloutFromNodeGroup {
+    fn gates(&self, ctx: &crate::ctx::DreContext) -> HostosRolloutGates {
+        HostosRolloutGates {
+            bake_time: self.bake_time,
+            max_failure_ratio: self.max_failure_ratio,
+            prometheus_network: self.check_prometheus.then(|| ctx.network().clone()),
+        }
+    }
 }
@@ -0,0 +1,136 @@
+use std::str::FromStr;
+
+use chrono::{NaiveDate, Utc};
+use clap::Args;
+use ic_management_backend::release::RolloutConfig;
+use itertools::Itertools;
+
+use crate::{
+    auth::AuthRequirement,
+    exe::ExecutableCommand,
+    exe::args::GlobalArgs,
+    operations::{
+        hostos_campaign::{Campaign, CampaignSettings, rollout_calendar},
+        hostos_rollout::NumberOfNodes,
+    },
+};
+
+#[derive(Args, Debug)]
+#[clap(
+    after_help = r#"Waves are computed like `dre hostos rollout-from-node-group` does, assuming every wave is adopted,
+for the DFINITY unassigned, DFINITY assigned, other unassigned and other assigned nodes, in this order.
+Waves are dated on working days, skipping the days excluded from rollouts."#
+)]
+pub struct SimulateCampaign {
+    /// Version to be rolled out
+    #[clap(long)]
+    pub version: String,
+
+    #[clap(
+        long,
+        help = r#"How many nodes in the group to update in each wave
+supported values are absolute numbers (10) or percentage (10%)"#
+    )]
+    pub nodes_in_group: String,
+
+    /// Filter in for rollout only the nodes that match the provided list of features
+    #[clap(long, num_args(1..))]
+    pub only: Vec<String>,
+
+    /// Filter out (exclude) nodes that match the provided list of features
+    #[clap(long, num_args(1..))]
+    pub exclude: Vec<String>,
+
+    /// Most nodes of a subnet to update in one wave, by default the number of
+    /// nodes the subnet can lose without stalling
+    #[clap(long)]
+    pub max_nodes_per_subnet: Option<usize>,
+
+    /// Number of waves submitted on each rollout day
+    #[clap(long, default_value_t = 1)]
+    pub waves_per_day: usize,
+
+    /// First day of the campaign, by default today
+    #[clap(long)]
+    pub start_date: Option<NaiveDate>,
+}
+
+impl ExecutableCommand for SimulateCampaign {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Anonymous
+    }
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        let settings = CampaignSettings {
+            version: self.version.clone(),
+            nodes_in_group: NumberOfNodes::from_str(&self.nodes_in_group)?,
+            only: self.only.clone(),
+            exclude: self.exclude.clone(),
+            max_nodes_per_subnet: self.max_nodes_per_subnet,
+        };
+        let registry = ctx.registry().await;
+        let health_client = ctx.health_client();
+        let mut campaign = Campaign::simulate(
+            &settings,
+            &registry.nodes().await?,
+            registry.subnets().await?,
+            &health_client.nodes().await?,
+            ctx.proposals_agent(),
+            health_client,
+        )
+        .await?;
+        let start = self.start_date.unwrap_or_else(|| Utc::now().date_naive());
+        campaign.schedule(rollout_calendar(start, RolloutConfig::bundled().exclude_days), self.waves_per_day);
+
+        let mut table = tabular::Table::new("{:>}    {:<}    {:<}    {:>}    {:<}    {:<}");
+        table.add_row(
+            tabular::Row::new()
+                .with_cell("Wave")
+                .with_cell("Date")
+                .with_cell("Group")
+                .with_cell("Nodes")
+                .with_cell("Nodes per subnet")
+                .with_cell("Over the subnet limit"),
+        );
+        for (i, wave) in campaign.waves.iter().enumerate() {
+            table.add_row(
+                tabular::Row::new()
+                    .with_cell(i + 1)
+                    .with_cell(wave.date.map(|d| d.to_string()).unwrap_or_default())
+                    .with_cell(format!("{} {}", wave.group.owner, wave.group.assignment))
+                    .with_cell(wave.nodes.len())
+                    .with_cell(
+                        wave.nodes_per_subnet
+                            .iter()
+                            .map(|(subnet_id, nodes)| format!("{}:{}", subnet_id.to_string().split('-').next().unwrap(), nodes))
+                            .join(" "),
+                    )
+                    .with_cell(
+                        wave.violations
+                            .iter()
+                            .map(|v| format!("{}:{}>{}", v.subnet_id.to_string().split('-').next().unwrap(), v.nodes, v.limit))
+                            .join(" "),
+                    ),
+            );
+        }
+        println!("{}", table);
+
+        match campaign.completion_date() {
+            Some(date) => println!("{} waves, projected to complete on {}", campaign.waves.len(), date),
+            None => println!("No node needs to be updated to version {}", self.version),
+        }
+        let violating = campaign.waves.iter().filter(|w| !w.violations.is_empty()).count();
+        if violating > 0 {
+            println!("{} waves update more nodes of a subnet than the limit, use smaller waves", violating);
+        }
+        for left_behind in &campaign.left_behind {
+            println!(
+                "{} nodes of group {} {} are not updated: {}",
+                left_behind.nodes, left_behind.group.owner, left_behind.group.assignment, left_behind.reason
+            );
+        }
+        Ok(())
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+}
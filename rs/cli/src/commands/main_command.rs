@@ -1,8 +1,11 @@
 use super::api_boundary_nodes::ApiBoundaryNodes;
+use super::bundle::{SignBundle, SubmitBundle};
+use super::business_rules::BusinessRules;
 use super::der_to_principal::DerToPrincipal;
 use super::firewall::Firewall;
 use super::get::Get;
 use super::governance::Governance;
+use super::history::History;
 use super::hostos::HostOs;
 use super::network::Network;
 use super::neuron::Neuron;
@@ -10,8 +13,11 @@ use super::node_metrics::NodeMetrics;
 use super::nodes::Nodes;
 use super::proposals::Proposals;
 use super::propose::Propose;
+use super::propose_batch::ProposeBatch;
+use super::provider_clusters::ProviderClusters;
 use super::qualify::Qualify;
 use super::registry::Registry;
+use super::rollout::Rollout;
 use super::update_default_subnets::UpdateDefaultSubnets;
 use super::update_unassigned_nodes::UpdateUnassignedNodes;
 use super::upgrade::Upgrade;
@@ -34,7 +40,7 @@ pub struct MainCommand {
     pub subcommands: Subcommands,
 }
 
-impl_executable_command_for_enums! { MainCommand, DerToPrincipal, Network, Subnet, Get, Propose, UpdateUnassignedNodes, Version, NodeMetrics, NodeRewards, HostOs, Nodes, ApiBoundaryNodes, Vote, Registry, Firewall, Upgrade, Proposals, Completions, Qualify, UpdateDefaultSubnets, Neuron, Governance }
+impl_executable_command_for_enums! { MainCommand, DerToPrincipal, Network, Subnet, Get, Propose, ProposeBatch, UpdateUnassignedNodes, Version, NodeMetrics, NodeRewards, HostOs, Nodes, ApiBoundaryNodes, Vote, Registry, Rollout, Firewall, Upgrade, Proposals, Completions, Qualify, UpdateDefaultSubnets, Neuron, Governance, History, BusinessRules, ProviderClusters, SignBundle, SubmitBundle }
 
 #[derive(Args, Debug)]
 pub struct Completions {
@@ -1,8 +1,11 @@
 pub(crate) mod api_boundary_nodes;
+pub(crate) mod bundle;
+pub(crate) mod business_rules;
 pub(crate) mod der_to_principal;
 pub(crate) mod firewall;
 pub mod get;
 pub(crate) mod governance;
+pub(crate) mod history;
 pub mod hostos;
 pub mod main_command;
 pub(crate) mod network;
@@ -11,9 +14,12 @@ pub(crate) mod node_metrics;
 pub(crate) mod node_rewards;
 pub(crate) mod nodes;
 pub(crate) mod proposals;
+pub(crate) mod provider_clusters;
 pub(crate) mod propose;
+pub(crate) mod propose_batch;
 pub mod qualify;
 pub(crate) mod registry;
+pub(crate) mod rollout;
 pub(crate) mod subnet;
 pub(crate) mod update_default_subnets;
 pub(crate) mod update_unassigned_nodes;
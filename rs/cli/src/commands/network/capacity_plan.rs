@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use decentralization::network::HypotheticalNodes;
+use log::info;
+
+use crate::{
+    auth::AuthRequirement,
+    ctx::DreContext,
+    exe::{ExecutableCommand, args::GlobalArgs},
+};
+
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre network capacity-plan --nodes-file new-nodes.yaml
+    dre network capacity-plan --nodes-file new-nodes.yaml --min-nakamoto-gain 0.1 --output plan.json
+
+  The nodes file lists the kinds of nodes that could be onboarded, for instance:
+
+    - node_provider: Example Provider
+      data_center: zh9
+      data_center_owner: Example DC Owner
+      country: CH
+      area: Zurich       # optional, the data center by default
+      count: 4           # optional, how many such nodes could be onboarded
+      features:          # optional, values of custom node features
+        asn: AS1234
+
+  Each new node replaces the subnet member for which that helps most. A new node only counts
+  as an improvement if the subnet gets better than with any of the healthy available nodes.
+"#)]
+pub struct CapacityPlan {
+    /// YAML or JSON file with the kinds of new nodes to evaluate
+    #[clap(long)]
+    pub nodes_file: PathBuf,
+
+    /// Maximum number of new nodes to place in the subnets
+    #[clap(long, default_value_t = 100)]
+    pub max_nodes: usize,
+
+    /// Smallest increase of the mean Nakamoto coefficient of a subnet that counts as an
+    /// improvement, when the business rules penalty of the subnet stays the same
+    #[clap(long, default_value_t = 0.0)]
+    pub min_nakamoto_gain: f64,
+
+    /// Do not place new nodes in the provided subnets
+    #[clap(long, num_args(1..), visible_alias = "skip-subnets")]
+    pub omit_subnets: Vec<String>,
+
+    /// Write the full plan as JSON to the provided file
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+impl ExecutableCommand for CapacityPlan {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Anonymous
+    }
+
+    async fn execute(&self, ctx: DreContext) -> anyhow::Result<()> {
+        let kinds: Vec<HypotheticalNodes> = serde_yaml::from_str(&fs_err::read_to_string(&self.nodes_file)?)?;
+        if kinds.is_empty() {
+            anyhow::bail!("No nodes in {}", self.nodes_file.display());
+        }
+
+        let plan = ctx
+            .runner()
+            .await?
+            .capacity_plan(&self.omit_subnets, kinds, self.max_nodes, self.min_nakamoto_gain)
+            .await?;
+        if let Some(path) = &self.output {
+            fs_err::write(path, serde_json::to_string_pretty(&plan)?)?;
+            info!("Wrote the capacity plan to {}", path.display());
+        }
+        println!("{}", plan);
+        Ok(())
+    }
+
+    fn validate(&self, _args: &GlobalArgs, cmd: &mut clap::Command) {
+        if self.min_nakamoto_gain < 0.0 {
+            cmd.error(clap::error::ErrorKind::InvalidValue, "The minimum Nakamoto gain cannot be negative")
+                .exit()
+        }
+    }
+}
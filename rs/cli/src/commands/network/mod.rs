@@ -1,4 +1,11 @@
-use clap::Args;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Subcommand};
+use decentralization::network::{MigrationPlan, MigrationTarget};
+use ic_canisters::governance::GovernanceCanisterWrapper;
+use ic_nns_governance::pb::v1::ProposalStatus;
+use ic_types::PrincipalId;
+use indexmap::IndexSet;
 use itertools::Itertools;
 use log::info;
 
@@ -11,9 +18,52 @@ use crate::{
 use crate::auth::AuthRequirement;
 use crate::exe::{ExecutableCommand, args::GlobalArgs};
 
+mod capacity_plan;
+mod outage_sim;
+mod report;
+
+#[derive(Subcommand, Debug)]
+pub enum Subcommands {
+    /// Decentralization report of all subnets over past registry versions
+    Report(report::Report),
+    /// Where new nodes of a node provider would improve the decentralization of the subnets
+    CapacityPlan(capacity_plan::CapacityPlan),
+    /// Which subnets an outage of a provider, data center, country or provider cluster would stall
+    OutageSim(outage_sim::OutageSim),
+}
+
+impl ExecutableCommand for Subcommands {
+    fn require_auth(&self) -> AuthRequirement {
+        match &self {
+            Subcommands::Report(report) => report.require_auth(),
+            Subcommands::CapacityPlan(capacity_plan) => capacity_plan.require_auth(),
+            Subcommands::OutageSim(outage_sim) => outage_sim.require_auth(),
+        }
+    }
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        match &self {
+            Subcommands::Report(report) => report.execute(ctx).await,
+            Subcommands::CapacityPlan(capacity_plan) => capacity_plan.execute(ctx).await,
+            Subcommands::OutageSim(outage_sim) => outage_sim.execute(ctx).await,
+        }
+    }
+
+    fn validate(&self, args: &GlobalArgs, cmd: &mut clap::Command) {
+        match &self {
+            Subcommands::Report(report) => report.validate(args, cmd),
+            Subcommands::CapacityPlan(capacity_plan) => capacity_plan.validate(args, cmd),
+            Subcommands::OutageSim(outage_sim) => outage_sim.validate(args, cmd),
+        }
+    }
+}
+
 #[derive(Args, Debug)]
-#[clap(alias = "heal")]
+#[clap(alias = "heal", args_conflicts_with_subcommands = true)]
 pub struct Network {
+    #[clap(subcommand)]
+    pub subcommands: Option<Subcommands>,
+
     /// Heal the unhealthy subnets by replacing unhealthy nodes in them.
     #[clap(long)]
     pub heal: bool,
@@ -48,16 +98,47 @@ pub struct Network {
     #[clap(long)]
     pub remove_cordoned_nodes: bool,
 
+    /// Plan a sequence of subnet membership changes after which every subnet passes the
+    /// business rules. Only the first wave of the plan is proposed, the next waves are
+    /// proposed with `--plan-resume` once the previous ones are executed.
+    #[clap(long, conflicts_with_all = ["heal", "optimize_decentralization", "ensure_operator_nodes_assigned", "ensure_operator_nodes_unassigned", "remove_cordoned_nodes", "plan_unassign_operator", "plan_resume"])]
+    pub plan_business_rules: bool,
+
+    /// Plan a sequence of subnet membership changes after which no node of the provided
+    /// node operator is assigned to a subnet. Only the first wave of the plan is proposed.
+    #[clap(long, conflicts_with_all = ["heal", "optimize_decentralization", "ensure_operator_nodes_assigned", "ensure_operator_nodes_unassigned", "remove_cordoned_nodes", "plan_resume"])]
+    pub plan_unassign_operator: Option<PrincipalId>,
+
+    /// Propose the next wave of a migration plan written with `--plan-output`, once the proposals
+    /// recorded in it are executed. Steps with an open proposal, or whose subnet has one, are skipped.
+    #[clap(long, conflicts_with_all = ["heal", "optimize_decentralization", "ensure_operator_nodes_assigned", "ensure_operator_nodes_unassigned", "remove_cordoned_nodes"])]
+    pub plan_resume: Option<PathBuf>,
+
+    /// Maximum number of steps in the migration plan.
+    #[clap(long, default_value_t = 50)]
+    pub plan_max_steps: usize,
+
+    /// Write the full migration plan as JSON to the provided file.
+    #[clap(long)]
+    pub plan_output: Option<PathBuf>,
+
     #[clap(flatten)]
     pub submission_parameters: SubmissionParameters,
 }
 
 impl ExecutableCommand for Network {
     fn require_auth(&self) -> AuthRequirement {
-        AuthRequirement::Neuron
+        match &self.subcommands {
+            Some(subcommands) => subcommands.require_auth(),
+            None => AuthRequirement::Neuron,
+        }
     }
 
     async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        if let Some(subcommands) = &self.subcommands {
+            return subcommands.execute(ctx).await;
+        }
+
         let runner = ctx.runner().await?;
         let mut errors = vec![];
         let network_heal = self.heal || std::env::args().any(|arg| arg == "heal");
@@ -107,7 +188,45 @@ impl ExecutableCommand for Network {
             );
         };
 
-        if network_heal || self.optimize_decentralization || self.remove_cordoned_nodes {
+        // The saved plan and the wave being proposed, to record the submitted proposals in the plan
+        let mut followed_plan: Option<(&PathBuf, MigrationPlan, usize)> = None;
+
+        if let Some(path) = &self.plan_resume {
+            // The plan may contain the custom node features it was computed with
+            let plan: MigrationPlan = serde_json::from_reader(fs_err::File::open(path)?)?;
+            ctx.custom_node_features().check_known(plan.features())?;
+            info!("Resuming the network migration towards: {}", plan.target);
+            let executed_proposals = executed_plan_proposals(&ctx, &plan, path).await?;
+            if let Some((wave, wave_proposals)) = runner.network_plan_next_wave_proposals(&plan, &executed_proposals).await? {
+                proposals.extend(wave_proposals);
+                followed_plan = Some((path, plan, wave));
+            }
+        } else if let Some(target) = self.migration_target() {
+            info!("Planning the network migration towards: {}", target);
+            let plan = runner.network_plan(&omit_subnets, &omit_nodes, &target, self.plan_max_steps).await?;
+            println!("{}", plan);
+            if let Some(path) = &self.plan_output {
+                serde_json::to_writer_pretty(fs_err::File::create(path)?, &plan)?;
+                info!("Migration plan written to {}", path.display());
+            }
+            if plan.num_waves() > 1 {
+                match &self.plan_output {
+                    Some(path) => info!(
+                        "Proposing wave 1 of {}. Run `dre network --plan-resume {}` once these proposals are executed to propose the next wave.",
+                        plan.num_waves(),
+                        path.display()
+                    ),
+                    None => info!(
+                        "Proposing wave 1 of {}. Write the plan with `--plan-output` to propose the next waves with `--plan-resume`.",
+                        plan.num_waves()
+                    ),
+                }
+            }
+            proposals.extend(runner.network_plan_first_wave_proposals(&plan).await?);
+            if let Some(path) = &self.plan_output {
+                followed_plan = Some((path, plan, 1));
+            }
+        } else if network_heal || self.optimize_decentralization || self.remove_cordoned_nodes {
             info!("Healing the network by replacing unhealthy nodes, removing cordoned nodes, and optimizing decentralization in subnets");
             let maybe_proposals = runner
                 .network_fix(
@@ -201,7 +320,7 @@ impl ExecutableCommand for Network {
                 };
                 match Submitter::from(&self.submission_parameters)
                     .propose(
-                        ctx.ic_admin_executor().await?.execution(proposal.clone()),
+                        ctx.proposal_execution(proposal.clone()).await?,
                         ForumPostKind::ReplaceNodes { subnet_id: *subnet_id, body },
                     )
                     .await
@@ -212,7 +331,12 @@ impl ExecutableCommand for Network {
                             error: e,
                         });
                     }
-                    Ok(p) => submitted_proposals.push(p),
+                    Ok(p) => {
+                        if let (Some((_, plan, wave)), Some(p)) = (&mut followed_plan, &p) {
+                            plan.record_proposal(*wave, *subnet_id, p.clone().into());
+                        }
+                        submitted_proposals.push(p)
+                    }
                 }
             } else {
                 errors.push(DetailedError {
@@ -226,6 +350,13 @@ impl ExecutableCommand for Network {
             println! {"{}", p};
         }
 
+        if let Some((path, plan, _)) = followed_plan
+            && submitted_proposals.iter().any(Option::is_some)
+        {
+            serde_json::to_writer_pretty(fs_err::File::create(path)?, &plan)?;
+            info!("Submitted proposals recorded in the migration plan {}", path.display());
+        }
+
         if errors.is_empty() {
             Ok(())
         } else {
@@ -236,24 +367,62 @@ impl ExecutableCommand for Network {
         }
     }
 
-    fn validate(&self, _args: &GlobalArgs, cmd: &mut clap::Command) {
+    fn validate(&self, args: &GlobalArgs, cmd: &mut clap::Command) {
+        if let Some(subcommands) = &self.subcommands {
+            return subcommands.validate(args, cmd);
+        }
+
         // At least one of the two options must be provided
         let network_heal = self.heal || std::env::args().any(|arg| arg == "heal");
         if !(network_heal
             || self.ensure_operator_nodes_assigned
             || self.ensure_operator_nodes_unassigned
             || self.remove_cordoned_nodes
-            || self.optimize_decentralization)
+            || self.optimize_decentralization
+            || self.migration_target().is_some()
+            || self.plan_resume.is_some())
         {
             cmd.error(
                 clap::error::ErrorKind::MissingRequiredArgument,
-                "At least one of '--heal' or '--ensure-operator-nodes-assigned' or '--ensure-operator-nodes-unassigned' or '--remove-cordoned-nodes' or '--optimize-decentralization' must be specified.",
+                "At least one of '--heal' or '--ensure-operator-nodes-assigned' or '--ensure-operator-nodes-unassigned' or '--remove-cordoned-nodes' or '--optimize-decentralization' or '--plan-business-rules' or '--plan-unassign-operator' or '--plan-resume' must be specified.",
             )
             .exit()
         }
     }
 }
 
+impl Network {
+    fn migration_target(&self) -> Option<MigrationTarget> {
+        if self.plan_business_rules {
+            Some(MigrationTarget::BusinessRulesCompliant)
+        } else {
+            self.plan_unassign_operator.map(MigrationTarget::OperatorUnassigned)
+        }
+    }
+}
+
+/// The proposals recorded in the plan which are executed. Fails if one of them was rejected or
+/// failed, since the following steps depend on it.
+async fn executed_plan_proposals(ctx: &crate::ctx::DreContext, plan: &MigrationPlan, path: &Path) -> anyhow::Result<IndexSet<u64>> {
+    let governance = GovernanceCanisterWrapper::from(ctx.create_ic_agent_canister_client().await?);
+    let mut executed = IndexSet::new();
+    for proposal_id in plan.proposal_ids() {
+        match ProposalStatus::try_from(governance.get_proposal(proposal_id).await?.status)? {
+            ProposalStatus::Executed => {
+                executed.insert(proposal_id);
+            }
+            status @ (ProposalStatus::Rejected | ProposalStatus::Failed) => anyhow::bail!(
+                "Proposal {} of the migration plan {} has status {}, plan the migration again",
+                proposal_id,
+                path.display(),
+                status.as_str_name()
+            ),
+            _ => {}
+        }
+    }
+    Ok(executed)
+}
+
 struct DetailedError {
     proposal: Option<IcAdminProposal>,
     error: anyhow::Error,
@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use decentralization::network::OutageScope;
+use log::info;
+
+use crate::{
+    auth::AuthRequirement,
+    ctx::DreContext,
+    exe::{ExecutableCommand, args::GlobalArgs},
+};
+
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre network outage-sim --feature-value data_center=zh1
+    dre network outage-sim --feature-value country=CH --output outage.json
+    dre network outage-sim --provider-cluster "Providers that share multiple dcs 1"
+
+  A subnet loses liveness if more than 1/3 of its nodes are down, and safety if more than 2/3 are.
+  Nodes that were already unhealthy count as down. The rescue proposals replace at most 1/6 of the
+  nodes of a subnet each, like `dre network --heal` does, so a subnet may need several rounds.
+"#)]
+pub struct OutageSim {
+    /// Take down the nodes with the feature value, e.g. `node_provider=<principal>`, `data_center=zh1` or `country=CH`
+    #[clap(long, conflicts_with = "provider_cluster")]
+    pub feature_value: Option<String>,
+
+    /// Take down the nodes of the providers in the linked provider cluster
+    #[clap(long)]
+    pub provider_cluster: Option<String>,
+
+    /// Leave the provided subnets out of the simulation
+    #[clap(long, num_args(1..), visible_alias = "skip-subnets")]
+    pub omit_subnets: Vec<String>,
+
+    /// Write the full simulation as JSON to the provided file
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+impl ExecutableCommand for OutageSim {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Anonymous
+    }
+
+    async fn execute(&self, ctx: DreContext) -> anyhow::Result<()> {
+        let scope = match (&self.feature_value, &self.provider_cluster) {
+            (Some(value), _) => OutageScope::FeatureValue(value.clone()),
+            (None, Some(cluster)) => OutageScope::ProviderCluster(cluster.clone()),
+            (None, None) => unreachable!("validated before execution"),
+        };
+
+        let simulation = ctx.runner().await?.outage_simulation(&self.omit_subnets, scope).await?;
+        if let Some(path) = &self.output {
+            fs_err::write(path, serde_json::to_string_pretty(&simulation)?)?;
+            info!("Wrote the outage simulation to {}", path.display());
+        }
+        println!("{}", simulation);
+        Ok(())
+    }
+
+    fn validate(&self, _args: &GlobalArgs, cmd: &mut clap::Command) {
+        if self.feature_value.is_none() && self.provider_cluster.is_none() {
+            cmd.error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "Either --feature-value or --provider-cluster must be provided",
+            )
+            .exit()
+        }
+    }
+}
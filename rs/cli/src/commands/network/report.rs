@@ -0,0 +1,329 @@
+use std::{collections::BTreeSet, path::PathBuf};
+
+use chrono::{Days, NaiveDate, Utc};
+use clap::{Args, ValueEnum};
+use decentralization::history::{SubnetSnapshot, find_regressions};
+use ic_management_types::TopologyChangeProposal;
+use ic_protobuf::registry::subnet::v1::SubnetRecord;
+use ic_registry_common_proto::pb::local_store::v1::{ChangelogEntry, MutationType};
+use ic_types::PrincipalId;
+use itertools::Itertools;
+use log::{info, warn};
+use prost::Message;
+
+use crate::{
+    auth::AuthRequirement,
+    commands::registry::{load_first_available_entries, local_registry_dirs_for_ctx, select_versions},
+    ctx::DreContext,
+    exe::{ExecutableCommand, args::GlobalArgs},
+};
+
+/// Proposals listed for the attribution when no start date is provided
+const DEFAULT_PROPOSALS_LOOKBACK_DAYS: u64 = 365;
+
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre network report                                   # Report over all local registry versions
+    dre network report --versions -5000                  # Report over the last 5000 registry versions
+    dre network report --format csv -o report.csv        # Write the time series as CSV
+    dre network report --subnets tdb26 --attribute-proposals
+
+  The report is evaluated at every registry version in the range which changed the membership of some subnet.
+"#)]
+pub struct Report {
+    /// Range of registry versions to walk, with the same index semantics as `dre registry --dump-versions`
+    #[clap(long, num_args(0..=2), value_names = ["FROM", "TO"], allow_hyphen_values = true)]
+    pub versions: Option<Vec<i64>>,
+
+    /// Maximum number of registry versions to evaluate, the most recent ones are kept
+    #[clap(long, default_value_t = 30)]
+    pub max_points: usize,
+
+    /// Only report on the subnets whose id contains one of the provided substrings
+    #[clap(long, num_args(1..))]
+    pub subnets: Vec<String>,
+
+    /// Format of the time series
+    #[clap(long, value_enum, default_value_t = ReportFormat::Json)]
+    pub format: ReportFormat,
+
+    /// Output file for the time series (default is stdout)
+    #[clap(short = 'o', long)]
+    pub output: Option<PathBuf>,
+
+    /// Find the executed proposals which caused the membership changes.
+    /// This lists the executed topology proposals from the governance canister.
+    #[clap(long)]
+    pub attribute_proposals: bool,
+
+    /// Only list the proposals made on or after this date, in format YYYY-MM-DD, by default one year ago.
+    /// Membership changes made by older proposals are not attributed
+    #[clap(long, requires = "attribute_proposals")]
+    pub proposals_since: Option<NaiveDate>,
+}
+
+impl ExecutableCommand for Report {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Anonymous
+    }
+
+    async fn execute(&self, ctx: DreContext) -> anyhow::Result<()> {
+        // Ensure the local registry is synced, same as `dre registry --dump-versions`
+        let _ = ctx.registry().await;
+
+        let mut entries = load_first_available_entries(&local_registry_dirs_for_ctx(&ctx)?)?;
+        entries.sort_by_key(|(v, _)| *v);
+        let versions = select_versions(self.versions.clone(), &entries.iter().map(|(v, _)| *v).collect_vec())?;
+        let points = report_points(&entries, &versions, self.max_points);
+        if points.is_empty() {
+            anyhow::bail!("No registry versions selected");
+        }
+        info!(
+            "Evaluating {} registry versions between {} and {}",
+            points.len(),
+            points.first().unwrap(),
+            points.last().unwrap()
+        );
+
+        let business_rules = ctx.business_rules().await?;
+        let mut snapshots = vec![];
+        for version in &points {
+            let registry = ctx.registry_at_version(*version).await?;
+            for subnet in registry.subnets().await?.values() {
+                if !self.subnets.is_empty() && !self.subnets.iter().any(|s| subnet.principal.to_string().contains(s)) {
+                    continue;
+                }
+                snapshots.push(SubnetSnapshot::new(*version, subnet, &business_rules)?);
+            }
+        }
+
+        if self.attribute_proposals {
+            let since = self
+                .proposals_since
+                .unwrap_or_else(|| Utc::now().date_naive() - Days::new(DEFAULT_PROPOSALS_LOOKBACK_DAYS));
+            info!("Listing the topology proposals executed since {}", since);
+            let since_timestamp_seconds = since.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as u64;
+            let proposals = ctx.proposals_agent().list_executed_topology_proposals(since_timestamp_seconds).await?;
+            attribute_proposals(&mut snapshots, &proposals);
+        }
+
+        let writer: Box<dyn std::io::Write> = match &self.output {
+            Some(path) => {
+                info!("Writing the time series to {}", path.display());
+                Box::new(std::io::BufWriter::new(fs_err::File::create(path)?))
+            }
+            None => Box::new(std::io::stdout()),
+        };
+        match self.format {
+            ReportFormat::Json => serde_json::to_writer_pretty(writer, &snapshots)?,
+            ReportFormat::Csv => write_csv(writer, &snapshots)?,
+        }
+        if self.output.is_none() {
+            println!();
+        }
+
+        let regressions = find_regressions(&snapshots);
+        if regressions.is_empty() {
+            println!(
+                "No decentralization regressions between registry versions {} and {}",
+                points[0],
+                points[points.len() - 1]
+            );
+        } else {
+            println!("Decentralization regressions:");
+            for regression in regressions {
+                println!(" - {}", regression);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self, _args: &GlobalArgs, cmd: &mut clap::Command) {
+        if self.max_points < 2 {
+            cmd.error(clap::error::ErrorKind::InvalidValue, "At least 2 points are needed to report a trend")
+                .exit()
+        }
+    }
+}
+
+/// Registry versions to evaluate: the first and the last selected version, and every selected
+/// version which changed the membership of some subnet. At most `max_points` most recent ones are kept.
+fn report_points(entries: &[(u64, ChangelogEntry)], selected_versions: &[u64], max_points: usize) -> Vec<u64> {
+    let (Some(first), Some(last)) = (selected_versions.first(), selected_versions.last()) else {
+        return vec![];
+    };
+
+    let mut points = BTreeSet::from([*first, *last]);
+    let mut membership: std::collections::HashMap<String, Vec<Vec<u8>>> = std::collections::HashMap::new();
+    for (version, entry) in entries.iter().take_while(|(v, _)| v <= last) {
+        for mutation in entry
+            .key_mutations
+            .iter()
+            .filter(|m| m.key.starts_with(ic_registry_keys::SUBNET_RECORD_KEY_PREFIX))
+        {
+            let nodes = match mutation.mutation_type() {
+                MutationType::Set => match SubnetRecord::decode(mutation.value.as_slice()) {
+                    Ok(record) => record.membership,
+                    Err(e) => {
+                        warn!("Failed to decode {} at version {}: {:?}", mutation.key, version, e);
+                        continue;
+                    }
+                },
+                _ => vec![],
+            };
+            if membership.get(&mutation.key) != Some(&nodes) {
+                if version >= first {
+                    points.insert(*version);
+                }
+                membership.insert(mutation.key.clone(), nodes);
+            }
+        }
+    }
+
+    let points = points.into_iter().collect_vec();
+    points[points.len().saturating_sub(max_points)..].to_vec()
+}
+
+/// Sets the proposal id on every snapshot whose membership change, compared to the previous
+/// snapshot of the same subnet, is covered by an executed proposal.
+fn attribute_proposals(snapshots: &mut [SubnetSnapshot], proposals: &[TopologyChangeProposal]) {
+    let mut previous: std::collections::HashMap<PrincipalId, SubnetSnapshot> = std::collections::HashMap::new();
+    for snapshot in snapshots.iter_mut().sorted_by_key(|s| s.registry_version) {
+        if let Some(earlier) = previous.get(&snapshot.subnet_id) {
+            let (added, removed) = snapshot.membership_diff(earlier);
+            if !added.is_empty() || !removed.is_empty() {
+                snapshot.proposal_id = proposals
+                    .iter()
+                    .filter(|p| p.subnet_id == Some(snapshot.subnet_id))
+                    .filter(|p| !p.node_ids_added.is_empty() || !p.node_ids_removed.is_empty())
+                    .filter(|p| p.node_ids_added.iter().all(|n| added.contains(n)) && p.node_ids_removed.iter().all(|n| removed.contains(n)))
+                    .map(|p| p.id)
+                    .max();
+            }
+        }
+        previous.insert(snapshot.subnet_id, snapshot.clone());
+    }
+}
+
+fn write_csv(writer: Box<dyn std::io::Write>, snapshots: &[SubnetSnapshot]) -> anyhow::Result<()> {
+    let features = snapshots.iter().flat_map(|s| s.nakamoto.keys()).unique().cloned().collect_vec();
+    let mut writer = csv::Writer::from_writer(writer);
+
+    let header = ["registry_version", "subnet_id", "subnet_name", "nodes", "penalty", "nakamoto_avg_linear"]
+        .into_iter()
+        .map(String::from)
+        .chain(features.iter().map(|f| format!("nakamoto_{}", f)))
+        .chain(features.iter().map(|f| format!("values_{}", f)))
+        .chain(std::iter::once("proposal_id".to_string()))
+        .collect_vec();
+    writer.write_record(&header)?;
+
+    for snapshot in snapshots {
+        let record = [
+            snapshot.registry_version.to_string(),
+            snapshot.subnet_id.to_string(),
+            snapshot.subnet_name.clone(),
+            snapshot.node_ids.len().to_string(),
+            snapshot.penalty.to_string(),
+            format!("{:.4}", snapshot.nakamoto_avg_linear),
+        ]
+        .into_iter()
+        .chain(
+            features
+                .iter()
+                .map(|f| snapshot.nakamoto.get(f).map(|s| format!("{:.2}", s)).unwrap_or_default()),
+        )
+        .chain(features.iter().map(|f| {
+            snapshot
+                .feature_value_counts
+                .get(f)
+                .map(|counts| counts.iter().map(|(value, count)| format!("{}:{}", value, count)).join(";"))
+                .unwrap_or_default()
+        }))
+        .chain(std::iter::once(snapshot.proposal_id.map(|p| p.to_string()).unwrap_or_default()))
+        .collect_vec();
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_registry_common_proto::pb::local_store::v1::KeyMutation;
+
+    fn subnet_entry(subnet: &str, nodes: &[u8]) -> ChangelogEntry {
+        ChangelogEntry {
+            key_mutations: vec![KeyMutation {
+                key: format!("{}{}", ic_registry_keys::SUBNET_RECORD_KEY_PREFIX, subnet),
+                value: SubnetRecord {
+                    membership: nodes.iter().map(|n| vec![*n]).collect(),
+                    ..Default::default()
+                }
+                .encode_to_vec(),
+                mutation_type: MutationType::Set as i32,
+            }],
+        }
+    }
+
+    #[test]
+    fn report_points_are_membership_changes() {
+        let entries = vec![
+            (1, subnet_entry("a", &[1, 2])),
+            (2, subnet_entry("b", &[3, 4])),
+            (3, subnet_entry("a", &[1, 2])),
+            (4, subnet_entry("a", &[1, 5])),
+            (5, ChangelogEntry::default()),
+            (6, subnet_entry("b", &[3, 6])),
+        ];
+
+        assert_eq!(report_points(&entries, &[2, 3, 4, 5], 10), vec![2, 4, 5]);
+        assert_eq!(report_points(&entries, &[1, 2, 3, 4, 5, 6], 10), vec![1, 2, 4, 6]);
+        assert_eq!(report_points(&entries, &[1, 2, 3, 4, 5, 6], 2), vec![4, 6]);
+    }
+
+    #[test]
+    fn membership_changes_are_attributed_to_proposals() {
+        let subnet_id = PrincipalId::new_subnet_test_id(1);
+        let snapshot = |version: u64, nodes: &[u64]| SubnetSnapshot {
+            registry_version: version,
+            subnet_id,
+            subnet_name: String::new(),
+            node_ids: nodes.iter().map(|n| PrincipalId::new_node_test_id(*n)).collect(),
+            nakamoto: Default::default(),
+            nakamoto_avg_linear: 0.,
+            penalty: 0,
+            penalty_reasons: vec![],
+            feature_value_counts: Default::default(),
+            proposal_id: None,
+        };
+        let mut snapshots = vec![snapshot(1, &[1, 2]), snapshot(2, &[1, 3]), snapshot(3, &[1, 3])];
+        let proposals = vec![
+            TopologyChangeProposal {
+                node_ids_added: vec![PrincipalId::new_node_test_id(3)],
+                node_ids_removed: vec![PrincipalId::new_node_test_id(2)],
+                subnet_id: Some(subnet_id),
+                id: 100,
+            },
+            TopologyChangeProposal {
+                node_ids_added: vec![PrincipalId::new_node_test_id(4)],
+                node_ids_removed: vec![PrincipalId::new_node_test_id(2)],
+                subnet_id: Some(subnet_id),
+                id: 101,
+            },
+        ];
+
+        attribute_proposals(&mut snapshots, &proposals);
+
+        assert_eq!(snapshots.iter().map(|s| s.proposal_id).collect_vec(), vec![None, Some(100), None]);
+    }
+}
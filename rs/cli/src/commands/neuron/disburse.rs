@@ -0,0 +1,129 @@
+use clap::{Args, error::ErrorKind};
+use ic_base_types::PrincipalId;
+use ic_nns_governance::pb::v1::manage_neuron::disburse::Amount;
+use ic_nns_governance::pb::v1::manage_neuron::{Command, Disburse as DisburseCommand, Spawn as SpawnCommand};
+use icp_ledger::AccountIdentifier;
+
+use crate::{auth::AuthRequirement, confirm::ConfirmationModeOptions, exe::ExecutableCommand, exe::args::GlobalArgs};
+
+use super::manage::{NeuronChange, format_e8s, manage_neuron};
+
+/// Disburse the stake of a dissolved neuron to a ledger account
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre neuron disburse                            # The whole stake to the account of the controller
+    dre neuron disburse --amount 10.5 --to-account <account-identifier-hex>
+"#)]
+pub struct Disburse {
+    /// Amount of ICP to disburse, by default the whole stake
+    #[clap(long)]
+    pub amount: Option<f64>,
+
+    /// Account identifier, in hex, receiving the ICP. By default the account of the controller of the neuron
+    #[clap(long)]
+    pub to_account: Option<String>,
+
+    #[clap(flatten)]
+    pub confirmation_mode: ConfirmationModeOptions,
+}
+
+impl ExecutableCommand for Disburse {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Neuron
+    }
+
+    fn validate(&self, _args: &GlobalArgs, cmd: &mut clap::Command) {
+        if self.amount.is_some_and(|amount| amount <= 0.0) {
+            cmd.error(ErrorKind::InvalidValue, "The amount must be positive").exit();
+        }
+        if let Some((account, Err(e))) = self.to_account.as_ref().map(|account| (account, AccountIdentifier::from_hex(account))) {
+            cmd.error(ErrorKind::InvalidValue, format!("Invalid account identifier {}: {}", account, e))
+                .exit();
+        }
+    }
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        let to_account = self
+            .to_account
+            .as_ref()
+            .map(|account| AccountIdentifier::from_hex(account).map_err(|e| anyhow::anyhow!("Invalid account identifier {}: {}", account, e)))
+            .transpose()?;
+        let amount_e8s = self.amount.map(|amount| (amount * 1e8).round() as u64);
+        manage_neuron(&ctx, &self.confirmation_mode, |neuron| {
+            let stake_e8s = neuron.cached_neuron_stake_e8s.saturating_sub(neuron.neuron_fees_e8s);
+            if let Some(amount) = amount_e8s.filter(|amount| *amount > stake_e8s) {
+                anyhow::bail!(
+                    "Cannot disburse {}, the stake of the neuron is {}",
+                    format_e8s(amount),
+                    format_e8s(stake_e8s)
+                );
+            }
+            Ok(NeuronChange {
+                description: format!(
+                    "disburse {} of the {} stake to {}",
+                    format_e8s(amount_e8s.unwrap_or(stake_e8s)),
+                    format_e8s(stake_e8s),
+                    to_account
+                        .map(|account| account.to_hex())
+                        .unwrap_or_else(|| "the account of the controller".to_string())
+                ),
+                command: Command::Disburse(DisburseCommand {
+                    amount: amount_e8s.map(|e8s| Amount { e8s }),
+                    to_account: to_account.map(|account| icp_ledger::protobuf::AccountIdentifier { hash: account.to_vec() }),
+                }),
+            })
+        })
+        .await
+    }
+}
+
+/// Spawn the maturity of the neuron into a new neuron
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre neuron spawn
+    dre neuron spawn --percentage 50 --new-controller <principal>
+"#)]
+pub struct Spawn {
+    /// Percentage of the maturity to spawn
+    #[clap(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..=100))]
+    pub percentage: u32,
+
+    /// Controller of the spawned neuron, by default the controller of the neuron
+    #[clap(long)]
+    pub new_controller: Option<PrincipalId>,
+
+    #[clap(flatten)]
+    pub confirmation_mode: ConfirmationModeOptions,
+}
+
+impl ExecutableCommand for Spawn {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Neuron
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        manage_neuron(&ctx, &self.confirmation_mode, |neuron| {
+            if neuron.maturity_e8s_equivalent == 0 {
+                anyhow::bail!("The neuron has no maturity to spawn");
+            }
+            Ok(NeuronChange {
+                description: format!(
+                    "spawn {}% of the {} maturity into a new neuron controlled by {}",
+                    self.percentage,
+                    format_e8s(neuron.maturity_e8s_equivalent),
+                    self.new_controller
+                        .map(|c| c.to_string())
+                        .unwrap_or_else(|| "the controller of the neuron".to_string())
+                ),
+                command: Command::Spawn(SpawnCommand {
+                    new_controller: self.new_controller,
+                    nonce: None,
+                    percentage_to_spawn: Some(self.percentage),
+                }),
+            })
+        })
+        .await
+    }
+}
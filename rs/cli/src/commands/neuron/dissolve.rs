@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use clap::{Args, error::ErrorKind};
+use humantime::parse_duration;
+use ic_nns_governance::pb::v1::manage_neuron::configure::Operation;
+use ic_nns_governance::pb::v1::manage_neuron::{
+    IncreaseDissolveDelay as IncreaseDissolveDelayOperation, StartDissolving as StartDissolvingOperation, StopDissolving as StopDissolvingOperation,
+};
+
+use crate::{auth::AuthRequirement, confirm::ConfirmationModeOptions, exe::ExecutableCommand, exe::args::GlobalArgs};
+
+use super::manage::{NeuronChange, dissolve_state, format_seconds, manage_neuron};
+
+fn now_seconds() -> u64 {
+    Utc::now().timestamp() as u64
+}
+
+/// Increase the dissolve delay of the neuron
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre neuron increase-dissolve-delay 180days
+    dre neuron increase-dissolve-delay 1year --dry-run
+"#)]
+pub struct IncreaseDissolveDelay {
+    /// Duration added to the dissolve delay, for example `30days`. Governance caps the dissolve delay at 8 years
+    #[clap(value_parser = parse_duration)]
+    pub additional_delay: Duration,
+
+    #[clap(flatten)]
+    pub confirmation_mode: ConfirmationModeOptions,
+}
+
+impl ExecutableCommand for IncreaseDissolveDelay {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Neuron
+    }
+
+    fn validate(&self, _args: &GlobalArgs, cmd: &mut clap::Command) {
+        if self.additional_delay.as_secs() == 0 || u32::try_from(self.additional_delay.as_secs()).is_err() {
+            cmd.error(ErrorKind::InvalidValue, "The additional delay must be between 1 second and 136 years")
+                .exit();
+        }
+    }
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        let additional_dissolve_delay_seconds = self.additional_delay.as_secs() as u32;
+        manage_neuron(&ctx, &self.confirmation_mode, |neuron| {
+            Ok(NeuronChange::configure(
+                format!(
+                    "increase the dissolve delay by {}, the neuron is {}",
+                    format_seconds(additional_dissolve_delay_seconds as u64),
+                    dissolve_state(neuron, now_seconds())
+                ),
+                Operation::IncreaseDissolveDelay(IncreaseDissolveDelayOperation {
+                    additional_dissolve_delay_seconds,
+                }),
+            ))
+        })
+        .await
+    }
+}
+
+/// Start dissolving the neuron
+#[derive(Args, Debug)]
+pub struct StartDissolving {
+    #[clap(flatten)]
+    pub confirmation_mode: ConfirmationModeOptions,
+}
+
+impl ExecutableCommand for StartDissolving {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Neuron
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        manage_neuron(&ctx, &self.confirmation_mode, |neuron| {
+            Ok(NeuronChange::configure(
+                format!("start dissolving, the neuron is {}", dissolve_state(neuron, now_seconds())),
+                Operation::StartDissolving(StartDissolvingOperation {}),
+            ))
+        })
+        .await
+    }
+}
+
+/// Stop dissolving the neuron, keeping its remaining dissolve delay
+#[derive(Args, Debug)]
+pub struct StopDissolving {
+    #[clap(flatten)]
+    pub confirmation_mode: ConfirmationModeOptions,
+}
+
+impl ExecutableCommand for StopDissolving {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Neuron
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        manage_neuron(&ctx, &self.confirmation_mode, |neuron| {
+            Ok(NeuronChange::configure(
+                format!("stop dissolving, the neuron is {}", dissolve_state(neuron, now_seconds())),
+                Operation::StopDissolving(StopDissolvingOperation {}),
+            ))
+        })
+        .await
+    }
+}
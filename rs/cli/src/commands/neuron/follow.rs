@@ -0,0 +1,60 @@
+use clap::Args;
+use ic_nns_common::pb::v1::NeuronId;
+use ic_nns_governance::pb::v1::Topic;
+use ic_nns_governance::pb::v1::manage_neuron::{Command, Follow as FollowCommand};
+use itertools::Itertools;
+
+use crate::{auth::AuthRequirement, confirm::ConfirmationModeOptions, exe::ExecutableCommand, exe::args::GlobalArgs};
+
+use super::manage::{NeuronChange, manage_neuron, parse_topic};
+
+/// Set the neurons the neuron follows on a topic
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre neuron follow --topic NodeAdmin 40 80
+    dre neuron follow --topic 12 40           # Topics can also be given by number
+    dre neuron follow --topic NodeAdmin       # Stop following on the topic
+"#)]
+pub struct Follow {
+    /// Topic, by name as in the voting rules (for example `IcOsVersionDeployment`) or by number
+    #[clap(long, value_parser = parse_topic)]
+    pub topic: Topic,
+
+    /// Neurons to follow, replacing the current followees of the topic. None stops following on the topic
+    pub followees: Vec<u64>,
+
+    #[clap(flatten)]
+    pub confirmation_mode: ConfirmationModeOptions,
+}
+
+impl ExecutableCommand for Follow {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Neuron
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        manage_neuron(&ctx, &self.confirmation_mode, |neuron| {
+            let current = neuron
+                .followees
+                .get(&(self.topic as i32))
+                .map(|f| f.followees.iter().map(|n| n.id).join(", "))
+                .filter(|f| !f.is_empty())
+                .unwrap_or_else(|| "nobody".to_string());
+            let new = if self.followees.is_empty() {
+                "nobody".to_string()
+            } else {
+                self.followees.iter().join(", ")
+            };
+            Ok(NeuronChange {
+                description: format!("follow {} instead of {} on topic {:?}", new, current, self.topic),
+                command: Command::Follow(FollowCommand {
+                    topic: self.topic as i32,
+                    followees: self.followees.iter().map(|id| NeuronId { id: *id }).collect(),
+                }),
+            })
+        })
+        .await
+    }
+}
@@ -0,0 +1,74 @@
+use clap::Args;
+use ic_base_types::PrincipalId;
+use ic_nns_governance::pb::v1::manage_neuron::configure::Operation;
+use ic_nns_governance::pb::v1::manage_neuron::{AddHotKey as AddHotKeyOperation, RemoveHotKey as RemoveHotKeyOperation};
+
+use crate::{auth::AuthRequirement, confirm::ConfirmationModeOptions, exe::ExecutableCommand, exe::args::GlobalArgs};
+
+use super::manage::{NeuronChange, manage_neuron};
+
+/// Add a hot key to the neuron, which can then vote and follow with it
+#[derive(Args, Debug)]
+pub struct AddHotKey {
+    /// Principal of the hot key
+    pub principal: PrincipalId,
+
+    #[clap(flatten)]
+    pub confirmation_mode: ConfirmationModeOptions,
+}
+
+impl ExecutableCommand for AddHotKey {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Neuron
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        manage_neuron(&ctx, &self.confirmation_mode, |neuron| {
+            if neuron.hot_keys.contains(&self.principal) {
+                anyhow::bail!("{} is already a hot key of the neuron", self.principal);
+            }
+            Ok(NeuronChange::configure(
+                format!("add hot key {} to the {} current ones", self.principal, neuron.hot_keys.len()),
+                Operation::AddHotKey(AddHotKeyOperation {
+                    new_hot_key: Some(self.principal),
+                }),
+            ))
+        })
+        .await
+    }
+}
+
+/// Remove a hot key from the neuron
+#[derive(Args, Debug)]
+pub struct RemoveHotKey {
+    /// Principal of the hot key
+    pub principal: PrincipalId,
+
+    #[clap(flatten)]
+    pub confirmation_mode: ConfirmationModeOptions,
+}
+
+impl ExecutableCommand for RemoveHotKey {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Neuron
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        manage_neuron(&ctx, &self.confirmation_mode, |neuron| {
+            if !neuron.hot_keys.contains(&self.principal) {
+                anyhow::bail!("{} is not a hot key of the neuron", self.principal);
+            }
+            Ok(NeuronChange::configure(
+                format!("remove hot key {}, {} remain", self.principal, neuron.hot_keys.len() - 1),
+                Operation::RemoveHotKey(RemoveHotKeyOperation {
+                    hot_key_to_remove: Some(self.principal),
+                }),
+            ))
+        })
+        .await
+    }
+}
@@ -0,0 +1,237 @@
+use ic_base_types::PrincipalId;
+use ic_canisters::governance::GovernanceCanisterWrapper;
+use ic_nns_governance::pb::v1::Topic;
+use ic_nns_governance::pb::v1::manage_neuron::configure::Operation;
+use ic_nns_governance::pb::v1::manage_neuron::{Command, Configure};
+use ic_nns_governance_api::Neuron as FullNeuron;
+use ic_nns_governance_api::manage_neuron_response::Command as CommandResponse;
+use ic_nns_governance_api::neuron::DissolveState;
+use itertools::Itertools;
+use log::info;
+
+use crate::confirm::{ConfirmationModeOptions, HowToProceed};
+use crate::ctx::DreContext;
+use crate::util::yesno;
+
+/// Change to the neuron of the context, with a description of the neuron before and after it
+pub(crate) struct NeuronChange {
+    pub description: String,
+    pub command: Command,
+}
+
+impl NeuronChange {
+    pub fn configure(description: String, operation: Operation) -> Self {
+        Self {
+            description,
+            command: Command::Configure(Configure { operation: Some(operation) }),
+        }
+    }
+}
+
+/// Previews a change to the neuron of the context, built from its current state, and sends it to the
+/// governance canister, unless this is a dry run or the user declines
+pub(crate) async fn manage_neuron(
+    ctx: &DreContext,
+    confirmation_mode: &ConfirmationModeOptions,
+    change: impl FnOnce(&FullNeuron) -> anyhow::Result<NeuronChange>,
+) -> anyhow::Result<()> {
+    let (neuron, client) = ctx.create_ic_agent_canister_client().await?;
+    let governance = GovernanceCanisterWrapper::from(client);
+    let full_neuron = governance.get_full_neuron(neuron.neuron_id).await?;
+    let change = change(&full_neuron)?;
+
+    println!("Neuron {}: {}", neuron.neuron_id, change.description);
+    for (name, value) in command_fields(&change.command) {
+        println!("  {}: {}", name, value);
+    }
+    let manage_neuron = GovernanceCanisterWrapper::neuron_command(neuron.neuron_id, change.command);
+
+    let mode: HowToProceed = confirmation_mode.into();
+    match mode {
+        HowToProceed::DryRun => {
+            info!("Dry run, the command was not sent");
+            return Ok(());
+        }
+        HowToProceed::Confirm => {
+            if !yesno("Do you want to continue?", false).await?? {
+                return Ok(());
+            }
+        }
+        HowToProceed::Unconditional | HowToProceed::UnitTests => {}
+    }
+
+    let response = governance.execute_neuron_command(&manage_neuron).await?;
+    info!("Neuron {}: {}", neuron.neuron_id, describe_response(&response));
+    Ok(())
+}
+
+/// Kind and main fields of the command, shown next to the description of the change before sending it
+fn command_fields(command: &Command) -> Vec<(&'static str, String)> {
+    let principal_or = |principal: Option<PrincipalId>, default: &str| principal.map(|p| p.to_string()).unwrap_or_else(|| default.to_string());
+    match command {
+        Command::Configure(Configure { operation: Some(operation) }) => match operation {
+            Operation::IncreaseDissolveDelay(op) => vec![
+                ("Command", "increase dissolve delay".to_string()),
+                ("Additional delay", format_seconds(op.additional_dissolve_delay_seconds as u64)),
+            ],
+            Operation::StartDissolving(_) => vec![("Command", "start dissolving".to_string())],
+            Operation::StopDissolving(_) => vec![("Command", "stop dissolving".to_string())],
+            Operation::AddHotKey(op) => vec![("Command", "add hot key".to_string()), ("Hot key", principal_or(op.new_hot_key, "none"))],
+            Operation::RemoveHotKey(op) => vec![
+                ("Command", "remove hot key".to_string()),
+                ("Hot key", principal_or(op.hot_key_to_remove, "none")),
+            ],
+            _ => vec![("Command", "configure".to_string())],
+        },
+        Command::Disburse(disburse) => vec![
+            ("Command", "disburse".to_string()),
+            (
+                "Amount",
+                disburse
+                    .amount
+                    .as_ref()
+                    .map(|amount| format_e8s(amount.e8s))
+                    .unwrap_or_else(|| "the whole stake".to_string()),
+            ),
+            (
+                "To account",
+                disburse
+                    .to_account
+                    .as_ref()
+                    .map(|account| hex::encode(&account.hash))
+                    .unwrap_or_else(|| "the account of the controller".to_string()),
+            ),
+        ],
+        Command::Spawn(spawn) => vec![
+            ("Command", "spawn".to_string()),
+            ("Percentage", format!("{}%", spawn.percentage_to_spawn.unwrap_or(100))),
+            ("New controller", principal_or(spawn.new_controller, "the controller of the neuron")),
+        ],
+        Command::Follow(follow) => vec![
+            ("Command", "follow".to_string()),
+            (
+                "Topic",
+                Topic::try_from(follow.topic)
+                    .map(|topic| format!("{:?}", topic))
+                    .unwrap_or_else(|_| follow.topic.to_string()),
+            ),
+            (
+                "Followees",
+                if follow.followees.is_empty() {
+                    "nobody".to_string()
+                } else {
+                    follow.followees.iter().map(|n| n.id).join(", ")
+                },
+            ),
+        ],
+        _ => vec![],
+    }
+}
+
+/// Human readable outcome of the command
+fn describe_response(response: &CommandResponse) -> String {
+    match response {
+        CommandResponse::Disburse(disburse) => format!("disbursed in ledger block {}", disburse.transfer_block_height),
+        CommandResponse::Spawn(spawn) => match &spawn.created_neuron_id {
+            Some(neuron_id) => format!("spawned neuron {}", neuron_id.id),
+            None => "spawned a new neuron".to_string(),
+        },
+        CommandResponse::Configure(_) => "configured".to_string(),
+        CommandResponse::Follow(_) => "followees set".to_string(),
+        _ => "command executed".to_string(),
+    }
+}
+
+/// Parses a topic from its name, as in the voting rules, or from its number
+pub(crate) fn parse_topic(topic: &str) -> anyhow::Result<Topic> {
+    match topic.parse::<i32>() {
+        Ok(number) => Topic::try_from(number).map_err(|_| anyhow::anyhow!("Unknown topic {}", number)),
+        Err(_) => (0..100)
+            .filter_map(|t| Topic::try_from(t).ok())
+            .find(|t| format!("{:?}", t) == topic)
+            .ok_or_else(|| anyhow::anyhow!("Unknown topic `{}`, expected a topic name such as `NodeAdmin` or its number", topic)),
+    }
+}
+
+pub(crate) fn format_e8s(e8s: u64) -> String {
+    format!("{} ICP", e8s as f64 / 1e8)
+}
+
+/// Human readable dissolve state of the neuron
+pub(crate) fn dissolve_state(neuron: &FullNeuron, now_seconds: u64) -> String {
+    match neuron.dissolve_state {
+        Some(DissolveState::DissolveDelaySeconds(delay)) => format!("not dissolving, dissolve delay {}", format_seconds(delay)),
+        Some(DissolveState::WhenDissolvedTimestampSeconds(when)) if when > now_seconds => {
+            format!("dissolving, dissolved in {}", format_seconds(when - now_seconds))
+        }
+        Some(DissolveState::WhenDissolvedTimestampSeconds(_)) | None => "dissolved".to_string(),
+    }
+}
+
+pub(crate) fn format_seconds(seconds: u64) -> String {
+    humantime::format_duration(std::time::Duration::from_secs(seconds)).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topics_by_name_or_number() {
+        assert_eq!(parse_topic("NodeAdmin").unwrap(), Topic::NodeAdmin);
+        assert_eq!(parse_topic(&(Topic::Governance as i32).to_string()).unwrap(), Topic::Governance);
+        assert!(parse_topic("NotATopic").is_err());
+        assert!(parse_topic("-1").is_err());
+    }
+
+    #[test]
+    fn disburse_preview_shows_the_amount_and_the_account() {
+        use ic_nns_governance::pb::v1::manage_neuron::{Disburse, disburse::Amount};
+        use icp_ledger::AccountIdentifier;
+
+        let account = AccountIdentifier::new(PrincipalId::new_user_test_id(1), None);
+        let disburse = |amount: Option<u64>, to_account: Option<AccountIdentifier>| {
+            Command::Disburse(Disburse {
+                amount: amount.map(|e8s| Amount { e8s }),
+                to_account: to_account.map(|account| icp_ledger::protobuf::AccountIdentifier { hash: account.to_vec() }),
+            })
+        };
+        assert_eq!(
+            command_fields(&disburse(Some(150_000_000), Some(account))),
+            vec![
+                ("Command", "disburse".to_string()),
+                ("Amount", "1.5 ICP".to_string()),
+                ("To account", account.to_hex()),
+            ]
+        );
+        assert_eq!(
+            command_fields(&disburse(None, None)),
+            vec![
+                ("Command", "disburse".to_string()),
+                ("Amount", "the whole stake".to_string()),
+                ("To account", "the account of the controller".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn dissolve_states() {
+        let neuron = |state| FullNeuron {
+            dissolve_state: state,
+            ..Default::default()
+        };
+        assert_eq!(
+            dissolve_state(&neuron(Some(DissolveState::DissolveDelaySeconds(86400))), 1000),
+            "not dissolving, dissolve delay 1day"
+        );
+        assert_eq!(
+            dissolve_state(&neuron(Some(DissolveState::WhenDissolvedTimestampSeconds(4600))), 1000),
+            "dissolving, dissolved in 1h"
+        );
+        assert_eq!(
+            dissolve_state(&neuron(Some(DissolveState::WhenDissolvedTimestampSeconds(500))), 1000),
+            "dissolved"
+        );
+        assert_eq!(dissolve_state(&neuron(None), 1000), "dissolved");
+    }
+}
@@ -1,13 +1,24 @@
 use clap::Args;
 
 use crate::commands::neuron::balance::Balance;
+use crate::commands::neuron::disburse::{Disburse, Spawn};
+use crate::commands::neuron::dissolve::{IncreaseDissolveDelay, StartDissolving, StopDissolving};
+use crate::commands::neuron::follow::Follow;
+use crate::commands::neuron::hot_key::{AddHotKey, RemoveHotKey};
 use crate::commands::neuron::refresh::Refresh;
 use crate::commands::neuron::top_up::TopUp;
+use crate::commands::neuron::watch::Watch;
 use crate::exe::impl_executable_command_for_enums;
 
 mod balance;
+mod disburse;
+mod dissolve;
+mod follow;
+mod hot_key;
+mod manage;
 mod refresh;
 mod top_up;
+mod watch;
 
 #[derive(Args, Debug)]
 pub struct Neuron {
@@ -15,4 +26,6 @@ pub struct Neuron {
     pub subcommands: Subcommands,
 }
 
-impl_executable_command_for_enums! { Neuron, Balance, TopUp, Refresh }
+impl_executable_command_for_enums! {
+    Neuron, Balance, TopUp, Refresh, Follow, AddHotKey, RemoveHotKey, IncreaseDissolveDelay, StartDissolving, StopDissolving, Disburse, Spawn, Watch
+}
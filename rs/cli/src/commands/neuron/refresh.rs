@@ -4,7 +4,12 @@ use ic_canisters::governance::GovernanceCanisterWrapper;
 use crate::{auth::AuthRequirement, exe::ExecutableCommand, exe::args::GlobalArgs};
 
 #[derive(Args, Debug)]
-pub struct Refresh {}
+pub struct Refresh {
+    /// Refresh the voting power of the neuron instead of its stake.
+    /// The voting power decays if it was not refreshed, by voting or following, for 6 months
+    #[clap(long)]
+    pub voting_power: bool,
+}
 
 impl ExecutableCommand for Refresh {
     fn require_auth(&self) -> AuthRequirement {
@@ -17,6 +22,12 @@ impl ExecutableCommand for Refresh {
         let (neuron, client) = ctx.create_ic_agent_canister_client().await?;
         let governance_canister = GovernanceCanisterWrapper::from(client);
 
+        if self.voting_power {
+            let resp = governance_canister.refresh_voting_power(neuron.neuron_id).await?;
+            println!("{:?}", resp);
+            return Ok(());
+        }
+
         let resp = governance_canister.refresh_neuron(neuron.neuron_id).await?;
         println!("{:?}", resp);
 
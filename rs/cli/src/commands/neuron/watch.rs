@@ -0,0 +1,560 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::Utc;
+use clap::Args;
+use humantime::parse_duration;
+use ic_canisters::governance::GovernanceCanisterWrapper;
+use ic_nns_governance::pb::v1::Topic;
+use ic_nns_governance_api::neuron::DissolveState;
+use ic_nns_governance_api::{ListProposalInfoRequest, ProposalInfo};
+use itertools::Itertools;
+use log::{info, warn};
+use serde_json::json;
+use spinners::{Spinner, Spinners};
+use url::Url;
+
+use crate::desktop_notify::DesktopNotifier;
+use crate::{auth::AuthRequirement, exe::ExecutableCommand, exe::args::GlobalArgs};
+
+use super::manage::{format_seconds, parse_topic};
+
+/// The voting power of a neuron starts decaying when it was not refreshed for this long
+const START_REDUCING_VOTING_POWER_AFTER_SECONDS: u64 = 15_778_800;
+/// ... and its following is cleared when the voting power was decaying for this long
+const CLEAR_FOLLOWING_AFTER_SECONDS: u64 = 2_629_800;
+/// Neurons with a lower dissolve delay cannot vote
+const MIN_DISSOLVE_DELAY_FOR_VOTE_SECONDS: u64 = 15_778_800;
+/// Governance keeps this many ballots of a neuron
+const MAX_RECENT_BALLOTS: usize = 100;
+/// Recent proposals checked for missed votes
+const PROPOSALS_CHECKED: u32 = 100;
+/// A webhook that doesn't answer in time must not hold back the next checks
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Check the health of neurons: voting power, dissolve delay, followees and missed votes
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre neuron watch                                      # One check, exits with an error if there are alerts
+    dre neuron watch --interval 1h --webhook <url>        # Check every hour, notify new alerts
+    dre neuron watch --neurons 40 80 --expected-followees followees.yaml
+
+EXPECTED FOLLOWEES FILE:
+    NodeAdmin: [40, 80]
+    IcOsVersionDeployment: [40]
+
+    Topics are named as in the voting rules of `dre vote`. Without this file, `--interval` compares the
+    followees with the ones of the first check.
+"#)]
+pub struct Watch {
+    /// Neurons to check, by default the neurons the identity controls or is a hot key of
+    #[clap(long, num_args(1..))]
+    pub neurons: Vec<u64>,
+
+    /// Check periodically with this interval instead of once, and notify the new alerts
+    #[clap(long, value_parser = parse_duration)]
+    pub interval: Option<Duration>,
+
+    /// Alert when the voting power starts decaying within this duration
+    #[clap(long, default_value = "30days", value_parser = parse_duration)]
+    pub refresh_warning: Duration,
+
+    /// Alert when the dissolve delay is below this duration, by default the minimum to vote
+    #[clap(long, default_value = "182days 15h", value_parser = parse_duration)]
+    pub min_dissolve_delay: Duration,
+
+    /// YAML file with the expected followees per topic
+    #[clap(long)]
+    pub expected_followees: Option<PathBuf>,
+
+    /// Also post the alerts to this webhook, as a JSON object with a `text` field
+    #[clap(long)]
+    pub webhook: Option<Url>,
+}
+
+impl ExecutableCommand for Watch {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Neuron
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        let (neuron, client) = ctx.create_ic_agent_canister_client().await?;
+        let governance = GovernanceCanisterWrapper::from(client);
+        let thresholds = Thresholds {
+            refresh_warning_seconds: self.refresh_warning.as_secs(),
+            min_dissolve_delay_seconds: self.min_dissolve_delay.as_secs(),
+        };
+        let expected_followees = match &self.expected_followees {
+            Some(path) => Some(load_expected_followees(path)?),
+            None => None,
+        };
+
+        let neuron_ids = if self.neurons.is_empty() {
+            let listed = governance
+                .list_neurons()
+                .await?
+                .full_neurons
+                .into_iter()
+                .filter_map(|n| n.id.map(|id| id.id))
+                .collect_vec();
+            if listed.is_empty() { vec![neuron.neuron_id] } else { listed }
+        } else {
+            self.neurons.clone()
+        };
+        info!("Checking neurons {}", neuron_ids.iter().join(", "));
+
+        let notifier = Notifier {
+            webhook: self.webhook.clone(),
+            client: reqwest::ClientBuilder::new().timeout(WEBHOOK_TIMEOUT).build()?,
+        };
+        // Without an expected followees file, the followees of the first check are the expected ones
+        let mut first_followees: BTreeMap<u64, BTreeMap<String, Vec<u64>>> = BTreeMap::new();
+        let mut active: BTreeMap<(u64, &'static str), Alert> = BTreeMap::new();
+        loop {
+            match gather_facts(&governance, &neuron_ids).await {
+                Ok(facts) => {
+                    let mut alerts = BTreeMap::new();
+                    for neuron_facts in &facts {
+                        let expected: &BTreeMap<String, Vec<u64>> = match &expected_followees {
+                            Some(expected_followees) => expected_followees,
+                            None => first_followees
+                                .entry(neuron_facts.neuron_id)
+                                .or_insert_with(|| neuron_facts.followees.clone()),
+                        };
+                        for alert in check_neuron(neuron_facts, expected, &thresholds, Utc::now().timestamp() as u64) {
+                            let key = (alert.neuron_id, alert.kind);
+                            if !active.contains_key(&key) {
+                                notifier.send(&alert).await;
+                            }
+                            alerts.insert(key, alert);
+                        }
+                    }
+                    for (neuron_id, kind) in active.keys().filter(|key| !alerts.contains_key(key)) {
+                        info!("Neuron {}: the {} alert cleared", neuron_id, kind);
+                    }
+                    active = alerts;
+                }
+                // A failed check does not stop the watch, the alerts stay as they were
+                Err(e) if self.interval.is_some() => warn!("Failed to check the neurons: {:?}", e),
+                Err(e) => return Err(e),
+            }
+
+            let Some(interval) = self.interval else {
+                if active.is_empty() {
+                    println!("All {} neurons are healthy", neuron_ids.len());
+                    return Ok(());
+                }
+                anyhow::bail!("{} alerts on neurons {}", active.len(), active.keys().map(|(n, _)| n).unique().join(", "));
+            };
+
+            let mut sp = Spinner::with_timer(
+                Spinners::Dots12,
+                format!("Sleeping {} before the next check...", humantime::format_duration(interval)),
+            );
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {
+                    info!("Received Ctrl-C, exiting...");
+                    sp.stop();
+                    return Ok(());
+                }
+                _ = tokio::time::sleep(interval) => {
+                    sp.stop_with_message("Done sleeping, checking the neurons...".into());
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Severity {
+    Warning,
+    Critical,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Alert {
+    neuron_id: u64,
+    severity: Severity,
+    /// Alerts of the same kind on a neuron are notified once while they last
+    kind: &'static str,
+    message: String,
+}
+
+struct Thresholds {
+    refresh_warning_seconds: u64,
+    min_dissolve_delay_seconds: u64,
+}
+
+/// What the checks look at in a neuron
+#[derive(Clone, Debug, Default)]
+struct NeuronFacts {
+    neuron_id: u64,
+    dissolve_delay_seconds: u64,
+    dissolving: bool,
+    voting_power_refreshed_timestamp_seconds: Option<u64>,
+    deciding_voting_power: Option<u64>,
+    potential_voting_power: Option<u64>,
+    /// Followees per topic name
+    followees: BTreeMap<String, Vec<u64>>,
+    /// Followees which cannot vote for the neuron, with the reason
+    followee_problems: BTreeMap<u64, String>,
+    /// Proposals in followed topics whose voting period ended without a ballot of the neuron
+    missed_votes: Vec<u64>,
+}
+
+fn topic_name(topic: i32) -> String {
+    Topic::try_from(topic).map(|t| format!("{:?}", t)).unwrap_or_else(|_| topic.to_string())
+}
+
+fn load_expected_followees(path: &Path) -> anyhow::Result<BTreeMap<String, Vec<u64>>> {
+    let expected: BTreeMap<String, Vec<u64>> = serde_yaml::from_str(&fs_err::read_to_string(path)?)?;
+    // Validate the topic names and use the canonical ones
+    expected
+        .into_iter()
+        .map(|(topic, followees)| Ok((format!("{:?}", parse_topic(&topic)?), followees)))
+        .collect()
+}
+
+async fn gather_facts(governance: &GovernanceCanisterWrapper, neuron_ids: &[u64]) -> anyhow::Result<Vec<NeuronFacts>> {
+    let proposals = governance
+        .list_proposals(ListProposalInfoRequest {
+            limit: PROPOSALS_CHECKED,
+            ..Default::default()
+        })
+        .await?;
+    let now = Utc::now().timestamp() as u64;
+
+    let mut facts = vec![];
+    let mut followee_problems: BTreeMap<u64, Option<String>> = BTreeMap::new();
+    for neuron_id in neuron_ids {
+        let full_neuron = governance.get_full_neuron(*neuron_id).await?;
+        let neuron_info = governance.get_neuron_info(*neuron_id).await?;
+
+        let followees: BTreeMap<String, Vec<u64>> = full_neuron
+            .followees
+            .iter()
+            .filter(|(_, f)| !f.followees.is_empty())
+            .map(|(topic, f)| (topic_name(*topic), f.followees.iter().map(|n| n.id).collect()))
+            .collect();
+        for followee in followees.values().flatten() {
+            if followee_problems.contains_key(followee) {
+                continue;
+            }
+            let problem = match governance.get_neuron_info(*followee).await {
+                Err(e) => Some(format!("cannot be read: {}", e)),
+                Ok(info) if info.dissolve_delay_seconds < MIN_DISSOLVE_DELAY_FOR_VOTE_SECONDS => Some(format!(
+                    "has a dissolve delay of {}, too low to vote",
+                    format_seconds(info.dissolve_delay_seconds)
+                )),
+                Ok(info)
+                    if info
+                        .deciding_voting_power
+                        .zip(info.potential_voting_power)
+                        .is_some_and(|(deciding, potential)| deciding < potential) =>
+                {
+                    Some("has a decaying voting power".to_string())
+                }
+                Ok(_) => None,
+            };
+            followee_problems.insert(*followee, problem);
+        }
+
+        let followed_topics: BTreeSet<i32> = full_neuron
+            .followees
+            .iter()
+            .filter(|(_, f)| !f.followees.is_empty())
+            .map(|(topic, _)| *topic)
+            .collect();
+        let ballots = full_neuron
+            .recent_ballots
+            .iter()
+            .filter(|b| b.vote != 0)
+            .filter_map(|b| b.proposal_id.map(|p| p.id))
+            .collect_vec();
+
+        facts.push(NeuronFacts {
+            neuron_id: *neuron_id,
+            dissolve_delay_seconds: neuron_info.dissolve_delay_seconds,
+            dissolving: matches!(full_neuron.dissolve_state, Some(DissolveState::WhenDissolvedTimestampSeconds(_))),
+            voting_power_refreshed_timestamp_seconds: full_neuron.voting_power_refreshed_timestamp_seconds,
+            deciding_voting_power: neuron_info.deciding_voting_power,
+            potential_voting_power: neuron_info.potential_voting_power,
+            followee_problems: followees
+                .values()
+                .flatten()
+                .filter_map(|f| followee_problems.get(f).cloned().flatten().map(|p| (*f, p)))
+                .collect(),
+            followees,
+            missed_votes: missed_votes(&followed_topics, &ballots, &proposals, now),
+        });
+    }
+    Ok(facts)
+}
+
+/// Proposals in the followed topics whose voting period ended without a ballot of the neuron.
+/// Only the proposals after the oldest ballot kept by governance are considered.
+fn missed_votes(followed_topics: &BTreeSet<i32>, ballots: &[u64], proposals: &[ProposalInfo], now: u64) -> Vec<u64> {
+    let follows_catch_all = followed_topics.contains(&(Topic::Unspecified as i32));
+    let not_in_catch_all = [Topic::Governance, Topic::SnsAndCommunityFund, Topic::NeuronManagement].map(|t| t as i32);
+    let oldest_ballot = if ballots.len() >= MAX_RECENT_BALLOTS {
+        ballots.iter().min().copied()
+    } else {
+        None
+    };
+
+    proposals
+        .iter()
+        .filter(|p| followed_topics.contains(&p.topic) || (follows_catch_all && !not_in_catch_all.contains(&p.topic)))
+        .filter(|p| p.deadline_timestamp_seconds.is_some_and(|deadline| deadline < now))
+        .filter_map(|p| p.id.map(|id| id.id))
+        .filter(|id| oldest_ballot.is_none_or(|oldest| *id > oldest))
+        .filter(|id| !ballots.contains(id))
+        .sorted()
+        .collect()
+}
+
+fn check_neuron(facts: &NeuronFacts, expected_followees: &BTreeMap<String, Vec<u64>>, thresholds: &Thresholds, now: u64) -> Vec<Alert> {
+    let mut alerts = vec![];
+    let mut alert = |severity: Severity, kind: &'static str, message: String| {
+        alerts.push(Alert {
+            neuron_id: facts.neuron_id,
+            severity,
+            kind,
+            message,
+        })
+    };
+
+    let refreshed_ago = facts.voting_power_refreshed_timestamp_seconds.map(|t| now.saturating_sub(t));
+    let decaying = facts
+        .deciding_voting_power
+        .zip(facts.potential_voting_power)
+        .filter(|(deciding, potential)| deciding < potential);
+    if refreshed_ago.is_some_and(|ago| ago >= START_REDUCING_VOTING_POWER_AFTER_SECONDS + CLEAR_FOLLOWING_AFTER_SECONDS) {
+        alert(
+            Severity::Critical,
+            "voting-power",
+            "voting power fully decayed and following cleared: set the followees and run `dre neuron refresh --voting-power`".to_string(),
+        );
+    } else if refreshed_ago.is_some_and(|ago| ago >= START_REDUCING_VOTING_POWER_AFTER_SECONDS) || decaying.is_some() {
+        let left = decaying
+            .map(|(deciding, potential)| format!(", {:.0}% left", deciding as f64 / potential as f64 * 100.0))
+            .unwrap_or_default();
+        alert(
+            Severity::Critical,
+            "voting-power",
+            format!("voting power decaying{}: run `dre neuron refresh --voting-power`", left),
+        );
+    } else if let Some(ago) = refreshed_ago.filter(|ago| ago + thresholds.refresh_warning_seconds >= START_REDUCING_VOTING_POWER_AFTER_SECONDS) {
+        alert(
+            Severity::Warning,
+            "voting-power",
+            format!(
+                "voting power starts decaying in {}: run `dre neuron refresh --voting-power`",
+                format_seconds(START_REDUCING_VOTING_POWER_AFTER_SECONDS - ago)
+            ),
+        );
+    }
+
+    if facts.dissolve_delay_seconds < thresholds.min_dissolve_delay_seconds {
+        alert(
+            Severity::Critical,
+            "dissolve-delay",
+            format!(
+                "dissolve delay {} below {}",
+                format_seconds(facts.dissolve_delay_seconds),
+                format_seconds(thresholds.min_dissolve_delay_seconds)
+            ),
+        );
+    } else if facts.dissolving {
+        alert(
+            Severity::Warning,
+            "dissolve-delay",
+            format!(
+                "dissolving, the dissolve delay falls below {} in {}: run `dre neuron stop-dissolving`",
+                format_seconds(thresholds.min_dissolve_delay_seconds),
+                format_seconds(facts.dissolve_delay_seconds - thresholds.min_dissolve_delay_seconds)
+            ),
+        );
+    }
+
+    let drift = expected_followees
+        .iter()
+        .filter(|(topic, expected)| {
+            facts.followees.get(*topic).map(|f| f.iter().sorted().collect_vec()) != Some(expected.iter().sorted().collect_vec())
+        })
+        .map(|(topic, expected)| {
+            format!(
+                "{} follows [{}] instead of [{}]",
+                topic,
+                facts.followees.get(topic).map(|f| f.iter().join(", ")).unwrap_or_default(),
+                expected.iter().join(", ")
+            )
+        })
+        .collect_vec();
+    if !drift.is_empty() {
+        alert(Severity::Critical, "followees", format!("followees changed: {}", drift.join("; ")));
+    }
+    if !facts.followee_problems.is_empty() {
+        alert(
+            Severity::Warning,
+            "followee-health",
+            facts
+                .followee_problems
+                .iter()
+                .map(|(followee, problem)| format!("followee {} {}", followee, problem))
+                .join("; "),
+        );
+    }
+
+    if !facts.missed_votes.is_empty() {
+        alert(
+            Severity::Critical,
+            "missed-votes",
+            format!(
+                "missed the vote on {} proposals in followed topics: {}",
+                facts.missed_votes.len(),
+                facts.missed_votes.iter().join(", ")
+            ),
+        );
+    }
+
+    alerts
+}
+
+/// Sends alerts as desktop notifications, and to the webhook if any
+struct Notifier {
+    webhook: Option<Url>,
+    client: reqwest::Client,
+}
+
+impl Notifier {
+    async fn send(&self, alert: &Alert) {
+        let title = format!("DRE neuron watch: neuron {}", alert.neuron_id);
+        match alert.severity {
+            Severity::Critical => DesktopNotifier::send_critical(&title, &alert.message),
+            Severity::Warning => DesktopNotifier::send_info(&title, &alert.message),
+        }
+        if let Some(webhook) = &self.webhook {
+            let text = format!("{:?} {}: {}", alert.severity, title, alert.message);
+            let response = self.client.post(webhook.clone()).json(&json!({ "text": text })).send().await;
+            if let Err(e) = response.and_then(|r| r.error_for_status()) {
+                warn!("Failed to post the alert to the webhook: {:?}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_nns_common::pb::v1::ProposalId;
+
+    const DAY: u64 = 86_400;
+    const NOW: u64 = 1_000 * DAY;
+
+    fn thresholds() -> Thresholds {
+        Thresholds {
+            refresh_warning_seconds: 30 * DAY,
+            min_dissolve_delay_seconds: MIN_DISSOLVE_DELAY_FOR_VOTE_SECONDS,
+        }
+    }
+
+    fn healthy() -> NeuronFacts {
+        NeuronFacts {
+            neuron_id: 40,
+            dissolve_delay_seconds: 8 * 365 * DAY,
+            voting_power_refreshed_timestamp_seconds: Some(NOW - DAY),
+            deciding_voting_power: Some(100),
+            potential_voting_power: Some(100),
+            followees: BTreeMap::from([("NodeAdmin".to_string(), vec![80, 40])]),
+            ..Default::default()
+        }
+    }
+
+    fn kinds(alerts: &[Alert]) -> Vec<(&'static str, Severity)> {
+        alerts.iter().map(|a| (a.kind, a.severity)).collect()
+    }
+
+    #[test]
+    fn healthy_neuron_has_no_alerts() {
+        let expected = BTreeMap::from([("NodeAdmin".to_string(), vec![40, 80])]);
+        assert_eq!(check_neuron(&healthy(), &expected, &thresholds(), NOW), vec![]);
+    }
+
+    #[test]
+    fn voting_power_alerts() {
+        let mut facts = healthy();
+        facts.voting_power_refreshed_timestamp_seconds = Some(NOW - START_REDUCING_VOTING_POWER_AFTER_SECONDS + 10 * DAY);
+        let alerts = check_neuron(&facts, &BTreeMap::new(), &thresholds(), NOW);
+        assert_eq!(kinds(&alerts), vec![("voting-power", Severity::Warning)]);
+        assert_eq!(
+            alerts[0].message,
+            "voting power starts decaying in 10days: run `dre neuron refresh --voting-power`"
+        );
+
+        facts.voting_power_refreshed_timestamp_seconds = Some(NOW - START_REDUCING_VOTING_POWER_AFTER_SECONDS - DAY);
+        facts.deciding_voting_power = Some(75);
+        let alerts = check_neuron(&facts, &BTreeMap::new(), &thresholds(), NOW);
+        assert_eq!(kinds(&alerts), vec![("voting-power", Severity::Critical)]);
+        assert_eq!(
+            alerts[0].message,
+            "voting power decaying, 75% left: run `dre neuron refresh --voting-power`"
+        );
+    }
+
+    #[test]
+    fn dissolve_delay_and_followee_alerts() {
+        let mut facts = healthy();
+        facts.dissolving = true;
+        facts.followee_problems = BTreeMap::from([(80, "has a decaying voting power".to_string())]);
+        let expected = BTreeMap::from([("NodeAdmin".to_string(), vec![40]), ("Governance".to_string(), vec![40])]);
+        let alerts = check_neuron(&facts, &expected, &thresholds(), NOW);
+        assert_eq!(
+            kinds(&alerts),
+            vec![
+                ("dissolve-delay", Severity::Warning),
+                ("followees", Severity::Critical),
+                ("followee-health", Severity::Warning)
+            ]
+        );
+        assert_eq!(
+            alerts[1].message,
+            "followees changed: Governance follows [] instead of [40]; NodeAdmin follows [80, 40] instead of [40]"
+        );
+
+        facts.dissolve_delay_seconds = 30 * DAY;
+        assert_eq!(
+            kinds(&check_neuron(&facts, &BTreeMap::new(), &thresholds(), NOW))[0],
+            ("dissolve-delay", Severity::Critical)
+        );
+    }
+
+    #[test]
+    fn missed_votes_in_followed_topics() {
+        let proposal = |id: u64, topic: Topic, deadline: u64| ProposalInfo {
+            id: Some(ProposalId { id }),
+            topic: topic as i32,
+            deadline_timestamp_seconds: Some(deadline),
+            ..Default::default()
+        };
+        let proposals = vec![
+            proposal(1, Topic::NodeAdmin, NOW - DAY),
+            proposal(2, Topic::NodeAdmin, NOW - DAY),
+            proposal(3, Topic::Governance, NOW - DAY),
+            proposal(4, Topic::SubnetManagement, NOW - DAY),
+            proposal(5, Topic::NodeAdmin, NOW + DAY),
+        ];
+
+        let node_admin = BTreeSet::from([Topic::NodeAdmin as i32]);
+        assert_eq!(missed_votes(&node_admin, &[2], &proposals, NOW), vec![1]);
+
+        let catch_all = BTreeSet::from([Topic::Unspecified as i32]);
+        assert_eq!(missed_votes(&catch_all, &[2], &proposals, NOW), vec![1, 4]);
+
+        let full_ballots = (2..2 + MAX_RECENT_BALLOTS as u64).collect_vec();
+        assert_eq!(missed_votes(&node_admin, &full_ballots, &proposals, NOW), Vec::<u64>::new());
+    }
+}
@@ -16,7 +16,7 @@ use log::{info, warn};
 
 use crate::{auth::AuthRequirement, exe::ExecutableCommand, exe::args::GlobalArgs};
 
-type CLINodeMetrics = BTreeMap<PrincipalId, Vec<NodeMetricsHistoryResponse>>;
+pub(crate) type CLINodeMetrics = BTreeMap<PrincipalId, Vec<NodeMetricsHistoryResponse>>;
 
 #[derive(Args, Debug)]
 pub struct NodeMetrics {
@@ -38,89 +38,99 @@ pub struct NodeMetrics {
     pub subnet_ids: Vec<PrincipalId>,
 }
 
-impl NodeMetrics {
-    async fn get_trustworthy_metrics(&self, canister_agent: ic_canisters::IcAgentCanisterClient) -> anyhow::Result<CLINodeMetrics> {
-        let mut metrics_by_subnet = BTreeMap::new();
-        let wallet: CanisterId = CanisterId::from_str(self.wallet.as_ref().unwrap().as_str())?;
-        let wallet_client = Arc::new(WalletCanisterWrapper::new(canister_agent.agent.clone()));
-
-        let subnets = match &self.subnet_ids.is_empty() {
-            false => self.subnet_ids.clone(),
-            true => {
-                let registry_client = RegistryCanisterWrapper::new(canister_agent.agent, canister_agent.nns_url);
-                registry_client.get_subnets().await?
+/// Trustworthy node metrics history from the management canister, queried through the wallet canister.
+/// The metrics of all subnets are fetched if `subnet_ids` is empty.
+pub(crate) async fn fetch_trustworthy_metrics(
+    canister_agent: ic_canisters::IcAgentCanisterClient,
+    wallet: CanisterId,
+    start_at_timestamp: u64,
+    subnet_ids: Vec<PrincipalId>,
+) -> anyhow::Result<CLINodeMetrics> {
+    let mut metrics_by_subnet = BTreeMap::new();
+    let wallet_client = Arc::new(WalletCanisterWrapper::new(canister_agent.agent.clone()));
+
+    let subnets = match &subnet_ids.is_empty() {
+        false => subnet_ids,
+        true => {
+            let registry_client = RegistryCanisterWrapper::new(canister_agent.agent, canister_agent.nns_url);
+            registry_client.get_subnets().await?
+        }
+    };
+
+    let handles = subnets
+        .into_iter()
+        .map(|s| (s, wallet_client.clone(), start_at_timestamp))
+        .map(|(s, w, start)| {
+            info!("Spawning thread for subnet: {}", s);
+            tokio::spawn(async move { (s, w.get_node_metrics_history(wallet, start, s).await) })
+        });
+
+    for (subnet, maybe_metrics) in futures::future::try_join_all(handles).await? {
+        match maybe_metrics {
+            Result::Ok(m) => {
+                info!("Received metrics for subnet: {}", subnet);
+                metrics_by_subnet.insert(subnet, m);
+            }
+            Err(e) => {
+                warn!("Couldn't fetch trustworthy metrics for subnet {}: {}", subnet, e);
             }
         };
+    }
 
-        let handles = subnets
+    Ok(metrics_by_subnet)
+}
+
+/// Untrusted node metrics history from the node metrics canister.
+/// The metrics of all subnets are fetched if `subnet_ids` is empty.
+pub(crate) async fn fetch_untrusted_metrics(
+    canister_agent: ic_canisters::IcAgentCanisterClient,
+    start_at_timestamp: u64,
+    subnet_ids: Vec<PrincipalId>,
+) -> anyhow::Result<CLINodeMetrics> {
+    let mut metrics_by_subnet = BTreeMap::new();
+    let metrics_client: NodeMetricsCanisterWrapper = canister_agent.into();
+
+    let node_metrics_response = match &subnet_ids.is_empty() {
+        true => metrics_client.get_node_metrics(None, Some(start_at_timestamp)).await?,
+        false => {
+            let subnets = subnet_ids;
+            let metrics_client_ref = &metrics_client;
+
+            futures::future::try_join_all(
+                subnets
+                    .into_iter()
+                    .map(|subnet| async move { metrics_client_ref.get_node_metrics(Some(subnet), Some(start_at_timestamp)).await }),
+            )
+            .await?
             .into_iter()
-            .map(|s| (s, wallet_client.clone(), self.start_at_timestamp))
-            .map(|(s, w, start)| {
-                info!("Spawning thread for subnet: {}", s);
-                tokio::spawn(async move { (s, w.get_node_metrics_history(wallet, start, s).await) })
-            });
-
-        for (subnet, maybe_metrics) in futures::future::try_join_all(handles).await? {
-            match maybe_metrics {
-                Result::Ok(m) => {
-                    info!("Received metrics for subnet: {}", subnet);
-                    metrics_by_subnet.insert(subnet, m);
-                }
-                Err(e) => {
-                    warn!("Couldn't fetch trustworthy metrics for subnet {}: {}", subnet, e);
-                }
-            };
+            .flatten()
+            .collect_vec()
         }
+    };
 
-        Ok(metrics_by_subnet)
-    }
-
-    async fn get_untrusted_metrics(&self, canister_agent: ic_canisters::IcAgentCanisterClient) -> anyhow::Result<CLINodeMetrics> {
-        let mut metrics_by_subnet = BTreeMap::new();
-        let metrics_client: NodeMetricsCanisterWrapper = canister_agent.into();
-
-        let node_metrics_response = match &self.subnet_ids.is_empty() {
-            true => metrics_client.get_node_metrics(None, Some(self.start_at_timestamp)).await?,
-            false => {
-                let subnets = self.subnet_ids.clone();
-                let metrics_client_ref = &metrics_client;
-
-                futures::future::try_join_all(
-                    subnets
-                        .into_iter()
-                        .map(|subnet| async move { metrics_client_ref.get_node_metrics(Some(subnet), Some(self.start_at_timestamp)).await }),
-                )
-                .await?
-                .into_iter()
-                .flatten()
-                .collect_vec()
-            }
-        };
+    for metrics in node_metrics_response {
+        let subnet = PrincipalId::from(metrics.subnet_id);
 
-        for metrics in node_metrics_response {
-            let subnet = PrincipalId::from(metrics.subnet_id);
+        let management_metrics = metrics.node_metrics.into_iter().map(|m| m.into()).collect_vec();
 
-            let management_metrics = metrics.node_metrics.into_iter().map(|m| m.into()).collect_vec();
-
-            let management_metrics_history = NodeMetricsHistoryResponse {
-                timestamp_nanos: metrics.ts,
-                node_metrics: management_metrics,
-            };
+        let management_metrics_history = NodeMetricsHistoryResponse {
+            timestamp_nanos: metrics.ts,
+            node_metrics: management_metrics,
+        };
 
-            match metrics_by_subnet.entry(subnet) {
-                Entry::Occupied(mut entry) => {
-                    let v: &mut Vec<NodeMetricsHistoryResponse> = entry.get_mut();
-                    v.push(management_metrics_history)
-                }
-                Entry::Vacant(entry) => {
-                    entry.insert(vec![management_metrics_history]);
-                }
+        match metrics_by_subnet.entry(subnet) {
+            Entry::Occupied(mut entry) => {
+                let v: &mut Vec<NodeMetricsHistoryResponse> = entry.get_mut();
+                v.push(management_metrics_history)
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(vec![management_metrics_history]);
             }
         }
-        metrics_by_subnet.values_mut().for_each(|f| f.sort_by_key(|k| k.timestamp_nanos));
-
-        Ok(metrics_by_subnet)
     }
+    metrics_by_subnet.values_mut().for_each(|f| f.sort_by_key(|k| k.timestamp_nanos));
+
+    Ok(metrics_by_subnet)
 }
 
 impl ExecutableCommand for NodeMetrics {
@@ -133,9 +143,10 @@ impl ExecutableCommand for NodeMetrics {
         info!("Started action...");
 
         let metrics_by_subnet = if self.trustworthy {
-            self.get_trustworthy_metrics(canister_agent).await
+            let wallet = CanisterId::from_str(self.wallet.as_ref().unwrap().as_str())?;
+            fetch_trustworthy_metrics(canister_agent, wallet, self.start_at_timestamp, self.subnet_ids.clone()).await
         } else {
-            self.get_untrusted_metrics(canister_agent).await
+            fetch_untrusted_metrics(canister_agent, self.start_at_timestamp, self.subnet_ids.clone()).await
         }?;
 
         println!("{}", serde_json::to_string_pretty(&metrics_by_subnet)?);
@@ -0,0 +1,600 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{NaiveDate, Utc};
+use clap::{Args, error::ErrorKind};
+use csv::Writer;
+use ic_base_types::PrincipalId;
+use ic_canisters::node_rewards::NodeRewardsCanisterWrapper;
+use ic_canisters::registry::RegistryCanisterWrapper;
+use ic_management_types::Node;
+use ic_node_rewards_canister_api::{DateUtc, RewardsCalculationAlgorithmVersion};
+use ic_protobuf::registry::node::v1::NodeRewardType;
+use ic_protobuf::registry::node_rewards::v2::NodeRewardsTable;
+use ic_types::CanisterId;
+use itertools::Itertools;
+use log::info;
+use tabled::builder::Builder;
+use tabled::settings::{Alignment, Modify, Style, object::Rows};
+
+use crate::auth::AuthRequirement;
+use crate::commands::node_metrics::{CLINodeMetrics, fetch_trustworthy_metrics, fetch_untrusted_metrics};
+use crate::ctx::DreContext;
+use crate::exe::ExecutableCommand;
+use crate::exe::args::GlobalArgs;
+
+use super::calculator::{NodeDailyInput, NodeDailyRewards, NodeFailureRate, PerformancePenalty, RewardsAlgorithm, daily_metrics, day_start_nanos};
+use super::common::{fetch_nrc_data_for_days, format_date_utc, get_provider_prefix};
+
+/// Rows of the flagged nodes table printed to the console, the CSV has all of them
+const MAX_FLAGGED_ROWS: usize = 50;
+/// Subnet failure rates are compared with this precision
+const FAILURE_RATE_TOLERANCE: f64 = 1e-6;
+/// Registry versions looked back from the latest one when searching the version of the first audited day
+const REGISTRY_VERSIONS_LOOKBACK: u64 = 1000;
+
+/// Recompute the rewards locally from the node metrics and the registry and compare them with the Node Rewards Canister
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre node-rewards audit 2025-03-01 --end-date 2025-03-31
+    dre node-rewards audit 2025-03-01 --wallet <wallet-canister-id> --csv-output audit.csv
+
+  The rewards table, node types, regions and providers of each day come from the last registry version
+  of that day. Nodes of subnets without metrics on a day are reported as unknown rather than flagged.
+"#)]
+pub struct Audit {
+    /// First day to audit, in format YYYY-MM-DD
+    pub start_date: NaiveDate,
+
+    /// Last day to audit, in format YYYY-MM-DD, by default yesterday
+    #[clap(long)]
+    pub end_date: Option<NaiveDate>,
+
+    /// Version of the rewards calculation algorithm, by default the one the canister uses by default
+    #[clap(long)]
+    pub algorithm_version: Option<u32>,
+
+    /// Flag the nodes whose daily rewards differ by more than this amount of XDR permyriad
+    #[clap(long, default_value_t = 1)]
+    pub tolerance_xdr_permyriad: u64,
+
+    /// Wallet canister used to query the trustworthy node metrics from the management canister.
+    /// If not provided, the untrusted metrics of the node metrics canister are used
+    #[clap(long)]
+    pub wallet: Option<String>,
+
+    /// Write the comparison of every node and day to this CSV file
+    #[clap(long)]
+    pub csv_output: Option<PathBuf>,
+}
+
+impl ExecutableCommand for Audit {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Signer
+    }
+
+    fn validate(&self, _args: &GlobalArgs, cmd: &mut clap::Command) {
+        if self.end_date.is_some_and(|end_date| end_date < self.start_date) {
+            cmd.error(ErrorKind::InvalidValue, "The end date must not be before the start date")
+                .exit();
+        }
+    }
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        let algorithm_version = self.algorithm_version.map(|version| RewardsCalculationAlgorithmVersion { version });
+        let algorithm = RewardsAlgorithm::for_version(algorithm_version)?;
+        let end_date = self.end_date.unwrap_or_else(|| Utc::now().date_naive().pred_opt().unwrap());
+
+        let (_, canister_agent) = ctx.create_ic_agent_canister_client().await?;
+        let registry_client = RegistryCanisterWrapper::new(canister_agent.agent.clone(), canister_agent.nns_url.clone());
+        info!("Looking up the registry version of each day...");
+        let registry_versions = registry_versions_of_days(&registry_client, self.start_date, end_date).await?;
+
+        // The metrics of the first day are the difference with the totals of the day before
+        let metrics_start = day_start_nanos(self.start_date.pred_opt().unwrap());
+        info!("Fetching node metrics since {}...", self.start_date.pred_opt().unwrap());
+        let history = match &self.wallet {
+            Some(wallet) => fetch_trustworthy_metrics(canister_agent.clone(), CanisterId::from_str(wallet)?, metrics_start, vec![]).await?,
+            None => fetch_untrusted_metrics(canister_agent.clone(), metrics_start, vec![]).await?,
+        };
+
+        let node_rewards_client: NodeRewardsCanisterWrapper = canister_agent.into();
+        let nrc_data = fetch_nrc_data_for_days(&node_rewards_client, self.start_date, end_date, algorithm_version).await?;
+
+        let mut nrc_nodes: BTreeMap<DateUtc, BTreeMap<PrincipalId, NrcNodeRewards>> = BTreeMap::new();
+        let mut nrc_providers: BTreeMap<PrincipalId, (u64, u64)> = BTreeMap::new();
+        for (provider_id, daily_rewards) in &nrc_data.providers_rewards {
+            for (day, rewards) in daily_rewards {
+                let totals = nrc_providers.entry(*provider_id).or_default();
+                totals.0 += rewards.total_base_rewards_xdr_permyriad.unwrap_or_default();
+                totals.1 += rewards.total_adjusted_rewards_xdr_permyriad.unwrap_or_default();
+                for node in &rewards.daily_nodes_rewards {
+                    let Some(node_id) = node.node_id else { continue };
+                    nrc_nodes.entry(*day).or_default().insert(
+                        node_id,
+                        NrcNodeRewards {
+                            provider_id: *provider_id,
+                            node_reward_type: node.node_reward_type.clone().unwrap_or_default(),
+                            region: node.region.clone().unwrap_or_default(),
+                            performance_multiplier: node.performance_multiplier.unwrap_or_default(),
+                            base_rewards_xdr_permyriad: node.base_rewards_xdr_permyriad.unwrap_or_default(),
+                            adjusted_rewards_xdr_permyriad: node.adjusted_rewards_xdr_permyriad.unwrap_or_default(),
+                        },
+                    );
+                }
+            }
+        }
+        let nrc_subnets: BTreeMap<(DateUtc, PrincipalId), f64> = nrc_data
+            .subnets_failure_rates
+            .iter()
+            .flat_map(|(subnet_id, failure_rates)| failure_rates.iter().map(move |(day, fr)| ((*day, subnet_id.get()), *fr)))
+            .collect();
+
+        let mut comparisons = vec![];
+        let mut subnet_days_disagreeing = 0;
+        let mut subnet_days_unknown = 0;
+        let mut day_registry: Option<DayRegistry> = None;
+        for day in self.start_date.iter_days().take_while(|day| *day <= end_date) {
+            let day_utc = DateUtc::from(day);
+            let version = registry_versions[&day];
+            if day_registry.as_ref().is_none_or(|registry| registry.version != version) {
+                day_registry = Some(DayRegistry::load(&ctx, version).await?);
+            }
+            let registry = day_registry.as_ref().unwrap();
+
+            let metrics = daily_metrics(&history, day);
+            let inputs = registry
+                .nodes
+                .iter()
+                .cloned()
+                .map(|mut node| {
+                    node.metrics = metrics.get(&node.node_id).copied();
+                    node
+                })
+                .collect_vec();
+            let mut local = algorithm.calculate_daily(&registry.rewards_table, &PerformancePenalty::default(), &inputs);
+
+            // Without the metrics of a subnet, its nodes would be taken for unassigned ones,
+            // and the failure rates extrapolated for the other nodes of their providers would be off
+            let subnets_without_metrics = registry
+                .subnets
+                .keys()
+                .filter(|subnet_id| !has_metrics_on(&history, subnet_id, day))
+                .copied()
+                .collect::<BTreeSet<_>>();
+            subnet_days_unknown += subnets_without_metrics.len();
+            let nodes_without_metrics: BTreeSet<PrincipalId> =
+                subnets_without_metrics.iter().flat_map(|s| registry.subnets[s].iter().copied()).collect();
+            let providers_without_metrics: BTreeSet<PrincipalId> = inputs
+                .iter()
+                .filter(|n| nodes_without_metrics.contains(&n.node_id))
+                .map(|n| n.provider_id)
+                .collect();
+
+            for (subnet_id, failure_rate) in &local.subnets_failure_rate {
+                if nrc_subnets
+                    .get(&(day_utc, *subnet_id))
+                    .is_some_and(|nrc_failure_rate| (failure_rate - nrc_failure_rate).abs() > FAILURE_RATE_TOLERANCE)
+                {
+                    subnet_days_disagreeing += 1;
+                }
+            }
+
+            let mut nrc_day = nrc_nodes.remove(&day_utc).unwrap_or_default();
+            let node_ids: BTreeSet<PrincipalId> = local.nodes.keys().chain(nrc_day.keys()).copied().collect();
+            for node_id in node_ids {
+                let input = inputs.iter().find(|n| n.node_id == node_id);
+                comparisons.push(NodeComparison {
+                    day: day_utc,
+                    node_id,
+                    metrics_unknown: nodes_without_metrics.contains(&node_id)
+                        || input.is_some_and(|n| n.metrics.is_none() && providers_without_metrics.contains(&n.provider_id)),
+                    local_registry: input.map(|n| (n.node_reward_type.clone(), n.region.clone())),
+                    local: local.nodes.remove(&node_id),
+                    nrc: nrc_day.remove(&node_id),
+                });
+            }
+        }
+
+        let tolerance = self.tolerance_xdr_permyriad as f64;
+        println!(
+            "\n=== NODE REWARDS AUDIT: algorithm version {}, {} to {} ===",
+            algorithm.version(),
+            self.start_date,
+            end_date
+        );
+        println!(
+            "Node metrics from the {}",
+            if self.wallet.is_some() {
+                "management canister (trustworthy)"
+            } else {
+                "node metrics canister"
+            }
+        );
+        println!("Unit: XDRPermyriad | Tolerance: {} per node and day", self.tolerance_xdr_permyriad);
+
+        print_providers_table(&comparisons, &nrc_providers, tolerance);
+        let flagged = comparisons.iter().filter_map(|c| c.disagreement(tolerance).map(|d| (c, d))).collect_vec();
+        print_flagged_nodes_table(&flagged);
+
+        if let Some(path) = &self.csv_output {
+            write_csv(path, &comparisons, tolerance)?;
+            info!("Wrote the comparison of every node and day to {}", path.display());
+        }
+
+        println!("\n=== SUMMARY ===");
+        println!("{} of {} node-days disagree beyond the tolerance", flagged.len(), comparisons.len());
+        println!("{} subnet-days have a different subnet failure rate", subnet_days_disagreeing);
+        if subnet_days_unknown > 0 {
+            println!(
+                "{} subnet-days have no node metrics, the {} node-days depending on them are unknown and not flagged",
+                subnet_days_unknown,
+                comparisons.iter().filter(|c| c.metrics_unknown).count()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Inputs of the rewards of a day, from the registry version of that day
+struct DayRegistry {
+    version: u64,
+    rewards_table: NodeRewardsTable,
+    nodes: Vec<NodeDailyInput>,
+    /// Members of each subnet
+    subnets: BTreeMap<PrincipalId, Vec<PrincipalId>>,
+}
+
+impl DayRegistry {
+    async fn load(ctx: &DreContext, version: u64) -> anyhow::Result<Self> {
+        info!("Loading the registry at version {}...", version);
+        let registry = ctx.registry_at_version(version).await?;
+        let rewards_table = registry
+            .get_node_rewards_table()?
+            .into_values()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No node rewards table found in the registry at version {}", version))?;
+
+        Ok(Self {
+            version,
+            rewards_table,
+            nodes: rewardable_nodes(registry.nodes().await?.values()),
+            subnets: registry
+                .subnets()
+                .await?
+                .values()
+                .map(|subnet| (subnet.principal, subnet.nodes.iter().map(|n| n.principal).collect()))
+                .collect(),
+        })
+    }
+}
+
+/// Registry version the rewards of each day are computed with, which is the last version of the day.
+async fn registry_versions_of_days(
+    registry_client: &RegistryCanisterWrapper,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> anyhow::Result<BTreeMap<NaiveDate, u64>> {
+    let first_day_end = day_start_nanos(start_date.succ_opt().unwrap());
+    let latest = registry_client.get_latest_version().await?;
+
+    // Look back further and further until a version of the first day or before is found
+    let mut lookback = REGISTRY_VERSIONS_LOOKBACK;
+    let since = loop {
+        let since = latest.saturating_sub(lookback);
+        let page = registry_client.get_version_timestamps_page(since).await?;
+        if page.values().any(|timestamp| *timestamp > 0 && *timestamp < first_day_end) {
+            break since;
+        }
+        if since == 0 {
+            anyhow::bail!("The registry has no timestamped version before the end of {}", start_date);
+        }
+        lookback *= 4;
+    };
+
+    let timestamps = registry_client.get_version_timestamps_since(since).await?;
+    Ok(versions_of_days(&timestamps, start_date, end_date))
+}
+
+/// Last version of each day, from the timestamps in nanoseconds of the registry versions
+fn versions_of_days(timestamps: &BTreeMap<u64, u64>, start_date: NaiveDate, end_date: NaiveDate) -> BTreeMap<NaiveDate, u64> {
+    start_date
+        .iter_days()
+        .take_while(|day| *day <= end_date)
+        .filter_map(|day| {
+            let day_end = day_start_nanos(day.succ_opt().unwrap());
+            timestamps
+                .iter()
+                .rev()
+                .find(|(_, timestamp)| **timestamp < day_end)
+                .map(|(version, _)| (day, *version))
+        })
+        .collect()
+}
+
+/// Whether the history has a snapshot of the subnet on `day`, from which its daily metrics are computed
+fn has_metrics_on(history: &CLINodeMetrics, subnet_id: &PrincipalId, day: NaiveDate) -> bool {
+    let day_range = day_start_nanos(day)..day_start_nanos(day.succ_opt().unwrap());
+    history
+        .get(subnet_id)
+        .is_some_and(|snapshots| snapshots.iter().any(|s| day_range.contains(&s.timestamp_nanos)))
+}
+
+/// Nodes which have a reward type and a data center in the registry, without metrics
+fn rewardable_nodes<'a>(nodes: impl IntoIterator<Item = &'a Node>) -> Vec<NodeDailyInput> {
+    nodes
+        .into_iter()
+        .filter_map(|node| {
+            let node_reward_type = match node.node_reward_type {
+                Some(NodeRewardType::Unspecified) | None => return None,
+                Some(t) => t.to_string(),
+            };
+            let dc = node.operator.datacenter.as_ref()?;
+            Some(NodeDailyInput {
+                node_id: node.principal,
+                provider_id: node.operator.provider.principal,
+                node_reward_type,
+                region: format!("{},{},{}", dc.continent, dc.country, dc.area),
+                metrics: None,
+            })
+        })
+        .collect()
+}
+
+struct NrcNodeRewards {
+    provider_id: PrincipalId,
+    node_reward_type: String,
+    region: String,
+    performance_multiplier: f64,
+    base_rewards_xdr_permyriad: u64,
+    adjusted_rewards_xdr_permyriad: u64,
+}
+
+/// Rewards of a node on one day, as computed locally and by the canister
+struct NodeComparison {
+    day: DateUtc,
+    node_id: PrincipalId,
+    /// The metrics the local rewards depend on are missing, so both sides can't be compared
+    metrics_unknown: bool,
+    /// Reward type and region of the node in the registry of the day
+    local_registry: Option<(String, String)>,
+    local: Option<NodeDailyRewards>,
+    nrc: Option<NrcNodeRewards>,
+}
+
+impl NodeComparison {
+    fn provider_id(&self) -> PrincipalId {
+        match (&self.local, &self.nrc) {
+            (Some(local), _) => local.provider_id,
+            (None, Some(nrc)) => nrc.provider_id,
+            (None, None) => unreachable!("a node is compared if at least one side rewarded it"),
+        }
+    }
+
+    /// Why both sides disagree beyond the tolerance, if they do
+    fn disagreement(&self, tolerance: f64) -> Option<String> {
+        if self.metrics_unknown {
+            return None;
+        }
+        let (local, nrc) = match (&self.local, &self.nrc) {
+            (Some(_), None) => return Some("not rewarded by the canister".to_string()),
+            (None, Some(_)) => return Some("not rewardable in the local registry".to_string()),
+            (Some(local), Some(nrc)) => (local, nrc),
+            (None, None) => return None,
+        };
+        let mut reasons = vec![];
+        let base_diff = local.base_rewards_xdr_permyriad - nrc.base_rewards_xdr_permyriad as f64;
+        if base_diff.abs() > tolerance {
+            reasons.push(format!("base differs by {:.0}", base_diff));
+        }
+        let adjusted_diff = local.adjusted_rewards_xdr_permyriad - nrc.adjusted_rewards_xdr_permyriad as f64;
+        if adjusted_diff.abs() > tolerance {
+            reasons.push(format!("adjusted differs by {:.0}", adjusted_diff));
+        }
+        if reasons.is_empty() {
+            return None;
+        }
+        // Registry changes during the audited days are the usual suspect
+        if self
+            .local_registry
+            .as_ref()
+            .is_some_and(|(node_reward_type, region)| *node_reward_type != nrc.node_reward_type || *region != nrc.region)
+        {
+            reasons.push(format!("canister used {} in {}", nrc.node_reward_type, nrc.region));
+        }
+        Some(reasons.join(", "))
+    }
+}
+
+fn print_providers_table(comparisons: &[NodeComparison], nrc_providers: &BTreeMap<PrincipalId, (u64, u64)>, tolerance: f64) {
+    let mut local_providers: BTreeMap<PrincipalId, (f64, f64, BTreeSet<PrincipalId>)> = BTreeMap::new();
+    for comparison in comparisons {
+        let totals = local_providers.entry(comparison.provider_id()).or_default();
+        if let Some(local) = &comparison.local {
+            totals.0 += local.base_rewards_xdr_permyriad;
+            totals.1 += local.adjusted_rewards_xdr_permyriad;
+        }
+        if comparison.disagreement(tolerance).is_some() {
+            totals.2.insert(comparison.node_id);
+        }
+    }
+
+    let rows = local_providers
+        .keys()
+        .chain(nrc_providers.keys())
+        .unique()
+        .map(|provider_id| {
+            let (local_base, local_adjusted, flagged) = local_providers.get(provider_id).cloned().unwrap_or_default();
+            let (nrc_base, nrc_adjusted) = nrc_providers.get(provider_id).copied().unwrap_or_default();
+            let adjusted_diff = local_adjusted.round() as i64 - nrc_adjusted as i64;
+            let base_diff = local_base.round() as i64 - nrc_base as i64;
+            (
+                adjusted_diff.abs(),
+                vec![
+                    get_provider_prefix(&provider_id.to_string()),
+                    format!("{:.0}", local_adjusted),
+                    nrc_adjusted.to_string(),
+                    adjusted_diff.to_string(),
+                    format!("{:.0}", local_base),
+                    nrc_base.to_string(),
+                    base_diff.to_string(),
+                    flagged.len().to_string(),
+                ],
+            )
+        })
+        .sorted_by(|a, b| b.0.cmp(&a.0))
+        .map(|(_, row)| row)
+        .collect_vec();
+
+    let mut builder = Builder::default();
+    builder.push_record([
+        "Provider",
+        "Local Adjusted",
+        "NRC Adjusted",
+        "Adjusted Diff",
+        "Local Base",
+        "NRC Base",
+        "Base Diff",
+        "Flagged Nodes",
+    ]);
+    for row in rows {
+        builder.push_record(row);
+    }
+    let mut table = builder.build();
+    table.with(Style::modern()).with(Modify::new(Rows::new(0..1)).with(Alignment::center()));
+
+    println!("\n=== PROVIDERS: LOCAL vs NRC ===");
+    println!("Sorted by decreasing |Adjusted Diff|, Flagged Nodes counts the nodes that disagree on at least one day");
+    println!("{}", table);
+}
+
+fn print_flagged_nodes_table(flagged: &[(&NodeComparison, String)]) {
+    if flagged.is_empty() {
+        println!("\nAll nodes agree within the tolerance");
+        return;
+    }
+
+    let mut builder = Builder::default();
+    builder.push_record([
+        "Day",
+        "Node",
+        "Provider",
+        "Local Base",
+        "NRC Base",
+        "Local Adjusted",
+        "NRC Adjusted",
+        "Local Mult",
+        "NRC Mult",
+        "Disagreement",
+    ]);
+    for (comparison, disagreement) in flagged.iter().take(MAX_FLAGGED_ROWS) {
+        let local = comparison.local.as_ref();
+        let nrc = comparison.nrc.as_ref();
+        builder.push_record([
+            format_date_utc(comparison.day),
+            get_provider_prefix(&comparison.node_id.to_string()),
+            get_provider_prefix(&comparison.provider_id().to_string()),
+            local.map(|l| format!("{:.0}", l.base_rewards_xdr_permyriad)).unwrap_or_default(),
+            nrc.map(|n| n.base_rewards_xdr_permyriad.to_string()).unwrap_or_default(),
+            local.map(|l| format!("{:.0}", l.adjusted_rewards_xdr_permyriad)).unwrap_or_default(),
+            nrc.map(|n| n.adjusted_rewards_xdr_permyriad.to_string()).unwrap_or_default(),
+            local.map(|l| format!("{:.4}", l.performance_multiplier)).unwrap_or_default(),
+            nrc.map(|n| format!("{:.4}", n.performance_multiplier)).unwrap_or_default(),
+            disagreement.clone(),
+        ]);
+    }
+    let mut table = builder.build();
+    table.with(Style::modern()).with(Modify::new(Rows::new(0..1)).with(Alignment::center()));
+
+    println!("\n=== NODES DISAGREEING BEYOND THE TOLERANCE ===");
+    println!("{}", table);
+    if flagged.len() > MAX_FLAGGED_ROWS {
+        println!("... and {} more, use --csv-output to get all of them", flagged.len() - MAX_FLAGGED_ROWS);
+    }
+}
+
+fn write_csv(path: &Path, comparisons: &[NodeComparison], tolerance: f64) -> anyhow::Result<()> {
+    let mut wtr = Writer::from_path(path)?;
+    wtr.write_record([
+        "day_utc",
+        "node_id",
+        "provider_id",
+        "local_node_reward_type",
+        "local_region",
+        "nrc_node_reward_type",
+        "nrc_region",
+        "subnet_assigned",
+        "subnet_assigned_failure_rate",
+        "original_failure_rate",
+        "local_effective_failure_rate",
+        "local_performance_multiplier",
+        "nrc_performance_multiplier",
+        "local_base_rewards_xdr_permyriad",
+        "nrc_base_rewards_xdr_permyriad",
+        "local_adjusted_rewards_xdr_permyriad",
+        "nrc_adjusted_rewards_xdr_permyriad",
+        "metrics_unknown",
+        "disagreement",
+    ])?;
+
+    for comparison in comparisons {
+        let local = comparison.local.as_ref();
+        let nrc = comparison.nrc.as_ref();
+        let (subnet_assigned, subnet_failure_rate, original_failure_rate) = match local.map(|l| &l.failure_rate) {
+            Some(NodeFailureRate::Assigned {
+                subnet_id,
+                subnet_failure_rate,
+                original_failure_rate,
+                ..
+            }) => (subnet_id.to_string(), subnet_failure_rate.to_string(), original_failure_rate.to_string()),
+            _ => Default::default(),
+        };
+        let (local_node_reward_type, local_region) = comparison.local_registry.clone().unwrap_or_default();
+        wtr.write_record([
+            format_date_utc(comparison.day),
+            comparison.node_id.to_string(),
+            comparison.provider_id().to_string(),
+            local_node_reward_type,
+            local_region,
+            nrc.map(|n| n.node_reward_type.clone()).unwrap_or_default(),
+            nrc.map(|n| n.region.clone()).unwrap_or_default(),
+            subnet_assigned,
+            subnet_failure_rate,
+            original_failure_rate,
+            local.map(|l| l.failure_rate.effective().to_string()).unwrap_or_default(),
+            local.map(|l| l.performance_multiplier.to_string()).unwrap_or_default(),
+            nrc.map(|n| n.performance_multiplier.to_string()).unwrap_or_default(),
+            local.map(|l| l.base_rewards_xdr_permyriad.to_string()).unwrap_or_default(),
+            nrc.map(|n| n.base_rewards_xdr_permyriad.to_string()).unwrap_or_default(),
+            local.map(|l| l.adjusted_rewards_xdr_permyriad.to_string()).unwrap_or_default(),
+            nrc.map(|n| n.adjusted_rewards_xdr_permyriad.to_string()).unwrap_or_default(),
+            comparison.metrics_unknown.to_string(),
+            comparison.disagreement(tolerance).unwrap_or_default(),
+        ])?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_day_uses_its_last_registry_version() {
+        let day = |d: u32| NaiveDate::from_ymd_opt(2025, 3, d).unwrap();
+        let timestamps = BTreeMap::from([
+            (10, day_start_nanos(day(1)) + 5),
+            (11, day_start_nanos(day(2)) + 5),
+            (12, day_start_nanos(day(2)) + 10),
+            (13, day_start_nanos(day(5))),
+        ]);
+
+        assert_eq!(
+            versions_of_days(&timestamps, day(1), day(5)),
+            BTreeMap::from([(day(1), 10), (day(2), 12), (day(3), 12), (day(4), 12), (day(5), 13)])
+        );
+    }
+}
@@ -0,0 +1,473 @@
+//! Local implementation of the reward algorithms of the Node Rewards Canister.
+//!
+//! The canister is the source of truth, this implementation only exists to audit its results
+//! from the raw node metrics and the registry.
+
+use std::collections::{BTreeMap, btree_map::Entry};
+
+use anyhow::bail;
+use chrono::NaiveDate;
+use ic_base_types::PrincipalId;
+use ic_canisters::management::NodeMetricsHistoryResponse;
+use ic_node_rewards_canister_api::RewardsCalculationAlgorithmVersion;
+use ic_protobuf::registry::node_rewards::v2::{NodeRewardRate, NodeRewardsTable};
+use itertools::Itertools;
+
+/// The rewards table holds monthly rates, the canister divides them by the average month length
+const REWARDS_TABLE_DAYS: f64 = 30.4375;
+/// The failure rate of a subnet is the failure rate of its nodes at this percentile
+const SUBNET_FAILURE_RATE_PERCENTILE: f64 = 0.75;
+const MIN_FAILURE_RATE: f64 = 0.1;
+const MAX_FAILURE_RATE: f64 = 0.6;
+const MAX_REWARDS_REDUCTION: f64 = 0.8;
+/// Coefficient of type3 nodes if the rewards table doesn't provide one
+const DEFAULT_TYPE3_COEFFICIENT_PERCENT: i32 = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RewardsAlgorithm {
+    PerformanceBasedV1,
+}
+
+impl RewardsAlgorithm {
+    /// The algorithm for the version the canister is asked for, `None` standing for its default one.
+    pub fn for_version(version: Option<RewardsCalculationAlgorithmVersion>) -> anyhow::Result<Self> {
+        match version.map(|v| v.version) {
+            None | Some(1) => Ok(Self::PerformanceBasedV1),
+            Some(v) => bail!("Rewards calculation algorithm version {} is not implemented locally", v),
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        match self {
+            Self::PerformanceBasedV1 => 1,
+        }
+    }
+
+    /// Rewards of one day for the provided nodes, which must be all the rewardable nodes of that day
+    /// since the failure rates of subnets and providers depend on each other.
+    pub fn calculate_daily(&self, rewards_table: &NodeRewardsTable, penalty: &PerformancePenalty, nodes: &[NodeDailyInput]) -> DailyRewards {
+        match self {
+            Self::PerformanceBasedV1 => performance_based_v1(rewards_table, penalty, nodes),
+        }
+    }
+
+    /// Daily base rewards of each node, before the performance penalty
+    pub fn base_rewards(&self, rewards_table: &NodeRewardsTable, nodes: &[NodeDailyInput]) -> BTreeMap<PrincipalId, f64> {
+        match self {
+            Self::PerformanceBasedV1 => base_rewards_v1(rewards_table, nodes),
+        }
+    }
+}
+
+/// How the failure rate of a node reduces its rewards
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerformancePenalty {
+    /// Nodes with a lower failure rate get the full rewards
+    pub min_failure_rate: f64,
+    /// Nodes with a higher failure rate get the maximum reduction
+    pub max_failure_rate: f64,
+    pub max_rewards_reduction: f64,
+}
+
+impl Default for PerformancePenalty {
+    fn default() -> Self {
+        Self {
+            min_failure_rate: MIN_FAILURE_RATE,
+            max_failure_rate: MAX_FAILURE_RATE,
+            max_rewards_reduction: MAX_REWARDS_REDUCTION,
+        }
+    }
+}
+
+impl PerformancePenalty {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(0.0..=1.0).contains(&self.min_failure_rate) || !(0.0..=1.0).contains(&self.max_failure_rate) {
+            bail!("Failure rates must be between 0 and 1");
+        }
+        if self.min_failure_rate >= self.max_failure_rate {
+            bail!(
+                "The minimum failure rate {} must be lower than the maximum failure rate {}",
+                self.min_failure_rate,
+                self.max_failure_rate
+            );
+        }
+        if !(0.0..=1.0).contains(&self.max_rewards_reduction) {
+            bail!("The maximum rewards reduction must be between 0 and 1");
+        }
+        Ok(())
+    }
+
+    /// Share of the base rewards a node with the failure rate gets, reduced linearly between the thresholds
+    pub fn multiplier(&self, failure_rate: f64) -> f64 {
+        let reduction = if failure_rate < self.min_failure_rate {
+            0.0
+        } else if failure_rate > self.max_failure_rate {
+            self.max_rewards_reduction
+        } else {
+            (failure_rate - self.min_failure_rate) / (self.max_failure_rate - self.min_failure_rate) * self.max_rewards_reduction
+        };
+        1.0 - reduction
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeDailyInput {
+    pub node_id: PrincipalId,
+    pub provider_id: PrincipalId,
+    pub node_reward_type: String,
+    /// `<continent>,<country>,<area>` as in the data center record
+    pub region: String,
+    /// `None` if the node was not a subnet member on that day
+    pub metrics: Option<NodeDailyMetrics>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NodeDailyMetrics {
+    pub subnet_id: PrincipalId,
+    pub num_blocks_proposed: u64,
+    pub num_blocks_failed: u64,
+}
+
+impl NodeDailyMetrics {
+    fn total_blocks(&self) -> u64 {
+        self.num_blocks_proposed + self.num_blocks_failed
+    }
+
+    pub fn original_failure_rate(&self) -> f64 {
+        match self.total_blocks() {
+            0 => 0.0,
+            total => self.num_blocks_failed as f64 / total as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeFailureRate {
+    Assigned {
+        subnet_id: PrincipalId,
+        subnet_failure_rate: f64,
+        original_failure_rate: f64,
+        relative_failure_rate: f64,
+    },
+    /// Unassigned nodes get the average relative failure rate of the assigned nodes of their provider
+    Unassigned { extrapolated_failure_rate: f64 },
+}
+
+impl NodeFailureRate {
+    /// The failure rate that determines the rewards reduction
+    pub fn effective(&self) -> f64 {
+        match self {
+            Self::Assigned { relative_failure_rate, .. } => *relative_failure_rate,
+            Self::Unassigned { extrapolated_failure_rate } => *extrapolated_failure_rate,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeDailyRewards {
+    pub provider_id: PrincipalId,
+    pub failure_rate: NodeFailureRate,
+    pub performance_multiplier: f64,
+    pub base_rewards_xdr_permyriad: f64,
+    pub adjusted_rewards_xdr_permyriad: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DailyRewards {
+    pub subnets_failure_rate: BTreeMap<PrincipalId, f64>,
+    pub nodes: BTreeMap<PrincipalId, NodeDailyRewards>,
+}
+
+fn performance_based_v1(rewards_table: &NodeRewardsTable, penalty: &PerformancePenalty, nodes: &[NodeDailyInput]) -> DailyRewards {
+    let subnets_failure_rate: BTreeMap<PrincipalId, f64> = nodes
+        .iter()
+        .filter_map(|n| n.metrics.as_ref())
+        .map(|m| (m.subnet_id, m.original_failure_rate()))
+        .into_group_map()
+        .into_iter()
+        .map(|(subnet_id, failure_rates)| (subnet_id, percentile(failure_rates, SUBNET_FAILURE_RATE_PERCENTILE)))
+        .collect();
+
+    let assigned_failure_rates: BTreeMap<PrincipalId, NodeFailureRate> = nodes
+        .iter()
+        .filter_map(|n| {
+            let metrics = n.metrics.as_ref()?;
+            let subnet_failure_rate = subnets_failure_rate[&metrics.subnet_id];
+            let original_failure_rate = metrics.original_failure_rate();
+            Some((
+                n.node_id,
+                NodeFailureRate::Assigned {
+                    subnet_id: metrics.subnet_id,
+                    subnet_failure_rate,
+                    original_failure_rate,
+                    relative_failure_rate: (original_failure_rate - subnet_failure_rate).max(0.0),
+                },
+            ))
+        })
+        .collect();
+
+    let extrapolated_failure_rates: BTreeMap<PrincipalId, f64> = nodes
+        .iter()
+        .filter_map(|n| assigned_failure_rates.get(&n.node_id).map(|fr| (n.provider_id, fr.effective())))
+        .into_group_map()
+        .into_iter()
+        .map(|(provider_id, failure_rates)| (provider_id, failure_rates.iter().sum::<f64>() / failure_rates.len() as f64))
+        .collect();
+
+    let base_rewards = base_rewards_v1(rewards_table, nodes);
+
+    let nodes = nodes
+        .iter()
+        .map(|n| {
+            let failure_rate = assigned_failure_rates.get(&n.node_id).cloned().unwrap_or(NodeFailureRate::Unassigned {
+                extrapolated_failure_rate: extrapolated_failure_rates.get(&n.provider_id).copied().unwrap_or_default(),
+            });
+            let performance_multiplier = penalty.multiplier(failure_rate.effective());
+            let base_rewards_xdr_permyriad = base_rewards[&n.node_id];
+            (
+                n.node_id,
+                NodeDailyRewards {
+                    provider_id: n.provider_id,
+                    failure_rate,
+                    performance_multiplier,
+                    base_rewards_xdr_permyriad,
+                    adjusted_rewards_xdr_permyriad: base_rewards_xdr_permyriad * performance_multiplier,
+                },
+            )
+        })
+        .collect();
+
+    DailyRewards { subnets_failure_rate, nodes }
+}
+
+/// Daily base rewards of each node. The rewards of type3 nodes decrease with every additional
+/// node a provider has in the same country.
+fn base_rewards_v1(rewards_table: &NodeRewardsTable, nodes: &[NodeDailyInput]) -> BTreeMap<PrincipalId, f64> {
+    let rate = |n: &NodeDailyInput| {
+        rewards_table.get_rate(&n.region, &n.node_reward_type).unwrap_or(NodeRewardRate {
+            xdr_permyriad_per_node_per_month: 1,
+            reward_coefficient_percent: Some(100),
+        })
+    };
+    let (type3, others): (Vec<_>, Vec<_>) = nodes.iter().partition(|n| n.node_reward_type.starts_with("type3"));
+
+    let mut rewards: BTreeMap<PrincipalId, f64> = others
+        .into_iter()
+        .map(|n| (n.node_id, rate(n).xdr_permyriad_per_node_per_month as f64 / REWARDS_TABLE_DAYS))
+        .collect();
+
+    for (_, group) in type3
+        .into_iter()
+        .into_group_map_by(|n| (n.provider_id, n.region.splitn(3, ',').take(2).join(",")))
+    {
+        let rates = group.iter().map(|n| rate(n)).collect_vec();
+        let count = rates.len() as f64;
+        let avg_rate = rates.iter().map(|r| r.xdr_permyriad_per_node_per_month as f64).sum::<f64>() / count;
+        let avg_coefficient = rates
+            .iter()
+            .map(|r| r.reward_coefficient_percent.unwrap_or(DEFAULT_TYPE3_COEFFICIENT_PERCENT) as f64 / 100.0)
+            .sum::<f64>()
+            / count;
+        let total: f64 = (0..rates.len()).map(|i| avg_rate * avg_coefficient.powi(i as i32)).sum();
+        for n in group {
+            rewards.insert(n.node_id, total / count / REWARDS_TABLE_DAYS);
+        }
+    }
+    rewards
+}
+
+fn percentile(mut values: Vec<f64>, percentile: f64) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let index = ((values.len() as f64 * percentile).ceil() as usize).saturating_sub(1);
+    values[index]
+}
+
+pub fn day_start_nanos(day: NaiveDate) -> u64 {
+    day.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp_nanos_opt().unwrap() as u64
+}
+
+/// Blocks each node proposed and failed on `day`, from the cumulative totals in the metrics history of each subnet.
+///
+/// A snapshot counts for the UTC day of its timestamp. If a node shows up in several subnets on the same day,
+/// the subnet where it handled the most blocks wins.
+pub fn daily_metrics(history: &BTreeMap<PrincipalId, Vec<NodeMetricsHistoryResponse>>, day: NaiveDate) -> BTreeMap<PrincipalId, NodeDailyMetrics> {
+    let day_start = day_start_nanos(day);
+    let day_end = day_start_nanos(day.succ_opt().unwrap());
+    let mut metrics: BTreeMap<PrincipalId, NodeDailyMetrics> = BTreeMap::new();
+
+    for (subnet_id, snapshots) in history {
+        let Some(current) = snapshots
+            .iter()
+            .filter(|s| (day_start..day_end).contains(&s.timestamp_nanos))
+            .max_by_key(|s| s.timestamp_nanos)
+        else {
+            continue;
+        };
+        let previous: BTreeMap<PrincipalId, (u64, u64)> = snapshots
+            .iter()
+            .filter(|s| s.timestamp_nanos < day_start)
+            .max_by_key(|s| s.timestamp_nanos)
+            .map(|s| {
+                s.node_metrics
+                    .iter()
+                    .map(|m| (m.node_id, (m.num_blocks_proposed_total, m.num_block_failures_total)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for m in &current.node_metrics {
+            let (proposed_before, failed_before) = previous.get(&m.node_id).copied().unwrap_or_default();
+            let daily = NodeDailyMetrics {
+                subnet_id: *subnet_id,
+                num_blocks_proposed: m.num_blocks_proposed_total.saturating_sub(proposed_before),
+                num_blocks_failed: m.num_block_failures_total.saturating_sub(failed_before),
+            };
+            match metrics.entry(m.node_id) {
+                Entry::Vacant(entry) => {
+                    entry.insert(daily);
+                }
+                Entry::Occupied(mut entry) => {
+                    if daily.total_blocks() > entry.get().total_blocks() {
+                        entry.insert(daily);
+                    }
+                }
+            }
+        }
+    }
+    metrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_canisters::management::NodeMetrics;
+    use ic_protobuf::registry::node_rewards::v2::NodeRewardRates;
+
+    fn rewards_table() -> NodeRewardsTable {
+        let rates = |rates: &[(&str, u64, Option<i32>)]| NodeRewardRates {
+            rates: rates
+                .iter()
+                .map(|(node_type, rate, coefficient)| {
+                    (
+                        node_type.to_string(),
+                        NodeRewardRate {
+                            xdr_permyriad_per_node_per_month: *rate,
+                            reward_coefficient_percent: *coefficient,
+                        },
+                    )
+                })
+                .collect(),
+        };
+        NodeRewardsTable {
+            table: BTreeMap::from([
+                ("Europe".to_string(), rates(&[("type1", 304375, None)])),
+                ("North America,US".to_string(), rates(&[("type3.1", 1000000, Some(90))])),
+            ]),
+        }
+    }
+
+    fn node(number: u64, provider: u64, node_reward_type: &str, region: &str, metrics: Option<(u64, u64, u64)>) -> NodeDailyInput {
+        NodeDailyInput {
+            node_id: PrincipalId::new_node_test_id(number),
+            provider_id: PrincipalId::new_user_test_id(provider),
+            node_reward_type: node_reward_type.to_string(),
+            region: region.to_string(),
+            metrics: metrics.map(|(subnet, proposed, failed)| NodeDailyMetrics {
+                subnet_id: PrincipalId::new_subnet_test_id(subnet),
+                num_blocks_proposed: proposed,
+                num_blocks_failed: failed,
+            }),
+        }
+    }
+
+    #[test]
+    fn unknown_algorithm_versions_are_rejected() {
+        assert_eq!(RewardsAlgorithm::for_version(None).unwrap(), RewardsAlgorithm::PerformanceBasedV1);
+        assert!(RewardsAlgorithm::for_version(Some(RewardsCalculationAlgorithmVersion { version: 2 })).is_err());
+    }
+
+    #[test]
+    fn rewards_reduction_is_linear_between_thresholds() {
+        let penalty = PerformancePenalty::default();
+        assert_eq!(penalty.multiplier(0.05), 1.0);
+        assert!((penalty.multiplier(0.35) - 0.6).abs() < 1e-9);
+        assert!((penalty.multiplier(0.9) - 0.2).abs() < 1e-9);
+
+        let invalid = PerformancePenalty {
+            min_failure_rate: 0.5,
+            max_failure_rate: 0.3,
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn performance_based_v1_rewards() {
+        let nodes = [
+            // Subnet failure rate is the 75th percentile: 0.1
+            node(1, 1, "type1", "Europe,Switzerland,Zurich", Some((1, 50, 50))),
+            node(2, 1, "type1", "Europe,Switzerland,Zurich", Some((1, 90, 10))),
+            node(3, 2, "type1", "Europe,Germany,Berlin", Some((1, 100, 0))),
+            node(4, 2, "type1", "Europe,Germany,Berlin", Some((1, 100, 0))),
+            node(5, 1, "type1", "Europe,Switzerland,Zurich", None),
+        ];
+        let rewards = RewardsAlgorithm::PerformanceBasedV1.calculate_daily(&rewards_table(), &PerformancePenalty::default(), &nodes);
+
+        assert!((rewards.subnets_failure_rate[&PrincipalId::new_subnet_test_id(1)] - 0.1).abs() < 1e-9);
+        let node_rewards = |number: u64| &rewards.nodes[&PrincipalId::new_node_test_id(number)];
+        // Relative failure rate of 0.4 gives a reduction of 0.48
+        assert!((node_rewards(1).performance_multiplier - 0.52).abs() < 1e-9);
+        assert!((node_rewards(1).adjusted_rewards_xdr_permyriad - 5200.0).abs() < 1e-6);
+        assert_eq!(node_rewards(2).performance_multiplier, 1.0);
+        assert_eq!(node_rewards(3).adjusted_rewards_xdr_permyriad, 10000.0);
+        // The unassigned node gets the average of 0.4 and 0.0
+        assert!(matches!(node_rewards(5).failure_rate, NodeFailureRate::Unassigned { .. }));
+        assert!((node_rewards(5).failure_rate.effective() - 0.2).abs() < 1e-9);
+        assert!((node_rewards(5).performance_multiplier - 0.84).abs() < 1e-9);
+        assert!((node_rewards(5).base_rewards_xdr_permyriad - 10000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn type3_rewards_decrease_per_country() {
+        let nodes = [
+            node(1, 1, "type3.1", "North America,US,California", None),
+            node(2, 1, "type3.1", "North America,US,Florida", None),
+            node(3, 2, "type3.1", "North America,US,Florida", None),
+        ];
+        let rewards = base_rewards_v1(&rewards_table(), &nodes);
+
+        let expected_shared = (1000000.0 + 900000.0) / 2.0 / REWARDS_TABLE_DAYS;
+        assert!((rewards[&PrincipalId::new_node_test_id(1)] - expected_shared).abs() < 1e-6);
+        assert!((rewards[&PrincipalId::new_node_test_id(2)] - expected_shared).abs() < 1e-6);
+        assert!((rewards[&PrincipalId::new_node_test_id(3)] - 1000000.0 / REWARDS_TABLE_DAYS).abs() < 1e-6);
+    }
+
+    #[test]
+    fn daily_metrics_are_deltas_of_totals() {
+        let day = NaiveDate::from_ymd_opt(2025, 3, 2).unwrap();
+        let snapshot = |day: NaiveDate, totals: &[(u64, u64, u64)]| NodeMetricsHistoryResponse {
+            timestamp_nanos: day_start_nanos(day) + 1,
+            node_metrics: totals
+                .iter()
+                .map(|(node, proposed, failed)| NodeMetrics {
+                    node_id: PrincipalId::new_node_test_id(*node),
+                    num_blocks_proposed_total: *proposed,
+                    num_block_failures_total: *failed,
+                })
+                .collect(),
+        };
+        let history = BTreeMap::from([(
+            PrincipalId::new_subnet_test_id(1),
+            vec![
+                snapshot(day.pred_opt().unwrap(), &[(1, 100, 10)]),
+                snapshot(day, &[(1, 150, 12), (2, 20, 1)]),
+            ],
+        )]);
+
+        let metrics = daily_metrics(&history, day);
+        assert_eq!(metrics[&PrincipalId::new_node_test_id(1)].num_blocks_proposed, 50);
+        assert_eq!(metrics[&PrincipalId::new_node_test_id(1)].num_blocks_failed, 2);
+        assert_eq!(metrics[&PrincipalId::new_node_test_id(2)].num_blocks_proposed, 20);
+        assert!(daily_metrics(&history, day.succ_opt().unwrap()).is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::NaiveDate;
+use chrono::{DateTime, Datelike, NaiveDate};
 use csv::Writer;
 use futures_util::future::join_all;
 use ic_base_types::{PrincipalId, SubnetId};
@@ -53,43 +53,50 @@ pub trait NodeRewardsDataFetcher {
 
     async fn fetch_nrc_data(&self, node_rewards_client: &NodeRewardsCanisterWrapper) -> anyhow::Result<NrcData> {
         let ctx = self.ctx();
-        let start_date = ctx.start_date;
-        let end_date = ctx.end_date;
-
-        println!("Fetching node rewards for all providers from NRC from {} to {}...", start_date, end_date);
-
-        let days: Vec<DateUtc> = start_date.iter_days().take_while(|day| day <= &end_date).map(DateUtc::from).collect();
-        let responses: Vec<anyhow::Result<DailyResults>> = join_all(
-            days.iter()
-                .map(|day| async move { node_rewards_client.get_rewards_daily(*day, ctx.algorithm_version).await }),
-        )
-        .await;
-
-        let mut providers_rewards: BTreeMap<PrincipalId, Vec<(DateUtc, DailyNodeProviderRewards)>> = BTreeMap::new();
-        let mut subnets_failure_rates: BTreeMap<SubnetId, Vec<(DateUtc, f64)>> = BTreeMap::new();
-
-        for (day, response) in days.into_iter().zip(responses.into_iter()) {
-            match response {
-                Ok(daily_results) => {
-                    for (provider_id, provider_rewards) in daily_results.provider_results {
-                        providers_rewards.entry(provider_id).or_default().push((day, provider_rewards));
-                    }
+        fetch_nrc_data_for_days(node_rewards_client, ctx.start_date, ctx.end_date, ctx.algorithm_version).await
+    }
+}
 
-                    for (subnet_id, failure_rate) in daily_results.subnets_failure_rate {
-                        subnets_failure_rates.entry(subnet_id).or_default().push((day, failure_rate));
-                    }
+/// Fetch the rewards the Node Rewards Canister computed for every day from `start_date` to `end_date`
+pub async fn fetch_nrc_data_for_days(
+    node_rewards_client: &NodeRewardsCanisterWrapper,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    algorithm_version: Option<RewardsCalculationAlgorithmVersion>,
+) -> anyhow::Result<NrcData> {
+    println!("Fetching node rewards for all providers from NRC from {} to {}...", start_date, end_date);
+
+    let days: Vec<DateUtc> = start_date.iter_days().take_while(|day| day <= &end_date).map(DateUtc::from).collect();
+    let responses: Vec<anyhow::Result<DailyResults>> = join_all(
+        days.iter()
+            .map(|day| async move { node_rewards_client.get_rewards_daily(*day, algorithm_version).await }),
+    )
+    .await;
+
+    let mut providers_rewards: BTreeMap<PrincipalId, Vec<(DateUtc, DailyNodeProviderRewards)>> = BTreeMap::new();
+    let mut subnets_failure_rates: BTreeMap<SubnetId, Vec<(DateUtc, f64)>> = BTreeMap::new();
+
+    for (day, response) in days.into_iter().zip(responses.into_iter()) {
+        match response {
+            Ok(daily_results) => {
+                for (provider_id, provider_rewards) in daily_results.provider_results {
+                    providers_rewards.entry(provider_id).or_default().push((day, provider_rewards));
                 }
-                Err(e) => {
-                    println!("Error fetching node rewards for provider: {}", e);
+
+                for (subnet_id, failure_rate) in daily_results.subnets_failure_rate {
+                    subnets_failure_rates.entry(subnet_id).or_default().push((day, failure_rate));
                 }
             }
+            Err(e) => {
+                println!("Error fetching node rewards for provider: {}", e);
+            }
         }
-
-        Ok(NrcData {
-            providers_rewards,
-            subnets_failure_rates,
-        })
     }
+
+    Ok(NrcData {
+        providers_rewards,
+        subnets_failure_rates,
+    })
 }
 
 /// Trait for console output operations
@@ -860,6 +867,62 @@ pub struct CommonArgs {
     pub compare_with_governance: bool,
 }
 
+/// Rewarding period ended by the governance snapshot taken in a month
+pub struct GovernanceRewardsPeriod {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub snapshot: ic_nns_governance_api::MonthlyNodeProviderRewards,
+    pub xdr_permyriad_per_icp: u64,
+    pub algorithm_version: Option<RewardsCalculationAlgorithmVersion>,
+}
+
+/// Find the governance snapshot taken in `month` (YYYY-MM) in the list returned by governance, newest first.
+/// The period starts on the day of the previous snapshot and ends the day before the snapshot.
+pub fn governance_rewards_period(
+    gov_rewards_list: &[ic_nns_governance_api::MonthlyNodeProviderRewards],
+    month: &str,
+) -> anyhow::Result<GovernanceRewardsPeriod> {
+    let target = NaiveDate::parse_from_str(&(month.to_string() + "-01"), "%Y-%m-%d")?;
+    let mut idx_in_month: Option<usize> = None;
+    for (i, snap) in gov_rewards_list.iter().enumerate() {
+        let dt = DateTime::from_timestamp(snap.timestamp as i64, 0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid governance timestamp"))?
+            .date_naive();
+        if dt.year() == target.year() && dt.month() == target.month() {
+            idx_in_month = Some(i);
+            break;
+        }
+    }
+    let i = idx_in_month.ok_or_else(|| anyhow::anyhow!("No governance snapshot found for {}", month))?;
+    let last = &gov_rewards_list[i];
+    let xdr_permyriad_per_icp = last
+        .xdr_conversion_rate
+        .as_ref()
+        .and_then(|x| x.xdr_permyriad_per_icp)
+        .ok_or_else(|| anyhow::anyhow!("Missing XDR conversion rate"))?;
+
+    let prev = gov_rewards_list
+        .get(i + 1)
+        .ok_or_else(|| anyhow::anyhow!("Previous governance snapshot not found for {}", month))?;
+
+    let start_date = DateTime::from_timestamp(prev.timestamp as i64, 0)
+        .ok_or_else(|| anyhow::anyhow!("Invalid previous timestamp"))?
+        .date_naive();
+    let end_date = DateTime::from_timestamp(last.timestamp as i64, 0)
+        .ok_or_else(|| anyhow::anyhow!("Invalid last timestamp"))?
+        .date_naive()
+        .pred_opt()
+        .ok_or_else(|| anyhow::anyhow!("Cannot get previous day"))?;
+
+    Ok(GovernanceRewardsPeriod {
+        start_date,
+        end_date,
+        snapshot: last.clone(),
+        xdr_permyriad_per_icp,
+        algorithm_version: last.algorithm_version.map(|v| RewardsCalculationAlgorithmVersion { version: v }),
+    })
+}
+
 /// Helper to compute governance providers rewards from monthly rewards
 pub fn compute_governance_providers_rewards(
     rewards: &[ic_nns_governance_api::RewardNodeProvider],
@@ -892,3 +955,62 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_nns_governance_api::{MonthlyNodeProviderRewards, XdrConversionRate};
+
+    fn snapshot(day: &str, xdr_permyriad_per_icp: Option<u64>) -> MonthlyNodeProviderRewards {
+        let timestamp = NaiveDate::parse_from_str(day, "%Y-%m-%d")
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp() as u64;
+        MonthlyNodeProviderRewards {
+            timestamp,
+            xdr_conversion_rate: Some(XdrConversionRate {
+                xdr_permyriad_per_icp,
+                ..Default::default()
+            }),
+            algorithm_version: Some(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn governance_rewards_period_ends_the_day_before_the_snapshot() {
+        // Governance lists the newest snapshot first
+        let snapshots = [
+            snapshot("2025-03-14", Some(40000)),
+            snapshot("2025-02-13", Some(35000)),
+            snapshot("2025-01-14", None),
+        ];
+
+        let period = governance_rewards_period(&snapshots, "2025-03").unwrap();
+        assert_eq!(period.start_date, NaiveDate::from_ymd_opt(2025, 2, 13).unwrap());
+        assert_eq!(period.end_date, NaiveDate::from_ymd_opt(2025, 3, 13).unwrap());
+        assert_eq!(period.xdr_permyriad_per_icp, 40000);
+        assert_eq!(period.algorithm_version.map(|v| v.version), Some(1));
+        assert_eq!(period.snapshot, snapshots[0]);
+
+        let period = governance_rewards_period(&snapshots, "2025-02").unwrap();
+        assert_eq!(period.start_date, NaiveDate::from_ymd_opt(2025, 1, 14).unwrap());
+        assert_eq!(period.end_date, NaiveDate::from_ymd_opt(2025, 2, 12).unwrap());
+    }
+
+    #[test]
+    fn governance_rewards_period_needs_both_snapshots_and_a_conversion_rate() {
+        let snapshots = [snapshot("2025-03-14", Some(40000)), snapshot("2025-02-13", None)];
+
+        // No previous snapshot to start the period from
+        assert!(governance_rewards_period(&snapshots, "2025-02").is_err());
+        // No snapshot in the month
+        assert!(governance_rewards_period(&snapshots, "2025-04").is_err());
+        assert!(governance_rewards_period(&snapshots, "March").is_err());
+        // No conversion rate in the snapshot ending the period
+        let snapshots = [snapshot("2025-03-14", None), snapshot("2025-02-13", Some(35000))];
+        assert!(governance_rewards_period(&snapshots, "2025-03").is_err());
+    }
+}
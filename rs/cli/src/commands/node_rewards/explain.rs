@@ -0,0 +1,926 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{Days, NaiveDate, Utc};
+use clap::{Args, error::ErrorKind};
+use csv::Writer;
+use futures_util::future::join_all;
+use ic_base_types::PrincipalId;
+use ic_canisters::node_rewards::NodeRewardsCanisterWrapper;
+use ic_management_backend::prometheus;
+use ic_management_backend::proposal::MAX_PROPOSAL_OPEN_TIME;
+use ic_management_types::{HealthStatus, Node, TopologyChangeProposal};
+use ic_node_rewards_canister_api::provider_rewards_calculation::DailyNodeFailureRate;
+use ic_node_rewards_canister_api::{DateUtc, RewardsCalculationAlgorithmVersion};
+use ic_protobuf::registry::node::v1::NodeRecord;
+use ic_protobuf::registry::subnet::v1::SubnetRecord;
+use ic_registry_common_proto::pb::local_store::v1::{ChangelogEntry, MutationType};
+use ic_registry_keys::{NODE_RECORD_KEY_PREFIX, SUBNET_RECORD_KEY_PREFIX};
+use itertools::Itertools;
+use log::{info, warn};
+use prost::Message;
+use tabled::builder::Builder;
+use tabled::settings::{Alignment, Modify, Style, object::Rows};
+
+use crate::auth::AuthRequirement;
+use crate::commands::registry::{load_first_available_entries, local_registry_dirs_for_ctx};
+use crate::ctx::DreContext;
+use crate::exe::ExecutableCommand;
+use crate::exe::args::GlobalArgs;
+
+use super::calculator::{PerformancePenalty, day_start_nanos};
+use super::common::{NrcData, fetch_nrc_data_for_days, get_provider_prefix};
+
+/// Days explained when no start date is provided
+const DEFAULT_DAYS: u64 = 30;
+/// Most recent registry changes of the node printed to the console
+const MAX_REGISTRY_CHANGES: usize = 20;
+/// Registry changes are attributed to the topology proposals executed since this many days before the start date
+const PROPOSALS_LOOKBACK_DAYS: u64 = 365;
+
+/// Explain the daily rewards of a node: why it was penalized, next to its subnet and the changes around it
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre node-rewards explain <node-id>
+    dre node-rewards explain <node-id> --start-date 2025-03-01 --end-date 2025-03-31 --availability
+
+  Registry versions have no timestamps, so the registry changes of the node are listed by registry version.
+  The changes with a date are the executed HostOS and GuestOS upgrade proposals and the subnet membership
+  changes seen by the canister from one day to the next.
+"#)]
+pub struct Explain {
+    /// Node to explain
+    pub node_id: PrincipalId,
+
+    /// First day to explain, in format YYYY-MM-DD, by default 30 days before the end date
+    #[clap(long)]
+    pub start_date: Option<NaiveDate>,
+
+    /// Last day to explain, in format YYYY-MM-DD, by default yesterday
+    #[clap(long)]
+    pub end_date: Option<NaiveDate>,
+
+    /// Version of the rewards calculation algorithm, by default the one the canister uses by default
+    #[clap(long)]
+    pub algorithm_version: Option<u32>,
+
+    /// Query Prometheus for the daily availability of the replica of the node.
+    /// The health status source only knows the current status of the node
+    #[clap(long)]
+    pub availability: bool,
+
+    /// Write the timeline to this CSV file
+    #[clap(long)]
+    pub csv_output: Option<PathBuf>,
+}
+
+impl ExecutableCommand for Explain {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Signer
+    }
+
+    fn validate(&self, _args: &GlobalArgs, cmd: &mut clap::Command) {
+        if self
+            .start_date
+            .zip(self.end_date)
+            .is_some_and(|(start_date, end_date)| end_date < start_date)
+        {
+            cmd.error(ErrorKind::InvalidValue, "The end date must not be before the start date")
+                .exit();
+        }
+    }
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        let end_date = self.end_date.unwrap_or_else(|| Utc::now().date_naive().pred_opt().unwrap());
+        let start_date = self
+            .start_date
+            .unwrap_or_else(|| end_date.checked_sub_days(Days::new(DEFAULT_DAYS - 1)).unwrap());
+        if end_date < start_date {
+            anyhow::bail!("The end date {} is before the start date {}", end_date, start_date);
+        }
+        let algorithm_version = self.algorithm_version.map(|version| RewardsCalculationAlgorithmVersion { version });
+
+        let registry = ctx.registry().await;
+        let node = registry.nodes().await?.get(&self.node_id).cloned();
+        let health = ctx.health_client().nodes().await?.get(&self.node_id).cloned();
+
+        let (_, canister_agent) = ctx.create_ic_agent_canister_client().await?;
+        let node_rewards_client: NodeRewardsCanisterWrapper = canister_agent.into();
+        let nrc_data = fetch_nrc_data_for_days(&node_rewards_client, start_date, end_date, algorithm_version).await?;
+        let availability = if self.availability {
+            self.fetch_availability(&ctx, start_date, end_date).await?
+        } else {
+            vec![]
+        };
+        let days = node_days(&nrc_data, self.node_id, start_date, end_date, &availability);
+
+        let events = self.fetch_events(&ctx, &days, start_date, end_date).await?;
+        let registry_changes = self.fetch_registry_changes(&ctx, start_date).await?;
+
+        let penalty = PerformancePenalty::default();
+        self.print_node(node.as_ref(), health, &days, start_date, end_date);
+        print_timeline(&days, self.availability);
+        print_penalized_days(&days, &events, &penalty);
+        print_registry_changes(&registry_changes);
+
+        if let Some(path) = &self.csv_output {
+            write_csv(path, &days, &events, &penalty)?;
+            info!("Wrote the timeline to {}", path.display());
+        }
+
+        print_summary(&days, &events);
+        Ok(())
+    }
+}
+
+impl Explain {
+    /// Daily availability of the replica of the node, one entry per day from the start date
+    async fn fetch_availability(&self, ctx: &DreContext, start_date: NaiveDate, end_date: NaiveDate) -> anyhow::Result<Vec<Option<f64>>> {
+        info!("Fetching the daily availability of the replica from Prometheus...");
+        let client = prometheus::client(ctx.network());
+        let responses = join_all(start_date.iter_days().take_while(|day| *day <= end_date).map(|day| {
+            let end_timestamp = (day_start_nanos(day.succ_opt().unwrap()) / 1_000_000_000) as i64;
+            prometheus::replica_daily_availability(&client, ctx.network(), self.node_id, end_timestamp)
+        }))
+        .await;
+        responses.into_iter().collect()
+    }
+
+    /// Membership changes and the HostOS and GuestOS upgrades of the node, by day
+    async fn fetch_events(
+        &self,
+        ctx: &DreContext,
+        days: &[NodeDay],
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> anyhow::Result<BTreeMap<NaiveDate, Vec<String>>> {
+        let mut events: BTreeMap<NaiveDate, Vec<String>> = BTreeMap::new();
+        for (day, event) in membership_events(days) {
+            events.entry(day).or_default().push(event);
+        }
+        let in_window = |executed_timestamp_seconds: u64| {
+            chrono::DateTime::from_timestamp(executed_timestamp_seconds as i64, 0)
+                .map(|executed_at| executed_at.date_naive())
+                .filter(|day| (start_date..=end_date).contains(day))
+        };
+        // Upgrades executed in the window were proposed at most MAX_PROPOSAL_OPEN_TIME before it
+        let proposed_since = start_date.and_time(chrono::NaiveTime::MIN).and_utc() - chrono::Duration::from_std(MAX_PROPOSAL_OPEN_TIME)?;
+        let proposed_since = proposed_since.timestamp().max(0) as u64;
+        for proposal in ctx.proposals_agent().list_update_nodes_hostos_versions_proposals(proposed_since).await? {
+            if !proposal.info.executed || !proposal.payload.node_ids.iter().any(|n| n.get() == self.node_id) {
+                continue;
+            }
+            if let Some(day) = in_window(proposal.info.executed_timestamp_seconds) {
+                events.entry(day).or_default().push(format!(
+                    "HostOS upgraded to {} (proposal {})",
+                    proposal.payload.hostos_version_id.unwrap_or_default(),
+                    proposal.info.id
+                ));
+            }
+        }
+        for proposal in ctx.proposals_agent().list_update_subnet_version_proposals_since(proposed_since).await? {
+            let subnet_id = proposal.payload.subnet_id.to_string();
+            let Some(day) = in_window(proposal.info.executed_timestamp_seconds).filter(|_| proposal.info.executed) else {
+                continue;
+            };
+            if days
+                .iter()
+                .find(|d| d.day == day)
+                .and_then(|d| d.rewards.as_ref())
+                .is_some_and(|r| r.subnet_id.as_ref() == Some(&subnet_id))
+            {
+                events.entry(day).or_default().push(format!(
+                    "GuestOS of subnet {} upgraded to {} (proposal {})",
+                    get_provider_prefix(&subnet_id),
+                    proposal.payload.replica_version_id,
+                    proposal.info.id
+                ));
+            }
+        }
+        Ok(events)
+    }
+
+    /// Registry changes of the node, with the proposals that changed its subnet membership
+    async fn fetch_registry_changes(&self, ctx: &DreContext, start_date: NaiveDate) -> anyhow::Result<Vec<NodeRegistryChange>> {
+        // The local registry was synced by `ctx.registry()` before
+        let mut entries = load_first_available_entries(&local_registry_dirs_for_ctx(ctx)?)?;
+        entries.sort_by_key(|(v, _)| *v);
+        let mut registry_changes = registry_changes(&entries, self.node_id);
+        if registry_changes.iter().any(|c| c.is_membership()) {
+            let since = start_date - Days::new(PROPOSALS_LOOKBACK_DAYS);
+            let since_timestamp_seconds = since.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as u64;
+            let proposals = ctx.proposals_agent().list_executed_topology_proposals(since_timestamp_seconds).await?;
+            for change in registry_changes.iter_mut() {
+                change.proposal_id = change.attribute(self.node_id, &proposals);
+            }
+        }
+        Ok(registry_changes)
+    }
+
+    fn print_node(&self, node: Option<&Node>, health: Option<HealthStatus>, days: &[NodeDay], start_date: NaiveDate, end_date: NaiveDate) {
+        let provider_id = node
+            .map(|n| n.operator.provider.principal)
+            .or_else(|| days.iter().find_map(|d| d.rewards.as_ref().map(|r| r.provider_id)));
+        println!("\n=== NODE {}: {} to {} ===", self.node_id, start_date, end_date);
+        println!(
+            "Provider: {}",
+            provider_id.map(|p| p.to_string()).unwrap_or_else(|| "unknown".to_string())
+        );
+        match node {
+            Some(node) => {
+                println!(
+                    "Data center: {} | Subnet: {} | HostOS: {}",
+                    node.operator.datacenter.as_ref().map(|dc| dc.name.clone()).unwrap_or_default(),
+                    node.subnet_id.map(|s| s.to_string()).unwrap_or_else(|| "unassigned".to_string()),
+                    node.hostos_version
+                );
+            }
+            None => println!("The node is not in the latest registry version"),
+        }
+        println!(
+            "Current health: {}{}",
+            health.map(|h| h.to_string()).unwrap_or_else(|| "unknown".to_string()),
+            if self.availability {
+                ""
+            } else {
+                " (the health status source has no history, use --availability for the daily availability)"
+            }
+        );
+    }
+}
+
+/// Joins the rewards of the node, as computed by the canister, with the availability into one entry per day
+fn node_days(nrc_data: &NrcData, node_id: PrincipalId, start_date: NaiveDate, end_date: NaiveDate, availability: &[Option<f64>]) -> Vec<NodeDay> {
+    let subnets_failure_rates: BTreeMap<(DateUtc, String), f64> = nrc_data
+        .subnets_failure_rates
+        .iter()
+        .flat_map(|(subnet_id, failure_rates)| failure_rates.iter().map(move |(day, fr)| ((*day, subnet_id.to_string()), *fr)))
+        .collect();
+
+    let mut rewards: BTreeMap<DateUtc, NodeDayRewards> = BTreeMap::new();
+    for (provider_id, daily_rewards) in &nrc_data.providers_rewards {
+        for (day, provider_rewards) in daily_rewards {
+            let Some(node_rewards) = provider_rewards.daily_nodes_rewards.iter().find(|n| n.node_id == Some(node_id)) else {
+                continue;
+            };
+            let mut day_rewards = NodeDayRewards {
+                provider_id: *provider_id,
+                subnet_id: None,
+                num_blocks_proposed: None,
+                num_blocks_failed: None,
+                original_failure_rate: None,
+                subnet_failure_rate: None,
+                relative_failure_rate: None,
+                extrapolated_failure_rate: None,
+                performance_multiplier: node_rewards.performance_multiplier.unwrap_or(1.0),
+                base_rewards_xdr_permyriad: node_rewards.base_rewards_xdr_permyriad.unwrap_or_default(),
+                adjusted_rewards_xdr_permyriad: node_rewards.adjusted_rewards_xdr_permyriad.unwrap_or_default(),
+            };
+            match &node_rewards.daily_node_failure_rate {
+                Some(DailyNodeFailureRate::SubnetMember { node_metrics: Some(m) }) => {
+                    let subnet_id = m.subnet_assigned.map(|s| s.to_string());
+                    day_rewards.subnet_failure_rate = subnet_id
+                        .as_ref()
+                        .and_then(|s| subnets_failure_rates.get(&(*day, s.clone())).copied())
+                        .or(m.subnet_assigned_failure_rate);
+                    day_rewards.subnet_id = subnet_id;
+                    day_rewards.num_blocks_proposed = m.num_blocks_proposed;
+                    day_rewards.num_blocks_failed = m.num_blocks_failed;
+                    day_rewards.original_failure_rate = m.original_failure_rate;
+                    day_rewards.relative_failure_rate = m.relative_failure_rate;
+                }
+                Some(DailyNodeFailureRate::NonSubnetMember { extrapolated_failure_rate }) => {
+                    day_rewards.extrapolated_failure_rate = *extrapolated_failure_rate;
+                }
+                _ => {}
+            }
+            rewards.insert(*day, day_rewards);
+        }
+    }
+
+    start_date
+        .iter_days()
+        .take_while(|day| *day <= end_date)
+        .enumerate()
+        .map(|(i, day)| NodeDay {
+            day,
+            rewards: rewards.remove(&DateUtc::from(day)),
+            availability: availability.get(i).copied().flatten(),
+        })
+        .collect()
+}
+
+/// The node on one day of the timeline
+struct NodeDay {
+    day: NaiveDate,
+    /// None if the canister didn't reward the node on that day
+    rewards: Option<NodeDayRewards>,
+    availability: Option<f64>,
+}
+
+/// Rewards of the node on one day, as computed by the canister
+struct NodeDayRewards {
+    provider_id: PrincipalId,
+    subnet_id: Option<String>,
+    num_blocks_proposed: Option<u64>,
+    num_blocks_failed: Option<u64>,
+    original_failure_rate: Option<f64>,
+    subnet_failure_rate: Option<f64>,
+    relative_failure_rate: Option<f64>,
+    extrapolated_failure_rate: Option<f64>,
+    performance_multiplier: f64,
+    base_rewards_xdr_permyriad: u64,
+    adjusted_rewards_xdr_permyriad: u64,
+}
+
+impl NodeDayRewards {
+    fn is_penalized(&self) -> bool {
+        self.performance_multiplier < 1.0
+    }
+
+    /// The failure rate the node was penalized for
+    fn effective_failure_rate(&self) -> Option<f64> {
+        self.relative_failure_rate.or(self.extrapolated_failure_rate)
+    }
+
+    /// Why the node was penalized, if it was
+    fn penalty_reason(&self, penalty: &PerformancePenalty) -> Option<String> {
+        if !self.is_penalized() {
+            return None;
+        }
+        let effective = self.effective_failure_rate().unwrap_or_default();
+        let mut reason = match &self.subnet_id {
+            Some(subnet_id) => {
+                let blocks = match (self.num_blocks_proposed, self.num_blocks_failed) {
+                    (Some(proposed), Some(failed)) => format!("failed {} of {} blocks, ", failed, proposed + failed),
+                    _ => String::new(),
+                };
+                format!(
+                    "{}failure rate {:.2} is {:.2} above the failure rate {:.2} of subnet {}",
+                    blocks,
+                    self.original_failure_rate.unwrap_or_default(),
+                    effective,
+                    self.subnet_failure_rate.unwrap_or_default(),
+                    get_provider_prefix(subnet_id)
+                )
+            }
+            None => format!(
+                "unassigned, failure rate {:.2} extrapolated from the assigned nodes of the provider",
+                effective
+            ),
+        };
+        if effective >= penalty.max_failure_rate {
+            reason.push_str(&format!(", maximum reduction from {}", penalty.max_failure_rate));
+        } else {
+            reason.push_str(&format!(", above the {} threshold", penalty.min_failure_rate));
+        }
+        Some(reason)
+    }
+}
+
+/// Subnet membership changes seen by the canister from one rewarded day to the next
+fn membership_events(days: &[NodeDay]) -> Vec<(NaiveDate, String)> {
+    days.iter()
+        .filter_map(|d| d.rewards.as_ref().map(|r| (d.day, r.subnet_id.as_ref())))
+        .tuple_windows()
+        .filter_map(|((_, before), (day, after))| {
+            let event = match (before, after) {
+                (None, Some(after)) => format!("joined subnet {}", get_provider_prefix(after)),
+                (Some(before), None) => format!("left subnet {}", get_provider_prefix(before)),
+                (Some(before), Some(after)) if before != after => {
+                    format!(
+                        "moved from subnet {} to subnet {}",
+                        get_provider_prefix(before),
+                        get_provider_prefix(after)
+                    )
+                }
+                _ => return None,
+            };
+            Some((day, event))
+        })
+        .collect()
+}
+
+/// Events on the day or the day before
+fn events_around(events: &BTreeMap<NaiveDate, Vec<String>>, day: NaiveDate) -> Vec<String> {
+    events
+        .range(day.pred_opt().unwrap_or(day)..=day)
+        .flat_map(|(_, events)| events.iter().cloned())
+        .collect()
+}
+
+/// A change of the node between two local registry versions
+#[derive(Debug, PartialEq)]
+enum RegistryChange {
+    Added,
+    Removed,
+    HostosVersion(String),
+    JoinedSubnet {
+        subnet_id: PrincipalId,
+        replica_version: String,
+        from: Option<PrincipalId>,
+    },
+    LeftSubnet(PrincipalId),
+    GuestosVersion {
+        subnet_id: PrincipalId,
+        replica_version: String,
+    },
+}
+
+impl std::fmt::Display for RegistryChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryChange::Added => write!(f, "added to the registry"),
+            RegistryChange::Removed => write!(f, "removed from the registry"),
+            RegistryChange::HostosVersion(version) => write!(f, "HostOS version set to {}", version),
+            RegistryChange::JoinedSubnet {
+                subnet_id,
+                replica_version,
+                from: None,
+            } => write!(f, "joined subnet {} running GuestOS {}", subnet_id, replica_version),
+            RegistryChange::JoinedSubnet {
+                subnet_id,
+                replica_version,
+                from: Some(from),
+            } => write!(
+                f,
+                "moved from subnet {} to subnet {} running GuestOS {}",
+                from, subnet_id, replica_version
+            ),
+            RegistryChange::LeftSubnet(subnet_id) => write!(f, "left subnet {}", subnet_id),
+            RegistryChange::GuestosVersion { subnet_id, replica_version } => {
+                write!(f, "GuestOS of subnet {} set to {}", subnet_id, replica_version)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct NodeRegistryChange {
+    registry_version: u64,
+    change: RegistryChange,
+    /// Executed proposal which caused the change, if known
+    proposal_id: Option<u64>,
+}
+
+impl NodeRegistryChange {
+    fn is_membership(&self) -> bool {
+        matches!(self.change, RegistryChange::JoinedSubnet { .. } | RegistryChange::LeftSubnet(_))
+    }
+
+    /// The most recent executed topology proposal which adds the node to, or removes it from, the subnet
+    fn attribute(&self, node_id: PrincipalId, proposals: &[TopologyChangeProposal]) -> Option<u64> {
+        let (subnet_id, added) = match &self.change {
+            RegistryChange::JoinedSubnet { subnet_id, .. } => (*subnet_id, true),
+            RegistryChange::LeftSubnet(subnet_id) => (*subnet_id, false),
+            _ => return None,
+        };
+        proposals
+            .iter()
+            .filter(|p| p.subnet_id == Some(subnet_id))
+            .filter(|p| {
+                if added {
+                    p.node_ids_added.contains(&node_id)
+                } else {
+                    p.node_ids_removed.contains(&node_id)
+                }
+            })
+            .map(|p| p.id)
+            .max()
+    }
+}
+
+/// Changes of the node record, of the subnet membership of the node, and of the GuestOS version of its subnet,
+/// in the order of the local registry versions
+fn registry_changes(entries: &[(u64, ChangelogEntry)], node_id: PrincipalId) -> Vec<NodeRegistryChange> {
+    let node_key = format!("{}{}", NODE_RECORD_KEY_PREFIX, node_id);
+    let mut in_registry = false;
+    let mut hostos_version: Option<String> = None;
+    let mut subnet: Option<(PrincipalId, String)> = None;
+    let mut changes = vec![];
+
+    for (version, entry) in entries {
+        let mut push = |change: RegistryChange| {
+            changes.push(NodeRegistryChange {
+                registry_version: *version,
+                change,
+                proposal_id: None,
+            })
+        };
+        for mutation in &entry.key_mutations {
+            if mutation.key == node_key {
+                if mutation.mutation_type() != MutationType::Set {
+                    if in_registry {
+                        push(RegistryChange::Removed);
+                    }
+                    in_registry = false;
+                    hostos_version = None;
+                    continue;
+                }
+                let record = match NodeRecord::decode(mutation.value.as_slice()) {
+                    Ok(record) => record,
+                    Err(e) => {
+                        warn!("Failed to decode {} at version {}: {:?}", mutation.key, version, e);
+                        continue;
+                    }
+                };
+                if !in_registry {
+                    push(RegistryChange::Added);
+                    in_registry = true;
+                }
+                if record.hostos_version_id.is_some() && record.hostos_version_id != hostos_version {
+                    push(RegistryChange::HostosVersion(record.hostos_version_id.clone().unwrap_or_default()));
+                }
+                hostos_version = record.hostos_version_id;
+            } else if let Some(subnet_id) = mutation.key.strip_prefix(SUBNET_RECORD_KEY_PREFIX) {
+                let Ok(subnet_id) = PrincipalId::from_str(subnet_id) else {
+                    continue;
+                };
+                let record = match mutation.mutation_type() {
+                    MutationType::Set => match SubnetRecord::decode(mutation.value.as_slice()) {
+                        Ok(record) => Some(record),
+                        Err(e) => {
+                            warn!("Failed to decode {} at version {}: {:?}", mutation.key, version, e);
+                            continue;
+                        }
+                    },
+                    _ => None,
+                };
+                let member = record
+                    .as_ref()
+                    .is_some_and(|r| r.membership.iter().any(|m| m.as_slice() == node_id.as_slice()));
+                let current = subnet.as_ref().map(|(id, _)| *id);
+                match (member, current) {
+                    (true, Some(current)) if current == subnet_id => {
+                        let replica_version = record.map(|r| r.replica_version_id).unwrap_or_default();
+                        if subnet.as_ref().is_some_and(|(_, v)| *v != replica_version) {
+                            push(RegistryChange::GuestosVersion {
+                                subnet_id,
+                                replica_version: replica_version.clone(),
+                            });
+                        }
+                        subnet = Some((subnet_id, replica_version));
+                    }
+                    (true, from) => {
+                        let replica_version = record.map(|r| r.replica_version_id).unwrap_or_default();
+                        push(RegistryChange::JoinedSubnet {
+                            subnet_id,
+                            replica_version: replica_version.clone(),
+                            from,
+                        });
+                        subnet = Some((subnet_id, replica_version));
+                    }
+                    (false, Some(current)) if current == subnet_id => {
+                        push(RegistryChange::LeftSubnet(subnet_id));
+                        subnet = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    changes
+}
+
+fn format_rate(rate: Option<f64>) -> String {
+    rate.map(|r| format!("{:.4}", r)).unwrap_or_default()
+}
+
+fn print_timeline(days: &[NodeDay], with_availability: bool) {
+    let mut header = vec![
+        "Day",
+        "Subnet",
+        "Proposed",
+        "Failed",
+        "Node FR",
+        "Subnet FR",
+        "Relative FR",
+        "Extrapolated FR",
+        "Multiplier",
+        "Base",
+        "Adjusted",
+    ];
+    if with_availability {
+        header.push("Availability");
+    }
+    let mut builder = Builder::default();
+    builder.push_record(header);
+    for day in days {
+        let mut row = match &day.rewards {
+            Some(r) => vec![
+                day.day.to_string(),
+                r.subnet_id.as_deref().map(get_provider_prefix).unwrap_or_else(|| "-".to_string()),
+                r.num_blocks_proposed.map(|n| n.to_string()).unwrap_or_default(),
+                r.num_blocks_failed.map(|n| n.to_string()).unwrap_or_default(),
+                format_rate(r.original_failure_rate),
+                format_rate(r.subnet_failure_rate),
+                format_rate(r.relative_failure_rate),
+                format_rate(r.extrapolated_failure_rate),
+                format!("{:.4}", r.performance_multiplier),
+                r.base_rewards_xdr_permyriad.to_string(),
+                r.adjusted_rewards_xdr_permyriad.to_string(),
+            ],
+            None => {
+                let mut row = vec![day.day.to_string(), "not rewarded".to_string()];
+                row.resize(11, String::new());
+                row
+            }
+        };
+        if with_availability {
+            row.push(day.availability.map(|a| format!("{:.1}%", a * 100.0)).unwrap_or_default());
+        }
+        builder.push_record(row);
+    }
+    let mut table = builder.build();
+    table.with(Style::modern()).with(Modify::new(Rows::new(0..1)).with(Alignment::center()));
+
+    println!("\n=== DAILY TIMELINE ===");
+    println!("Unit: XDRPermyriad | FR: failure rate, the relative FR of assigned nodes and the extrapolated FR of unassigned nodes are penalized");
+    println!("{}", table);
+}
+
+fn print_penalized_days(days: &[NodeDay], events: &BTreeMap<NaiveDate, Vec<String>>, penalty: &PerformancePenalty) {
+    println!("\n=== PENALIZED DAYS ===");
+    let mut any = false;
+    for day in days {
+        let Some(reason) = day.rewards.as_ref().and_then(|r| r.penalty_reason(penalty)) else {
+            continue;
+        };
+        any = true;
+        println!("{}: {}", day.day, reason);
+        for event in events_around(events, day.day) {
+            println!("    after: {}", event);
+        }
+    }
+    if !any {
+        println!("The node was not penalized");
+    }
+
+    println!("\n=== DATED EVENTS ===");
+    if events.is_empty() {
+        println!("No upgrade or membership change in these days");
+    }
+    for (day, day_events) in events {
+        for event in day_events {
+            println!("{}: {}", day, event);
+        }
+    }
+}
+
+fn print_registry_changes(changes: &[NodeRegistryChange]) {
+    println!("\n=== REGISTRY CHANGES (local registry versions) ===");
+    if changes.is_empty() {
+        println!("No change of the node in the local registry");
+        return;
+    }
+    if changes.len() > MAX_REGISTRY_CHANGES {
+        println!("... {} earlier changes", changes.len() - MAX_REGISTRY_CHANGES);
+    }
+    for change in &changes[changes.len().saturating_sub(MAX_REGISTRY_CHANGES)..] {
+        match change.proposal_id {
+            Some(proposal_id) => println!("v{}: {} (proposal {})", change.registry_version, change.change, proposal_id),
+            None => println!("v{}: {}", change.registry_version, change.change),
+        }
+    }
+}
+
+fn print_summary(days: &[NodeDay], events: &BTreeMap<NaiveDate, Vec<String>>) {
+    let penalized = days.iter().filter(|d| d.rewards.as_ref().is_some_and(|r| r.is_penalized())).collect_vec();
+    let lost: u64 = penalized
+        .iter()
+        .filter_map(|d| d.rewards.as_ref())
+        .map(|r| r.base_rewards_xdr_permyriad.saturating_sub(r.adjusted_rewards_xdr_permyriad))
+        .sum();
+    let after_change = penalized.iter().filter(|d| !events_around(events, d.day).is_empty()).count();
+    println!("\n=== SUMMARY ===");
+    println!("{} of {} days penalized, {} XDRPermyriad lost", penalized.len(), days.len(), lost);
+    println!(
+        "{} penalized days had an upgrade or a membership change on the same or the previous day",
+        after_change
+    );
+}
+
+fn write_csv(path: &Path, days: &[NodeDay], events: &BTreeMap<NaiveDate, Vec<String>>, penalty: &PerformancePenalty) -> anyhow::Result<()> {
+    let mut wtr = Writer::from_path(path)?;
+    wtr.write_record([
+        "day_utc",
+        "rewarded",
+        "subnet_assigned",
+        "num_blocks_proposed",
+        "num_blocks_failed",
+        "original_failure_rate",
+        "subnet_failure_rate",
+        "relative_failure_rate",
+        "extrapolated_failure_rate",
+        "performance_multiplier",
+        "base_rewards_xdr_permyriad",
+        "adjusted_rewards_xdr_permyriad",
+        "availability",
+        "penalty_reason",
+        "events",
+    ])?;
+
+    for day in days {
+        let r = day.rewards.as_ref();
+        wtr.write_record([
+            day.day.to_string(),
+            r.is_some().to_string(),
+            r.and_then(|r| r.subnet_id.clone()).unwrap_or_default(),
+            r.and_then(|r| r.num_blocks_proposed).map(|n| n.to_string()).unwrap_or_default(),
+            r.and_then(|r| r.num_blocks_failed).map(|n| n.to_string()).unwrap_or_default(),
+            r.and_then(|r| r.original_failure_rate).map(|f| f.to_string()).unwrap_or_default(),
+            r.and_then(|r| r.subnet_failure_rate).map(|f| f.to_string()).unwrap_or_default(),
+            r.and_then(|r| r.relative_failure_rate).map(|f| f.to_string()).unwrap_or_default(),
+            r.and_then(|r| r.extrapolated_failure_rate).map(|f| f.to_string()).unwrap_or_default(),
+            r.map(|r| r.performance_multiplier.to_string()).unwrap_or_default(),
+            r.map(|r| r.base_rewards_xdr_permyriad.to_string()).unwrap_or_default(),
+            r.map(|r| r.adjusted_rewards_xdr_permyriad.to_string()).unwrap_or_default(),
+            day.availability.map(|a| a.to_string()).unwrap_or_default(),
+            r.and_then(|r| r.penalty_reason(penalty)).unwrap_or_default(),
+            events.get(&day.day).map(|e| e.join("; ")).unwrap_or_default(),
+        ])?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_registry_common_proto::pb::local_store::v1::KeyMutation;
+
+    fn day(day: u32, subnet_id: Option<&str>, multiplier: f64) -> NodeDay {
+        NodeDay {
+            day: NaiveDate::from_ymd_opt(2025, 3, day).unwrap(),
+            rewards: Some(NodeDayRewards {
+                provider_id: PrincipalId::new_user_test_id(1),
+                subnet_id: subnet_id.map(|s| s.to_string()),
+                num_blocks_proposed: Some(60),
+                num_blocks_failed: Some(40),
+                original_failure_rate: Some(0.4),
+                subnet_failure_rate: Some(0.05),
+                relative_failure_rate: subnet_id.map(|_| 0.35),
+                extrapolated_failure_rate: subnet_id.is_none().then_some(0.2),
+                performance_multiplier: multiplier,
+                base_rewards_xdr_permyriad: 1000,
+                adjusted_rewards_xdr_permyriad: (1000.0 * multiplier) as u64,
+            }),
+            availability: None,
+        }
+    }
+
+    fn mutation(key: String, value: Option<Vec<u8>>) -> KeyMutation {
+        KeyMutation {
+            key,
+            mutation_type: (if value.is_some() { MutationType::Set } else { MutationType::Unset }) as i32,
+            value: value.unwrap_or_default(),
+        }
+    }
+
+    fn node_entry(node_id: PrincipalId, hostos_version: Option<&str>) -> ChangelogEntry {
+        ChangelogEntry {
+            key_mutations: vec![mutation(
+                format!("{}{}", NODE_RECORD_KEY_PREFIX, node_id),
+                Some(
+                    NodeRecord {
+                        hostos_version_id: hostos_version.map(|v| v.to_string()),
+                        ..Default::default()
+                    }
+                    .encode_to_vec(),
+                ),
+            )],
+        }
+    }
+
+    fn subnet_entry(subnet_id: PrincipalId, nodes: &[PrincipalId], replica_version: &str) -> ChangelogEntry {
+        ChangelogEntry {
+            key_mutations: vec![mutation(
+                format!("{}{}", SUBNET_RECORD_KEY_PREFIX, subnet_id),
+                Some(
+                    SubnetRecord {
+                        membership: nodes.iter().map(|n| n.to_vec()).collect(),
+                        replica_version_id: replica_version.to_string(),
+                        ..Default::default()
+                    }
+                    .encode_to_vec(),
+                ),
+            )],
+        }
+    }
+
+    #[test]
+    fn penalty_reasons() {
+        let penalty = PerformancePenalty::default();
+        let healthy = day(1, Some("subnet-a"), 1.0);
+        assert_eq!(healthy.rewards.unwrap().penalty_reason(&penalty), None);
+
+        let assigned = day(2, Some("subnet-a"), 0.6);
+        assert_eq!(
+            assigned.rewards.unwrap().penalty_reason(&penalty).unwrap(),
+            "failed 40 of 100 blocks, failure rate 0.40 is 0.35 above the failure rate 0.05 of subnet subnet, above the 0.1 threshold"
+        );
+
+        let unassigned = day(3, None, 0.84);
+        assert_eq!(
+            unassigned.rewards.unwrap().penalty_reason(&penalty).unwrap(),
+            "unassigned, failure rate 0.20 extrapolated from the assigned nodes of the provider, above the 0.1 threshold"
+        );
+    }
+
+    #[test]
+    fn membership_events_between_rewarded_days() {
+        let mut days = vec![
+            day(1, None, 1.0),
+            day(2, Some("aaaaa-a"), 1.0),
+            day(3, Some("aaaaa-a"), 1.0),
+            day(4, Some("bbbbb-b"), 1.0),
+            day(5, None, 1.0),
+            day(6, Some("bbbbb-b"), 1.0),
+        ];
+        days[4].rewards = None;
+
+        assert_eq!(
+            membership_events(&days)
+                .into_iter()
+                .map(|(day, event)| format!("{} {}", day, event))
+                .collect_vec(),
+            vec!["2025-03-02 joined subnet aaaaa", "2025-03-04 moved from subnet aaaaa to subnet bbbbb"]
+        );
+    }
+
+    #[test]
+    fn registry_changes_of_the_node() {
+        let node_id = PrincipalId::new_node_test_id(1);
+        let other_node = PrincipalId::new_node_test_id(2);
+        let subnet_a = PrincipalId::new_subnet_test_id(1);
+        let subnet_b = PrincipalId::new_subnet_test_id(2);
+        let entries = vec![
+            (1, node_entry(node_id, None)),
+            (2, subnet_entry(subnet_a, &[other_node], "v1")),
+            (3, subnet_entry(subnet_a, &[node_id, other_node], "v1")),
+            (4, node_entry(node_id, Some("h1"))),
+            (5, subnet_entry(subnet_a, &[node_id, other_node], "v2")),
+            (6, subnet_entry(subnet_b, &[node_id], "v3")),
+            (7, subnet_entry(subnet_a, &[other_node], "v2")),
+            (8, subnet_entry(subnet_b, &[], "v3")),
+            (9, node_entry(node_id, None)),
+            (10, node_entry(node_id, Some("h1"))),
+            (
+                11,
+                ChangelogEntry {
+                    key_mutations: vec![mutation(format!("{}{}", NODE_RECORD_KEY_PREFIX, node_id), None)],
+                },
+            ),
+        ];
+
+        let changes = registry_changes(&entries, node_id);
+
+        assert_eq!(
+            changes.iter().map(|c| (c.registry_version, c.change.to_string())).collect_vec(),
+            vec![
+                (1, "added to the registry".to_string()),
+                (3, format!("joined subnet {} running GuestOS v1", subnet_a)),
+                (4, "HostOS version set to h1".to_string()),
+                (5, format!("GuestOS of subnet {} set to v2", subnet_a)),
+                (6, format!("moved from subnet {} to subnet {} running GuestOS v3", subnet_a, subnet_b)),
+                (8, format!("left subnet {}", subnet_b)),
+                (10, "HostOS version set to h1".to_string()),
+                (11, "removed from the registry".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn membership_changes_are_attributed_to_proposals() {
+        let node_id = PrincipalId::new_node_test_id(1);
+        let subnet_id = PrincipalId::new_subnet_test_id(1);
+        let joined = NodeRegistryChange {
+            registry_version: 1,
+            change: RegistryChange::JoinedSubnet {
+                subnet_id,
+                replica_version: String::new(),
+                from: None,
+            },
+            proposal_id: None,
+        };
+        let proposals = vec![
+            TopologyChangeProposal {
+                node_ids_added: vec![node_id],
+                node_ids_removed: vec![],
+                subnet_id: Some(subnet_id),
+                id: 100,
+            },
+            TopologyChangeProposal {
+                node_ids_added: vec![PrincipalId::new_node_test_id(2)],
+                node_ids_removed: vec![node_id],
+                subnet_id: Some(subnet_id),
+                id: 101,
+            },
+        ];
+
+        assert_eq!(joined.attribute(node_id, &proposals), Some(100));
+        assert_eq!(joined.attribute(PrincipalId::new_node_test_id(3), &proposals), None);
+    }
+}
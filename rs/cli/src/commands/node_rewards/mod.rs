@@ -1,12 +1,19 @@
+use audit::Audit;
 use clap::Args;
+use explain::Explain;
 use ongoing::Ongoing;
 use past_rewards::PastRewards;
+use simulate::Simulate;
 
 use crate::exe::impl_executable_command_for_enums;
 
+mod audit;
+mod calculator;
 pub mod common;
+mod explain;
 mod ongoing;
 mod past_rewards;
+mod simulate;
 
 #[derive(Args, Debug)]
 pub struct NodeRewards {
@@ -14,4 +21,4 @@ pub struct NodeRewards {
     pub subcommands: Subcommands,
 }
 
-impl_executable_command_for_enums! { NodeRewards, Ongoing, PastRewards }
+impl_executable_command_for_enums! { NodeRewards, Ongoing, PastRewards, Audit, Simulate, Explain }
@@ -0,0 +1,468 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, error::ErrorKind};
+use csv::Writer;
+use ic_base_types::PrincipalId;
+use ic_canisters::governance::GovernanceCanisterWrapper;
+use ic_canisters::node_rewards::NodeRewardsCanisterWrapper;
+use ic_node_rewards_canister_api::provider_rewards_calculation::DailyNodeFailureRate;
+use ic_protobuf::registry::node_rewards::v2::NodeRewardsTable;
+use itertools::Itertools;
+use log::info;
+use tabled::Table;
+use tabled::builder::Builder;
+use tabled::settings::{Alignment, Modify, Style, object::Rows};
+
+use crate::auth::AuthRequirement;
+use crate::commands::registry::NodeRewardsTableFlattened;
+use crate::exe::ExecutableCommand;
+use crate::exe::args::GlobalArgs;
+
+use super::calculator::{NodeDailyInput, PerformancePenalty, RewardsAlgorithm};
+use super::common::{NrcData, fetch_nrc_data_for_days, get_provider_prefix, governance_rewards_period};
+
+/// Replay past months with a modified rewards table or performance penalty and compare the rewards of every provider
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre node-rewards simulate 2025-02 2025-03 --rewards-table proposed_table.yaml
+    dre node-rewards simulate 2025-03 --min-failure-rate 0.05 --max-rewards-reduction 0.9 --csv-output simulation.csv
+
+  The rewards table has the format of the `node_rewards_table` printed by `dre registry`.
+  The replay starts from the per-node results of the Node Rewards Canister: a modified rewards
+  table changes the base rewards, modified thresholds change how the failure rates of the
+  canister reduce them. ICP amounts use the conversion rate of the governance snapshot of each month.
+"#)]
+pub struct Simulate {
+    /// Months to replay, in format YYYY-MM
+    #[clap(required = true, num_args(1..))]
+    pub months: Vec<String>,
+
+    /// Rewards table to use instead of the one of the canister, as YAML or JSON
+    #[clap(long)]
+    pub rewards_table: Option<PathBuf>,
+
+    /// Failure rate below which nodes get the full rewards
+    #[clap(long)]
+    pub min_failure_rate: Option<f64>,
+
+    /// Failure rate above which nodes get the maximum rewards reduction
+    #[clap(long)]
+    pub max_failure_rate: Option<f64>,
+
+    /// Maximum share of the base rewards that nodes can lose
+    #[clap(long)]
+    pub max_rewards_reduction: Option<f64>,
+
+    /// Number of winners and losers to show
+    #[clap(long, default_value_t = 10)]
+    pub top: usize,
+
+    /// Write the rewards of every provider and month before and after the change to this CSV file
+    #[clap(long)]
+    pub csv_output: Option<PathBuf>,
+}
+
+impl Simulate {
+    /// The simulated penalty, if any of its parameters changed
+    fn penalty(&self) -> Option<PerformancePenalty> {
+        if self.min_failure_rate.is_none() && self.max_failure_rate.is_none() && self.max_rewards_reduction.is_none() {
+            return None;
+        }
+        let default = PerformancePenalty::default();
+        Some(PerformancePenalty {
+            min_failure_rate: self.min_failure_rate.unwrap_or(default.min_failure_rate),
+            max_failure_rate: self.max_failure_rate.unwrap_or(default.max_failure_rate),
+            max_rewards_reduction: self.max_rewards_reduction.unwrap_or(default.max_rewards_reduction),
+        })
+    }
+}
+
+impl ExecutableCommand for Simulate {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Signer
+    }
+
+    fn validate(&self, _args: &GlobalArgs, cmd: &mut clap::Command) {
+        let penalty = self.penalty();
+        if self.rewards_table.is_none() && penalty.is_none() {
+            cmd.error(
+                ErrorKind::MissingRequiredArgument,
+                "Provide a rewards table or at least one of the performance penalty parameters to simulate",
+            )
+            .exit();
+        }
+        if let Some(Err(e)) = penalty.map(|p| p.validate()) {
+            cmd.error(ErrorKind::InvalidValue, e.to_string()).exit();
+        }
+    }
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        let rewards_table: Option<NodeRewardsTable> = match &self.rewards_table {
+            Some(path) => Some(NodeRewardsTableFlattened::from_yaml(&fs_err::read(path)?)?.into()),
+            None => None,
+        };
+        let penalty = self.penalty();
+
+        let (_, canister_agent) = ctx.create_ic_agent_canister_client().await?;
+        let node_rewards_client: NodeRewardsCanisterWrapper = canister_agent.clone().into();
+        let governance_client: GovernanceCanisterWrapper = canister_agent.into();
+        let gov_rewards_list = governance_client.list_node_provider_rewards(None).await?;
+
+        let mut months = vec![];
+        for month in &self.months {
+            let period = governance_rewards_period(&gov_rewards_list, month)?;
+            let algorithm = RewardsAlgorithm::for_version(period.algorithm_version)?;
+            let nrc_data = fetch_nrc_data_for_days(&node_rewards_client, period.start_date, period.end_date, period.algorithm_version).await?;
+            months.push(MonthSimulation {
+                month: month.clone(),
+                xdr_permyriad_per_icp: period.xdr_permyriad_per_icp,
+                providers: replay(&nrc_data, algorithm, rewards_table.as_ref(), penalty.as_ref()),
+            });
+        }
+
+        print_budget_table(&months);
+
+        let mut providers: BTreeMap<PrincipalId, ProviderSimulation> = BTreeMap::new();
+        for month in &months {
+            for (provider_id, rewards) in &month.providers {
+                let total = providers.entry(*provider_id).or_default();
+                total.before += rewards.before;
+                total.after += rewards.after;
+                total.diff_icp += month.to_icp(rewards.diff());
+            }
+        }
+        let by_diff = providers.iter().sorted_by(|a, b| b.1.diff().total_cmp(&a.1.diff())).collect_vec();
+        let winners = by_diff.iter().filter(|(_, p)| p.diff() > 0.0).take(self.top).copied().collect_vec();
+        let losers = by_diff.iter().rev().filter(|(_, p)| p.diff() < 0.0).take(self.top).copied().collect_vec();
+
+        println!("\n=== BIGGEST WINNERS ===");
+        println!("{}", providers_table(&winners));
+        println!("\n=== BIGGEST LOSERS ===");
+        println!("{}", providers_table(&losers));
+        println!(
+            "\n{} providers gain, {} lose and {} are unaffected",
+            by_diff.iter().filter(|(_, p)| p.diff() > 0.0).count(),
+            by_diff.iter().filter(|(_, p)| p.diff() < 0.0).count(),
+            by_diff.iter().filter(|(_, p)| p.diff() == 0.0).count()
+        );
+
+        if let Some(path) = &self.csv_output {
+            write_csv(path, &months)?;
+            info!("Wrote the simulation of every provider and month to {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct ProviderSimulation {
+    /// XDR permyriad computed by the canister
+    before: f64,
+    /// XDR permyriad with the simulated parameters
+    after: f64,
+    diff_icp: f64,
+}
+
+impl ProviderSimulation {
+    fn diff(&self) -> f64 {
+        self.after - self.before
+    }
+}
+
+struct MonthSimulation {
+    month: String,
+    xdr_permyriad_per_icp: u64,
+    providers: BTreeMap<PrincipalId, ProviderSimulation>,
+}
+
+impl MonthSimulation {
+    fn to_icp(&self, xdr_permyriad: f64) -> f64 {
+        xdr_permyriad / self.xdr_permyriad_per_icp as f64
+    }
+
+    fn totals(&self) -> (f64, f64) {
+        self.providers
+            .values()
+            .fold((0.0, 0.0), |(before, after), p| (before + p.before, after + p.after))
+    }
+}
+
+/// The failure rate the canister penalized the node for, if it reported one
+fn effective_failure_rate(failure_rate: &DailyNodeFailureRate) -> Option<f64> {
+    match failure_rate {
+        DailyNodeFailureRate::SubnetMember { node_metrics: Some(m) } => m.relative_failure_rate,
+        DailyNodeFailureRate::NonSubnetMember { extrapolated_failure_rate } => *extrapolated_failure_rate,
+        _ => None,
+    }
+}
+
+/// A node rewarded by the canister on one day, with what the replay needs of its results
+#[derive(Debug, Clone)]
+struct NrcNodeDay {
+    node_id: Option<PrincipalId>,
+    node_reward_type: String,
+    region: String,
+    base_rewards_xdr_permyriad: u64,
+    performance_multiplier: Option<f64>,
+    adjusted_rewards_xdr_permyriad: u64,
+    failure_rate: Option<DailyNodeFailureRate>,
+}
+
+/// Rewards of each provider as computed by the canister, and recomputed with the simulated parameters.
+/// Whatever isn't simulated is taken from the canister's results as is.
+fn replay(
+    nrc_data: &NrcData,
+    algorithm: RewardsAlgorithm,
+    rewards_table: Option<&NodeRewardsTable>,
+    penalty: Option<&PerformancePenalty>,
+) -> BTreeMap<PrincipalId, ProviderSimulation> {
+    let days = nrc_data.providers_rewards.iter().flat_map(|(provider_id, daily_rewards)| {
+        daily_rewards.iter().map(|(_, rewards)| {
+            let nodes = rewards
+                .daily_nodes_rewards
+                .iter()
+                .map(|node| NrcNodeDay {
+                    node_id: node.node_id,
+                    node_reward_type: node.node_reward_type.clone().unwrap_or_default(),
+                    region: node.region.clone().unwrap_or_default(),
+                    base_rewards_xdr_permyriad: node.base_rewards_xdr_permyriad.unwrap_or_default(),
+                    performance_multiplier: node.performance_multiplier,
+                    adjusted_rewards_xdr_permyriad: node.adjusted_rewards_xdr_permyriad.unwrap_or_default(),
+                    failure_rate: node.daily_node_failure_rate.clone(),
+                })
+                .collect_vec();
+            (*provider_id, nodes)
+        })
+    });
+    replay_days(days, algorithm, rewards_table, penalty)
+}
+
+/// [replay] of the nodes each provider had rewarded on each day
+fn replay_days(
+    days: impl IntoIterator<Item = (PrincipalId, Vec<NrcNodeDay>)>,
+    algorithm: RewardsAlgorithm,
+    rewards_table: Option<&NodeRewardsTable>,
+    penalty: Option<&PerformancePenalty>,
+) -> BTreeMap<PrincipalId, ProviderSimulation> {
+    let mut providers: BTreeMap<PrincipalId, ProviderSimulation> = BTreeMap::new();
+    for (provider_id, nrc_nodes) in days {
+        let total = providers.entry(provider_id).or_default();
+        let nodes = nrc_nodes
+            .iter()
+            .filter_map(|node| {
+                Some(NodeDailyInput {
+                    node_id: node.node_id?,
+                    provider_id,
+                    node_reward_type: node.node_reward_type.clone(),
+                    region: node.region.clone(),
+                    metrics: None,
+                })
+            })
+            .collect_vec();
+        let base_rewards = rewards_table.map(|table| algorithm.base_rewards(table, &nodes)).unwrap_or_default();
+
+        for node in &nrc_nodes {
+            let base = node
+                .node_id
+                .and_then(|node_id| base_rewards.get(&node_id).copied())
+                .unwrap_or(node.base_rewards_xdr_permyriad as f64);
+            let multiplier = penalty
+                .zip(node.failure_rate.as_ref().and_then(effective_failure_rate))
+                .map(|(penalty, failure_rate)| penalty.multiplier(failure_rate))
+                .unwrap_or(node.performance_multiplier.unwrap_or(1.0));
+            total.before += node.adjusted_rewards_xdr_permyriad as f64;
+            total.after += base * multiplier;
+        }
+    }
+    providers
+}
+
+fn percent(diff: f64, before: f64) -> String {
+    if before > 0.0 {
+        format!("{:.2}%", diff / before * 100.0)
+    } else {
+        "N/A".to_string()
+    }
+}
+
+fn print_budget_table(months: &[MonthSimulation]) {
+    let mut builder = Builder::default();
+    builder.push_record([
+        "Month",
+        "Before",
+        "After",
+        "Diff",
+        "Diff %",
+        "XDRPermyriad/ICP",
+        "Before ICP",
+        "After ICP",
+        "Diff ICP",
+    ]);
+    let (mut total_before, mut total_after, mut total_before_icp, mut total_after_icp) = (0.0, 0.0, 0.0, 0.0);
+    for month in months {
+        let (before, after) = month.totals();
+        total_before += before;
+        total_after += after;
+        total_before_icp += month.to_icp(before);
+        total_after_icp += month.to_icp(after);
+        builder.push_record([
+            month.month.clone(),
+            format!("{:.0}", before),
+            format!("{:.0}", after),
+            format!("{:.0}", after - before),
+            percent(after - before, before),
+            month.xdr_permyriad_per_icp.to_string(),
+            format!("{:.2}", month.to_icp(before)),
+            format!("{:.2}", month.to_icp(after)),
+            format!("{:.2}", month.to_icp(after - before)),
+        ]);
+    }
+    builder.push_record([
+        "Total".to_string(),
+        format!("{:.0}", total_before),
+        format!("{:.0}", total_after),
+        format!("{:.0}", total_after - total_before),
+        percent(total_after - total_before, total_before),
+        String::new(),
+        format!("{:.2}", total_before_icp),
+        format!("{:.2}", total_after_icp),
+        format!("{:.2}", total_after_icp - total_before_icp),
+    ]);
+    let mut table = builder.build();
+    table.with(Style::modern()).with(Modify::new(Rows::new(0..1)).with(Alignment::center()));
+
+    println!("\n=== REWARDS BUDGET: CANISTER vs SIMULATION ===");
+    println!("Unit: XDRPermyriad unless stated otherwise");
+    println!("{}", table);
+}
+
+fn providers_table(providers: &[(&PrincipalId, &ProviderSimulation)]) -> Table {
+    let mut builder = Builder::default();
+    builder.push_record(["Provider", "Before", "After", "Diff", "Diff %", "Diff ICP"]);
+    for (provider_id, simulation) in providers {
+        builder.push_record([
+            get_provider_prefix(&provider_id.to_string()),
+            format!("{:.0}", simulation.before),
+            format!("{:.0}", simulation.after),
+            format!("{:.0}", simulation.diff()),
+            percent(simulation.diff(), simulation.before),
+            format!("{:.2}", simulation.diff_icp),
+        ]);
+    }
+    let mut table = builder.build();
+    table.with(Style::modern()).with(Modify::new(Rows::new(0..1)).with(Alignment::center()));
+    table
+}
+
+fn write_csv(path: &Path, months: &[MonthSimulation]) -> anyhow::Result<()> {
+    let mut wtr = Writer::from_path(path)?;
+    wtr.write_record([
+        "month",
+        "provider_id",
+        "before_xdr_permyriad",
+        "after_xdr_permyriad",
+        "diff_xdr_permyriad",
+        "xdr_permyriad_per_icp",
+        "before_icp",
+        "after_icp",
+        "diff_icp",
+    ])?;
+    for month in months {
+        for (provider_id, simulation) in &month.providers {
+            wtr.write_record([
+                month.month.clone(),
+                provider_id.to_string(),
+                format!("{:.0}", simulation.before),
+                format!("{:.0}", simulation.after),
+                format!("{:.0}", simulation.diff()),
+                month.xdr_permyriad_per_icp.to_string(),
+                format!("{:.8}", month.to_icp(simulation.before)),
+                format!("{:.8}", month.to_icp(simulation.after)),
+                format!("{:.8}", month.to_icp(simulation.diff())),
+            ])?;
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_protobuf::registry::node_rewards::v2::{NodeRewardRate, NodeRewardRates};
+
+    fn nrc_node(number: Option<u64>, failure_rate: Option<DailyNodeFailureRate>) -> NrcNodeDay {
+        NrcNodeDay {
+            node_id: number.map(PrincipalId::new_node_test_id),
+            node_reward_type: "type1".to_string(),
+            region: "Europe,Switzerland,Zurich".to_string(),
+            base_rewards_xdr_permyriad: 5000,
+            performance_multiplier: Some(0.5),
+            adjusted_rewards_xdr_permyriad: 2500,
+            failure_rate,
+        }
+    }
+
+    fn replay_one(node: NrcNodeDay, rewards_table: Option<&NodeRewardsTable>, penalty: Option<&PerformancePenalty>) -> ProviderSimulation {
+        let provider_id = PrincipalId::new_user_test_id(1);
+        let providers = replay_days([(provider_id, vec![node])], RewardsAlgorithm::PerformanceBasedV1, rewards_table, penalty);
+        providers[&provider_id].clone()
+    }
+
+    #[test]
+    fn modified_rewards_table_changes_the_base_rewards() {
+        let rewards_table = NodeRewardsTable {
+            table: BTreeMap::from([(
+                "Europe".to_string(),
+                NodeRewardRates {
+                    rates: BTreeMap::from([(
+                        "type1".to_string(),
+                        NodeRewardRate {
+                            xdr_permyriad_per_node_per_month: 304375,
+                            reward_coefficient_percent: None,
+                        },
+                    )]),
+                },
+            )]),
+        };
+
+        // The canister's multiplier is kept
+        let simulation = replay_one(nrc_node(Some(1), None), Some(&rewards_table), None);
+        assert_eq!(simulation.before, 2500.0);
+        assert!((simulation.after - 5000.0).abs() < 1e-6);
+
+        // Without a node id the canister's base rewards are kept
+        let simulation = replay_one(nrc_node(None, None), Some(&rewards_table), None);
+        assert_eq!(simulation.after, 2500.0);
+    }
+
+    #[test]
+    fn modified_penalty_applies_to_the_reported_failure_rate() {
+        let penalty = PerformancePenalty::default();
+
+        let unassigned = DailyNodeFailureRate::NonSubnetMember {
+            extrapolated_failure_rate: Some(0.35),
+        };
+        let simulation = replay_one(nrc_node(Some(1), Some(unassigned)), None, Some(&penalty));
+        assert!((simulation.after - 5000.0 * 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn missing_failure_rate_keeps_the_canister_multiplier() {
+        let penalty = PerformancePenalty::default();
+        let missing_failure_rates = [
+            None,
+            Some(DailyNodeFailureRate::NonSubnetMember {
+                extrapolated_failure_rate: None,
+            }),
+            Some(DailyNodeFailureRate::SubnetMember { node_metrics: None }),
+        ];
+
+        for failure_rate in missing_failure_rates {
+            let simulation = replay_one(nrc_node(Some(1), failure_rate), None, Some(&penalty));
+            assert_eq!(simulation.before, 2500.0);
+            assert_eq!(simulation.after, 2500.0);
+        }
+    }
+}
@@ -52,7 +52,7 @@ impl ExecutableCommand for Remove {
             })
             .await?;
         Submitter::from(&self.submission_parameters)
-            .propose_and_print(ctx.ic_admin_executor().await?.execution(runner_proposal), ForumPostKind::Generic)
+            .propose_and_print(ctx.proposal_execution(runner_proposal).await?, ForumPostKind::Generic)
             .await
     }
 
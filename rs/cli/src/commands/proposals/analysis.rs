@@ -0,0 +1,917 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display, Formatter},
+};
+
+use candid::Decode;
+use cycles_minting_canister::SetAuthorizedSubnetworkListArgs;
+use decentralization::{SubnetChangeResponse, business_rules::BusinessRules, nakamoto::NakamotoScore, network::DecentralizedSubnet};
+use ic_canisters::cycles_minting::CyclesMintingCanisterWrapper;
+use ic_management_types::{HealthStatus, Node, NodeFeature, Subnet};
+use ic_nns_governance::pb::v1::NnsFunction;
+use ic_nns_governance_api::{ProposalInfo, proposal::Action};
+use ic_protobuf::registry::firewall::v1::FirewallRule;
+use ic_registry_subnet_type::SubnetType;
+use ic_types::PrincipalId;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use registry_canister::mutations::{
+    do_change_subnet_membership::ChangeSubnetMembershipPayload,
+    do_create_subnet::CreateSubnetPayload,
+    do_deploy_guestos_to_all_subnet_nodes::DeployGuestosToAllSubnetNodesPayload,
+    do_update_nodes_hostos_version::UpdateNodesHostosVersionPayload,
+    firewall::{AddFirewallRulesPayload, RemoveFirewallRulesPayload, UpdateFirewallRulesPayload},
+    node_management::do_remove_nodes::RemoveNodesPayload,
+};
+use serde::Serialize;
+
+use crate::ctx::DreContext;
+
+/// The NNS functions `dre proposals analyze` understands.
+pub const SUPPORTED_NNS_FUNCTIONS: &[NnsFunction] = &[
+    NnsFunction::ChangeSubnetMembership,
+    NnsFunction::CreateSubnet,
+    NnsFunction::RemoveNodes,
+    NnsFunction::DeployHostosToSomeNodes,
+    NnsFunction::DeployGuestosToAllSubnetNodes,
+    NnsFunction::SetAuthorizedSubnetworks,
+    NnsFunction::AddFirewallRules,
+    NnsFunction::RemoveFirewallRules,
+    NnsFunction::UpdateFirewallRules,
+];
+
+/// Analysis of a proposal, with the findings reviewers should look at before voting.
+#[derive(Debug, Serialize)]
+pub struct ProposalAnalysisReport {
+    pub proposal_id: u64,
+    pub title: String,
+    pub nns_function: String,
+    pub risks: Vec<String>,
+    pub analysis: ProposalAnalysis,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "details", rename_all = "snake_case")]
+pub enum ProposalAnalysis {
+    ChangeSubnetMembership(SubnetChangeResponse),
+    CreateSubnet(CreateSubnetAnalysis),
+    RemoveNodes(RemoveNodesAnalysis),
+    DeployHostosToSomeNodes(HostosDeploymentAnalysis),
+    DeployGuestosToAllSubnetNodes(GuestosDeploymentAnalysis),
+    SetAuthorizedSubnetworks(AuthorizedSubnetsAnalysis),
+    FirewallRules(FirewallRulesAnalysis),
+}
+
+impl ProposalAnalysis {
+    pub fn risks(&self) -> Vec<String> {
+        match self {
+            Self::ChangeSubnetMembership(change) => {
+                let mut risks = vec![];
+                if change.penalties_after_change.0 > change.penalties_before_change.0 {
+                    risks.push(format!(
+                        "Business rules penalties increase from {} to {}",
+                        change.penalties_before_change.0, change.penalties_after_change.0
+                    ));
+                }
+                if change.score_after < change.score_before {
+                    risks.push("The decentralization of the subnet gets worse".to_string());
+                }
+                risks
+            }
+            Self::CreateSubnet(a) => a.risks(),
+            Self::RemoveNodes(a) => a.risks(),
+            Self::DeployHostosToSomeNodes(a) => a.risks(),
+            Self::DeployGuestosToAllSubnetNodes(a) => a.risks(),
+            Self::SetAuthorizedSubnetworks(a) => a.risks(),
+            Self::FirewallRules(a) => a.risks(),
+        }
+    }
+}
+
+/// Number of nodes a subnet of the given size can lose and still make progress.
+fn fault_tolerance(subnet_size: usize) -> usize {
+    subnet_size.saturating_sub(1) / 3
+}
+
+pub async fn analyze(ctx: &DreContext, proposal: ProposalInfo) -> anyhow::Result<ProposalAnalysisReport> {
+    let proposal_id = proposal.id.map(|id| id.id).unwrap_or_default();
+    let body = proposal
+        .proposal
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Proposal {} carries no proposal body", proposal_id))?;
+    let (nns_function, payload) = match &body.action {
+        Some(Action::ExecuteNnsFunction(function)) => (NnsFunction::try_from(function.nns_function)?, function.payload.as_slice()),
+        _ => anyhow::bail!("Proposal {} does not execute an NNS function", proposal_id),
+    };
+
+    let registry = ctx.registry().await;
+    let nodes = registry.nodes().await?;
+    let subnets = registry.subnets().await?;
+    let analysis = match nns_function {
+        NnsFunction::ChangeSubnetMembership => {
+            let change = Decode!(payload, ChangeSubnetMembershipPayload)?;
+            let runner = ctx.runner().await?;
+            ProposalAnalysis::ChangeSubnetMembership(runner.decentralization_change(&change, None, Some(body.summary.clone())).await?)
+        }
+        NnsFunction::CreateSubnet => {
+            let payload = Decode!(payload, CreateSubnetPayload)?;
+            let health = ctx.runner().await?.health_of_nodes().await?;
+            ProposalAnalysis::CreateSubnet(CreateSubnetAnalysis::new(
+                payload.node_ids.iter().map(|n| n.get()).collect(),
+                payload.replica_version_id,
+                &nodes,
+                &subnets,
+                &health,
+                &ctx.business_rules().await?,
+            )?)
+        }
+        NnsFunction::RemoveNodes => {
+            let payload = Decode!(payload, RemoveNodesPayload)?;
+            ProposalAnalysis::RemoveNodes(RemoveNodesAnalysis::new(
+                payload.node_ids.iter().map(|n| n.get()).collect(),
+                &nodes,
+                &subnets,
+            ))
+        }
+        NnsFunction::DeployHostosToSomeNodes => {
+            let payload = Decode!(payload, UpdateNodesHostosVersionPayload)?;
+            let elected = match &payload.hostos_version_id {
+                Some(version) => registry.elected_hostos().await?.contains(version),
+                None => true,
+            };
+            ProposalAnalysis::DeployHostosToSomeNodes(HostosDeploymentAnalysis::new(
+                payload.node_ids.iter().map(|n| n.get()).collect(),
+                payload.hostos_version_id,
+                elected,
+                &nodes,
+                &subnets,
+            ))
+        }
+        NnsFunction::DeployGuestosToAllSubnetNodes => {
+            let payload = Decode!(payload, DeployGuestosToAllSubnetNodesPayload)?;
+            let elected = registry.elected_guestos().await?.contains(&payload.replica_version_id);
+            let health = ctx.runner().await?.health_of_nodes().await?;
+            ProposalAnalysis::DeployGuestosToAllSubnetNodes(GuestosDeploymentAnalysis::new(
+                payload.subnet_id,
+                payload.replica_version_id,
+                elected,
+                &subnets,
+                &health,
+            )?)
+        }
+        NnsFunction::SetAuthorizedSubnetworks => {
+            let payload = Decode!(payload, SetAuthorizedSubnetworkListArgs)?;
+            // Only the default subnets can be compared, the subnets authorized for a principal cannot be queried
+            let default_subnets = match payload.who {
+                None => {
+                    let (_, agent) = ctx.create_ic_agent_canister_client().await?;
+                    Some(CyclesMintingCanisterWrapper::from(agent).get_default_subnets().await?)
+                }
+                Some(_) => None,
+            };
+            ProposalAnalysis::SetAuthorizedSubnetworks(AuthorizedSubnetsAnalysis::new(
+                payload.who,
+                payload.subnets.iter().map(|s| s.get()).collect(),
+                default_subnets,
+                &subnets,
+            ))
+        }
+        NnsFunction::AddFirewallRules => {
+            let payload = Decode!(payload, AddFirewallRulesPayload)?;
+            let current = registry.firewall_rule_set(payload.scope.clone()).await?.entries;
+            ProposalAnalysis::FirewallRules(FirewallRulesAnalysis::additions(
+                payload.scope.to_string(),
+                current,
+                &payload.positions,
+                payload.rules,
+            ))
+        }
+        NnsFunction::RemoveFirewallRules => {
+            let payload = Decode!(payload, RemoveFirewallRulesPayload)?;
+            let current = registry.firewall_rule_set(payload.scope.clone()).await?.entries;
+            ProposalAnalysis::FirewallRules(FirewallRulesAnalysis::removals(payload.scope.to_string(), current, &payload.positions))
+        }
+        NnsFunction::UpdateFirewallRules => {
+            let payload = Decode!(payload, UpdateFirewallRulesPayload)?;
+            let current = registry.firewall_rule_set(payload.scope.clone()).await?.entries;
+            ProposalAnalysis::FirewallRules(FirewallRulesAnalysis::updates(
+                payload.scope.to_string(),
+                current,
+                &payload.positions,
+                payload.rules,
+            ))
+        }
+        other => anyhow::bail!(
+            "Proposal {} executes {}, which cannot be analyzed. Supported NNS functions: {}",
+            proposal_id,
+            other.as_str_name(),
+            SUPPORTED_NNS_FUNCTIONS.iter().map(|f| f.as_str_name()).join(", ")
+        ),
+    };
+
+    Ok(ProposalAnalysisReport {
+        proposal_id,
+        title: body.title.unwrap_or_default(),
+        nns_function: nns_function.as_str_name().to_string(),
+        risks: analysis.risks(),
+        analysis,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateSubnetAnalysis {
+    pub replica_version: String,
+    pub nodes: Vec<PrincipalId>,
+    pub unknown_nodes: Vec<PrincipalId>,
+    /// Nodes that are already members of a subnet, with their subnet
+    pub assigned_nodes: BTreeMap<PrincipalId, PrincipalId>,
+    pub unhealthy_nodes: BTreeMap<PrincipalId, HealthStatus>,
+    pub nakamoto: NakamotoScore,
+    /// Lowest Nakamoto coefficient of every feature over all subnets, before and after the subnet is created
+    pub network_min_nakamoto_before: IndexMap<NodeFeature, f64>,
+    pub network_min_nakamoto_after: IndexMap<NodeFeature, f64>,
+    pub business_rules_penalties: (usize, Vec<String>),
+}
+
+impl CreateSubnetAnalysis {
+    pub fn new(
+        node_ids: Vec<PrincipalId>,
+        replica_version: String,
+        nodes: &IndexMap<PrincipalId, Node>,
+        subnets: &IndexMap<PrincipalId, Subnet>,
+        health: &IndexMap<PrincipalId, HealthStatus>,
+        business_rules: &BusinessRules,
+    ) -> anyhow::Result<Self> {
+        let subnet_nodes = node_ids.iter().filter_map(|id| nodes.get(id)).cloned().collect_vec();
+        let nakamoto = NakamotoScore::new_from_nodes(&subnet_nodes);
+        let network_min_nakamoto_before = subnets.values().fold(IndexMap::new(), |acc, subnet| {
+            min_per_feature(acc, &NakamotoScore::new_from_nodes(&subnet.nodes).scores_individual())
+        });
+        let network_min_nakamoto_after = min_per_feature(network_min_nakamoto_before.clone(), &nakamoto.scores_individual());
+
+        Ok(Self {
+            replica_version,
+            unknown_nodes: node_ids.iter().filter(|id| !nodes.contains_key(*id)).cloned().collect(),
+            assigned_nodes: subnet_nodes.iter().filter_map(|n| n.subnet_id.map(|s| (n.principal, s))).collect(),
+            unhealthy_nodes: subnet_nodes
+                .iter()
+                .map(|n| (n.principal, health.get(&n.principal).cloned().unwrap_or(HealthStatus::Unknown)))
+                .filter(|(_, status)| *status != HealthStatus::Healthy)
+                .collect(),
+            business_rules_penalties: DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(
+                &PrincipalId::default(),
+                &subnet_nodes,
+                business_rules,
+            )?,
+            nakamoto,
+            network_min_nakamoto_before,
+            network_min_nakamoto_after,
+            nodes: node_ids,
+        })
+    }
+
+    fn risks(&self) -> Vec<String> {
+        let mut risks = vec![];
+        if !self.unknown_nodes.is_empty() {
+            risks.push(format!("Nodes not found in the registry: {}", self.unknown_nodes.iter().join(", ")));
+        }
+        for (node, subnet) in &self.assigned_nodes {
+            risks.push(format!("Node {} is already a member of subnet {}", node, subnet));
+        }
+        if !self.unhealthy_nodes.is_empty() {
+            risks.push(format!(
+                "{} of the {} nodes are not healthy: {}",
+                self.unhealthy_nodes.len(),
+                self.nodes.len(),
+                self.unhealthy_nodes.iter().map(|(n, s)| format!("{} ({})", n, s)).join(", ")
+            ));
+        }
+        for (feature, after) in &self.network_min_nakamoto_after {
+            if self.network_min_nakamoto_before.get(feature).is_some_and(|before| after < before) {
+                risks.push(format!(
+                    "The new subnet has the lowest {} Nakamoto coefficient of all subnets ({:.2})",
+                    feature, after
+                ));
+            }
+        }
+        if self.business_rules_penalties.0 > 0 {
+            risks.push(format!("The new subnet has business rules penalties {}", self.business_rules_penalties.0));
+        }
+        risks
+    }
+}
+
+fn min_per_feature(mut acc: IndexMap<NodeFeature, f64>, scores: &IndexMap<NodeFeature, f64>) -> IndexMap<NodeFeature, f64> {
+    for (feature, score) in scores {
+        let min = acc.entry(feature.clone()).or_insert(*score);
+        *min = min.min(*score);
+    }
+    acc
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoveNodesAnalysis {
+    pub nodes: Vec<PrincipalId>,
+    pub unknown_nodes: Vec<PrincipalId>,
+    pub api_boundary_nodes: Vec<PrincipalId>,
+    pub subnets: Vec<SubnetNodesImpact>,
+}
+
+/// How many nodes of a subnet a proposal touches at once.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SubnetNodesImpact {
+    pub subnet_id: PrincipalId,
+    pub subnet_size: usize,
+    pub nodes: Vec<PrincipalId>,
+    /// Number of nodes the subnet can lose and still make progress
+    pub fault_tolerance: usize,
+}
+
+impl SubnetNodesImpact {
+    fn fraction(&self) -> f64 {
+        self.nodes.len() as f64 / self.subnet_size as f64
+    }
+
+    fn exceeds_fault_tolerance(&self) -> bool {
+        self.nodes.len() > self.fault_tolerance
+    }
+}
+
+/// Groups the nodes by the subnet they are members of. Unassigned and unknown nodes are left out.
+fn subnet_impacts(node_ids: &[PrincipalId], nodes: &IndexMap<PrincipalId, Node>, subnets: &IndexMap<PrincipalId, Subnet>) -> Vec<SubnetNodesImpact> {
+    node_ids
+        .iter()
+        .filter_map(|id| nodes.get(id).and_then(|n| n.subnet_id.map(|s| (s, *id))))
+        .into_group_map()
+        .into_iter()
+        .sorted_by_key(|(subnet_id, _)| *subnet_id)
+        .map(|(subnet_id, nodes)| {
+            let subnet_size = subnets.get(&subnet_id).map(|s| s.nodes.len()).unwrap_or(nodes.len());
+            SubnetNodesImpact {
+                subnet_id,
+                subnet_size,
+                nodes,
+                fault_tolerance: fault_tolerance(subnet_size),
+            }
+        })
+        .collect()
+}
+
+impl RemoveNodesAnalysis {
+    pub fn new(node_ids: Vec<PrincipalId>, nodes: &IndexMap<PrincipalId, Node>, subnets: &IndexMap<PrincipalId, Subnet>) -> Self {
+        Self {
+            unknown_nodes: node_ids.iter().filter(|id| !nodes.contains_key(*id)).cloned().collect(),
+            api_boundary_nodes: node_ids
+                .iter()
+                .filter(|id| nodes.get(*id).is_some_and(|n| n.is_api_boundary_node))
+                .cloned()
+                .collect(),
+            subnets: subnet_impacts(&node_ids, nodes, subnets),
+            nodes: node_ids,
+        }
+    }
+
+    fn risks(&self) -> Vec<String> {
+        let mut risks = vec![];
+        if !self.unknown_nodes.is_empty() {
+            risks.push(format!("Nodes not found in the registry: {}", self.unknown_nodes.iter().join(", ")));
+        }
+        if !self.api_boundary_nodes.is_empty() {
+            risks.push(format!(
+                "Nodes still serving as API boundary nodes: {}",
+                self.api_boundary_nodes.iter().join(", ")
+            ));
+        }
+        for subnet in &self.subnets {
+            risks.push(format!(
+                "{} nodes are still members of subnet {}, the registry rejects the removal of assigned nodes",
+                subnet.nodes.len(),
+                subnet.subnet_id
+            ));
+            if subnet.exceeds_fault_tolerance() {
+                risks.push(format!(
+                    "Subnet {} would be orphaned: it loses {} of {} nodes, more than the {} it can tolerate",
+                    subnet.subnet_id,
+                    subnet.nodes.len(),
+                    subnet.subnet_size,
+                    subnet.fault_tolerance
+                ));
+            }
+        }
+        risks
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HostosDeploymentAnalysis {
+    pub version: Option<String>,
+    pub version_elected: bool,
+    pub nodes: Vec<PrincipalId>,
+    pub unknown_nodes: Vec<PrincipalId>,
+    pub unassigned_nodes: Vec<PrincipalId>,
+    pub subnets: Vec<SubnetNodesImpact>,
+}
+
+impl HostosDeploymentAnalysis {
+    pub fn new(
+        node_ids: Vec<PrincipalId>,
+        version: Option<String>,
+        version_elected: bool,
+        nodes: &IndexMap<PrincipalId, Node>,
+        subnets: &IndexMap<PrincipalId, Subnet>,
+    ) -> Self {
+        Self {
+            version,
+            version_elected,
+            unknown_nodes: node_ids.iter().filter(|id| !nodes.contains_key(*id)).cloned().collect(),
+            unassigned_nodes: node_ids
+                .iter()
+                .filter(|id| nodes.get(*id).is_some_and(|n| n.subnet_id.is_none()))
+                .cloned()
+                .collect(),
+            subnets: subnet_impacts(&node_ids, nodes, subnets),
+            nodes: node_ids,
+        }
+    }
+
+    fn risks(&self) -> Vec<String> {
+        let mut risks = vec![];
+        match &self.version {
+            Some(version) if !self.version_elected => risks.push(format!("HostOS version {} is not elected", version)),
+            Some(_) => (),
+            None => risks.push("The proposal removes the HostOS version of the nodes".to_string()),
+        }
+        if !self.unknown_nodes.is_empty() {
+            risks.push(format!("Nodes not found in the registry: {}", self.unknown_nodes.iter().join(", ")));
+        }
+        for subnet in self.subnets.iter().filter(|s| s.exceeds_fault_tolerance()) {
+            risks.push(format!(
+                "Upgrades {} of {} nodes ({:.0}%) of subnet {} at once, more than the {} it can lose",
+                subnet.nodes.len(),
+                subnet.subnet_size,
+                subnet.fraction() * 100.,
+                subnet.subnet_id,
+                subnet.fault_tolerance
+            ));
+        }
+        risks
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuestosDeploymentAnalysis {
+    pub subnet_id: PrincipalId,
+    pub subnet_size: usize,
+    pub current_version: String,
+    pub version: String,
+    pub version_elected: bool,
+    pub unhealthy_nodes: BTreeMap<PrincipalId, HealthStatus>,
+}
+
+impl GuestosDeploymentAnalysis {
+    pub fn new(
+        subnet_id: PrincipalId,
+        version: String,
+        version_elected: bool,
+        subnets: &IndexMap<PrincipalId, Subnet>,
+        health: &IndexMap<PrincipalId, HealthStatus>,
+    ) -> anyhow::Result<Self> {
+        let subnet = subnets
+            .get(&subnet_id)
+            .ok_or_else(|| anyhow::anyhow!("Subnet {} not found in the registry", subnet_id))?;
+        Ok(Self {
+            subnet_id,
+            subnet_size: subnet.nodes.len(),
+            current_version: subnet.replica_version.clone(),
+            version,
+            version_elected,
+            unhealthy_nodes: subnet
+                .nodes
+                .iter()
+                .map(|n| (n.principal, health.get(&n.principal).cloned().unwrap_or(HealthStatus::Unknown)))
+                .filter(|(_, status)| *status != HealthStatus::Healthy)
+                .collect(),
+        })
+    }
+
+    fn risks(&self) -> Vec<String> {
+        let mut risks = vec![];
+        if !self.version_elected {
+            risks.push(format!("GuestOS version {} is not elected", self.version));
+        }
+        if self.version == self.current_version {
+            risks.push(format!("Subnet {} already runs version {}", self.subnet_id, self.version));
+        }
+        if self.unhealthy_nodes.len() > fault_tolerance(self.subnet_size) {
+            risks.push(format!(
+                "{} of {} nodes of subnet {} are not healthy, the subnet may stall during the upgrade",
+                self.unhealthy_nodes.len(),
+                self.subnet_size,
+                self.subnet_id
+            ));
+        }
+        risks
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthorizedSubnetsAnalysis {
+    /// The principal the subnets are authorized for, none for the default subnets
+    pub who: Option<PrincipalId>,
+    pub subnets: Vec<PrincipalId>,
+    /// Only known for changes of the default subnets
+    pub added: Option<Vec<PrincipalId>>,
+    pub removed: Option<Vec<PrincipalId>>,
+    pub unknown_subnets: Vec<PrincipalId>,
+    pub system_subnets: Vec<PrincipalId>,
+}
+
+impl AuthorizedSubnetsAnalysis {
+    pub fn new(
+        who: Option<PrincipalId>,
+        subnet_ids: Vec<PrincipalId>,
+        current: Option<Vec<PrincipalId>>,
+        subnets: &IndexMap<PrincipalId, Subnet>,
+    ) -> Self {
+        let proposed: BTreeSet<_> = subnet_ids.iter().cloned().collect();
+        let current: Option<BTreeSet<_>> = current.map(|c| c.into_iter().collect());
+        Self {
+            who,
+            added: current.as_ref().map(|c| proposed.difference(c).cloned().collect()),
+            removed: current.as_ref().map(|c| c.difference(&proposed).cloned().collect()),
+            unknown_subnets: subnet_ids.iter().filter(|id| !subnets.contains_key(*id)).cloned().collect(),
+            system_subnets: subnet_ids
+                .iter()
+                .filter(|id| subnets.get(*id).is_some_and(|s| s.subnet_type == SubnetType::System))
+                .cloned()
+                .collect(),
+            subnets: subnet_ids,
+        }
+    }
+
+    fn risks(&self) -> Vec<String> {
+        let mut risks = vec![];
+        if !self.unknown_subnets.is_empty() {
+            risks.push(format!("Subnets not found in the registry: {}", self.unknown_subnets.iter().join(", ")));
+        }
+        if !self.system_subnets.is_empty() {
+            risks.push(format!("System subnets would be authorized: {}", self.system_subnets.iter().join(", ")));
+        }
+        if let Some(removed) = self.removed.as_ref().filter(|r| !r.is_empty()) {
+            risks.push(format!("Subnets would stop being default subnets: {}", removed.iter().join(", ")));
+        }
+        risks
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FirewallRulesAnalysis {
+    pub scope: String,
+    pub rules_before: usize,
+    pub rules_after: usize,
+    pub changes: Vec<FirewallRuleChange>,
+    /// Positions that do not exist in the current rule set
+    pub invalid_positions: Vec<i32>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct FirewallRuleChange {
+    pub position: i32,
+    pub before: Option<FirewallRule>,
+    pub after: Option<FirewallRule>,
+}
+
+impl FirewallRulesAnalysis {
+    /// Rules are inserted in the order of their positions, each position refers to the rule set with the previous rules inserted.
+    pub fn additions(scope: String, current: Vec<FirewallRule>, positions: &[i32], rules: Vec<FirewallRule>) -> Self {
+        let rules_before = current.len();
+        let mut entries = current;
+        let mut changes = vec![];
+        let mut invalid_positions = vec![];
+        for (position, rule) in positions.iter().cloned().zip(rules).sorted_by_key(|(position, _)| *position) {
+            if position < 0 || position as usize > entries.len() {
+                invalid_positions.push(position);
+                continue;
+            }
+            entries.insert(position as usize, rule.clone());
+            changes.push(FirewallRuleChange {
+                position,
+                before: None,
+                after: Some(rule),
+            });
+        }
+        Self {
+            scope,
+            rules_before,
+            rules_after: entries.len(),
+            changes,
+            invalid_positions,
+        }
+    }
+
+    pub fn removals(scope: String, current: Vec<FirewallRule>, positions: &[i32]) -> Self {
+        let rules_before = current.len();
+        let mut changes = vec![];
+        let mut invalid_positions = vec![];
+        for position in positions.iter().cloned().sorted().dedup() {
+            match usize::try_from(position).ok().and_then(|p| current.get(p)) {
+                Some(rule) => changes.push(FirewallRuleChange {
+                    position,
+                    before: Some(rule.clone()),
+                    after: None,
+                }),
+                None => invalid_positions.push(position),
+            }
+        }
+        Self {
+            scope,
+            rules_before,
+            rules_after: rules_before - changes.len(),
+            changes,
+            invalid_positions,
+        }
+    }
+
+    pub fn updates(scope: String, current: Vec<FirewallRule>, positions: &[i32], rules: Vec<FirewallRule>) -> Self {
+        let mut changes = vec![];
+        let mut invalid_positions = vec![];
+        for (position, rule) in positions.iter().cloned().zip(rules).sorted_by_key(|(position, _)| *position) {
+            match usize::try_from(position).ok().and_then(|p| current.get(p)) {
+                Some(before) => changes.push(FirewallRuleChange {
+                    position,
+                    before: Some(before.clone()),
+                    after: Some(rule),
+                }),
+                None => invalid_positions.push(position),
+            }
+        }
+        Self {
+            scope,
+            rules_before: current.len(),
+            rules_after: current.len(),
+            changes,
+            invalid_positions,
+        }
+    }
+
+    fn risks(&self) -> Vec<String> {
+        let mut risks = vec![];
+        if !self.invalid_positions.is_empty() {
+            risks.push(format!(
+                "Positions {} do not exist in the current {} rule set of {} rules",
+                self.invalid_positions.iter().join(", "),
+                self.scope,
+                self.rules_before
+            ));
+        }
+        for change in self.changes.iter().filter(|c| c.before.is_some() && c.before == c.after) {
+            risks.push(format!("Rule at position {} is updated without any change", change.position));
+        }
+        risks
+    }
+}
+
+impl Display for ProposalAnalysisReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Proposal {}: {}\n", self.proposal_id, self.title)?;
+        writeln!(f, "NNS function: `{}`\n", self.nns_function)?;
+        writeln!(f, "## Risks\n")?;
+        if self.risks.is_empty() {
+            writeln!(f, "No risks found.")?;
+        }
+        for risk in &self.risks {
+            writeln!(f, "- {}", risk)?;
+        }
+        writeln!(f, "\n## Analysis\n\n{}", self.analysis)
+    }
+}
+
+impl Display for ProposalAnalysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ChangeSubnetMembership(change) => write!(f, "{}", change),
+            Self::CreateSubnet(a) => {
+                writeln!(
+                    f,
+                    "New subnet of {} nodes running GuestOS version `{}`.\n",
+                    a.nodes.len(),
+                    a.replica_version
+                )?;
+                writeln!(
+                    f,
+                    "| Feature | Nakamoto coefficient | Lowest of all subnets before | Lowest of all subnets after |"
+                )?;
+                writeln!(f, "|---|---|---|---|")?;
+                for (feature, score) in a.nakamoto.scores_individual().iter().sorted_by_key(|(feature, _)| (*feature).clone()) {
+                    writeln!(
+                        f,
+                        "| {} | {:.2} | {:.2} | {:.2} |",
+                        feature,
+                        score,
+                        a.network_min_nakamoto_before.get(feature).unwrap_or(&0.),
+                        a.network_min_nakamoto_after.get(feature).unwrap_or(&0.)
+                    )?;
+                }
+                if !a.business_rules_penalties.1.is_empty() {
+                    writeln!(f, "\nBusiness rules checks:")?;
+                    for check in &a.business_rules_penalties.1 {
+                        writeln!(f, "- {}", check)?;
+                    }
+                }
+                Ok(())
+            }
+            Self::RemoveNodes(a) => {
+                writeln!(
+                    f,
+                    "Removes {} nodes from the registry, {} of them are members of a subnet.",
+                    a.nodes.len(),
+                    a.subnets.iter().map(|s| s.nodes.len()).sum::<usize>()
+                )?;
+                write_subnet_impacts(f, &a.subnets)
+            }
+            Self::DeployHostosToSomeNodes(a) => {
+                writeln!(
+                    f,
+                    "Deploys HostOS version `{}` to {} nodes, {} of them unassigned.",
+                    a.version.as_deref().unwrap_or("none"),
+                    a.nodes.len(),
+                    a.unassigned_nodes.len()
+                )?;
+                write_subnet_impacts(f, &a.subnets)
+            }
+            Self::DeployGuestosToAllSubnetNodes(a) => writeln!(
+                f,
+                "Upgrades the {} nodes of subnet {} from GuestOS version `{}` to `{}`. {} nodes are not healthy.",
+                a.subnet_size,
+                a.subnet_id,
+                a.current_version,
+                a.version,
+                a.unhealthy_nodes.len()
+            ),
+            Self::SetAuthorizedSubnetworks(a) => {
+                match &a.who {
+                    Some(who) => writeln!(f, "Authorizes {} subnets for {}.", a.subnets.len(), who)?,
+                    None => writeln!(f, "Sets {} default subnets.", a.subnets.len())?,
+                }
+                for (label, subnets) in [("Added", &a.added), ("Removed", &a.removed)] {
+                    if let Some(subnets) = subnets.as_ref().filter(|s| !s.is_empty()) {
+                        writeln!(f, "\n{}:", label)?;
+                        for subnet in subnets {
+                            writeln!(f, "- {}", subnet)?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Self::FirewallRules(a) => {
+                writeln!(
+                    f,
+                    "Changes the `{}` firewall rule set from {} to {} rules.\n",
+                    a.scope, a.rules_before, a.rules_after
+                )?;
+                for change in &a.changes {
+                    writeln!(f, "Position {}:\n```diff", change.position)?;
+                    if let Some(before) = &change.before {
+                        writeln!(f, "- {}", serde_json::to_string(before).map_err(|_| fmt::Error)?)?;
+                    }
+                    if let Some(after) = &change.after {
+                        writeln!(f, "+ {}", serde_json::to_string(after).map_err(|_| fmt::Error)?)?;
+                    }
+                    writeln!(f, "```")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn write_subnet_impacts(f: &mut Formatter<'_>, subnets: &[SubnetNodesImpact]) -> fmt::Result {
+    if subnets.is_empty() {
+        return Ok(());
+    }
+    writeln!(f, "\n| Subnet | Nodes | Subnet size | Fraction | Tolerated |")?;
+    writeln!(f, "|---|---|---|---|---|")?;
+    for subnet in subnets {
+        writeln!(
+            f,
+            "| {} | {} | {} | {:.0}% | {} |",
+            subnet.subnet_id,
+            subnet.nodes.len(),
+            subnet.subnet_size,
+            subnet.fraction() * 100.,
+            subnet.fault_tolerance
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_management_types::NodeFeatures;
+
+    use super::*;
+
+    fn network(subnet_sizes: &[usize], unassigned: usize) -> (IndexMap<PrincipalId, Node>, IndexMap<PrincipalId, Subnet>) {
+        let mut nodes = IndexMap::new();
+        let mut subnets = IndexMap::new();
+        let mut node_number = 0;
+        for (i, size) in subnet_sizes.iter().enumerate() {
+            let subnet_id = PrincipalId::new_subnet_test_id(i as u64);
+            let subnet_nodes = (0..*size)
+                .map(|_| {
+                    node_number += 1;
+                    let features = NodeFeatures::new_test_feature_set(&format!("n{}", node_number));
+                    Node::new_test_node(node_number, features, false).with_subnet_id(subnet_id)
+                })
+                .collect_vec();
+            nodes.extend(subnet_nodes.iter().map(|n| (n.principal, n.clone())));
+            subnets.insert(
+                subnet_id,
+                Subnet {
+                    principal: subnet_id,
+                    nodes: subnet_nodes,
+                    ..Default::default()
+                },
+            );
+        }
+        for _ in 0..unassigned {
+            node_number += 1;
+            let node = Node::new_test_node(node_number, NodeFeatures::new_test_feature_set(&format!("n{}", node_number)), false);
+            nodes.insert(node.principal, node);
+        }
+        (nodes, subnets)
+    }
+
+    #[test]
+    fn hostos_rollout_fraction_per_subnet() {
+        let (nodes, subnets) = network(&[13, 7], 2);
+        let subnet_0 = subnets[0].nodes.iter().map(|n| n.principal).collect_vec();
+        let subnet_1 = subnets[1].nodes.iter().map(|n| n.principal).collect_vec();
+        let unassigned = nodes.values().filter(|n| n.subnet_id.is_none()).map(|n| n.principal).collect_vec();
+
+        // 4 of 13 nodes is within the fault tolerance of 4, 3 of 7 is above the fault tolerance of 2
+        let rollout = [&subnet_0[..4], &subnet_1[..3], &unassigned[..]].concat();
+        let analysis = HostosDeploymentAnalysis::new(rollout, Some("abc".to_string()), true, &nodes, &subnets);
+        assert_eq!(analysis.unassigned_nodes.len(), 2);
+        assert_eq!(
+            analysis
+                .subnets
+                .iter()
+                .map(|s| (s.nodes.len(), s.subnet_size, s.fault_tolerance))
+                .collect_vec(),
+            vec![(4, 13, 4), (3, 7, 2)]
+        );
+        let risks = analysis.risks();
+        assert_eq!(risks.len(), 1, "{:?}", risks);
+        assert!(risks[0].contains(&subnets[1].principal.to_string()));
+    }
+
+    #[test]
+    fn removing_assigned_nodes_orphans_subnet() {
+        let (nodes, subnets) = network(&[4], 1);
+        let subnet_nodes = subnets[0].nodes.iter().map(|n| n.principal).collect_vec();
+        let unassigned = nodes.values().filter(|n| n.subnet_id.is_none()).map(|n| n.principal).collect_vec();
+
+        let analysis = RemoveNodesAnalysis::new(unassigned.clone(), &nodes, &subnets);
+        assert!(analysis.subnets.is_empty());
+        assert!(analysis.risks().is_empty());
+
+        let analysis = RemoveNodesAnalysis::new([unassigned, subnet_nodes[..2].to_vec()].concat(), &nodes, &subnets);
+        assert_eq!(analysis.subnets.len(), 1);
+        assert!(analysis.subnets[0].exceeds_fault_tolerance());
+        assert_eq!(analysis.risks().len(), 2);
+    }
+
+    #[test]
+    fn firewall_rule_diffs() {
+        let rule = |comment: &str| FirewallRule {
+            comment: comment.to_string(),
+            ..Default::default()
+        };
+        let current = vec![rule("a"), rule("b")];
+
+        let added = FirewallRulesAnalysis::additions("global".to_string(), current.clone(), &[2, 0], vec![rule("c"), rule("d")]);
+        assert_eq!(added.rules_after, 4);
+        assert_eq!(added.changes.iter().map(|c| c.position).collect_vec(), vec![0, 2]);
+        assert!(added.invalid_positions.is_empty());
+
+        let removed = FirewallRulesAnalysis::removals("global".to_string(), current.clone(), &[1, 5]);
+        assert_eq!(removed.rules_after, 1);
+        assert_eq!(removed.changes[0].before, Some(rule("b")));
+        assert_eq!(removed.invalid_positions, vec![5]);
+        assert_eq!(removed.risks().len(), 1);
+
+        let updated = FirewallRulesAnalysis::updates("global".to_string(), current, &[0], vec![rule("a")]);
+        assert_eq!(updated.risks().len(), 1);
+    }
+
+    #[test]
+    fn authorized_subnets_diff() {
+        let (_, subnets) = network(&[1, 1, 1], 0);
+        let ids = subnets.keys().cloned().collect_vec();
+        let analysis = AuthorizedSubnetsAnalysis::new(None, vec![ids[0], ids[2]], Some(vec![ids[0], ids[1]]), &subnets);
+        assert_eq!(analysis.added, Some(vec![ids[2]]));
+        assert_eq!(analysis.removed, Some(vec![ids[1]]));
+        assert_eq!(analysis.risks().len(), 1);
+    }
+}
@@ -1,17 +1,31 @@
-use clap::Args;
+use clap::{Args, ValueEnum};
 use ic_canisters::governance::GovernanceCanisterWrapper;
-use ic_management_types::filter_map_nns_function_proposals;
 use ic_nns_governance::pb::v1::ProposalStatus;
-use registry_canister::mutations::do_change_subnet_membership::ChangeSubnetMembershipPayload;
 
+use super::analysis;
 use crate::auth::AuthRequirement;
 use crate::exe::ExecutableCommand;
 use crate::exe::args::GlobalArgs;
 
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AnalysisFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
 #[derive(Args, Debug)]
+#[clap(after_help = r#"Supported NNS functions:
+    ChangeSubnetMembership, CreateSubnet, RemoveNodes, DeployHostosToSomeNodes,
+    DeployGuestosToAllSubnetNodes, SetAuthorizedSubnetworks,
+    AddFirewallRules, RemoveFirewallRules, UpdateFirewallRules"#)]
 pub struct Analyze {
     /// Proposal ID
     proposal_id: u64,
+
+    /// Format of the analysis
+    #[clap(long, value_enum, default_value_t = AnalysisFormat::Markdown)]
+    format: AnalysisFormat,
 }
 
 impl ExecutableCommand for Analyze {
@@ -32,23 +46,13 @@ impl ExecutableCommand for Analyze {
                 ProposalStatus::Open.as_str_name()
             ));
         }
-        let proposal_summary = proposal.clone().proposal.map(|p| p.summary);
 
-        let runner = ctx.runner().await?;
-
-        match filter_map_nns_function_proposals::<ChangeSubnetMembershipPayload>(&[proposal]).first() {
-            Some((_, change_membership)) => {
-                let change = runner.decentralization_change(change_membership, None, proposal_summary).await?;
-
-                println!("{}", change);
-                Ok(())
-            }
-            _ => Err(anyhow::anyhow!(
-                "Proposal {} must have {} type",
-                self.proposal_id,
-                ic_nns_governance::pb::v1::NnsFunction::ChangeSubnetMembership.as_str_name()
-            )),
+        let report = analysis::analyze(&ctx, proposal).await?;
+        match self.format {
+            AnalysisFormat::Markdown => println!("{}", report),
+            AnalysisFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
         }
+        Ok(())
     }
 
     fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
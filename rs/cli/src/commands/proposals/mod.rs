@@ -51,11 +51,13 @@ use serde::{Deserialize, Serialize};
 
 use crate::exe::impl_executable_command_for_enums;
 
+mod analysis;
 mod analyze;
 mod filter;
 mod get;
 mod list;
 mod pending;
+mod verify;
 
 #[derive(Args, Debug)]
 #[clap(alias = "proposal")]
@@ -64,7 +66,7 @@ pub struct Proposals {
     pub subcommands: Subcommands,
 }
 
-impl_executable_command_for_enums! { Proposals, Pending, Get, Analyze, Filter, List }
+impl_executable_command_for_enums! { Proposals, Pending, Get, Analyze, Verify, Filter, List }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Proposal {
@@ -0,0 +1,406 @@
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+use clap::Args;
+use decentralization::{SubnetChangeResponse, network::CordonedFeature};
+use ic_canisters::governance::GovernanceCanisterWrapper;
+use ic_management_backend::lazy_registry::LazyRegistryFamilyEntries;
+use ic_management_types::{HealthStatus, Node, filter_map_nns_function_proposals};
+use ic_nns_governance::pb::v1::{NnsFunction, ProposalStatus};
+use ic_types::PrincipalId;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use log::info;
+use registry_canister::mutations::do_change_subnet_membership::ChangeSubnetMembershipPayload;
+use serde::Serialize;
+
+use super::analyze::AnalysisFormat;
+use crate::{
+    auth::AuthRequirement,
+    commands::subnet::SelectionArgs,
+    ctx::DreContext,
+    exe::{ExecutableCommand, args::GlobalArgs},
+    subnet_manager::SubnetTarget,
+};
+
+#[derive(Args, Debug)]
+#[clap(after_help = r#"EXAMPLES:
+    dre proposals verify 123456                          # Verify an open proposal against the latest registry
+    dre proposals verify 123456 --registry-version 50000 # Verify against the registry the proposal was computed at
+    dre proposals verify 123456 --format json
+
+The proposed subnet is compared with the subnet `dre subnet replace` would have produced when
+replacing the same number of nodes, with the current health of the nodes and the cordoned features."#)]
+pub struct Verify {
+    /// Proposal ID
+    proposal_id: u64,
+
+    /// Registry version the proposal was computed at. Defaults to the latest version, which
+    /// is the state an open proposal is applied to. Required for proposals that are not open.
+    #[clap(long)]
+    registry_version: Option<u64>,
+
+    #[clap(flatten)]
+    selection: SelectionArgs,
+
+    /// Format of the verification report
+    #[clap(long, value_enum, default_value_t = AnalysisFormat::Markdown)]
+    format: AnalysisFormat,
+}
+
+impl ExecutableCommand for Verify {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Anonymous
+    }
+
+    async fn execute(&self, ctx: DreContext) -> anyhow::Result<()> {
+        // Pin the registry before anything else loads the latest version
+        let registry = ctx.registry_with_version(self.registry_version).await;
+        let registry_version = registry.get_latest_version().get();
+
+        let client = GovernanceCanisterWrapper::from(ctx.create_ic_agent_canister_client().await?);
+        let proposal = client.get_proposal(self.proposal_id).await?;
+        let status = ProposalStatus::try_from(proposal.status)?;
+        if status != ProposalStatus::Open && self.registry_version.is_none() {
+            anyhow::bail!(
+                "Proposal {} has status {}, the latest registry may already contain its changes. Pass the registry version the proposal was computed at with --registry-version",
+                self.proposal_id,
+                status.as_str_name()
+            );
+        }
+        let summary = proposal.proposal.as_ref().map(|p| p.summary.clone());
+        let payload = match filter_map_nns_function_proposals::<ChangeSubnetMembershipPayload>(&[proposal])
+            .into_iter()
+            .next()
+        {
+            Some((_, payload)) => payload,
+            None => anyhow::bail!(
+                "Proposal {} must have {} type",
+                self.proposal_id,
+                NnsFunction::ChangeSubnetMembership.as_str_name()
+            ),
+        };
+        info!("Verifying proposal {} against registry version {}", self.proposal_id, registry_version);
+
+        let all_nodes = registry.nodes().await?;
+        let subnet = registry
+            .subnets()
+            .await?
+            .get(&payload.subnet_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Subnet {} not found at registry version {}", payload.subnet_id, registry_version))?;
+        let inapplicable = inapplicable_reasons(&payload, &subnet.nodes, &all_nodes);
+
+        let runner = ctx.runner().await?;
+        let health_of_nodes = runner.health_of_nodes().await?;
+        let cordoned_features = ctx.cordoned_features_fetcher().fetch().await?;
+        let added_nodes = payload
+            .get_added_node_ids()
+            .iter()
+            .filter_map(|id| all_nodes.get(id))
+            .cloned()
+            .collect_vec();
+
+        let mut report = VerificationReport {
+            proposal_id: self.proposal_id,
+            subnet_id: payload.subnet_id,
+            registry_version,
+            unhealthy_added_nodes: unhealthy_nodes(&added_nodes, &health_of_nodes),
+            cordoned_added_nodes: cordoned_nodes(&added_nodes, &cordoned_features),
+            proposed: None,
+            recomputed: None,
+            comparison: None,
+            verdict: Verdict::Inapplicable,
+            reasons: inapplicable,
+        };
+
+        if report.reasons.is_empty() {
+            // Replace as many nodes as the proposal does, starting with the unhealthy ones like `dre subnet replace`
+            let unhealthy_in_subnet = unhealthy_nodes(&subnet.nodes, &health_of_nodes).len();
+            match healthy_nodes_to_replace(&payload, unhealthy_in_subnet) {
+                Err(reason) => {
+                    report.verdict = Verdict::Inconclusive;
+                    report.reasons.push(reason);
+                }
+                Ok(healthy_to_replace) => {
+                    let proposed = runner.decentralization_change(&payload, None, summary).await?;
+                    let recomputed = ctx
+                        .subnet_manager()
+                        .await?
+                        .with_target(SubnetTarget::FromId(payload.subnet_id))
+                        .with_selection_strategy(self.selection.strategy())
+                        .membership_replace(
+                            true,
+                            None,
+                            Some(healthy_to_replace),
+                            None,
+                            vec![],
+                            None,
+                            &all_nodes.values().cloned().collect_vec(),
+                        )
+                        .await?;
+
+                    report.comparison = Some(proposed.score_after.describe_difference_from(&recomputed.score_after).1);
+                    if recomputed.node_ids_removed.len() != payload.node_ids_remove.len() {
+                        report.verdict = Verdict::Inconclusive;
+                        report.reasons.push(format!(
+                            "The recomputed change removes {} nodes instead of {}, the node health the proposal was computed with can't be reproduced",
+                            recomputed.node_ids_removed.len(),
+                            payload.node_ids_remove.len()
+                        ));
+                    } else {
+                        (report.verdict, report.reasons) =
+                            verdict(&proposed, &recomputed, &report.unhealthy_added_nodes, &report.cordoned_added_nodes);
+                    }
+                    report.proposed = Some(proposed);
+                    report.recomputed = Some(recomputed);
+                }
+            }
+        }
+
+        report.print(self.format)
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy, strum::Display)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// The proposed subnet is at least as decentralized as the one `dre` computes, and adds only healthy, uncordoned nodes
+    #[strum(to_string = "at least as good as the recomputed change")]
+    AtLeastAsGood,
+    #[strum(to_string = "worse than the recomputed change")]
+    Worse,
+    /// The proposal cannot be applied to the registry version it was verified against
+    #[strum(to_string = "not applicable to the registry version")]
+    Inapplicable,
+    /// `dre subnet replace` can't replace exactly the nodes the proposal replaces with the current node health
+    #[strum(to_string = "inconclusive, the change can't be recomputed")]
+    Inconclusive,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerificationReport {
+    pub proposal_id: u64,
+    pub subnet_id: PrincipalId,
+    pub registry_version: u64,
+    pub unhealthy_added_nodes: BTreeMap<PrincipalId, HealthStatus>,
+    /// Added nodes with a cordoned feature, with the explanation of the cordon
+    pub cordoned_added_nodes: BTreeMap<PrincipalId, String>,
+    pub proposed: Option<SubnetChangeResponse>,
+    pub recomputed: Option<SubnetChangeResponse>,
+    /// How the decentralization of the proposed subnet differs from the recomputed one
+    pub comparison: Option<String>,
+    pub verdict: Verdict,
+    pub reasons: Vec<String>,
+}
+
+impl VerificationReport {
+    fn print(&self, format: AnalysisFormat) -> anyhow::Result<()> {
+        match format {
+            AnalysisFormat::Markdown => println!("{}", self),
+            AnalysisFormat::Json => println!("{}", serde_json::to_string_pretty(self)?),
+        }
+        Ok(())
+    }
+}
+
+/// Number of healthy nodes `dre subnet replace` must replace, on top of all unhealthy ones,
+/// to remove exactly as many nodes as the proposal does.
+fn healthy_nodes_to_replace(payload: &ChangeSubnetMembershipPayload, unhealthy_in_subnet: usize) -> Result<usize, String> {
+    let (added, removed) = (payload.node_ids_add.len(), payload.node_ids_remove.len());
+    if added != removed {
+        return Err(format!(
+            "The proposal adds {} nodes and removes {}, while `dre subnet replace` replaces nodes one for one",
+            added, removed
+        ));
+    }
+    removed.checked_sub(unhealthy_in_subnet).ok_or_else(|| {
+        format!(
+            "The subnet has {} unhealthy nodes but the proposal only removes {}, the node health the proposal was computed with can't be reproduced",
+            unhealthy_in_subnet, removed
+        )
+    })
+}
+
+fn inapplicable_reasons(payload: &ChangeSubnetMembershipPayload, subnet_nodes: &[Node], all_nodes: &IndexMap<PrincipalId, Node>) -> Vec<String> {
+    let mut reasons = vec![];
+    for id in payload.get_removed_node_ids() {
+        if !subnet_nodes.iter().any(|n| n.principal == id) {
+            reasons.push(format!("Removed node {} is not a member of subnet {}", id, payload.subnet_id));
+        }
+    }
+    for id in payload.get_added_node_ids() {
+        match all_nodes.get(&id) {
+            None => reasons.push(format!("Added node {} does not exist", id)),
+            Some(node) => {
+                if let Some(subnet_id) = node.subnet_id {
+                    reasons.push(format!("Added node {} is already a member of subnet {}", id, subnet_id));
+                }
+            }
+        }
+    }
+    reasons
+}
+
+fn unhealthy_nodes(nodes: &[Node], health_of_nodes: &IndexMap<PrincipalId, HealthStatus>) -> BTreeMap<PrincipalId, HealthStatus> {
+    nodes
+        .iter()
+        .map(|n| (n.principal, health_of_nodes.get(&n.principal).cloned().unwrap_or(HealthStatus::Unknown)))
+        .filter(|(_, status)| *status != HealthStatus::Healthy)
+        .collect()
+}
+
+fn cordoned_nodes(nodes: &[Node], cordoned_features: &[CordonedFeature]) -> BTreeMap<PrincipalId, String> {
+    nodes
+        .iter()
+        .filter_map(|node| {
+            cordoned_features
+                .iter()
+                .find(|c| node.get_feature(&c.feature) == Some(c.value.clone()))
+                .map(|c| {
+                    let explanation = c.explanation.as_ref().map(|e| format!(": {}", e)).unwrap_or_default();
+                    (node.principal, format!("{} {} is cordoned{}", c.feature, c.value, explanation))
+                })
+        })
+        .collect()
+}
+
+fn verdict(
+    proposed: &SubnetChangeResponse,
+    recomputed: &SubnetChangeResponse,
+    unhealthy_added_nodes: &BTreeMap<PrincipalId, HealthStatus>,
+    cordoned_added_nodes: &BTreeMap<PrincipalId, String>,
+) -> (Verdict, Vec<String>) {
+    let mut reasons = vec![];
+    for (node, status) in unhealthy_added_nodes {
+        reasons.push(format!("Added node {} is {}", node, status));
+    }
+    for (node, cordon) in cordoned_added_nodes {
+        reasons.push(format!("Added node {}: {}", node, cordon));
+    }
+    match proposed.penalties_after_change.0.cmp(&recomputed.penalties_after_change.0) {
+        Ordering::Greater => reasons.push(format!(
+            "Business rules penalties are {}, the recomputed change has {}",
+            proposed.penalties_after_change.0, recomputed.penalties_after_change.0
+        )),
+        Ordering::Less => (),
+        Ordering::Equal => {
+            if proposed.score_after < recomputed.score_after {
+                reasons.push(format!(
+                    "Decentralization is worse than the recomputed change: {}",
+                    proposed.score_after.describe_difference_from(&recomputed.score_after).1
+                ));
+            }
+        }
+    }
+    match reasons.is_empty() {
+        true => (Verdict::AtLeastAsGood, reasons),
+        false => (Verdict::Worse, reasons),
+    }
+}
+
+impl Display for VerificationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Verification of proposal {}\n", self.proposal_id)?;
+        writeln!(f, "Subnet {} at registry version {}.\n", self.subnet_id, self.registry_version)?;
+        writeln!(f, "**Verdict: {}**\n", self.verdict)?;
+        for reason in &self.reasons {
+            writeln!(f, "- {}", reason)?;
+        }
+        if let (Some(proposed), Some(recomputed)) = (&self.proposed, &self.recomputed) {
+            writeln!(f, "\n| | Proposed | Recomputed |")?;
+            writeln!(f, "|---|---|---|")?;
+            writeln!(
+                f,
+                "| Added | {} | {} |",
+                proposed.node_ids_added.iter().join(", "),
+                recomputed.node_ids_added.iter().join(", ")
+            )?;
+            writeln!(
+                f,
+                "| Removed | {} | {} |",
+                proposed.node_ids_removed.iter().join(", "),
+                recomputed.node_ids_removed.iter().join(", ")
+            )?;
+            writeln!(
+                f,
+                "| Penalties | {} | {} |",
+                proposed.penalties_after_change.0, recomputed.penalties_after_change.0
+            )?;
+            writeln!(f, "| Nakamoto score | {} | {} |", proposed.score_after, recomputed.score_after)?;
+            if let Some(comparison) = &self.comparison {
+                writeln!(f, "\nProposed compared to recomputed: {}", comparison)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(penalties: usize) -> SubnetChangeResponse {
+        SubnetChangeResponse {
+            penalties_after_change: (penalties, vec![]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn verdict_compares_penalties_and_added_nodes() {
+        let none = BTreeMap::new();
+        assert_eq!(verdict(&change(0), &change(0), &none, &none).0, Verdict::AtLeastAsGood);
+        assert_eq!(verdict(&change(0), &change(1), &none, &none).0, Verdict::AtLeastAsGood);
+
+        let (v, reasons) = verdict(&change(2), &change(1), &none, &none);
+        assert_eq!(v, Verdict::Worse);
+        assert_eq!(reasons.len(), 1);
+
+        let node = PrincipalId::new_node_test_id(1);
+        let unhealthy = BTreeMap::from([(node, HealthStatus::Dead)]);
+        let cordoned = BTreeMap::from([(node, "data_center dc1 is cordoned".to_string())]);
+        let (v, reasons) = verdict(&change(0), &change(0), &unhealthy, &cordoned);
+        assert_eq!(v, Verdict::Worse);
+        assert_eq!(reasons.len(), 2);
+    }
+
+    #[test]
+    fn recomputation_removes_exactly_the_proposed_number_of_nodes() {
+        let payload = |add: u64, remove: u64| ChangeSubnetMembershipPayload {
+            subnet_id: PrincipalId::new_subnet_test_id(1),
+            node_ids_add: (0..add).map(|i| PrincipalId::new_node_test_id(i).into()).collect(),
+            node_ids_remove: (10..10 + remove).map(|i| PrincipalId::new_node_test_id(i).into()).collect(),
+        };
+        assert_eq!(healthy_nodes_to_replace(&payload(2, 2), 0), Ok(2));
+        assert_eq!(healthy_nodes_to_replace(&payload(2, 2), 1), Ok(1));
+        assert_eq!(healthy_nodes_to_replace(&payload(2, 2), 2), Ok(0));
+        assert!(healthy_nodes_to_replace(&payload(1, 1), 2).is_err());
+        assert!(healthy_nodes_to_replace(&payload(2, 1), 0).is_err());
+    }
+
+    #[test]
+    fn proposal_not_matching_the_registry_is_inapplicable() {
+        let subnet_id = PrincipalId::new_subnet_test_id(1);
+        let member = Node::new_test_node(1, Default::default(), false).with_subnet_id(subnet_id);
+        let elsewhere = Node::new_test_node(2, Default::default(), false).with_subnet_id(PrincipalId::new_subnet_test_id(2));
+        let unassigned = Node::new_test_node(3, Default::default(), false);
+        let all_nodes: IndexMap<_, _> = [&member, &elsewhere, &unassigned].iter().map(|n| (n.principal, (*n).clone())).collect();
+
+        let payload = |add: &Node, remove: &Node| ChangeSubnetMembershipPayload {
+            subnet_id,
+            node_ids_add: vec![add.principal.into()],
+            node_ids_remove: vec![remove.principal.into()],
+        };
+        assert!(inapplicable_reasons(&payload(&unassigned, &member), std::slice::from_ref(&member), &all_nodes).is_empty());
+        assert_eq!(
+            inapplicable_reasons(&payload(&elsewhere, &unassigned), std::slice::from_ref(&member), &all_nodes).len(),
+            2
+        );
+    }
+}
@@ -53,7 +53,7 @@ impl ExecutableCommand for Propose {
         }
 
         Submitter::from(&submission_params)
-            .propose_and_print(ctx.ic_admin_executor().await?.execution(cmd), ForumPostKind::Generic)
+            .propose_and_print(ctx.proposal_execution(cmd).await?, ForumPostKind::Generic)
             .await
     }
 
@@ -0,0 +1,486 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use clap::{Args, error::ErrorKind};
+use colored::Colorize;
+use ic_nns_governance::pb::v1::Topic;
+use itertools::Itertools;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::AuthRequirement,
+    confirm::HowToProceed,
+    ctx::DreContext,
+    exe::{ExecutableCommand, args::GlobalArgs},
+    forum::ForumPostKind,
+    ic_admin::{IcAdminProposal, IcAdminProposalCommand, IcAdminProposalOptions},
+    submitter::{SubmissionParameters, Submitter},
+    util::yesno,
+};
+
+#[derive(Args, Debug)]
+#[clap(after_help = r#"MANIFEST EXAMPLE:
+    forum_topic:                      # Optional, all proposals are posted in this topic
+      title: HostOS rollout week 42
+      body: This week we roll out HostOS version 1a2b3c... to the following nodes.
+    proposals:
+      - name: hostos-dc1              # Unique, used to track submission in the state file
+        summary: Roll out HostOS to the nodes in dc1
+        motivation: Weekly HostOS rollout
+        command:
+          deploy_hostos_to_some_nodes:
+            nodes: [<node-id>, <node-id>]
+            version: 1a2b3c...
+      - name: replace-dead-node
+        title: Replace a dead node in subnet <subnet-id>   # Optional
+        summary: Replace a node that has been offline for a week
+        command:
+          change_subnet_membership:
+            subnet_id: <subnet-id>
+            node_ids_add: [<node-id>]
+            node_ids_remove: [<node-id>]
+
+  Proposals are submitted in order. The IDs of submitted proposals are recorded in the state file,
+  and running the same command again after a failure resumes with the first proposal not yet submitted.
+  A proposal is marked as submitting in the state file before it is sent. If the batch is interrupted
+  at that point, resuming looks the proposal up by neuron and summary and submits it again only if
+  the governance canister does not have it.
+"#)]
+pub struct ProposeBatch {
+    /// YAML manifest describing the proposals to submit
+    pub manifest: PathBuf,
+
+    /// File recording the submitted proposals, by default `<MANIFEST>.state.yaml`
+    #[clap(long)]
+    pub state_file: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub submission_parameters: SubmissionParameters,
+}
+
+impl ExecutableCommand for ProposeBatch {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Neuron
+    }
+
+    async fn execute(&self, ctx: DreContext) -> anyhow::Result<()> {
+        let manifest = BatchManifest::read(&self.manifest)?;
+        let state_file = self.state_file();
+        let mut state = BatchState::read_or_default(&state_file)?;
+        for name in state.proposals.keys().filter(|name| !manifest.proposals.iter().any(|p| &p.name == *name)) {
+            warn!("Proposal `{}` recorded in {} is not in the manifest anymore", name, state_file.display());
+        }
+        self.reconcile_interrupted_submissions(&ctx, &manifest, &mut state, &state_file).await?;
+
+        let pending = state.pending(&manifest);
+        if pending.is_empty() {
+            println!("{}", manifest.summary(&state));
+            info!("All proposals of the manifest were already submitted, see {}", state_file.display());
+            return Ok(());
+        }
+
+        let mut executions = vec![];
+        for proposal in &pending {
+            executions.push(ctx.proposal_execution(proposal.ic_admin_proposal()).await?);
+        }
+
+        let mode = HowToProceed::from(&self.submission_parameters.confirmation_mode);
+        if mode != HowToProceed::Unconditional {
+            for (proposal, execution) in pending.iter().zip(&executions) {
+                println!("{}", format!("Proposal `{}`", proposal.name).bold());
+                execution
+                    .simulate(self.submission_parameters.forum_parameters.forum_post_link_for_simulation())
+                    .await?;
+            }
+        }
+        println!("{}", manifest.summary(&state));
+
+        match mode {
+            HowToProceed::DryRun => return Ok(()),
+            HowToProceed::Confirm => {
+                if !yesno(&format!("Do you want to submit {} proposals?", pending.len()), false).await?? {
+                    return Ok(());
+                }
+            }
+            HowToProceed::Unconditional | HowToProceed::UnitTests => (),
+        }
+
+        let submitter = Submitter::from(&self.submission_parameters);
+        if let (Some(topic), None) = (&manifest.forum_topic, &state.forum_topic_url) {
+            state.forum_topic_url = submitter
+                .create_forum_topic(topic.title.clone(), topic.body.clone(), manifest.topics())
+                .await?;
+            state.write(&state_file)?;
+        }
+
+        let total = pending.len();
+        for (submitted, (proposal, execution)) in pending.into_iter().zip(executions).enumerate() {
+            state.start_submission(&proposal.name);
+            state.write(&state_file)?;
+            match submitter
+                .submit(execution.as_ref(), proposal.forum_post_kind(state.forum_topic_url.as_ref()))
+                .await
+            {
+                Ok(Some(response)) => {
+                    info!("Submitted `{}` as {}", proposal.name, response);
+                    state.record(&proposal.name, response.into());
+                    state.write(&state_file)?;
+                }
+                Ok(None) => {
+                    state.submitting.remove(&proposal.name);
+                    state.write(&state_file)?;
+                }
+                Err(e) => {
+                    return Err(e.context(format!(
+                        "Failed to submit proposal `{}` after submitting {} of {} pending proposals. Submitted proposals are recorded in {}, run the same command again to resume",
+                        proposal.name,
+                        submitted,
+                        total,
+                        state_file.display()
+                    )));
+                }
+            }
+        }
+        println!("{}", manifest.summary(&state));
+        Ok(())
+    }
+
+    fn validate(&self, args: &GlobalArgs, cmd: &mut clap::Command) {
+        if args.prepare_only.is_some() {
+            cmd.error(
+                ErrorKind::ArgumentConflict,
+                "Batches cannot be prepared for offline signing, a bundle holds a single proposal",
+            )
+            .exit()
+        }
+    }
+}
+
+impl ProposeBatch {
+    /// Resolves the proposals whose submission was interrupted: records the ones the governance
+    /// canister received, and leaves the others pending so that they are submitted again.
+    async fn reconcile_interrupted_submissions(
+        &self,
+        ctx: &DreContext,
+        manifest: &BatchManifest,
+        state: &mut BatchState,
+        state_file: &Path,
+    ) -> anyhow::Result<()> {
+        for (name, started_at) in state.submitting.clone() {
+            let Some(proposal) = manifest.proposals.iter().find(|p| p.name == name) else {
+                warn!(
+                    "Proposal `{}` marked as submitting in {} is not in the manifest anymore",
+                    name,
+                    state_file.display()
+                );
+                state.submitting.remove(&name);
+                continue;
+            };
+            // Allow for clock skew between this machine and the IC
+            let since = started_at - chrono::Duration::minutes(5);
+            match ctx.find_submitted_proposal(&proposal.ic_admin_proposal(), since).await? {
+                Some(proposal_id) => {
+                    info!("The interrupted submission of `{}` went through as proposal {}", name, proposal_id);
+                    state.record(&name, proposal_id);
+                }
+                None => {
+                    warn!(
+                        "The interrupted submission of `{}` did not reach the governance canister, it will be submitted again",
+                        name
+                    );
+                    state.submitting.remove(&name);
+                }
+            }
+        }
+        state.write(state_file)
+    }
+
+    fn state_file(&self) -> PathBuf {
+        self.state_file.clone().unwrap_or_else(|| {
+            let mut path = self.manifest.clone().into_os_string();
+            path.push(".state.yaml");
+            path.into()
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchManifest {
+    #[serde(default)]
+    forum_topic: Option<BatchForumTopic>,
+    proposals: Vec<BatchProposal>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchForumTopic {
+    title: String,
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchProposal {
+    name: String,
+    #[serde(default)]
+    title: Option<String>,
+    summary: String,
+    #[serde(default)]
+    motivation: Option<String>,
+    command: IcAdminProposalCommand,
+}
+
+impl BatchManifest {
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        Self::parse(&fs_err::read(path)?).map_err(|e| anyhow::anyhow!("Invalid batch manifest {}: {}", path.display(), e))
+    }
+
+    fn parse(contents: &[u8]) -> anyhow::Result<Self> {
+        let manifest: Self = serde_yaml::from_slice(contents)?;
+        if manifest.proposals.is_empty() {
+            anyhow::bail!("The manifest does not contain any proposal");
+        }
+        let mut names = HashSet::new();
+        for proposal in &manifest.proposals {
+            if proposal.name.trim().is_empty() {
+                anyhow::bail!("Every proposal must have a non-empty name");
+            }
+            if !names.insert(&proposal.name) {
+                anyhow::bail!("Proposal name `{}` is used more than once", proposal.name);
+            }
+            if let IcAdminProposalCommand::ChangeSubnetMembership {
+                node_ids_add,
+                node_ids_remove,
+                ..
+            } = &proposal.command
+            {
+                if node_ids_add.is_empty() && node_ids_remove.is_empty() {
+                    anyhow::bail!(
+                        "Proposal `{}` changes the membership of a subnet without adding or removing nodes",
+                        proposal.name
+                    );
+                }
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Governance topics of the proposals, in order of first appearance.
+    fn topics(&self) -> Vec<Topic> {
+        self.proposals.iter().map(|p| p.command.topic()).unique().collect()
+    }
+
+    fn summary(&self, state: &BatchState) -> tabular::Table {
+        let mut table = tabular::Table::new("{:<}    {:<}    {:<}    {:<}");
+        table.add_row(
+            tabular::Row::new()
+                .with_cell("Name")
+                .with_cell("Command")
+                .with_cell("Title")
+                .with_cell("Status"),
+        );
+        for proposal in &self.proposals {
+            table.add_row(
+                tabular::Row::new()
+                    .with_cell(&proposal.name)
+                    .with_cell(&proposal.command)
+                    .with_cell(proposal.title.as_deref().unwrap_or("-"))
+                    .with_cell(match state.proposals.get(&proposal.name) {
+                        Some(submitted) => format!("proposal {}", submitted.proposal_id),
+                        None => "pending".to_string(),
+                    }),
+            );
+        }
+        table
+    }
+}
+
+impl BatchProposal {
+    fn ic_admin_proposal(&self) -> IcAdminProposal {
+        IcAdminProposal::new(
+            self.command.clone(),
+            IcAdminProposalOptions {
+                title: self.title.clone(),
+                summary: Some(self.summary.clone()),
+                motivation: self.motivation.clone(),
+            },
+        )
+    }
+
+    fn forum_post_body(&self) -> String {
+        match &self.motivation {
+            Some(motivation) => format!("{}\nMotivation:\n{}", self.summary, motivation),
+            None => self.summary.clone(),
+        }
+    }
+
+    fn forum_post_kind(&self, topic_url: Option<&url::Url>) -> ForumPostKind {
+        match (topic_url, &self.command) {
+            (Some(topic_url), _) => ForumPostKind::PostInTopic {
+                topic_url: topic_url.clone(),
+                body: match &self.title {
+                    Some(title) => format!("## {}\n\n{}", title, self.forum_post_body()),
+                    None => self.forum_post_body(),
+                },
+            },
+            (None, IcAdminProposalCommand::ChangeSubnetMembership { subnet_id, .. }) => ForumPostKind::ReplaceNodes {
+                subnet_id: *subnet_id,
+                body: self.forum_post_body(),
+            },
+            (None, _) => ForumPostKind::Generic,
+        }
+    }
+}
+
+/// Progress of a batch, written before and after every submission so that a failed batch can be resumed.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+struct BatchState {
+    #[serde(default)]
+    forum_topic_url: Option<url::Url>,
+    #[serde(default)]
+    proposals: BTreeMap<String, SubmittedProposal>,
+    /// Proposals being submitted, with the time the submission started
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    submitting: BTreeMap<String, DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct SubmittedProposal {
+    proposal_id: u64,
+    submitted_at: DateTime<Utc>,
+}
+
+impl BatchState {
+    fn read_or_default(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let state: Self =
+            serde_yaml::from_slice(&fs_err::read(path)?).map_err(|e| anyhow::anyhow!("Invalid batch state file {}: {}", path.display(), e))?;
+        info!(
+            "Resuming batch from {}, {} proposals were already submitted",
+            path.display(),
+            state.proposals.len()
+        );
+        Ok(state)
+    }
+
+    fn write(&self, path: &Path) -> anyhow::Result<()> {
+        fs_err::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn start_submission(&mut self, name: &str) {
+        self.submitting.insert(name.to_string(), Utc::now());
+    }
+
+    fn record(&mut self, name: &str, proposal_id: u64) {
+        self.submitting.remove(name);
+        self.proposals.insert(
+            name.to_string(),
+            SubmittedProposal {
+                proposal_id,
+                submitted_at: Utc::now(),
+            },
+        );
+    }
+
+    fn pending<'a>(&self, manifest: &'a BatchManifest) -> Vec<&'a BatchProposal> {
+        manifest.proposals.iter().filter(|p| !self.proposals.contains_key(&p.name)).collect_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+forum_topic:
+  title: HostOS rollout week 42
+  body: Rolling out HostOS
+proposals:
+  - name: hostos-dc1
+    summary: Roll out HostOS to dc1
+    command:
+      deploy_hostos_to_some_nodes:
+        nodes: [x33ed-h457x-bsgyx-oqxqf-6pzwv-wkhzr-rm2j3-npodi-purzm-n66cg-gae]
+        version: 1a2b3c
+  - name: replace
+    title: Replace a node
+    summary: Replace a dead node
+    motivation: The node is offline
+    command:
+      change_subnet_membership:
+        subnet_id: tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe
+        node_ids_remove: [x33ed-h457x-bsgyx-oqxqf-6pzwv-wkhzr-rm2j3-npodi-purzm-n66cg-gae]
+"#;
+
+    #[test]
+    fn parse_manifest() {
+        let manifest = BatchManifest::parse(MANIFEST.as_bytes()).unwrap();
+        assert_eq!(manifest.forum_topic.as_ref().unwrap().title, "HostOS rollout week 42");
+        assert_eq!(manifest.proposals.len(), 2);
+        assert!(matches!(
+            &manifest.proposals[0].command,
+            IcAdminProposalCommand::DeployHostosToSomeNodes { nodes, version } if nodes.len() == 1 && version == "1a2b3c"
+        ));
+        assert!(matches!(
+            &manifest.proposals[1].command,
+            IcAdminProposalCommand::ChangeSubnetMembership { node_ids_add, node_ids_remove, .. } if node_ids_add.is_empty() && node_ids_remove.len() == 1
+        ));
+        assert_eq!(
+            manifest.proposals[1].forum_post_body(),
+            "Replace a dead node\nMotivation:\nThe node is offline"
+        );
+    }
+
+    #[test]
+    fn reject_invalid_manifests() {
+        let duplicated = MANIFEST.replace("name: replace", "name: hostos-dc1");
+        assert!(BatchManifest::parse(duplicated.as_bytes()).is_err());
+        assert!(BatchManifest::parse(b"proposals: []").is_err());
+        let unknown_command = MANIFEST.replace("deploy_hostos_to_some_nodes", "deploy_hostos_everywhere");
+        assert!(BatchManifest::parse(unknown_command.as_bytes()).is_err());
+        let empty_membership_change = MANIFEST.replace(
+            "node_ids_remove: [x33ed-h457x-bsgyx-oqxqf-6pzwv-wkhzr-rm2j3-npodi-purzm-n66cg-gae]",
+            "node_ids_remove: []",
+        );
+        assert!(BatchManifest::parse(empty_membership_change.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn forum_topic_is_tagged_with_proposal_topics() {
+        let manifest = BatchManifest::parse(MANIFEST.as_bytes()).unwrap();
+        assert_eq!(manifest.topics(), vec![Topic::IcOsVersionDeployment, Topic::SubnetManagement]);
+    }
+
+    #[test]
+    fn resume_from_state() {
+        let manifest = BatchManifest::parse(MANIFEST.as_bytes()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.yaml.state.yaml");
+
+        let mut state = BatchState::read_or_default(&path).unwrap();
+        assert_eq!(state.pending(&manifest).len(), 2);
+        state.record("hostos-dc1", 42);
+        state.write(&path).unwrap();
+
+        let mut state = BatchState::read_or_default(&path).unwrap();
+        assert_eq!(state.proposals["hostos-dc1"].proposal_id, 42);
+        assert_eq!(state.pending(&manifest).iter().map(|p| p.name.as_str()).collect_vec(), vec!["replace"]);
+
+        // An interrupted submission is persisted, and recording the proposal completes it
+        state.start_submission("replace");
+        state.write(&path).unwrap();
+        let mut state = BatchState::read_or_default(&path).unwrap();
+        assert!(state.submitting.contains_key("replace"));
+        state.record("replace", 43);
+        assert!(state.submitting.is_empty());
+        assert!(state.pending(&manifest).is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+use clap::Args;
+use itertools::Itertools;
+
+use crate::{auth::AuthRequirement, exe::ExecutableCommand, exe::args::GlobalArgs};
+
+/// List the clusters of linked node providers that `dre` currently uses, and the subnets
+/// that contain more than one node of a cluster
+#[derive(Args, Debug)]
+pub struct List {}
+
+impl ExecutableCommand for List {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Anonymous
+    }
+
+    fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
+
+    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+        let clusters = ctx.provider_clusters().await?;
+        let registry = ctx.registry().await;
+        let nodes = registry.nodes().await?;
+        let subnets = registry.subnets().await?;
+
+        for cluster in &clusters.clusters {
+            let providers = cluster.provider_ids();
+            let cluster_nodes = nodes
+                .values()
+                .filter(|n| providers.contains(&n.operator.provider.principal))
+                .collect_vec();
+            println!(
+                "{}: {} node(s), {} in subnets\n  source: {}",
+                cluster.name,
+                cluster_nodes.len(),
+                cluster_nodes.iter().filter(|n| n.subnet_id.is_some()).count(),
+                cluster.source
+            );
+            for provider in providers.iter().sorted() {
+                let name = nodes
+                    .values()
+                    .find(|n| n.operator.provider.principal == *provider)
+                    .and_then(|n| n.operator.provider.name.clone())
+                    .unwrap_or_else(|| "no nodes".to_string());
+                println!("  - {} ({})", provider, name);
+            }
+        }
+
+        let members = clusters.subnets_with_several_members(subnets.values());
+        if members.is_empty() {
+            println!("\nNo subnet contains more than one node of a cluster");
+            return Ok(());
+        }
+        let mut table = tabular::Table::new("{:<}    {:<}    {:<}    {:>}    {:<}");
+        table.add_row(
+            tabular::Row::new()
+                .with_cell("Subnet")
+                .with_cell("Name")
+                .with_cell("Cluster")
+                .with_cell("Nodes")
+                .with_cell("Node ids"),
+        );
+        for member in &members {
+            table.add_row(
+                tabular::Row::new()
+                    .with_cell(member.subnet_id)
+                    .with_cell(subnets.get(&member.subnet_id).map(|s| s.metadata.name.as_str()).unwrap_or_default())
+                    .with_cell(&member.cluster)
+                    .with_cell(member.nodes.len())
+                    .with_cell(
+                        member
+                            .nodes
+                            .iter()
+                            .map(|n| n.to_string().split('-').next().unwrap().to_string())
+                            .join(" "),
+                    ),
+            );
+        }
+        println!("\nSubnets with more than one node of a cluster:\n{}", table);
+        Ok(())
+    }
+}
@@ -0,0 +1,15 @@
+use clap::Args;
+use list::List;
+
+use crate::exe::impl_executable_command_for_enums;
+
+mod list;
+
+/// Inspect the clusters of linked node providers
+#[derive(Args, Debug)]
+pub struct ProviderClusters {
+    #[clap(subcommand)]
+    pub subcommands: Subcommands,
+}
+
+impl_executable_command_for_enums! { ProviderClusters, List }
@@ -10,6 +10,7 @@ use ic_protobuf::registry::{
     dc::v1::DataCenterRecord,
     hostos_version::v1::HostosVersionRecord,
     node::v1::{ConnectionEndpoint, IPv4InterfaceConfig, NodeRewardType},
+    node_rewards::v2::{NodeRewardRate, NodeRewardRates, NodeRewardsTable},
     replica_version::v1::ReplicaVersionRecord,
     subnet::v1::{ChainKeyConfig, SubnetFeatures},
     unassigned_nodes_config::v1::UnassignedNodesConfigRecord,
@@ -185,7 +186,7 @@ impl Registry {
 }
 
 // Helper: collect candidate base dirs
-fn local_registry_dirs_for_ctx(ctx: &DreContext) -> anyhow::Result<Vec<std::path::PathBuf>> {
+pub(crate) fn local_registry_dirs_for_ctx(ctx: &DreContext) -> anyhow::Result<Vec<std::path::PathBuf>> {
     let mut base_dirs = vec![
         dirs::cache_dir()
             .ok_or_else(|| anyhow::anyhow!("Couldn't find cache dir for dre-store"))?
@@ -200,7 +201,7 @@ fn local_registry_dirs_for_ctx(ctx: &DreContext) -> anyhow::Result<Vec<std::path
     Ok(base_dirs)
 }
 
-fn load_first_available_entries(
+pub(crate) fn load_first_available_entries(
     base_dirs: &[std::path::PathBuf],
 ) -> anyhow::Result<Vec<(u64, ic_registry_common_proto::pb::local_store::v1::ChangelogEntry)>> {
     use ic_registry_common_proto::pb::local_store::v1::ChangelogEntry as PbChangelogEntry;
@@ -235,7 +236,7 @@ fn load_first_available_entries(
 // - 0 means start (same as omitting FROM)
 // - Negative indices are from the end (-1 is last)
 // - Reversed ranges yield empty results
-fn select_versions(versions: Option<Vec<i64>>, versions_sorted: &[u64]) -> anyhow::Result<Vec<u64>> {
+pub(crate) fn select_versions(versions: Option<Vec<i64>>, versions_sorted: &[u64]) -> anyhow::Result<Vec<u64>> {
     let n = versions_sorted.len();
     let args = versions.unwrap_or_default();
     let (from_opt, to_opt): (Option<i64>, Option<i64>) = match args.as_slice() {
@@ -648,38 +649,12 @@ fn get_node_rewards_table(local_registry: &Arc<dyn LazyRegistry>, network: &Netw
         }
     };
 
-    let table = match rewards_table.first_entry() {
-        Some(f) => f.get().table.clone(),
+    match rewards_table.first_entry() {
+        Some(f) => f.get().into(),
         None => {
             warn!("Failed to get Node Rewards Table for {}", network.name);
-            BTreeMap::new()
+            NodeRewardsTableFlattened::default()
         }
-    };
-
-    NodeRewardsTableFlattened {
-        table: table
-            .iter()
-            .map(|(k, v)| {
-                (
-                    k.clone(),
-                    NodeRewardRatesFlattened {
-                        rates: v
-                            .rates
-                            .iter()
-                            .map(|(rate_key, rate_val)| {
-                                (
-                                    rate_key.clone(),
-                                    NodeRewardRateFlattened {
-                                        xdr_permyriad_per_node_per_month: rate_val.xdr_permyriad_per_node_per_month,
-                                        reward_coefficient_percent: rate_val.reward_coefficient_percent,
-                                    },
-                                )
-                            })
-                            .collect(),
-                    },
-                )
-            })
-            .collect(),
     }
 }
 
@@ -874,7 +849,7 @@ struct NodeOperatorComputed {
 // A more proper way would be to adjust the upstream structs to flatten the "rates" and "table" fields
 // directly, but this breaks some of the candid encoding and decoding and also some of the tests.
 // Make sure to keep these structs in sync with the upstream ones.
-#[derive(serde::Serialize, PartialEq, ::prost::Message)]
+#[derive(serde::Serialize, serde::Deserialize, PartialEq, ::prost::Message)]
 pub struct NodeRewardRateFlattened {
     #[prost(uint64, tag = "1")]
     pub xdr_permyriad_per_node_per_month: u64,
@@ -883,20 +858,89 @@ pub struct NodeRewardRateFlattened {
     pub reward_coefficient_percent: Option<i32>,
 }
 
-#[derive(serde::Serialize, PartialEq, ::prost::Message)]
+#[derive(serde::Serialize, serde::Deserialize, PartialEq, ::prost::Message)]
 pub struct NodeRewardRatesFlattened {
     #[prost(btree_map = "string, message", tag = "1")]
     #[serde(flatten)]
     pub rates: BTreeMap<String, NodeRewardRateFlattened>,
 }
 
-#[derive(serde::Serialize, PartialEq, ::prost::Message)]
+#[derive(serde::Serialize, serde::Deserialize, PartialEq, ::prost::Message)]
 pub struct NodeRewardsTableFlattened {
     #[prost(btree_map = "string, message", tag = "1")]
     #[serde(flatten)]
     pub table: BTreeMap<String, NodeRewardRatesFlattened>,
 }
 
+impl NodeRewardsTableFlattened {
+    /// Parses a table in the format of `dre registry`'s `node_rewards_table`, as YAML or JSON
+    pub fn from_yaml(contents: &[u8]) -> anyhow::Result<Self> {
+        serde_yaml::from_slice(contents).map_err(|e| anyhow::anyhow!("Failed to parse node rewards table: {}", e))
+    }
+}
+
+impl From<&NodeRewardsTable> for NodeRewardsTableFlattened {
+    fn from(table: &NodeRewardsTable) -> Self {
+        NodeRewardsTableFlattened {
+            table: table
+                .table
+                .iter()
+                .map(|(region, rates)| {
+                    (
+                        region.clone(),
+                        NodeRewardRatesFlattened {
+                            rates: rates
+                                .rates
+                                .iter()
+                                .map(|(node_type, rate)| {
+                                    (
+                                        node_type.clone(),
+                                        NodeRewardRateFlattened {
+                                            xdr_permyriad_per_node_per_month: rate.xdr_permyriad_per_node_per_month,
+                                            reward_coefficient_percent: rate.reward_coefficient_percent,
+                                        },
+                                    )
+                                })
+                                .collect(),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<NodeRewardsTableFlattened> for NodeRewardsTable {
+    fn from(flattened: NodeRewardsTableFlattened) -> Self {
+        NodeRewardsTable {
+            table: flattened
+                .table
+                .into_iter()
+                .map(|(region, rates)| {
+                    (
+                        region,
+                        NodeRewardRates {
+                            rates: rates
+                                .rates
+                                .into_iter()
+                                .map(|(node_type, rate)| {
+                                    (
+                                        node_type,
+                                        NodeRewardRate {
+                                            xdr_permyriad_per_node_per_month: rate.xdr_permyriad_per_node_per_month,
+                                            reward_coefficient_percent: rate.reward_coefficient_percent,
+                                        },
+                                    )
+                                })
+                                .collect(),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
 #[derive(serde::Serialize, Debug)]
 struct NodeProvider {
     name: String,
@@ -1027,3 +1071,43 @@ fn filter_json_value(current: &mut Value, key: &str, value: &Value, comparison:
         _ => false, // Since this is a string comparison, non-object and non-array values don't match
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn printed_node_rewards_table_parses_back() {
+        let rate = |xdr_permyriad_per_node_per_month: u64, reward_coefficient_percent: Option<i32>| NodeRewardRate {
+            xdr_permyriad_per_node_per_month,
+            reward_coefficient_percent,
+        };
+        let table = NodeRewardsTable {
+            table: BTreeMap::from([
+                (
+                    "Europe".to_string(),
+                    NodeRewardRates {
+                        rates: BTreeMap::from([("type0".to_string(), rate(240000, None)), ("type1".to_string(), rate(304375, None))]),
+                    },
+                ),
+                (
+                    "North America,US,California".to_string(),
+                    NodeRewardRates {
+                        rates: BTreeMap::from([("type3.1".to_string(), rate(1000000, Some(90)))]),
+                    },
+                ),
+            ]),
+        };
+
+        // `dre registry` prints the table as JSON, the proposals take it as YAML
+        let flattened = NodeRewardsTableFlattened::from(&table);
+        for printed in [
+            serde_json::to_string_pretty(&flattened).unwrap(),
+            serde_yaml::to_string(&flattened).unwrap(),
+        ] {
+            assert!(!printed.contains("rates"), "the rates must be flattened in:\n{}", printed);
+            let parsed = NodeRewardsTableFlattened::from_yaml(printed.as_bytes()).unwrap();
+            assert_eq!(NodeRewardsTable::from(parsed), table);
+        }
+    }
+}
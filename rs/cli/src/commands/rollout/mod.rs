@@ -0,0 +1,14 @@
+use clap::Args;
+use run::Run;
+
+use crate::exe::impl_executable_command_for_enums;
+
+mod run;
+
+#[derive(Args, Debug)]
+pub struct Rollout {
+    #[clap(subcommand)]
+    pub subcommands: Subcommands,
+}
+
+impl_executable_command_for_enums! { Rollout, Run }
@@ -0,0 +1,717 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Args, error::ErrorKind};
+use humantime::parse_duration;
+use ic_canisters::governance::GovernanceCanisterWrapper;
+use ic_management_backend::{
+    prometheus,
+    proposal::ProposalAgent,
+    registry::NNS_SUBNET_NAME,
+    release::{RolloutConfig, RolloutDay, RolloutState},
+};
+use ic_management_types::HealthStatus;
+use ic_nns_governance::pb::v1::ProposalStatus;
+use ic_types::PrincipalId;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use crate::{
+    auth::AuthRequirement,
+    confirm::HowToProceed,
+    ctx::DreContext,
+    desktop_notify::DesktopNotifier,
+    exe::{ExecutableCommand, args::GlobalArgs},
+    forum::ForumPostKind,
+    submitter::{SubmissionParameters, Submitter},
+    util::yesno,
+};
+
+/// Replicas only run the new version a few minutes after the proposal is executed, and the
+/// finalization rate is averaged over the last 10 minutes, so upgraded subnets are not
+/// checked before this much time has passed.
+const UPGRADE_GRACE_PERIOD: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Args, Debug)]
+#[clap(
+    after_help = r#"The rollout follows the schedule of the release dashboard: app subnets are upgraded in stages
+over the working days of the week, skipping the excluded days, and the NNS is upgraded last.
+For every stage, the command:
+  1. submits a proposal to deploy the version to each subnet of the stage,
+  2. waits until the proposals are adopted and executed,
+  3. watches the upgraded subnets for the bake time, and halts if more of their nodes become unhealthy
+     or their finalization rate drops below the given fraction of the rate before the upgrade.
+
+  Progress is recorded in the state file after every step, and running the same command again resumes
+  the rollout. A subnet is marked as submitting before its proposal is sent, and if the rollout is
+  interrupted at that point, resuming looks the proposal up instead of submitting it twice. A halted rollout is not resumed until `halted` is removed from the state file.
+"#
+)]
+pub struct Run {
+    /// GuestOS version (git commit) to roll out, it must already be elected
+    #[clap(long, short)]
+    pub version: String,
+
+    /// File recording the progress of the rollout, by default in the local store
+    #[clap(long)]
+    pub state_file: Option<PathBuf>,
+
+    /// How long upgraded subnets are watched before the next stage is submitted
+    #[clap(long, default_value = "30m", value_parser = parse_duration)]
+    pub bake_time: Duration,
+
+    /// Time between checks of the submitted proposals and the upgraded subnets
+    #[clap(long, default_value = "1m", value_parser = parse_duration)]
+    pub poll_interval: Duration,
+
+    /// Halt when the finalization rate of an upgraded subnet drops below this fraction
+    /// of its rate before the upgrade
+    #[clap(long, default_value_t = 0.8)]
+    pub min_finalization_rate_ratio: f64,
+
+    #[clap(flatten)]
+    pub submission_parameters: SubmissionParameters,
+}
+
+impl ExecutableCommand for Run {
+    fn require_auth(&self) -> AuthRequirement {
+        AuthRequirement::Neuron
+    }
+
+    async fn execute(&self, ctx: DreContext) -> anyhow::Result<()> {
+        let state_file = match &self.state_file {
+            Some(path) => path.clone(),
+            None => ctx.rollout_state_file(&self.version)?,
+        };
+        let mode = HowToProceed::from(&self.submission_parameters.confirmation_mode);
+        let mut plan = match RolloutPlan::read(&state_file)? {
+            Some(plan) if plan.version != self.version => {
+                anyhow::bail!(
+                    "State file {} records the rollout of version {}, not {}",
+                    state_file.display(),
+                    plan.version,
+                    self.version
+                )
+            }
+            Some(plan) => {
+                info!("Resuming the rollout from {}", state_file.display());
+                plan
+            }
+            None => {
+                let subnets = ctx.registry().await.subnets().await?;
+                let nns_subnet = subnets
+                    .values()
+                    .find(|s| s.metadata.name == NNS_SUBNET_NAME)
+                    .ok_or_else(|| anyhow::anyhow!("NNS subnet not found in the registry"))?
+                    .principal;
+                let pending = subnets
+                    .values()
+                    .filter(|s| s.replica_version != self.version)
+                    .map(|s| s.principal)
+                    .collect_vec();
+                let today = Utc::now().date_naive();
+                let plan = RolloutPlan::new(&self.version, nns_subnet, &pending, &RolloutConfig::bundled().rollout_days(today), today)?;
+                if mode != HowToProceed::DryRun {
+                    plan.write(&state_file)?;
+                }
+                plan
+            }
+        };
+        println!("{}", plan.summary());
+        if let Some(reason) = &plan.halted {
+            anyhow::bail!(
+                "The rollout was halted: {}\nOnce the problem is resolved, remove `halted` from {} to resume",
+                reason,
+                state_file.display()
+            )
+        }
+
+        let submitter = Submitter::from(&self.submission_parameters);
+        if mode == HowToProceed::DryRun {
+            if let Some(index) = plan.current_stage() {
+                self.submit_stage(&ctx, &submitter, &mode, &mut plan, index, &state_file).await?;
+            }
+            return Ok(());
+        }
+
+        loop {
+            let Some(index) = plan.current_stage() else {
+                info!("Version {} was rolled out to all subnets", self.version);
+                DesktopNotifier::send_info(
+                    "DRE rollout: complete",
+                    &format!("Version {} was rolled out to all subnets", self.version),
+                );
+                return Ok(());
+            };
+            let now = Utc::now();
+            match plan.stages[index].phase() {
+                StagePhase::Pending if now.date_naive() < plan.stages[index].date => {
+                    info!("Stage {} is scheduled for {}", index + 1, plan.stages[index].date)
+                }
+                StagePhase::Pending => {
+                    if !self.submit_stage(&ctx, &submitter, &mode, &mut plan, index, &state_file).await? {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                StagePhase::Submitted => self.update_executions(&ctx, &mut plan, index, &state_file).await?,
+                StagePhase::Baking { since } => {
+                    if now < since + chrono::Duration::from_std(UPGRADE_GRACE_PERIOD)? {
+                        info!("Waiting for the subnets of stage {} to run the new version", index + 1);
+                    } else {
+                        self.check_regressions(&ctx, &mut plan, index, &state_file).await?;
+                        if now >= since + chrono::Duration::from_std(self.bake_time)? {
+                            plan.stages[index].completed_at = Some(now);
+                            plan.write(&state_file)?;
+                            println!("{}", plan.summary());
+                            DesktopNotifier::send_info(
+                                "DRE rollout: stage complete",
+                                &format!("Stage {} of {} is complete", index + 1, plan.stages.len()),
+                            );
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            let interrupted = tokio::select! {
+                _ = tokio::signal::ctrl_c() => true,
+                _ = tokio::time::sleep(self.poll_interval) => false,
+            };
+            if interrupted {
+                info!("Received Ctrl-C, run the same command again to resume the rollout");
+                return Ok(());
+            }
+        }
+    }
+
+    fn validate(&self, args: &GlobalArgs, cmd: &mut clap::Command) {
+        if args.prepare_only.is_some() {
+            cmd.error(
+                ErrorKind::ArgumentConflict,
+                "Rollouts cannot be prepared for offline signing, a bundle holds a single proposal",
+            )
+            .exit()
+        }
+        if !(0.0..=1.0).contains(&self.min_finalization_rate_ratio) {
+            cmd.error(ErrorKind::InvalidValue, "The finalization rate ratio must be between 0 and 1")
+                .exit()
+        }
+    }
+}
+
+impl Run {
+    /// Submits the proposals of the subnets of the stage not submitted yet.  Returns false if
+    /// the proposals were only simulated or the user declined to submit them.
+    async fn submit_stage(
+        &self,
+        ctx: &DreContext,
+        submitter: &Submitter,
+        mode: &HowToProceed,
+        plan: &mut RolloutPlan,
+        index: usize,
+        state_file: &Path,
+    ) -> anyhow::Result<bool> {
+        if *mode != HowToProceed::DryRun {
+            self.reconcile_interrupted_submissions(ctx, plan, index, state_file).await?;
+        }
+        let pending = plan.stages[index]
+            .subnets
+            .iter()
+            .filter(|s| s.status == SubnetRolloutStatus::Pending)
+            .map(|s| s.subnet_id)
+            .collect_vec();
+        let runner = ctx.runner().await?;
+        let mut executions = vec![];
+        for subnet_id in &pending {
+            executions.push(ctx.proposal_execution(runner.deploy(subnet_id, &plan.version).await?).await?);
+        }
+
+        if *mode != HowToProceed::Unconditional {
+            for execution in &executions {
+                execution
+                    .simulate(self.submission_parameters.forum_parameters.forum_post_link_for_simulation())
+                    .await?;
+            }
+        }
+        match mode {
+            HowToProceed::DryRun => return Ok(false),
+            HowToProceed::Confirm => {
+                if !yesno(&format!("Do you want to submit stage {} ({} subnets)?", index + 1, pending.len()), false).await?? {
+                    return Ok(false);
+                }
+            }
+            HowToProceed::Unconditional | HowToProceed::UnitTests => (),
+        }
+
+        let finalization_rates = prometheus::finalization_rates(&prometheus::client(ctx.network()), ctx.network()).await?;
+        let health_client = ctx.health_client();
+        for (subnet_id, execution) in pending.into_iter().zip(executions) {
+            let unhealthy_nodes = unhealthy_nodes(&health_client.subnet(subnet_id).await?);
+            let subnet = plan.stages[index].subnet_mut(subnet_id);
+            subnet.status = SubnetRolloutStatus::Submitting;
+            subnet.submission_started_at = Some(Utc::now());
+            subnet.baseline_finalization_rate = finalization_rates.get(&subnet_id).copied();
+            subnet.baseline_unhealthy_nodes = unhealthy_nodes;
+            plan.write(state_file)?;
+
+            let response = submitter.submit(execution.as_ref(), ForumPostKind::Generic).await.map_err(|e| {
+                e.context(format!(
+                    "Failed to submit the upgrade of subnet {}. Submitted proposals are recorded in {}, run the same command again to resume",
+                    subnet_id,
+                    state_file.display()
+                ))
+            })?;
+            let subnet = plan.stages[index].subnet_mut(subnet_id);
+            let Some(response) = response else {
+                warn!("The upgrade of subnet {} was not submitted", subnet_id);
+                subnet.status = SubnetRolloutStatus::Pending;
+                subnet.submission_started_at = None;
+                plan.write(state_file)?;
+                return Ok(false);
+            };
+            info!("Submitted the upgrade of subnet {} as {}", subnet_id, response);
+            subnet.status = SubnetRolloutStatus::Submitted;
+            subnet.proposal_id = Some(response.into());
+            plan.write(state_file)?;
+        }
+        Ok(true)
+    }
+
+    /// Resolves the subnets whose submission was interrupted: records the proposals the governance
+    /// canister received, and marks the other subnets as pending so that they are submitted again.
+    async fn reconcile_interrupted_submissions(
+        &self,
+        ctx: &DreContext,
+        plan: &mut RolloutPlan,
+        index: usize,
+        state_file: &Path,
+    ) -> anyhow::Result<()> {
+        let runner = ctx.runner().await?;
+        let version = plan.version.clone();
+        for subnet in plan.stages[index]
+            .subnets
+            .iter_mut()
+            .filter(|s| s.status == SubnetRolloutStatus::Submitting)
+        {
+            let started_at = subnet.submission_started_at.unwrap_or(DateTime::UNIX_EPOCH);
+            // Allow for clock skew between this machine and the IC
+            let since = started_at - chrono::Duration::minutes(5);
+            let proposal = runner.deploy(&subnet.subnet_id, &version).await?;
+            match ctx.find_submitted_proposal(&proposal, since).await? {
+                Some(proposal_id) => {
+                    info!(
+                        "The interrupted upgrade of subnet {} was submitted as proposal {}",
+                        subnet.subnet_id, proposal_id
+                    );
+                    subnet.status = SubnetRolloutStatus::Submitted;
+                    subnet.proposal_id = Some(proposal_id);
+                }
+                None => {
+                    warn!(
+                        "The interrupted upgrade of subnet {} did not reach the governance canister, it will be submitted again",
+                        subnet.subnet_id
+                    );
+                    subnet.status = SubnetRolloutStatus::Pending;
+                    subnet.submission_started_at = None;
+                }
+            }
+        }
+        plan.write(state_file)
+    }
+
+    /// Records the executed proposals of the stage, and halts if one of them was rejected or failed.
+    async fn update_executions(&self, ctx: &DreContext, plan: &mut RolloutPlan, index: usize, state_file: &Path) -> anyhow::Result<()> {
+        let governance = GovernanceCanisterWrapper::from(ctx.create_ic_agent_canister_client().await?);
+        let mut failures = vec![];
+        for subnet in plan.stages[index]
+            .subnets
+            .iter_mut()
+            .filter(|s| s.status == SubnetRolloutStatus::Submitted)
+        {
+            let proposal_id = subnet.proposal_id.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Subnet {} is submitted but has no proposal in {}, set its `proposal_id` or its status back to pending",
+                    subnet.subnet_id,
+                    state_file.display()
+                )
+            })?;
+            let proposal = governance.get_proposal(proposal_id).await?;
+            if proposal.executed_timestamp_seconds > 0 {
+                info!("Proposal {} to upgrade subnet {} was executed", proposal_id, subnet.subnet_id);
+                subnet.status = SubnetRolloutStatus::Executed;
+                subnet.executed_at = Some(DateTime::from_timestamp(proposal.executed_timestamp_seconds as i64, 0).unwrap_or_else(Utc::now));
+                continue;
+            }
+            let status = ProposalStatus::try_from(proposal.status)?;
+            if matches!(status, ProposalStatus::Rejected | ProposalStatus::Failed) {
+                failures.push(format!(
+                    "proposal {} to upgrade subnet {} has status {}",
+                    proposal_id,
+                    subnet.subnet_id,
+                    status.as_str_name()
+                ));
+            }
+        }
+        plan.write(state_file)?;
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            halt(plan, state_file, failures.join("; "))
+        }
+    }
+
+    /// Halts the rollout if the subnets of the stage regressed since their upgrade was submitted.
+    async fn check_regressions(&self, ctx: &DreContext, plan: &mut RolloutPlan, index: usize, state_file: &Path) -> anyhow::Result<()> {
+        let finalization_rates = prometheus::finalization_rates(&prometheus::client(ctx.network()), ctx.network()).await?;
+        let health_client = ctx.health_client();
+        let mut regressions = vec![];
+        for subnet in &plan.stages[index].subnets {
+            let health = health_client.subnet(subnet.subnet_id).await?;
+            regressions.extend(subnet.regressions(
+                &health,
+                finalization_rates.get(&subnet.subnet_id).copied(),
+                self.min_finalization_rate_ratio,
+            ));
+        }
+        if regressions.is_empty() {
+            Ok(())
+        } else {
+            halt(plan, state_file, regressions.join("; "))
+        }
+    }
+}
+
+fn halt(plan: &mut RolloutPlan, state_file: &Path, reason: String) -> anyhow::Result<()> {
+    DesktopNotifier::send_critical("DRE rollout: halted", &reason);
+    plan.halted = Some(reason.clone());
+    plan.write(state_file)?;
+    Err(anyhow::anyhow!(
+        "Halted the rollout: {}\nOnce the problem is resolved, remove `halted` from {} to resume",
+        reason,
+        state_file.display()
+    ))
+}
+
+fn unhealthy_nodes(health: &IndexMap<PrincipalId, HealthStatus>) -> usize {
+    health.values().filter(|status| **status != HealthStatus::Healthy).count()
+}
+
+/// Progress of a rollout, written after every step so that an interrupted rollout can be resumed.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct RolloutPlan {
+    version: String,
+    stages: Vec<RolloutStage>,
+    #[serde(default)]
+    halted: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct RolloutStage {
+    date: NaiveDate,
+    subnets: Vec<SubnetRollout>,
+    #[serde(default)]
+    completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct SubnetRollout {
+    subnet_id: PrincipalId,
+    #[serde(default)]
+    status: SubnetRolloutStatus,
+    #[serde(default)]
+    proposal_id: Option<u64>,
+    /// When the proposal was sent, kept to look it up if the submission was interrupted
+    #[serde(default)]
+    submission_started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    executed_at: Option<DateTime<Utc>>,
+    /// Finalized blocks per second when the upgrade was submitted
+    #[serde(default)]
+    baseline_finalization_rate: Option<f64>,
+    /// Nodes of the subnet that were not healthy when the upgrade was submitted
+    #[serde(default)]
+    baseline_unhealthy_nodes: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+enum SubnetRolloutStatus {
+    #[default]
+    Pending,
+    /// The proposal is being sent, or was when the rollout was interrupted
+    Submitting,
+    Submitted,
+    Executed,
+}
+
+#[derive(Debug, PartialEq)]
+enum StagePhase {
+    /// Some proposals of the stage were not submitted yet, or their submission was interrupted
+    Pending,
+    /// Some proposals of the stage were not executed yet
+    Submitted,
+    /// All proposals of the stage were executed, the last one at the given time
+    Baking { since: DateTime<Utc> },
+}
+
+impl RolloutPlan {
+    fn new(
+        version: &str,
+        nns_subnet: PrincipalId,
+        pending_subnets: &[PrincipalId],
+        rollout_days: &[NaiveDate],
+        today: NaiveDate,
+    ) -> anyhow::Result<Self> {
+        if pending_subnets.is_empty() {
+            anyhow::bail!("All subnets already run version {}", version);
+        }
+        let stages = if pending_subnets == [nns_subnet] {
+            vec![RolloutStage::new(today, vec![nns_subnet])]
+        } else {
+            RolloutState {
+                nns_subnet,
+                subnets: pending_subnets.to_vec(),
+                rollout_days: rollout_days
+                    .iter()
+                    .map(|date| RolloutDay {
+                        date: *date,
+                        rollout_stages_subnets: vec![],
+                    })
+                    .collect(),
+                date: today,
+            }
+            .schedule()
+            .into_iter()
+            .flat_map(|day| {
+                let date = day.date;
+                day.rollout_stages_subnets.into_iter().map(move |subnets| (date, subnets))
+            })
+            // The NNS is always scheduled, even when it already runs the version
+            .map(|(date, subnets)| RolloutStage::new(date, subnets.into_iter().filter(|s| pending_subnets.contains(s)).collect()))
+            .filter(|stage| !stage.subnets.is_empty())
+            .collect()
+        };
+        Ok(Self {
+            version: version.to_string(),
+            stages,
+            halted: None,
+        })
+    }
+
+    fn read(path: &Path) -> anyhow::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        serde_yaml::from_slice(&fs_err::read(path)?)
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("Invalid rollout state file {}: {}", path.display(), e))
+    }
+
+    fn write(&self, path: &Path) -> anyhow::Result<()> {
+        fs_err::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Index of the first stage that is not complete yet
+    fn current_stage(&self) -> Option<usize> {
+        self.stages.iter().position(|stage| stage.completed_at.is_none())
+    }
+
+    fn summary(&self) -> tabular::Table {
+        let mut table = tabular::Table::new("{:<}    {:<}    {:<}    {:<}    {:<}");
+        table.add_row(
+            tabular::Row::new()
+                .with_cell("Stage")
+                .with_cell("Date")
+                .with_cell("Subnet")
+                .with_cell("Proposal")
+                .with_cell("Status"),
+        );
+        for (i, stage) in self.stages.iter().enumerate() {
+            for subnet in &stage.subnets {
+                table.add_row(
+                    tabular::Row::new()
+                        .with_cell(i + 1)
+                        .with_cell(stage.date)
+                        .with_cell(subnet.subnet_id)
+                        .with_cell(subnet.proposal_id.map(|id| id.to_string()).unwrap_or_else(|| "-".to_string()))
+                        .with_cell(match stage.completed_at {
+                            Some(_) => "complete".to_string(),
+                            None => subnet.status.to_string(),
+                        }),
+                );
+            }
+        }
+        table
+    }
+}
+
+impl RolloutStage {
+    fn new(date: NaiveDate, subnets: Vec<PrincipalId>) -> Self {
+        Self {
+            date,
+            subnets: subnets
+                .into_iter()
+                .map(|subnet_id| SubnetRollout {
+                    subnet_id,
+                    status: SubnetRolloutStatus::Pending,
+                    proposal_id: None,
+                    submission_started_at: None,
+                    executed_at: None,
+                    baseline_finalization_rate: None,
+                    baseline_unhealthy_nodes: 0,
+                })
+                .collect(),
+            completed_at: None,
+        }
+    }
+
+    fn phase(&self) -> StagePhase {
+        if self
+            .subnets
+            .iter()
+            .any(|s| matches!(s.status, SubnetRolloutStatus::Pending | SubnetRolloutStatus::Submitting))
+        {
+            StagePhase::Pending
+        } else if self.subnets.iter().any(|s| s.status == SubnetRolloutStatus::Submitted) {
+            StagePhase::Submitted
+        } else {
+            StagePhase::Baking {
+                since: self.subnets.iter().filter_map(|s| s.executed_at).max().unwrap_or_else(Utc::now),
+            }
+        }
+    }
+
+    fn subnet_mut(&mut self, subnet_id: PrincipalId) -> &mut SubnetRollout {
+        self.subnets
+            .iter_mut()
+            .find(|s| s.subnet_id == subnet_id)
+            .expect("subnet should be part of the stage")
+    }
+}
+
+impl SubnetRollout {
+    /// Describes how the subnet got worse compared to when its upgrade was submitted.
+    fn regressions(
+        &self,
+        health: &IndexMap<PrincipalId, HealthStatus>,
+        finalization_rate: Option<f64>,
+        min_finalization_rate_ratio: f64,
+    ) -> Vec<String> {
+        let mut regressions = vec![];
+        let unhealthy = unhealthy_nodes(health);
+        if unhealthy > self.baseline_unhealthy_nodes {
+            regressions.push(format!(
+                "subnet {} has {} unhealthy nodes, {} before the upgrade",
+                self.subnet_id, unhealthy, self.baseline_unhealthy_nodes
+            ));
+        }
+        if let Some(baseline) = self.baseline_finalization_rate {
+            match finalization_rate {
+                Some(rate) if rate >= baseline * min_finalization_rate_ratio => (),
+                Some(rate) => regressions.push(format!(
+                    "finalization rate of subnet {} dropped to {:.3} blocks/s from {:.3} blocks/s",
+                    self.subnet_id, rate, baseline
+                )),
+                None => regressions.push(format!("no finalization rate is reported for subnet {}", self.subnet_id)),
+            }
+        }
+        regressions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn plan() -> (RolloutPlan, PrincipalId, Vec<PrincipalId>) {
+        let nns = PrincipalId::new_subnet_test_id(100);
+        let subnets = (0..12).map(PrincipalId::new_subnet_test_id).chain([nns]).collect_vec();
+        // Monday to Friday
+        let days = (4..=8).map(date).collect_vec();
+        (RolloutPlan::new("abc", nns, &subnets, &days, date(4)).unwrap(), nns, subnets)
+    }
+
+    #[test]
+    fn plan_schedules_every_subnet_once_with_nns_last() {
+        let (plan, nns, subnets) = plan();
+        let scheduled = plan.stages.iter().flat_map(|s| s.subnets.iter().map(|s| s.subnet_id)).collect_vec();
+        assert_eq!(scheduled.iter().sorted().collect_vec(), subnets.iter().sorted().collect_vec());
+        assert_eq!(plan.stages.last().unwrap().subnets.iter().map(|s| s.subnet_id).collect_vec(), vec![nns]);
+        assert!(plan.stages.windows(2).all(|w| w[0].date <= w[1].date));
+
+        let app_subnet = PrincipalId::new_subnet_test_id(0);
+        let plan = RolloutPlan::new("abc", nns, &[app_subnet], &[date(4), date(5)], date(4)).unwrap();
+        assert_eq!(
+            plan.stages.iter().flat_map(|s| s.subnets.iter().map(|s| s.subnet_id)).collect_vec(),
+            vec![app_subnet]
+        );
+
+        let plan = RolloutPlan::new("abc", nns, &[nns], &[date(4), date(5)], date(4)).unwrap();
+        assert_eq!(plan.stages.len(), 1);
+        assert!(RolloutPlan::new("abc", nns, &[], &[date(4), date(5)], date(4)).is_err());
+    }
+
+    #[test]
+    fn resume_from_state() {
+        let (mut plan, _, _) = plan();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("guestos-abc.yaml");
+        assert_eq!(plan.current_stage(), Some(0));
+        assert_eq!(plan.stages[0].phase(), StagePhase::Pending);
+
+        // An interrupted submission keeps the stage pending until it is reconciled
+        plan.stages[0].subnets[0].status = SubnetRolloutStatus::Submitting;
+        plan.stages[0].subnets[0].submission_started_at = Some(Utc::now());
+        plan.write(&path).unwrap();
+        assert_eq!(RolloutPlan::read(&path).unwrap().unwrap(), plan);
+        assert_eq!(plan.stages[0].phase(), StagePhase::Pending);
+
+        let executed_at = DateTime::from_timestamp(1_709_550_000, 0).unwrap();
+        for subnet in plan.stages[0].subnets.iter_mut() {
+            subnet.status = SubnetRolloutStatus::Submitted;
+            subnet.proposal_id = Some(42);
+        }
+        assert_eq!(plan.stages[0].phase(), StagePhase::Submitted);
+        for subnet in plan.stages[0].subnets.iter_mut() {
+            subnet.status = SubnetRolloutStatus::Executed;
+            subnet.executed_at = Some(executed_at);
+        }
+        assert_eq!(plan.stages[0].phase(), StagePhase::Baking { since: executed_at });
+        plan.stages[0].completed_at = Some(executed_at);
+        plan.write(&path).unwrap();
+
+        let resumed = RolloutPlan::read(&path).unwrap().unwrap();
+        assert_eq!(resumed, plan);
+        assert_eq!(resumed.current_stage(), Some(1));
+        assert!(RolloutPlan::read(&dir.path().join("missing.yaml")).unwrap().is_none());
+    }
+
+    #[test]
+    fn detect_regressions() {
+        let node = |id| PrincipalId::new_node_test_id(id);
+        let mut subnet = RolloutStage::new(date(4), vec![PrincipalId::new_subnet_test_id(0)]).subnets.remove(0);
+        subnet.baseline_finalization_rate = Some(1.0);
+        subnet.baseline_unhealthy_nodes = 1;
+
+        let health = IndexMap::from([(node(1), HealthStatus::Healthy), (node(2), HealthStatus::Degraded)]);
+        assert!(subnet.regressions(&health, Some(0.9), 0.8).is_empty());
+        assert_eq!(subnet.regressions(&health, Some(0.5), 0.8).len(), 1);
+        assert_eq!(subnet.regressions(&health, None, 0.8).len(), 1);
+
+        let health = IndexMap::from([(node(1), HealthStatus::Dead), (node(2), HealthStatus::Degraded)]);
+        assert_eq!(subnet.regressions(&health, Some(0.5), 0.8).len(), 2);
+    }
+}
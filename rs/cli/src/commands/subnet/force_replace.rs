@@ -177,7 +177,7 @@ impl ExecutableCommand for ForceReplace {
 
         Submitter::from(&self.submission_parameters)
             .propose_and_print(
-                ctx.ic_admin_executor().await?.execution(runner_proposal.clone()),
+                ctx.proposal_execution(runner_proposal.clone()).await?,
                 match subnet_change_response.subnet_id {
                     Some(id) => ForumPostKind::ReplaceNodes {
                         subnet_id: id,
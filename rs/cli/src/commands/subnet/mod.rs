@@ -1,5 +1,8 @@
-use clap::Parser;
+use std::time::Duration;
+
+use clap::{Args, Parser};
 use create::Create;
+use decentralization::network::SelectionStrategy;
 use deploy::Deploy;
 use force_replace::ForceReplace;
 use replace::Replace;
@@ -26,3 +29,27 @@ pub struct Subnet {
 }
 
 impl_executable_command_for_enums! { Subnet, WhatifDecentralization, Deploy, Replace, Resize, Create, Rescue, SetAuthorization, ForceReplace }
+
+/// How to select the nodes that are added to or removed from a subnet
+#[derive(Args, Debug)]
+pub struct SelectionArgs {
+    /// Search for the best combination of nodes to add and remove, instead of picking
+    /// them one at a time. Slower, but finds changes that the default selection misses.
+    #[clap(long)]
+    pub exact: bool,
+
+    /// Time budget for the exact search, in seconds. When exhausted, the best result found so far is used.
+    #[clap(long, default_value = "60")]
+    pub exact_time_budget_secs: u64,
+}
+
+impl SelectionArgs {
+    pub fn strategy(&self) -> SelectionStrategy {
+        match self.exact {
+            true => SelectionStrategy::Exact {
+                time_budget: Duration::from_secs(self.exact_time_budget_secs),
+            },
+            false => SelectionStrategy::Greedy,
+        }
+    }
+}
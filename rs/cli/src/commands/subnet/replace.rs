@@ -4,6 +4,7 @@ use decentralization::network::SubnetQueryBy;
 use ic_types::PrincipalId;
 use itertools::Itertools;
 
+use super::SelectionArgs;
 use crate::exe::args::GlobalArgs;
 use crate::forum::ForumPostKind;
 use crate::submitter::{SubmissionParameters, Submitter};
@@ -43,6 +44,9 @@ pub struct Replace {
     #[clap(long, short, visible_aliases = &["subnet", "id"])]
     pub subnet_id: Option<PrincipalId>,
 
+    #[clap(flatten)]
+    pub selection: SelectionArgs,
+
     #[clap(flatten)]
     pub submission_parameters: SubmissionParameters,
 }
@@ -65,6 +69,7 @@ impl ExecutableCommand for Replace {
         let subnet_manager = ctx.subnet_manager().await?;
         let subnet_change_response = subnet_manager
             .with_target(subnet_target)
+            .with_selection_strategy(self.selection.strategy())
             .membership_replace(
                 !self.no_heal,
                 self.motivation.clone(),
@@ -83,7 +88,7 @@ impl ExecutableCommand for Replace {
 
         Submitter::from(&self.submission_parameters)
             .propose_and_print(
-                ctx.ic_admin_executor().await?.execution(runner_proposal.clone()),
+                ctx.proposal_execution(runner_proposal.clone()).await?,
                 match subnet_change_response.subnet_id {
                     Some(id) => ForumPostKind::ReplaceNodes {
                         subnet_id: id,
@@ -3,6 +3,7 @@ use clap::Args;
 use ic_management_types::requests::SubnetResizeRequest;
 use ic_types::PrincipalId;
 
+use super::SelectionArgs;
 use crate::exe::args::GlobalArgs;
 use crate::{
     auth::AuthRequirement,
@@ -41,6 +42,9 @@ pub struct Resize {
     #[clap(long, short, alias = "subnet-id")]
     pub id: PrincipalId,
 
+    #[clap(flatten)]
+    pub selection: SelectionArgs,
+
     #[clap(flatten)]
     pub submission_parameters: SubmissionParameters,
 }
@@ -56,6 +60,7 @@ impl ExecutableCommand for Resize {
         let subnet_change_response = ctx
             .subnet_manager()
             .await?
+            .with_selection_strategy(self.selection.strategy())
             .subnet_resize(
                 SubnetResizeRequest {
                     subnet: self.id,
@@ -75,7 +80,7 @@ impl ExecutableCommand for Resize {
             None => return Ok(()),
         };
         Submitter::from(&self.submission_parameters)
-            .propose_and_print(ctx.ic_admin_executor().await?.execution(runner_proposal), ForumPostKind::Generic)
+            .propose_and_print(ctx.proposal_execution(runner_proposal).await?, ForumPostKind::Generic)
             .await
     }
 
@@ -102,7 +102,7 @@ impl ExecutableCommand for SetAuthorization {
 
         Submitter::from(&self.submission_parameters)
             .propose_and_print(
-                ctx.ic_admin_executor().await?.execution(prop),
+                ctx.proposal_execution(prop).await?,
                 ForumPostKind::AuthorizedSubnetsUpdate { body: summary },
             )
             .await
@@ -1,5 +1,8 @@
+use std::path::PathBuf;
+
 use crate::exe::args::GlobalArgs;
 use clap::Args;
+use decentralization::report::{ReportFormat, SubnetChangeReport};
 use ic_types::PrincipalId;
 use registry_canister::mutations::do_change_subnet_membership::ChangeSubnetMembershipPayload;
 
@@ -22,6 +25,14 @@ pub struct WhatifDecentralization {
     /// can be provided to override the current list of subnet nodes for the sake of analysis
     #[clap(long, num_args(1..))]
     subnet_nodes_initial: Option<Vec<PrincipalId>>,
+
+    /// Format of the report: markdown, as in proposal summaries, a self-contained html page, or json
+    #[clap(long, default_value_t = ReportFormat::Markdown)]
+    format: ReportFormat,
+
+    /// Output file for the report (default is stdout)
+    #[clap(short = 'o', long)]
+    output: Option<PathBuf>,
 }
 
 impl ExecutableCommand for WhatifDecentralization {
@@ -42,7 +53,11 @@ impl ExecutableCommand for WhatifDecentralization {
             .decentralization_change(&change_membership, self.subnet_nodes_initial.clone(), None)
             .await?;
 
-        println!("{}", change);
+        let report = SubnetChangeReport::from(&change).render(self.format)?;
+        match &self.output {
+            Some(path) => std::fs::write(path, report)?,
+            None => println!("{}", report),
+        }
         Ok(())
     }
 
@@ -156,7 +156,7 @@ impl ExecutableCommand for UpdateDefaultSubnets {
 
         Submitter::from(&self.submission_parameters)
             .propose_and_print(
-                ctx.ic_admin_executor().await?.execution(prop),
+                ctx.proposal_execution(prop).await?,
                 ForumPostKind::AuthorizedSubnetsUpdate { body: summary },
             )
             .await
@@ -1,21 +1,57 @@
-use std::{collections::HashSet, io::Write, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Write,
+    path::PathBuf,
+    time::Duration,
+};
 
+use candid::Decode;
+use chrono::{DateTime, Utc};
 use clap::Args;
 use humantime::{format_duration, parse_duration};
 use ic_canisters::governance::GovernanceCanisterWrapper;
-use ic_nns_governance::pb::v1::Topic;
-use ic_nns_governance_api::ProposalInfo;
-use log::info;
+use ic_nns_governance::pb::v1::{NnsFunction, Vote as Ballot};
+use ic_nns_governance_api::{ListProposalInfoRequest, ProposalInfo, proposal::Action};
+use itertools::Itertools;
+use log::{debug, info, warn};
+use registry_canister::mutations::{
+    do_change_subnet_membership::ChangeSubnetMembershipPayload, do_deploy_guestos_to_all_subnet_nodes::DeployGuestosToAllSubnetNodesPayload,
+    do_deploy_guestos_to_all_unassigned_nodes::DeployGuestosToAllUnassignedNodesPayload,
+    do_update_nodes_hostos_version::UpdateNodesHostosVersionPayload,
+};
 use spinners::{Spinner, Spinners};
 
 use crate::auth::AuthRequirement;
+use crate::ctx::DreContext;
 use crate::exe::{ExecutableCommand, args::GlobalArgs};
+use crate::voting_rules::{Evaluation, PayloadCondition, ProposalFacts, VoteAction, VoteDecision, VotingRule, VotingRules};
 use crate::{
     confirm::{ConfirmationModeOptions, HowToProceed},
     desktop_notify::DesktopNotifier,
 };
 
 #[derive(Args, Debug)]
+#[clap(after_help = r#"VOTING RULES FILE:
+    rules:
+      - name: dre-replacements
+        proposers: [40, 80]
+        nns_functions: [ChangeSubnetMembership]
+        conditions: [nakamoto_not_reduced]
+        action: yes
+      - name: guestos-upgrades-in-office-hours
+        topics: [IcOsVersionDeployment]
+        conditions: [elected_version]
+        time_window: { days: [Mon, Tue, Wed, Thu, Fri], start_hour: 7, end_hour: 17 }
+        action: yes
+      - name: other-upgrades
+        topics: [IcOsVersionDeployment]
+        action: manual
+
+    The first matching rule decides. If its time window is closed, the proposal waits for the
+    window to open instead of falling through to the next rule.
+    Actions: yes, no, abstain_and_alert, manual. Manual alerts and queues the proposal for you to vote on.
+    Conditions: elected_version, nakamoto_not_reduced, evaluated again whenever the registry changes.
+    Time windows are in UTC."#)]
 pub struct Vote {
     /// Override default accepted proposers
     /// These are the proposers which proposals will
@@ -40,6 +76,15 @@ pub struct Vote {
     #[clap(long, use_value_delimiter = true, value_delimiter = ',', default_value = "12")]
     pub accepted_topics: Vec<i32>,
 
+    /// Voting rules file, replaces the accepted neurons and topics
+    #[clap(long, conflicts_with_all = ["accepted_neurons", "accepted_topics"])]
+    pub rules_file: Option<PathBuf>,
+
+    /// Evaluate the rules against this many of the most recent proposals and print
+    /// the decisions, without voting
+    #[clap(long, value_name = "COUNT")]
+    pub replay: Option<u32>,
+
     /// Sleep time between voting cycles.  If set to 0s,
     /// only one voting cycle will take place.
     #[clap(long, default_value = "60s", value_parser = parse_duration)]
@@ -51,18 +96,35 @@ pub struct Vote {
 
 impl ExecutableCommand for Vote {
     fn require_auth(&self) -> AuthRequirement {
-        AuthRequirement::Neuron
+        match self.replay {
+            Some(_) => AuthRequirement::Anonymous,
+            None => AuthRequirement::Neuron,
+        }
     }
 
     fn validate(&self, _args: &GlobalArgs, _cmd: &mut clap::Command) {}
 
-    async fn execute(&self, ctx: crate::ctx::DreContext) -> anyhow::Result<()> {
+    async fn execute(&self, ctx: DreContext) -> anyhow::Result<()> {
+        let rules = match &self.rules_file {
+            Some(path) => VotingRules::load(path)?,
+            None => VotingRules::accepting(&self.accepted_neurons, &self.accepted_topics)?,
+        };
+        if let Some(count) = self.replay {
+            return replay(&ctx, &rules, count).await;
+        }
+
         let mut had_error = false;
         let (neuron, client) = ctx.create_ic_agent_canister_client().await?;
         let wrapper: GovernanceCanisterWrapper = client.into();
         let no_duration = Duration::from_secs(0);
-        let mut voted_proposals: HashSet<u64> = HashSet::new();
+        // Proposals a rule decided on, they are not evaluated again
+        let mut decided_proposals: HashSet<u64> = HashSet::new();
+        // Facts of undecided proposals, with the registry version the payload conditions were evaluated at
+        let mut facts_cache: HashMap<u64, (u64, ProposalFacts)> = HashMap::new();
+        // Proposals a `manual` rule left for a human to vote on, with their titles
+        let mut manual_queue: BTreeMap<u64, String> = BTreeMap::new();
         let mode: HowToProceed = (&self.confirmation_mode).into();
+        let vote_log = ctx.vote_log()?;
 
         if self.sleep_time != no_duration {
             DesktopNotifier::send_info("DRE vote: starting", "Starting the voting loop...");
@@ -71,14 +133,18 @@ impl ExecutableCommand for Vote {
         loop {
             match wrapper.get_pending_proposals().await {
                 Ok(proposals) => {
-                    let proposals: Vec<&ProposalInfo> = proposals
-                        .iter()
-                        .filter(|p| {
-                            self.accepted_topics.contains(&p.topic)
-                                && self.accepted_neurons.contains(&p.proposer.unwrap().id)
-                                && !voted_proposals.contains(&p.id.unwrap().id)
-                        })
-                        .collect();
+                    // Proposals that are not pending anymore never come back
+                    let pending: HashSet<u64> = proposals.iter().map(|p| p.id.unwrap().id).collect();
+                    decided_proposals.retain(|id| pending.contains(id));
+                    facts_cache.retain(|id, _| pending.contains(id));
+                    manual_queue.retain(|id, _| pending.contains(id));
+                    let proposals: Vec<&ProposalInfo> = proposals.iter().filter(|p| !decided_proposals.contains(&p.id.unwrap().id)).collect();
+
+                    // Payload conditions look at the registry, which changes while the loop runs
+                    let registry_version = match rules.conditions().is_empty() || proposals.is_empty() {
+                        true => 0,
+                        false => ctx.refresh_registry().await.get_latest_version().get(),
+                    };
 
                     // Clear last line in terminal
                     print!("\x1B[1A\x1B[K");
@@ -86,42 +152,48 @@ impl ExecutableCommand for Vote {
                     let _ = std::io::stdout().flush();
 
                     for proposal in proposals {
-                        DesktopNotifier::send_info(
-                            "DRE vote: voting",
-                            &format!(
-                                "Voting on proposal {} (topic {:?}, proposer {}) -> {}",
-                                proposal.id.unwrap().id,
-                                Topic::try_from(proposal.topic)?,
-                                proposal.proposer.unwrap_or_default().id,
-                                proposal.proposal.clone().unwrap().title.unwrap()
-                            ),
-                        );
-
                         let prop_id = proposal.id.unwrap().id;
-                        match &mode {
-                            // Confirm mode in this command does not ask for confirmation.  It just votes.
-                            HowToProceed::Confirm | HowToProceed::Unconditional | HowToProceed::UnitTests => {
-                                match wrapper.register_vote(neuron.neuron_id, proposal.id.unwrap().id).await {
-                                    Ok(response) => {
-                                        info!("Voted successfully: {}", response);
-                                    }
-                                    Err(e) => {
-                                        DesktopNotifier::send_critical(
-                                            "DRE vote: error",
-                                            &format!(
-                                                "Error voting on proposal {} (topic {:?}, proposer {}) -> {}",
-                                                prop_id,
-                                                Topic::try_from(proposal.topic)?,
-                                                proposal.proposer.unwrap_or_default().id,
-                                                e
-                                            ),
-                                        );
-                                    }
-                                }
+                        if facts_cache.get(&prop_id).is_none_or(|(version, _)| *version != registry_version) {
+                            facts_cache.insert(prop_id, (registry_version, proposal_facts(&ctx, &rules, proposal).await));
+                        }
+                        let facts = &facts_cache[&prop_id].1;
+                        let rule = match rules.evaluate(facts, Utc::now()) {
+                            Evaluation::Decided(rule) => rule,
+                            Evaluation::Deferred(rule) => {
+                                debug!("Proposal {} waits for the time window of voting rule `{}`", prop_id, rule.name);
+                                continue;
                             }
-                            HowToProceed::DryRun => info!("Would have voted for proposal {}", prop_id),
+                            Evaluation::NoMatch => {
+                                debug!("No voting rule matches proposal {}", prop_id);
+                                continue;
+                            }
+                        };
+                        info!(
+                            "Proposal {} ({}) matches voting rule `{}`: {}",
+                            prop_id, facts.title, rule.name, rule.action
+                        );
+
+                        let mut decision = VoteDecision::new(facts, rule, mode == HowToProceed::DryRun);
+                        decision.result = Some(act(&wrapper, neuron.neuron_id, facts, rule, &mode).await?);
+                        if rule.action == VoteAction::Manual && mode != HowToProceed::DryRun {
+                            manual_queue.insert(prop_id, facts.title.clone());
                         }
-                        voted_proposals.insert(prop_id);
+                        if let Err(e) = vote_log.append(&decision) {
+                            warn!(
+                                "Failed to record the vote on proposal {} in {}: {:?}",
+                                prop_id,
+                                vote_log.path().display(),
+                                e
+                            );
+                        }
+                        decided_proposals.insert(prop_id);
+                        facts_cache.remove(&prop_id);
+                    }
+                    if !manual_queue.is_empty() {
+                        info!(
+                            "Proposals waiting for a manual vote: {}",
+                            manual_queue.iter().map(|(id, title)| format!("{} ({})", id, title)).join(", ")
+                        );
                     }
                     had_error = false;
 
@@ -168,3 +240,148 @@ impl ExecutableCommand for Vote {
         Ok(())
     }
 }
+
+/// Carries out the action of the matched rule, returning what happened for the vote log.
+async fn act(
+    wrapper: &GovernanceCanisterWrapper,
+    neuron_id: u64,
+    proposal: &ProposalFacts,
+    rule: &VotingRule,
+    mode: &HowToProceed,
+) -> anyhow::Result<String> {
+    let description = format!(
+        "proposal {} (topic {}, proposer {}) -> {}",
+        proposal.proposal_id,
+        proposal.topic,
+        proposal.proposer.unwrap_or_default(),
+        proposal.title
+    );
+    let ballot = match (rule.action, mode) {
+        (_, HowToProceed::DryRun) => {
+            info!("Would have acted on {} with `{}` (rule `{}`)", description, rule.action, rule.name);
+            return Ok(format!("dry run: {}", rule.action));
+        }
+        (VoteAction::AbstainAndAlert, _) => {
+            DesktopNotifier::send_critical(
+                "DRE vote: needs attention",
+                &format!("Not voting on {}, voting rule `{}` requires attention", description, rule.name),
+            );
+            return Ok("not voted, alert sent".to_string());
+        }
+        // Asking on the terminal would hold up the votes on every other proposal
+        (VoteAction::Manual, _) => {
+            DesktopNotifier::send_critical(
+                "DRE vote: manual vote needed",
+                &format!("Not voting on {}, voting rule `{}` leaves the vote to you", description, rule.name),
+            );
+            return Ok("not voted, queued for a manual vote".to_string());
+        }
+        // Confirm mode in this command does not ask for confirmation.  It just votes.
+        (VoteAction::Yes, _) => Ballot::Yes,
+        (VoteAction::No, _) => Ballot::No,
+    };
+
+    DesktopNotifier::send_info("DRE vote: voting", &format!("Voting {:?} on {}", ballot, description));
+    match wrapper.register_vote(neuron_id, proposal.proposal_id, ballot).await {
+        Ok(response) => {
+            info!("Voted successfully: {}", response);
+            Ok(response)
+        }
+        Err(e) => {
+            DesktopNotifier::send_critical("DRE vote: error", &format!("Error voting on {} -> {}", description, e));
+            Ok(format!("error: {}", e))
+        }
+    }
+}
+
+/// Prints the decisions the rules would have made on recent proposals, at the time each proposal was created.
+async fn replay(ctx: &DreContext, rules: &VotingRules, count: u32) -> anyhow::Result<()> {
+    let client = GovernanceCanisterWrapper::from(ctx.create_ic_agent_canister_client().await?);
+    let proposals = client
+        .list_proposals(ListProposalInfoRequest {
+            limit: count,
+            ..Default::default()
+        })
+        .await?;
+    if !rules.conditions().is_empty() {
+        warn!("Payload conditions use the current registry, so executed proposals may evaluate differently than when they were open");
+    }
+
+    let mut table = tabular::Table::new("{:>}    {:<}    {:<}    {:<}    {:<}    {:<}");
+    table.add_row(
+        tabular::Row::new()
+            .with_cell("Proposal")
+            .with_cell("Created")
+            .with_cell("Topic")
+            .with_cell("Title")
+            .with_cell("Rule")
+            .with_cell("Action"),
+    );
+    for proposal in &proposals {
+        let facts = proposal_facts(ctx, rules, proposal).await;
+        let created = DateTime::from_timestamp(proposal.proposal_timestamp_seconds as i64, 0).unwrap_or_default();
+        let (rule, action) = match rules.evaluate(&facts, created) {
+            Evaluation::Decided(rule) => (rule.name.as_str(), rule.action.to_string()),
+            Evaluation::Deferred(rule) => (rule.name.as_str(), format!("{} once the time window opens", rule.action)),
+            Evaluation::NoMatch => ("-", "no vote".to_string()),
+        };
+        table.add_row(
+            tabular::Row::new()
+                .with_cell(facts.proposal_id)
+                .with_cell(created.format("%Y-%m-%d %H:%M"))
+                .with_cell(&facts.topic)
+                .with_cell(facts.title.chars().take(60).collect::<String>())
+                .with_cell(rule)
+                .with_cell(action),
+        );
+    }
+    println!("{}", table);
+    Ok(())
+}
+
+async fn proposal_facts(ctx: &DreContext, rules: &VotingRules, proposal: &ProposalInfo) -> ProposalFacts {
+    let mut facts = ProposalFacts::new(proposal);
+    for condition in rules.conditions() {
+        let holds = match evaluate_condition(ctx, condition, proposal).await {
+            Ok(holds) => holds,
+            Err(e) => {
+                warn!(
+                    "Could not evaluate `{}` for proposal {}, assuming it does not hold: {:?}",
+                    condition, facts.proposal_id, e
+                );
+                false
+            }
+        };
+        facts.conditions.insert(condition, holds);
+    }
+    facts
+}
+
+async fn evaluate_condition(ctx: &DreContext, condition: PayloadCondition, proposal: &ProposalInfo) -> anyhow::Result<bool> {
+    let (function, payload) = match proposal.proposal.as_ref().and_then(|p| p.action.as_ref()) {
+        Some(Action::ExecuteNnsFunction(f)) => (NnsFunction::try_from(f.nns_function)?, f.payload.as_slice()),
+        _ => return Ok(false),
+    };
+    match (condition, function) {
+        (PayloadCondition::ElectedVersion, NnsFunction::DeployGuestosToAllSubnetNodes) => {
+            let version = Decode!(payload, DeployGuestosToAllSubnetNodesPayload)?.replica_version_id;
+            Ok(ctx.registry().await.elected_guestos().await?.contains(&version))
+        }
+        (PayloadCondition::ElectedVersion, NnsFunction::DeployGuestosToAllUnassignedNodes) => {
+            let version = Decode!(payload, DeployGuestosToAllUnassignedNodesPayload)?.elected_replica_version;
+            Ok(ctx.registry().await.elected_guestos().await?.contains(&version))
+        }
+        (PayloadCondition::ElectedVersion, NnsFunction::DeployHostosToSomeNodes | NnsFunction::UpdateNodesHostosVersion) => {
+            match Decode!(payload, UpdateNodesHostosVersionPayload)?.hostos_version_id {
+                Some(version) => Ok(ctx.registry().await.elected_hostos().await?.contains(&version)),
+                None => Ok(false),
+            }
+        }
+        (PayloadCondition::NakamotoNotReduced, NnsFunction::ChangeSubnetMembership) => {
+            let payload = Decode!(payload, ChangeSubnetMembershipPayload)?;
+            let change = ctx.runner().await?.decentralization_change(&payload, None, None).await?;
+            Ok(change.score_after >= change.score_before)
+        }
+        _ => Ok(false),
+    }
+}
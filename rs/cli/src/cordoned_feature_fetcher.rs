@@ -1,13 +1,12 @@
-use std::{path::PathBuf, time::Duration};
+use std::path::PathBuf;
 
 use decentralization::network::CordonedFeature;
 use futures::future::BoxFuture;
-use ic_management_types::NodeFeature;
+use ic_management_types::{NodeFeature, custom_features::CustomNodeFeatures};
 use itertools::Itertools;
-use log::{info, warn};
 use mockall::automock;
-use reqwest::{Client, ClientBuilder};
-use strum::VariantNames;
+
+use crate::github_file_fetcher::GithubFileFetcher;
 
 #[automock]
 pub trait CordonedFeatureFetcher: Sync + Send {
@@ -18,54 +17,29 @@ pub trait CordonedFeatureFetcher: Sync + Send {
 }
 
 pub struct CordonedFeatureFetcherImpl {
-    client: Client,
-    // Represents a local store which will
-    // be overwritten with every successful
-    // fetch from github. If github is
-    // unreachable, this cache will be used
-    local_copy: PathBuf,
-    use_local_file: bool, // By default, cordoned features are fetched from github
+    file: GithubFileFetcher,
+    // Custom node features which may be cordoned, besides the built-in ones
+    custom_features: Vec<NodeFeature>,
 }
 
-const CORDONED_FEATURES_FILE_URL: &str = "https://raw.githubusercontent.com/dfinity/dre/refs/heads/main/cordoned_features.yaml";
-
 impl CordonedFeatureFetcherImpl {
     pub fn new(local_copy: PathBuf, use_local_file: bool) -> anyhow::Result<Self> {
-        let client = ClientBuilder::new().timeout(Duration::from_secs(10)).build()?;
-
         Ok(Self {
-            client,
-            local_copy,
-            use_local_file,
+            file: GithubFileFetcher::new("cordoned_features.yaml", "cordoned features", local_copy, use_local_file)?,
+            custom_features: vec![],
         })
     }
 
-    async fn fetch_from_git(&self) -> anyhow::Result<Vec<CordonedFeature>> {
-        let bytes = self
-            .client
-            .get(CORDONED_FEATURES_FILE_URL)
-            .send()
-            .await?
-            .error_for_status()?
-            .bytes()
-            .await?;
-
-        if let Err(e) = fs_err::write(&self.local_copy, &bytes) {
-            warn!(
-                "Failed to update cordoned features cache on path `{}` due to: {:?}",
-                self.local_copy.display(),
-                e
-            );
-            warn!("This is not critical since the cordoned features are fetched from github");
+    pub fn with_custom_features(self, custom: &CustomNodeFeatures) -> Self {
+        Self {
+            custom_features: custom.names().collect(),
+            ..self
         }
-
-        self.parse(&bytes)
     }
 
+    #[cfg(test)]
     fn fetch_from_file(&self) -> anyhow::Result<Vec<CordonedFeature>> {
-        let contents = fs_err::read(&self.local_copy)?;
-
-        self.parse(&contents)
+        self.file.fetch_from_file(|contents| self.parse(contents))
     }
 
     fn parse(&self, contents: &[u8]) -> anyhow::Result<Vec<CordonedFeature>> {
@@ -86,13 +60,16 @@ impl CordonedFeatureFetcherImpl {
                 feature: feature
                     .get("feature")
                     .map(|value| {
-                        serde_yaml::from_value(value.clone()).map_err(|_| {
-                            anyhow::anyhow!(
-                                "Failed to parse feature `{}`. Expected one of: [{}]",
-                                serde_yaml::to_string(value).unwrap(),
-                                NodeFeature::VARIANTS.iter().join(",")
-                            )
-                        })
+                        value
+                            .as_str()
+                            .and_then(|s| NodeFeature::parse_known(s, &self.custom_features).ok())
+                            .ok_or_else(|| {
+                                anyhow::anyhow!(
+                                    "Failed to parse feature `{}`. Expected one of: [{}]",
+                                    serde_yaml::to_string(value).unwrap(),
+                                    NodeFeature::variants_all().iter().chain(&self.custom_features).join(",")
+                                )
+                            })
                     })
                     .ok_or(anyhow::anyhow!("Expected `feature` field to be present. Got: \n{:?}", feature))??,
                 value: feature
@@ -117,17 +94,7 @@ impl CordonedFeatureFetcherImpl {
 
 impl CordonedFeatureFetcher for CordonedFeatureFetcherImpl {
     fn fetch(&self) -> BoxFuture<'_, anyhow::Result<Vec<CordonedFeature>>> {
-        Box::pin(async {
-            if self.use_local_file {
-                info!(
-                    "Received request to load cordoned features from local cache path: {}",
-                    self.local_copy.display()
-                );
-                self.fetch_from_file()
-            } else {
-                self.fetch_from_git().await
-            }
-        })
+        Box::pin(self.file.fetch(|contents| self.parse(contents)))
     }
 
     #[cfg(test)]
@@ -139,7 +106,6 @@ impl CordonedFeatureFetcher for CordonedFeatureFetcherImpl {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use reqwest::Client;
     use std::io::Write;
     use tempfile::NamedTempFile;
 
@@ -160,11 +126,7 @@ mod tests {
         .unwrap();
 
         // Create a fetcher instance
-        let fetcher = CordonedFeatureFetcherImpl {
-            client: Client::new(),
-            local_copy: temp_file.path().to_path_buf(),
-            use_local_file: true,
-        };
+        let fetcher = CordonedFeatureFetcherImpl::new(temp_file.path().to_path_buf(), true).unwrap();
 
         // Fetch from the file
         let result = fetcher.fetch_from_file();
@@ -176,11 +138,7 @@ mod tests {
     #[test]
     fn test_fetch_from_file_failure() {
         // Create a fetcher instance with a non-existent file
-        let fetcher = CordonedFeatureFetcherImpl {
-            client: Client::new(),
-            local_copy: PathBuf::from("non_existent_file.yaml"),
-            use_local_file: true,
-        };
+        let fetcher = CordonedFeatureFetcherImpl::new(PathBuf::from("non_existent_file.yaml"), true).unwrap();
 
         // Fetch from the file
         let result = fetcher.fetch_from_file();
@@ -249,6 +207,24 @@ mod tests {
         assert_eq!(parsed.len(), 7);
     }
 
+    #[test]
+    fn custom_features_must_be_loaded() {
+        let contents = br#"
+        features:
+            - feature: asn
+              value: AS1234
+        "#;
+
+        let fetcher = CordonedFeatureFetcherImpl::new(PathBuf::new(), true).unwrap();
+        assert!(fetcher.parse(contents).is_err());
+
+        let custom: CustomNodeFeatures = serde_yaml::from_str("features:\n  asn: {}").unwrap();
+        let fetcher = fetcher.with_custom_features(&custom);
+        let parsed = fetcher.parse(contents).unwrap();
+        assert_eq!(parsed[0].feature, NodeFeature::Custom("asn".to_string()));
+        assert!(fetcher.parse(String::from_utf8_lossy(contents).replace("asn", "asm").as_bytes()).is_err());
+    }
+
     #[test]
     fn valid_empty_file() {
         let contents = br#"
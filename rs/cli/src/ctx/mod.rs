@@ -1,25 +1,37 @@
-use std::{cell::RefCell, rc::Rc, sync::Arc};
+use std::{cell::RefCell, path::PathBuf, rc::Rc, sync::Arc};
 
+use chrono::{DateTime, Utc};
+use decentralization::business_rules::{BusinessRules, BusinessRulesPolicy};
+use decentralization::provider_clusters::ProviderClusters;
 use ic_canisters::{IcAgentCanisterClient, governance::GovernanceCanisterWrapper};
 use ic_management_backend::{
     health::HealthStatusQuerier,
     lazy_git::LazyGit,
-    lazy_registry::LazyRegistry,
+    lazy_registry::{LazyRegistry, LazyRegistryFamilyEntries},
     proposal::{ProposalAgent, ProposalAgentImpl},
 };
 use ic_management_types::Network;
+use ic_management_types::custom_features::CustomNodeFeatures;
+use itertools::Itertools;
 use log::{info, warn};
 
 use crate::{
     artifact_downloader::{ArtifactDownloader, ArtifactDownloaderImpl},
     auth::{AuthOpts, AuthRequirement, Neuron},
+    business_rules_fetcher::BusinessRulesFetcher,
     cordoned_feature_fetcher::CordonedFeatureFetcher,
     exe::{args::GlobalArgs, args::IcAdminVersion},
     governance::GovernanceCanisterProposalExecutor,
-    ic_admin::{IcAdmin, IcAdminImpl, IcAdminProposalExecutor},
+    ic_admin::{IcAdmin, IcAdminImpl, IcAdminProposal, IcAdminProposalCommand, IcAdminProposalExecutor},
+    native_proposals::NativeProposal,
+    proposal_bundle::BundleTarget,
+    proposal_executors::ProposalExecution,
+    proposal_history::{ProposalHistory, ProposalRecord, RecordedProposalExecution},
+    provider_clusters_fetcher::ProviderClustersFetcher,
     runner::Runner,
     store::Store,
     subnet_manager::SubnetManager,
+    voting_rules::VoteLog,
 };
 
 #[cfg(test)]
@@ -48,9 +60,17 @@ pub struct DreContext {
     /// Explicit ic-admin binary path/command (from `--ic-admin`). When set, it
     /// takes precedence over `version` and no download is performed.
     ic_admin_path_override: Option<String>,
+    /// Submit proposals with ic-admin even if they can be built natively.
+    use_ic_admin: bool,
+    /// Bundle file to prepare proposals into, instead of submitting them.
+    prepare_only: Option<PathBuf>,
     neuron_opts: NeuronOpts,
     cordoned_features_fetcher: Arc<dyn CordonedFeatureFetcher>,
+    business_rules_fetcher: Arc<dyn BusinessRulesFetcher>,
+    provider_clusters_fetcher: Arc<dyn ProviderClustersFetcher>,
+    business_rules: RefCell<Option<BusinessRules>>,
     health_client: Arc<dyn HealthStatusQuerier>,
+    custom_node_features: Arc<CustomNodeFeatures>,
     store: Store,
 }
 
@@ -64,8 +84,13 @@ impl DreContext {
         auth_requirement: AuthRequirement,
         ic_admin_version: IcAdminVersion,
         ic_admin_path_override: Option<String>,
+        use_ic_admin: bool,
+        prepare_only: Option<PathBuf>,
         cordoned_features_fetcher: Arc<dyn CordonedFeatureFetcher>,
+        business_rules_fetcher: Arc<dyn BusinessRulesFetcher>,
+        provider_clusters_fetcher: Arc<dyn ProviderClustersFetcher>,
         health_client: Arc<dyn HealthStatusQuerier>,
+        custom_node_features: Arc<CustomNodeFeatures>,
         store: Store,
         neuron_override: Option<Neuron>,
     ) -> anyhow::Result<Self> {
@@ -81,6 +106,8 @@ impl DreContext {
             neuron: RefCell::new(None),
             version: ic_admin_version,
             ic_admin_path_override,
+            use_ic_admin,
+            prepare_only,
             neuron_opts: NeuronOpts {
                 auth_opts: auth,
                 requirement: auth_requirement,
@@ -88,7 +115,11 @@ impl DreContext {
                 neuron_override,
             },
             cordoned_features_fetcher,
+            business_rules_fetcher,
+            provider_clusters_fetcher,
+            business_rules: RefCell::new(None),
             health_client,
+            custom_node_features,
             store,
         })
     }
@@ -112,6 +143,21 @@ impl DreContext {
             }
         }
 
+        let custom_node_features = match &args.node_features_file {
+            Some(path) => {
+                let features = load_custom_node_features(path)?;
+                info!("Loaded {} custom node feature(s) from `{}`", features.features.len(), path);
+                features
+            }
+            None => CustomNodeFeatures::default(),
+        };
+
+        // Proposals are only signed later, on another machine, so the key is not needed to prepare them
+        let require_auth = match (&args.prepare_only, require_auth) {
+            (Some(_), AuthRequirement::Neuron) => AuthRequirement::Anonymous,
+            (_, require_auth) => require_auth,
+        };
+
         Self::new(
             network.clone(),
             args.auth_opts.clone(),
@@ -120,8 +166,13 @@ impl DreContext {
             require_auth,
             args.ic_admin_version.clone(),
             args.ic_admin.clone(),
-            store.cordoned_features_fetcher(args.cordoned_features_file.clone())?,
+            args.use_ic_admin,
+            args.prepare_only.clone(),
+            store.cordoned_features_fetcher(args.cordoned_features_file.clone(), &custom_node_features)?,
+            store.business_rules_fetcher(args.business_rules_file.clone())?,
+            store.provider_clusters_fetcher(args.provider_clusters_file.clone())?,
             store.health_client(&network)?,
+            Arc::new(custom_node_features),
             store,
             neuron_override,
         )
@@ -132,11 +183,33 @@ impl DreContext {
         if let Some(reg) = self.registry.borrow().as_ref() {
             return reg.clone();
         }
-        let registry = self.store.registry(self.network(), self.proposals_agent(), version_height).await.unwrap();
+        let registry = self
+            .store
+            .registry(self.network(), self.proposals_agent(), version_height, self.custom_node_features.clone())
+            .await
+            .unwrap();
         *self.registry.borrow_mut() = Some(registry.clone());
         registry
     }
 
+    /// Registry at the provided version. Unlike `registry_with_version`, the result is not
+    /// cached in the context, so it can be called for many versions.
+    pub async fn registry_at_version(&self, version_height: u64) -> anyhow::Result<Arc<dyn LazyRegistry>> {
+        self.store
+            .registry(
+                self.network(),
+                self.proposals_agent(),
+                Some(version_height),
+                self.custom_node_features.clone(),
+            )
+            .await
+    }
+
+    /// Custom node features loaded from the node features file, empty if none was provided.
+    pub fn custom_node_features(&self) -> Arc<CustomNodeFeatures> {
+        self.custom_node_features.clone()
+    }
+
     pub fn is_offline(&self) -> bool {
         self.store.is_offline()
     }
@@ -145,6 +218,13 @@ impl DreContext {
         self.registry_with_version(None).await
     }
 
+    /// Syncs the registry again and drops everything built on the previous one, for commands that keep running.
+    pub async fn refresh_registry(&self) -> Arc<dyn LazyRegistry> {
+        self.registry.borrow_mut().take();
+        self.runner.borrow_mut().take();
+        self.registry().await
+    }
+
     pub fn network(&self) -> &Network {
         &self.network
     }
@@ -199,14 +279,115 @@ impl DreContext {
     }
 
     pub async fn ic_admin_executor(&self) -> anyhow::Result<IcAdminProposalExecutor> {
+        if self.prepare_only.is_some() {
+            anyhow::bail!("Proposals submitted with ic-admin cannot be prepared for offline signing with --prepare-only");
+        }
         Ok(self.ic_admin().await?.into())
     }
 
+    /// Execution of a proposal built natively and submitted directly to the governance canister,
+    /// or through ic-admin if the proposal has no native equivalent or `--use-ic-admin` is set.
+    ///
+    /// The proposal is recorded in the proposal history of the local store.
+    pub async fn proposal_execution(&self, proposal: IcAdminProposal) -> anyhow::Result<Box<dyn ProposalExecution>> {
+        let record = self.proposal_record(&proposal).await?;
+        let execution = self.unrecorded_proposal_execution(proposal).await?;
+        Ok(Box::new(RecordedProposalExecution::new(execution, self.proposal_history()?, record)))
+    }
+
+    /// Records an execution the caller built itself in the proposal history, described by `proposal`.
+    ///
+    /// For proposals that [DreContext::proposal_execution] can't build, such as firewall changes and motions.
+    pub async fn recorded_execution(
+        &self,
+        execution: Box<dyn ProposalExecution>,
+        proposal: &IcAdminProposal,
+    ) -> anyhow::Result<Box<dyn ProposalExecution>> {
+        let record = self.proposal_record(proposal).await?;
+        Ok(Box::new(RecordedProposalExecution::new(execution, self.proposal_history()?, record)))
+    }
+
+    /// ID of a proposal with the summary of `proposal`, made by the current neuron at or after `since`.
+    ///
+    /// Finds out whether a submission that was interrupted reached the governance canister.
+    pub async fn find_submitted_proposal(&self, proposal: &IcAdminProposal, since: DateTime<Utc>) -> anyhow::Result<Option<u64>> {
+        let summary = proposal
+            .summary_with_motivation()?
+            .ok_or_else(|| anyhow::anyhow!("Proposals without a summary can't be looked up"))?;
+        let neuron_id = self.neuron().await?.neuron_id;
+        let ids = self
+            .proposals_agent()
+            .find_proposals_by_summary(neuron_id, summary, since.timestamp().max(0) as u64)
+            .await?;
+        match ids.as_slice() {
+            [] => Ok(None),
+            [id] => Ok(Some(*id)),
+            ids => anyhow::bail!(
+                "Found {} proposals with the same summary made by neuron {}: {}",
+                ids.len(),
+                neuron_id,
+                ids.iter().join(", ")
+            ),
+        }
+    }
+
+    async fn unrecorded_proposal_execution(&self, proposal: IcAdminProposal) -> anyhow::Result<Box<dyn ProposalExecution>> {
+        if !self.use_ic_admin || self.prepare_only.is_some() {
+            if let Some(native) = NativeProposal::try_from_ic_admin(&proposal)? {
+                return Ok(self.governance_executor().await?.execution(native));
+            }
+            if !matches!(proposal.command, IcAdminProposalCommand::Raw(_)) {
+                info!("Proposal `{}` has no native equivalent, submitting it with ic-admin", proposal.command);
+            }
+        }
+        Ok(self.ic_admin_executor().await?.execution(proposal))
+    }
+
+    /// Proposals simulated and submitted from this workstation, for the current network.
+    pub fn proposal_history(&self) -> anyhow::Result<ProposalHistory> {
+        self.store.proposal_history(self.network())
+    }
+
+    pub fn vote_log(&self) -> anyhow::Result<VoteLog> {
+        self.store.vote_log(self.network())
+    }
+
+    /// Progress of the rollout of a GuestOS version, for the current network.
+    pub fn rollout_state_file(&self, version: &str) -> anyhow::Result<PathBuf> {
+        self.store.rollout_state_file(self.network(), version)
+    }
+
+    async fn proposal_record(&self, proposal: &IcAdminProposal) -> anyhow::Result<ProposalRecord> {
+        let neuron_id = match &self.prepare_only {
+            Some(_) => self.neuron_opts.neuron_id,
+            None => Some(self.neuron().await?.neuron_id),
+        };
+        // The registry is not loaded just for the record
+        let registry_version = self.registry.borrow().as_ref().map(|registry| registry.get_latest_version().get());
+        Ok(ProposalRecord::new(&self.network.name, proposal, neuron_id, registry_version))
+    }
+
     pub async fn governance_executor(&self) -> anyhow::Result<GovernanceCanisterProposalExecutor> {
         let (neuron, client) = self.create_ic_agent_canister_client().await?;
-        let neuron_id = neuron.neuron_id;
         let governance = GovernanceCanisterWrapper::from(client);
-        Ok((neuron_id, governance).into())
+        match &self.prepare_only {
+            None => Ok((neuron.neuron_id, governance).into()),
+            Some(path) => {
+                // Authentication is skipped with --prepare-only, so the neuron comes from the arguments
+                let neuron_id = self
+                    .neuron_opts
+                    .neuron_id
+                    .ok_or_else(|| anyhow::anyhow!("--prepare-only requires the neuron ID"))?;
+                let executor: GovernanceCanisterProposalExecutor = (neuron_id, governance).into();
+                Ok(executor.with_bundle_target(BundleTarget {
+                    path: path.clone(),
+                    network: self.network.name.clone(),
+                    nns_urls: self.network.get_nns_urls().to_vec(),
+                    registry_version: self.registry().await.get_latest_version().get(),
+                    neuron_id,
+                }))
+            }
+        }
     }
 
     pub async fn neuron(&self) -> anyhow::Result<Neuron> {
@@ -253,11 +434,10 @@ impl DreContext {
     pub async fn subnet_manager(&self) -> anyhow::Result<SubnetManager> {
         let registry = self.registry().await;
 
-        Ok(SubnetManager::new(
-            registry,
-            self.cordoned_features_fetcher.clone(),
-            self.health_client.clone(),
-        ))
+        Ok(
+            SubnetManager::new(registry, self.cordoned_features_fetcher.clone(), self.health_client.clone())
+                .with_business_rules(self.business_rules().await?),
+        )
     }
 
     pub fn proposals_agent(&self) -> Arc<dyn ProposalAgent> {
@@ -278,6 +458,7 @@ impl DreContext {
             self.artifact_downloader.clone(),
             self.cordoned_features_fetcher.clone(),
             self.health_client.clone(),
+            self.business_rules().await?,
         ));
         *self.runner.borrow_mut() = Some(runner.clone());
         Ok(runner)
@@ -290,6 +471,36 @@ impl DreContext {
     pub fn cordoned_features_fetcher(&self) -> Arc<dyn CordonedFeatureFetcher> {
         self.cordoned_features_fetcher.clone()
     }
+
+    /// Business rules policy from `--business-rules-file`, github or the local cache,
+    /// with the subnet types of the current registry filled in.
+    pub async fn business_rules_policy(&self) -> anyhow::Result<BusinessRulesPolicy> {
+        let policy = self.business_rules_fetcher.fetch().await?;
+        let subnets = self.registry().await.subnets().await?;
+        Ok(policy.with_subnet_types(subnets.values()))
+    }
+
+    /// Clusters of linked node providers from `--provider-clusters-file`, github or the local cache.
+    pub async fn provider_clusters(&self) -> anyhow::Result<ProviderClusters> {
+        self.provider_clusters_fetcher.fetch().await
+    }
+
+    /// Business rules that the subnets are checked against, loaded once and shared by the commands.
+    pub async fn business_rules(&self) -> anyhow::Result<BusinessRules> {
+        if let Some(business_rules) = self.business_rules.borrow().as_ref() {
+            return Ok(business_rules.clone());
+        }
+        let business_rules = BusinessRules::new(self.business_rules_policy().await?, self.provider_clusters().await?);
+        *self.business_rules.borrow_mut() = Some(business_rules.clone());
+        Ok(business_rules)
+    }
+}
+
+fn load_custom_node_features(path: &str) -> anyhow::Result<CustomNodeFeatures> {
+    let features: CustomNodeFeatures =
+        serde_yaml::from_slice(&fs_err::read(path)?).map_err(|e| anyhow::anyhow!("Failed to parse node features file `{}`: {}", path, e))?;
+    features.validate()?;
+    Ok(features)
 }
 
 #[cfg(test)]
@@ -305,9 +516,11 @@ pub mod tests {
         artifact_downloader::ArtifactDownloader,
         auth::Neuron,
         auth::{AuthOpts, AuthRequirement, HsmOpts, HsmParams},
+        business_rules_fetcher::MockBusinessRulesFetcher,
         cordoned_feature_fetcher::CordonedFeatureFetcher,
         exe::args::IcAdminVersion,
         ic_admin::IcAdmin,
+        provider_clusters_fetcher::MockProviderClustersFetcher,
         store::Store,
     };
 
@@ -334,6 +547,9 @@ pub mod tests {
             neuron: RefCell::new(Some(neuron.clone())),
             version: IcAdminVersion::Strict("Shouldn't reach this because of mock".to_string()),
             ic_admin_path_override: None,
+            // Tests mock ic-admin and assert on the submitted ic-admin arguments
+            use_ic_admin: true,
+            prepare_only: None,
             neuron_opts: super::NeuronOpts {
                 auth_opts: AuthOpts {
                     private_key_pem: None,
@@ -353,8 +569,20 @@ pub mod tests {
                 },
             },
             cordoned_features_fetcher,
+            // Tests run against the built-in business rules
+            business_rules_fetcher: Arc::new(MockBusinessRulesFetcher::new()),
+            provider_clusters_fetcher: Arc::new(MockProviderClustersFetcher::new()),
+            business_rules: RefCell::new(Some(BusinessRules::default())),
             health_client,
+            custom_node_features: Default::default(),
             store: Store::new(false).unwrap(),
         }
     }
+
+    impl DreContext {
+        /// Builds proposals natively, which is the default outside of the tests.
+        pub fn with_native_proposals(self) -> Self {
+            Self { use_ic_admin: false, ..self }
+        }
+    }
 }
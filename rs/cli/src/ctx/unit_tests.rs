@@ -4,7 +4,9 @@ use crate::auth::{AuthRequirement, HsmParams};
 use crate::store::Store;
 use crate::{
     auth::{Auth, AuthOpts, HsmOpts, Neuron, STAGING_KEY_PATH_FROM_HOME, STAGING_NEURON_ID},
+    business_rules_fetcher::MockBusinessRulesFetcher,
     cordoned_feature_fetcher::MockCordonedFeatureFetcher,
+    provider_clusters_fetcher::MockProviderClustersFetcher,
     store::FALLBACK_IC_ADMIN_VERSION,
 };
 use ic_canisters::{parallel_hardware_identity::KeyIdVec, registry::registry_canister_version};
@@ -44,8 +46,13 @@ async fn get_context(network: &Network, version: IcAdminVersion) -> anyhow::Resu
         AuthRequirement::Anonymous,
         version,
         None,
+        false,
+        None,
         Arc::new(MockCordonedFeatureFetcher::new()),
+        Arc::new(MockBusinessRulesFetcher::new()),
+        Arc::new(MockProviderClustersFetcher::new()),
         Arc::new(MockHealthStatusQuerier::new()),
+        Default::default(),
         Store::new(false)?,
         None,
     )
@@ -191,8 +198,13 @@ async fn get_ctx_for_neuron_test(
         requirement,
         IcAdminVersion::Strict("Shouldn't get to here".to_string()),
         None,
+        false,
+        None,
         Arc::new(MockCordonedFeatureFetcher::new()),
+        Arc::new(MockBusinessRulesFetcher::new()),
+        Arc::new(MockProviderClustersFetcher::new()),
         Arc::new(MockHealthStatusQuerier::new()),
+        Default::default(),
         Store::new(offline)?,
         None,
     )
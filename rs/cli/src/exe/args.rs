@@ -1,5 +1,6 @@
 use crate::auth::AuthOpts;
 use clap::Parser;
+use std::path::PathBuf;
 use strum::Display;
 use url::Url;
 
@@ -25,6 +26,17 @@ pub struct GlobalArgs {
     3. <commit>                                                              => specific commit/release (scans all IC releases)"#)]
     pub ic_admin_version: IcAdminVersion,
 
+    /// Submit all proposals with ic-admin instead of building them natively and submitting
+    /// them directly to the governance canister.
+    #[clap(long, global = true, env = "USE_IC_ADMIN")]
+    pub use_ic_admin: bool,
+
+    /// Write proposals to a bundle file instead of submitting them, for signing on an offline
+    /// machine with `dre sign-bundle` and submitting later with `dre submit-bundle`.
+    /// No key is needed for this, but the neuron ID must be provided.
+    #[clap(long, global = true, value_name = "BUNDLE_FILE", conflicts_with = "use_ic_admin", requires = "neuron_id")]
+    pub prepare_only: Option<PathBuf>,
+
     #[clap(
         long,
         env = "NETWORK",
@@ -55,6 +67,22 @@ The argument is mandatory for testnets, and is optional for mainnet and staging"
     #[clap(long, global = true, visible_aliases = &["cf-file", "cfff", "cordone"])]
     pub cordoned_features_file: Option<String>,
 
+    /// Path to file which contains the business rules policy (target topology).
+    /// By default, the policy is fetched from the dre repository on github.
+    #[clap(long, global = true, visible_aliases = &["br-file"])]
+    pub business_rules_file: Option<String>,
+
+    /// Path to file which lists the clusters of linked node providers.
+    /// By default, the clusters are fetched from the dre repository on github.
+    #[clap(long, global = true, visible_aliases = &["pc-file"])]
+    pub provider_clusters_file: Option<String>,
+
+    /// Path to file which labels nodes with extra decentralization features, e.g. the ASN or
+    /// the jurisdiction group. The features take part in the Nakamoto score, the business rules
+    /// and the `--only`/`--exclude` filters.
+    #[clap(long, global = true, visible_aliases = &["nf-file"])]
+    pub node_features_file: Option<String>,
+
     /// Override health by data center ID or node ID, e.g. "sg2:healthy". Accepts multiple entries.
     #[clap(long, global = true, num_args(1..), visible_aliases = &["oh", "override-healths"], help = r#"Override health for nodes. Matches exact data center ID (dc_id) or full node principal.
 Examples:
@@ -7,6 +7,7 @@ use std::{
 };
 
 use futures::{FutureExt, future::BoxFuture};
+use ic_nns_governance::pb::v1::Topic;
 use ic_types::PrincipalId;
 use itertools::Itertools;
 use log::{info, warn};
@@ -298,6 +299,34 @@ impl ForumPostHandler for Discourse {
                     )
                     .await
                 }
+                ForumPostKind::Topic { title, body, topics } => {
+                    create_topic_or_request_it(
+                        client,
+                        DiscourseTopic {
+                            title,
+                            content: body,
+                            tags: topics.into_iter().filter_map(topic_tag).unique().map(String::from).collect(),
+                            category: NNS_PROPOSAL_DISCUSSION.to_string(),
+                        },
+                    )
+                    .await
+                }
+                ForumPostKind::PostInTopic { topic_url, body } => {
+                    let topic_id = topic_id_from_url(&topic_url).ok_or(anyhow::anyhow!(
+                        "The topic URL {} does not have any topic ID this tool can use to create a post in it",
+                        topic_url
+                    ))?;
+                    let poast = match self.client.create_post(body.clone(), topic_id).await {
+                        Ok(poast) => poast,
+                        Err(e) => self.request_from_user_post(Some(e), body, topic_url.to_string()).await?,
+                    };
+                    Ok(DiscoursePost {
+                        client,
+                        post_url: url::Url::from_str(poast.url.as_str())?,
+                        post_id: poast.update_id,
+                        put_original_post_behind_details_discloser: !poast.is_topic,
+                    })
+                }
             }?;
             Ok(Box::new(res) as Box<dyn ForumPost>)
         };
@@ -631,6 +660,21 @@ impl DiscourseClientImp {
 const NNS_PROPOSAL_DISCUSSION: &str = "NNS proposal discussions";
 const SUBNET_MANAGEMENT_TAG: &str = "Subnet-management";
 
+/// Forum tag of the proposals of a governance topic, if the forum has one.
+fn topic_tag(topic: Topic) -> Option<&'static str> {
+    match topic {
+        Topic::SubnetManagement => Some(SUBNET_MANAGEMENT_TAG),
+        Topic::NodeAdmin => Some("Node-admin"),
+        Topic::IcOsVersionDeployment => Some("IC-OS-version-deployment"),
+        Topic::IcOsVersionElection => Some("IC-OS-version-election"),
+        Topic::ApiBoundaryNodeManagement => Some("API-boundary-node-management"),
+        Topic::ParticipantManagement => Some("Participant-management"),
+        Topic::NodeProviderRewards => Some("Node-provider-rewards"),
+        Topic::Governance => Some("Governance"),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 struct CategoryResponse {
     id: u64,
@@ -662,6 +706,15 @@ struct SubnetTopicInfo {
     topic_id: u64,
 }
 
+/// Extracts the topic ID from topic or post URLs of the form `/t/<slug>/<topic id>[/<post number>]`.
+fn topic_id_from_url(url: &Url) -> Option<u64> {
+    let segments = url.path_segments()?.filter(|s| !s.is_empty()).collect::<Vec<_>>();
+    match segments.as_slice() {
+        ["t", _slug, topic_id] | ["t", _slug, topic_id, _] => topic_id.parse().ok(),
+        _ => None,
+    }
+}
+
 const SUBNET_TOPICS_AND_SLUGS: &str = include_str!("../assets/subnet_topic_map.json");
 fn get_subnet_topics_map() -> BTreeMap<PrincipalId, SubnetTopicInfo> {
     serde_json::from_str(SUBNET_TOPICS_AND_SLUGS).unwrap()
@@ -726,4 +779,17 @@ mod tests {
             "https://forum.dfinity.org/new-topic?title=Test+automatic+forum+post+creation&body=Test+content&category=NNS+proposal+discussions&tags=tag1%2Ctag2"
         )
     }
+
+    #[test]
+    fn topic_id_from_topic_and_post_urls() {
+        for (url, expected) in [
+            ("https://forum.dfinity.org/t/hostos-rollout-week-42/41234", Some(41234)),
+            ("https://forum.dfinity.org/t/hostos-rollout-week-42/41234/", Some(41234)),
+            ("https://forum.dfinity.org/t/hostos-rollout-week-42/41234/7", Some(41234)),
+            ("https://forum.dfinity.org/c/governance/nns-proposal-discussions/76", None),
+            ("https://forum.dfinity.org/t/hostos-rollout-week-42", None),
+        ] {
+            assert_eq!(topic_id_from_url(&Url::parse(url).unwrap()), expected, "for {}", url);
+        }
+    }
 }
@@ -2,6 +2,7 @@ use std::{path::PathBuf, str::FromStr};
 
 use clap::Args as ClapArgs;
 use futures::future::BoxFuture;
+use ic_nns_governance::pb::v1::Topic;
 use ic_types::PrincipalId;
 
 mod impls;
@@ -136,9 +137,28 @@ impl ForumParameters {
 // the forum post can be decentralized to the right places in the code,
 // instead of living divorced from the proposal type itself.
 pub enum ForumPostKind {
-    ReplaceNodes { subnet_id: PrincipalId, body: String },
-    AuthorizedSubnetsUpdate { body: String },
-    Motion { title: Option<String>, summary: String },
+    ReplaceNodes {
+        subnet_id: PrincipalId,
+        body: String,
+    },
+    AuthorizedSubnetsUpdate {
+        body: String,
+    },
+    Motion {
+        title: Option<String>,
+        summary: String,
+    },
+    /// A new topic that groups the posts of several related proposals, tagged with their governance topics.
+    Topic {
+        title: String,
+        body: String,
+        topics: Vec<Topic>,
+    },
+    /// A new post in an existing topic, usually one created as a [ForumPostKind::Topic].
+    PostInTopic {
+        topic_url: url::Url,
+        body: String,
+    },
     Generic,
 }
 
@@ -0,0 +1,130 @@
+use std::{path::PathBuf, time::Duration};
+
+use log::{info, warn};
+use reqwest::{Client, ClientBuilder};
+
+const DRE_REPOSITORY_RAW_URL: &str = "https://raw.githubusercontent.com/dfinity/dre/refs/heads/main";
+
+/// A file from the root of the `dre` repository, kept up to date on github
+/// so that it can change without releasing a new `dre`.
+pub struct GithubFileFetcher {
+    client: Client,
+    file_name: &'static str,
+    // What the file contains, used in the logs
+    description: &'static str,
+    // Overwritten with every successful fetch from github,
+    // and used instead of github if it's unreachable.
+    local_copy: PathBuf,
+    use_local_file: bool, // By default, the file is fetched from github
+}
+
+impl GithubFileFetcher {
+    pub fn new(file_name: &'static str, description: &'static str, local_copy: PathBuf, use_local_file: bool) -> anyhow::Result<Self> {
+        let client = ClientBuilder::new().timeout(Duration::from_secs(10)).build()?;
+
+        Ok(Self {
+            client,
+            file_name,
+            description,
+            local_copy,
+            use_local_file,
+        })
+    }
+
+    /// Fetches and parses the file from github, or from the local copy if
+    /// a local file was requested or github can't provide a usable file.
+    pub async fn fetch<T>(&self, parse: impl Fn(&[u8]) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        if self.use_local_file {
+            info!("Loading {} from local path: {}", self.description, self.local_copy.display());
+            return self.fetch_from_file(parse);
+        }
+
+        match self.fetch_from_git(&parse).await {
+            Ok(parsed) => Ok(parsed),
+            Err(e) => {
+                warn!("Failed to fetch {} from github: {:?}", self.description, e);
+                warn!("Falling back to the cached copy at `{}`", self.local_copy.display());
+                self.fetch_from_file(parse)
+            }
+        }
+    }
+
+    async fn fetch_from_git<T>(&self, parse: impl Fn(&[u8]) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        let url = format!("{}/{}", DRE_REPOSITORY_RAW_URL, self.file_name);
+        let bytes = self.client.get(url).send().await?.error_for_status()?.bytes().await?;
+
+        // Don't overwrite a good cache with contents this version of `dre` can't use
+        let parsed = parse(&bytes)?;
+
+        if let Err(e) = fs_err::write(&self.local_copy, &bytes) {
+            warn!(
+                "Failed to update {} cache on path `{}` due to: {:?}",
+                self.description,
+                self.local_copy.display(),
+                e
+            );
+            warn!("This is not critical since the {} are fetched from github", self.description);
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn fetch_from_file<T>(&self, parse: impl Fn(&[u8]) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        let contents = fs_err::read(&self.local_copy)?;
+
+        parse(&contents)
+    }
+
+    /// Parses the contents, or uses the default if they are empty, as the local copy is when it was just created.
+    pub fn parse_or_default<T: Default>(&self, contents: &[u8], parse: impl Fn(&[u8]) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        if contents.iter().all(|c| c.is_ascii_whitespace()) {
+            info!(
+                "The {} file `{}` is empty, using the built-in {}",
+                self.description,
+                self.local_copy.display(),
+                self.description
+            );
+            return Ok(T::default());
+        }
+
+        parse(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn fetcher(local_copy: PathBuf) -> GithubFileFetcher {
+        GithubFileFetcher::new("numbers.yaml", "numbers", local_copy, true).unwrap()
+    }
+
+    #[test]
+    fn local_file_is_parsed() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "[1, 2, 3]").unwrap();
+
+        let parsed: Vec<u32> = fetcher(temp_file.path().to_path_buf())
+            .fetch_from_file(|contents| Ok(serde_yaml::from_slice(contents)?))
+            .unwrap();
+        assert_eq!(parsed, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn missing_local_file_is_rejected() {
+        let parsed = fetcher(PathBuf::from("non_existent_file.yaml")).fetch_from_file(|contents| Ok(serde_yaml::from_slice::<Vec<u32>>(contents)?));
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn empty_contents_give_the_default() {
+        let fetcher = fetcher(PathBuf::new());
+        let parse = |contents: &[u8]| Ok(serde_yaml::from_slice::<Vec<u32>>(contents)?);
+
+        assert_eq!(fetcher.parse_or_default(b" \n", parse).unwrap(), Vec::<u32>::new());
+        assert_eq!(fetcher.parse_or_default(b"[4]", parse).unwrap(), vec![4]);
+        assert!(fetcher.parse_or_default(b"{}", parse).is_err());
+    }
+}
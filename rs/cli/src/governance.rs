@@ -1,14 +1,18 @@
+use std::path::PathBuf;
+
 use futures::future::BoxFuture;
 use ic_canisters::governance::GovernanceCanisterWrapper;
 use ic_nns_common::pb::v1::NeuronId;
 use ic_nns_governance_api::MakeProposalRequest;
 use url::Url;
 
-use crate::proposal_executors::{ProposableViaGovernanceCanister, ProposalExecution, ProposalResponseWithId};
+use crate::proposal_bundle::{BundleTarget, ProposalBundle};
+use crate::proposal_executors::{ProposableViaGovernanceCanister, ProposalExecution, ProposalResponseWithId, ProposalSimulation};
 
 pub struct GovernanceCanisterProposalExecutor {
     neuron_id: u64,
     governance_canister: GovernanceCanisterWrapper,
+    bundle_target: Option<BundleTarget>,
 }
 
 impl From<(u64, GovernanceCanisterWrapper)> for GovernanceCanisterProposalExecutor {
@@ -16,11 +20,20 @@ impl From<(u64, GovernanceCanisterWrapper)> for GovernanceCanisterProposalExecut
         Self {
             neuron_id: args.0,
             governance_canister: args.1,
+            bundle_target: None,
         }
     }
 }
 
 impl GovernanceCanisterProposalExecutor {
+    /// Prepares proposals for offline signing instead of submitting them.
+    pub fn with_bundle_target(self, bundle_target: BundleTarget) -> Self {
+        Self {
+            bundle_target: Some(bundle_target),
+            ..self
+        }
+    }
+
     pub fn execution<T>(self, p: T) -> Box<dyn ProposalExecution>
     where
         T: 'static,
@@ -38,7 +51,10 @@ impl GovernanceCanisterProposalExecutor {
         'd: 'c,
     {
         Box::pin(async move {
-            println!("Proposal that would be submitted:\n{:#?}", cmd);
+            match cmd.simulation() {
+                Some(simulation) => println!("Proposal that would be submitted:\n{}", simulation),
+                None => println!("Proposal that would be submitted:\n{:#?}", cmd),
+            }
             println!("Forum post link: {}", forum_post_link_description.unwrap_or("None".to_string()));
             Ok(())
         })
@@ -89,6 +105,20 @@ where
         Box::pin(async { self.executor.simulate(&self.proposal, forum_post_link_description).await })
     }
 
+    fn simulation(&self) -> Option<ProposalSimulation> {
+        self.proposal.simulation()
+    }
+
+    fn prepare_bundle(&self, forum_post_link: Option<Url>) -> anyhow::Result<Option<PathBuf>> {
+        let Some(target) = &self.executor.bundle_target else {
+            return Ok(None);
+        };
+        let bundle = ProposalBundle::prepare(target, self.proposal.clone().into(), self.proposal.simulation(), forum_post_link)?;
+        bundle.write(&target.path)?;
+        println!("{}", bundle);
+        Ok(Some(target.path.clone()))
+    }
+
     fn submit<'a, 'b>(&'a self, forum_post_link: Option<Url>) -> BoxFuture<'b, anyhow::Result<ProposalResponseWithId>>
     where
         'a: 'b,
@@ -9,10 +9,12 @@ use colored::Colorize;
 use futures::future::BoxFuture;
 use ic_base_types::PrincipalId;
 use ic_management_types::{Artifact, Network};
+use ic_nns_governance::pb::v1::Topic;
 use log::debug;
 use log::info;
 use mockall::automock;
 use regex::Regex;
+use serde::Deserialize;
 use shlex::try_quote;
 use std::fmt::Debug;
 use std::fmt::Display;
@@ -288,12 +290,15 @@ impl IcAdminImpl {
     }
 }
 
-#[derive(StrumDisplay, Clone, Debug)]
+#[derive(StrumDisplay, Clone, Debug, Deserialize)]
 #[strum(serialize_all = "kebab-case")]
+#[serde(rename_all = "snake_case")]
 pub enum IcAdminProposalCommand {
     ChangeSubnetMembership {
         subnet_id: PrincipalId,
+        #[serde(default)]
         node_ids_add: Vec<PrincipalId>,
+        #[serde(default)]
         node_ids_remove: Vec<PrincipalId>,
     },
     DeployGuestosToAllSubnetNodes {
@@ -337,7 +342,23 @@ pub enum IcAdminProposalCommand {
 }
 
 impl IcAdminProposalCommand {
-    fn args(&self) -> (String, Vec<String>) {
+    /// Governance topic of the proposal, `Unspecified` for free-form ic-admin arguments.
+    pub fn topic(&self) -> Topic {
+        match self {
+            Self::ChangeSubnetMembership { .. } | Self::CreateSubnet { .. } | Self::SetAuthorizedSubnetworks { .. } => Topic::SubnetManagement,
+            Self::RemoveNodes { .. } => Topic::NodeAdmin,
+            Self::DeployGuestosToAllSubnetNodes { .. } | Self::DeployGuestosToAllUnassignedNodes { .. } | Self::DeployHostosToSomeNodes { .. } => {
+                Topic::IcOsVersionDeployment
+            }
+            Self::ReviseElectedVersions { .. } => Topic::IcOsVersionElection,
+            Self::AddApiBoundaryNodes { .. } | Self::RemoveApiBoundaryNodes { .. } | Self::DeployGuestosToSomeApiBoundaryNodes { .. } => {
+                Topic::ApiBoundaryNodeManagement
+            }
+            Self::Raw(_) => Topic::Unspecified,
+        }
+    }
+
+    pub(crate) fn args(&self) -> (String, Vec<String>) {
         let head: String = match &self {
             Self::Raw(args) => args
                 .first()
@@ -455,18 +476,34 @@ impl ProducesProposalResult for IcAdminProposal {
 }
 
 impl IcAdminProposal {
-    fn to_args(&self) -> anyhow::Result<Vec<String>> {
-        if let Some(summary) = &self.options.summary {
-            let summary_count = summary.chars().count();
-            if summary_count > MAX_SUMMARY_CHAR_COUNT {
-                return Err(anyhow!(
-                    "Summary length {} exceeded MAX_SUMMARY_CHAR_COUNT {}",
-                    summary_count,
-                    MAX_SUMMARY_CHAR_COUNT,
-                ));
-            }
+    /// The summary as submitted with the proposal, with the motivation appended.
+    pub fn summary_with_motivation(&self) -> anyhow::Result<Option<String>> {
+        let Some(summary) = &self.options.summary else {
+            return Ok(None);
+        };
+        let summary_count = summary.chars().count();
+        if summary_count > MAX_SUMMARY_CHAR_COUNT {
+            return Err(anyhow!(
+                "Summary length {} exceeded MAX_SUMMARY_CHAR_COUNT {}",
+                summary_count,
+                MAX_SUMMARY_CHAR_COUNT,
+            ));
         }
 
+        Ok(Some(format!(
+            "{}{}",
+            summary,
+            self.options
+                .motivation
+                .as_ref()
+                .map(|m| format!("\n\nMotivation: {m}"))
+                .unwrap_or_default(),
+        )))
+    }
+
+    fn to_args(&self) -> anyhow::Result<Vec<String>> {
+        let summary = self.summary_with_motivation()?;
+
         let (head, tail) = self.command.args();
         let head = match head.as_str() {
             "" => vec![],
@@ -481,24 +518,7 @@ impl IcAdminProposal {
                 .title
                 .map(|t| vec!["--proposal-title".to_string(), t])
                 .unwrap_or_default(),
-            self.options
-                .clone()
-                .summary
-                .map(|s| {
-                    vec![
-                        "--summary".to_string(),
-                        format!(
-                            "{}{}",
-                            s,
-                            self.options
-                                .clone()
-                                .motivation
-                                .map(|m| format!("\n\nMotivation: {m}"))
-                                .unwrap_or_default(),
-                        ),
-                    ]
-                })
-                .unwrap_or_default(),
+            summary.map(|s| vec!["--summary".to_string(), s]).unwrap_or_default(),
             tail,
         ]
         .concat())
@@ -1,5 +1,6 @@
 mod artifact_downloader;
 mod auth;
+mod business_rules_fetcher;
 pub mod commands;
 mod confirm;
 mod cordoned_feature_fetcher;
@@ -7,17 +8,23 @@ pub mod ctx;
 mod desktop_notify;
 pub mod exe;
 mod forum;
+mod github_file_fetcher;
 mod governance;
 mod ic_admin;
+mod native_proposals;
 mod operations;
 mod pin;
+mod proposal_bundle;
 mod proposal_executors;
+mod proposal_history;
+mod provider_clusters_fetcher;
 mod qualification;
 mod runner;
 mod store;
 mod submitter;
 mod subnet_manager;
 mod util;
+mod voting_rules;
 
 #[cfg(test)]
 mod unit_tests;
@@ -0,0 +1,387 @@
+use candid::{CandidType, Encode};
+use cycles_minting_canister::SetAuthorizedSubnetworkListArgs;
+use ic_base_types::{NodeId, PrincipalId, SubnetId};
+use ic_management_types::Artifact;
+use ic_nns_governance::pb::v1::NnsFunction;
+use ic_nns_governance_api::{ExecuteNnsFunction, MakeProposalRequest, ProposalActionRequest};
+use registry_canister::mutations::{
+    do_add_api_boundary_nodes::AddApiBoundaryNodesPayload, do_change_subnet_membership::ChangeSubnetMembershipPayload,
+    do_deploy_guestos_to_all_subnet_nodes::DeployGuestosToAllSubnetNodesPayload,
+    do_deploy_guestos_to_all_unassigned_nodes::DeployGuestosToAllUnassignedNodesPayload, do_remove_api_boundary_nodes::RemoveApiBoundaryNodesPayload,
+    do_revise_elected_replica_versions::ReviseElectedGuestosVersionsPayload,
+    do_update_api_boundary_nodes_version::UpdateApiBoundaryNodesVersionPayload,
+    do_update_elected_hostos_versions::UpdateElectedHostosVersionsPayload, do_update_nodes_hostos_version::UpdateNodesHostosVersionPayload,
+    node_management::do_remove_nodes::RemoveNodesPayload,
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    ic_admin::{IcAdminProposal, IcAdminProposalCommand},
+    proposal_executors::{ProposableViaGovernanceCanister, ProposalResponseWithId, ProposalSimulation},
+};
+
+/// A proposal built in Rust and submitted directly to the governance canister, without ic-admin.
+#[derive(Debug, Clone)]
+pub struct NativeProposal {
+    request: MakeProposalRequest,
+    simulation: ProposalSimulation,
+}
+
+impl NativeProposal {
+    /// Builds the governance canister request for a proposal that would otherwise be submitted
+    /// with ic-admin. Returns `None` for the proposals whose arguments are free-form, and fails for
+    /// subnet creation, for which ic-admin fills in the subnet config defaults.
+    pub fn try_from_ic_admin(proposal: &IcAdminProposal) -> anyhow::Result<Option<Self>> {
+        let Some((nns_function, payload)) = NativePayload::try_from_command(&proposal.command)? else {
+            return Ok(None);
+        };
+        let summary = proposal.summary_with_motivation()?.unwrap_or_default();
+        let title = proposal
+            .options
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Propose to {}", proposal.command.to_string().replace('-', " ")));
+
+        Ok(Some(Self {
+            simulation: ProposalSimulation {
+                action: format!("{:?}", nns_function),
+                title: title.clone(),
+                summary: summary.clone(),
+                url: String::new(),
+                payload: payload.json,
+            },
+            request: MakeProposalRequest {
+                title: Some(title),
+                summary,
+                url: String::new(),
+                action: Some(ProposalActionRequest::ExecuteNnsFunction(ExecuteNnsFunction {
+                    nns_function: nns_function as i32,
+                    payload: payload.candid,
+                })),
+            },
+        }))
+    }
+
+    pub fn simulation(&self) -> &ProposalSimulation {
+        &self.simulation
+    }
+
+    /// Candid encoding of the `ExecuteNnsFunction` action described by a simulation, to check
+    /// byte for byte that the simulation shown to the signer is the payload which gets signed.
+    pub fn encode_simulated_action(simulation: &ProposalSimulation) -> anyhow::Result<(NnsFunction, Vec<u8>)> {
+        let nns_function = (0..=i32::from(u8::MAX))
+            .filter_map(|f| NnsFunction::try_from(f).ok())
+            .find(|f| format!("{:?}", f) == simulation.action)
+            .ok_or_else(|| anyhow::anyhow!("Unknown proposal action `{}`", simulation.action))?;
+        let json = &simulation.payload;
+        let candid = match nns_function {
+            NnsFunction::ChangeSubnetMembership => NativePayload::encode_json::<ChangeSubnetMembershipPayload>(json)?,
+            NnsFunction::DeployGuestosToAllSubnetNodes => NativePayload::encode_json::<DeployGuestosToAllSubnetNodesPayload>(json)?,
+            NnsFunction::DeployGuestosToAllUnassignedNodes => NativePayload::encode_json::<DeployGuestosToAllUnassignedNodesPayload>(json)?,
+            NnsFunction::DeployHostosToSomeNodes => NativePayload::encode_json::<UpdateNodesHostosVersionPayload>(json)?,
+            NnsFunction::RemoveNodes => NativePayload::encode_json::<RemoveNodesPayload>(json)?,
+            NnsFunction::ReviseElectedGuestosVersions => NativePayload::encode_json::<ReviseElectedGuestosVersionsPayload>(json)?,
+            NnsFunction::ReviseElectedHostosVersions => NativePayload::encode_json::<UpdateElectedHostosVersionsPayload>(json)?,
+            NnsFunction::AddApiBoundaryNodes => NativePayload::encode_json::<AddApiBoundaryNodesPayload>(json)?,
+            NnsFunction::RemoveApiBoundaryNodes => NativePayload::encode_json::<RemoveApiBoundaryNodesPayload>(json)?,
+            NnsFunction::DeployGuestosToSomeApiBoundaryNodes => NativePayload::encode_json::<UpdateApiBoundaryNodesVersionPayload>(json)?,
+            NnsFunction::SetAuthorizedSubnetworks => NativePayload::encode_json::<SetAuthorizedSubnetworkListArgs>(json)?,
+            other => anyhow::bail!("Proposal action `{:?}` is not built natively", other),
+        };
+        Ok((nns_function, candid))
+    }
+}
+
+impl From<NativeProposal> for MakeProposalRequest {
+    fn from(proposal: NativeProposal) -> Self {
+        proposal.request
+    }
+}
+
+impl ProposableViaGovernanceCanister for NativeProposal {
+    type ProposalResult = ProposalResponseWithId;
+
+    fn simulation(&self) -> Option<ProposalSimulation> {
+        Some(self.simulation.clone())
+    }
+}
+
+/// The payload of an `ExecuteNnsFunction` proposal, both candid encoded for the
+/// governance canister and as JSON for the simulation.
+struct NativePayload {
+    candid: Vec<u8>,
+    json: serde_json::Value,
+}
+
+impl NativePayload {
+    fn new<T: CandidType + Serialize>(payload: T) -> anyhow::Result<Self> {
+        Ok(Self {
+            candid: Encode!(&payload)?,
+            json: serde_json::to_value(&payload)?,
+        })
+    }
+
+    /// Candid encoding of a payload from its JSON form, as shown in the simulation.
+    fn encode_json<T: CandidType + DeserializeOwned>(json: &serde_json::Value) -> anyhow::Result<Vec<u8>> {
+        let payload: T = serde_json::from_value(json.clone())?;
+        Ok(Encode!(&payload)?)
+    }
+
+    fn try_from_command(command: &IcAdminProposalCommand) -> anyhow::Result<Option<(NnsFunction, Self)>> {
+        let built = match command {
+            IcAdminProposalCommand::ChangeSubnetMembership {
+                subnet_id,
+                node_ids_add,
+                node_ids_remove,
+            } => (
+                NnsFunction::ChangeSubnetMembership,
+                Self::new(ChangeSubnetMembershipPayload {
+                    subnet_id: *subnet_id,
+                    node_ids_add: node_ids(node_ids_add),
+                    node_ids_remove: node_ids(node_ids_remove),
+                })?,
+            ),
+            IcAdminProposalCommand::DeployGuestosToAllSubnetNodes { subnet, version } => (
+                NnsFunction::DeployGuestosToAllSubnetNodes,
+                Self::new(DeployGuestosToAllSubnetNodesPayload {
+                    subnet_id: *subnet,
+                    replica_version_id: version.clone(),
+                })?,
+            ),
+            IcAdminProposalCommand::DeployGuestosToAllUnassignedNodes { replica_version } => (
+                NnsFunction::DeployGuestosToAllUnassignedNodes,
+                Self::new(DeployGuestosToAllUnassignedNodesPayload {
+                    elected_replica_version: replica_version.clone(),
+                })?,
+            ),
+            IcAdminProposalCommand::DeployHostosToSomeNodes { nodes, version } => (
+                NnsFunction::DeployHostosToSomeNodes,
+                Self::new(UpdateNodesHostosVersionPayload {
+                    node_ids: node_ids(nodes),
+                    hostos_version_id: Some(version.clone()),
+                })?,
+            ),
+            IcAdminProposalCommand::RemoveNodes { nodes } => (NnsFunction::RemoveNodes, Self::new(RemoveNodesPayload { node_ids: node_ids(nodes) })?),
+            IcAdminProposalCommand::ReviseElectedVersions { release_artifact, args } => {
+                let Some(flags) = ReviseElectedVersionsFlags::parse(release_artifact, args) else {
+                    return Ok(None);
+                };
+                match release_artifact {
+                    Artifact::GuestOs => (
+                        NnsFunction::ReviseElectedGuestosVersions,
+                        Self::new(ReviseElectedGuestosVersionsPayload {
+                            replica_version_to_elect: flags.version_to_elect,
+                            release_package_sha256_hex: flags.release_package_sha256_hex,
+                            release_package_urls: flags.release_package_urls,
+                            replica_versions_to_unelect: flags.versions_to_unelect,
+                            ..Default::default()
+                        })?,
+                    ),
+                    Artifact::HostOs => (
+                        NnsFunction::ReviseElectedHostosVersions,
+                        Self::new(UpdateElectedHostosVersionsPayload {
+                            hostos_version_to_elect: flags.version_to_elect,
+                            release_package_sha256_hex: flags.release_package_sha256_hex,
+                            release_package_urls: flags.release_package_urls,
+                            hostos_versions_to_unelect: flags.versions_to_unelect,
+                            ..Default::default()
+                        })?,
+                    ),
+                }
+            }
+            IcAdminProposalCommand::AddApiBoundaryNodes { nodes, version } => (
+                NnsFunction::AddApiBoundaryNodes,
+                Self::new(AddApiBoundaryNodesPayload {
+                    node_ids: node_ids(nodes),
+                    version: version.clone(),
+                })?,
+            ),
+            IcAdminProposalCommand::RemoveApiBoundaryNodes { nodes } => (
+                NnsFunction::RemoveApiBoundaryNodes,
+                Self::new(RemoveApiBoundaryNodesPayload { node_ids: node_ids(nodes) })?,
+            ),
+            IcAdminProposalCommand::DeployGuestosToSomeApiBoundaryNodes { nodes, version } => (
+                NnsFunction::DeployGuestosToSomeApiBoundaryNodes,
+                Self::new(UpdateApiBoundaryNodesVersionPayload {
+                    node_ids: node_ids(nodes),
+                    version: version.clone(),
+                })?,
+            ),
+            IcAdminProposalCommand::SetAuthorizedSubnetworks { subnets } => (
+                NnsFunction::SetAuthorizedSubnetworks,
+                Self::new(SetAuthorizedSubnetworkListArgs {
+                    who: None,
+                    subnets: subnets.iter().map(|s| SubnetId::from(*s)).collect(),
+                })?,
+            ),
+            // ic-admin fills in the subnet config defaults from its own version of the registry types,
+            // so a payload built here could silently differ from the one ic-admin would submit
+            IcAdminProposalCommand::CreateSubnet { .. } => {
+                anyhow::bail!(
+                    "Subnet creation can't be proposed natively, since ic-admin fills in the subnet config defaults. Rerun with `--use-ic-admin`."
+                )
+            }
+            // Free-form ic-admin arguments
+            IcAdminProposalCommand::Raw(_) => return Ok(None),
+        };
+        Ok(Some(built))
+    }
+}
+
+fn node_ids(nodes: &[PrincipalId]) -> Vec<NodeId> {
+    nodes.iter().map(|n| NodeId::from(*n)).collect()
+}
+
+/// The ic-admin flags of a `ReviseElectedVersions` proposal, as produced by `UpdateVersion::get_update_cmd_args`
+/// and the qualification steps.
+#[derive(Debug, Default, PartialEq)]
+struct ReviseElectedVersionsFlags {
+    version_to_elect: Option<String>,
+    release_package_sha256_hex: Option<String>,
+    release_package_urls: Vec<String>,
+    versions_to_unelect: Vec<String>,
+}
+
+impl ReviseElectedVersionsFlags {
+    /// Returns `None` if some flag has no native equivalent, such as the guest launch measurements
+    /// which ic-admin reads from a file.
+    fn parse(release_artifact: &Artifact, args: &[String]) -> Option<Self> {
+        let (elect_flag, unelect_flag) = match release_artifact {
+            Artifact::GuestOs => ("--replica-version-to-elect", "--replica-versions-to-unelect"),
+            Artifact::HostOs => ("--hostos-version-to-elect", "--hostos-versions-to-unelect"),
+        };
+
+        let mut flags = Self::default();
+        let mut args = args.iter().peekable();
+        while let Some(flag) = args.next() {
+            let mut values = vec![];
+            while let Some(value) = args.next_if(|a| !a.starts_with("--")) {
+                values.push(value.clone());
+            }
+            match flag.as_str() {
+                f if f == elect_flag && values.len() == 1 => flags.version_to_elect = values.pop(),
+                f if f == unelect_flag => flags.versions_to_unelect.extend(values),
+                "--release-package-sha256-hex" if values.len() == 1 => flags.release_package_sha256_hex = values.pop(),
+                "--release-package-urls" => flags.release_package_urls.extend(values),
+                _ => return None,
+            }
+        }
+        Some(flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ic_admin::IcAdminProposalOptions;
+    use candid::Decode;
+
+    #[test]
+    fn change_subnet_membership_is_built_natively() {
+        let subnet_id = PrincipalId::new_subnet_test_id(1);
+        let proposal = IcAdminProposal::new(
+            IcAdminProposalCommand::ChangeSubnetMembership {
+                subnet_id,
+                node_ids_add: vec![PrincipalId::new_node_test_id(1)],
+                node_ids_remove: vec![PrincipalId::new_node_test_id(2)],
+            },
+            IcAdminProposalOptions {
+                title: Some("Replace a node".to_string()),
+                summary: Some("Summary".to_string()),
+                motivation: Some("Because".to_string()),
+            },
+        );
+
+        let native = NativeProposal::try_from_ic_admin(&proposal).unwrap().unwrap();
+        assert_eq!(native.simulation().action, "ChangeSubnetMembership");
+
+        let request: MakeProposalRequest = native.into();
+        assert_eq!(request.title, Some("Replace a node".to_string()));
+        assert_eq!(request.summary, "Summary\n\nMotivation: Because");
+        let Some(ProposalActionRequest::ExecuteNnsFunction(function)) = request.action else {
+            panic!("Expected an ExecuteNnsFunction action");
+        };
+        assert_eq!(function.nns_function, NnsFunction::ChangeSubnetMembership as i32);
+        let payload = Decode!(function.payload.as_slice(), ChangeSubnetMembershipPayload).unwrap();
+        assert_eq!(payload.subnet_id, subnet_id);
+        assert_eq!(payload.node_ids_add, vec![NodeId::from(PrincipalId::new_node_test_id(1))]);
+        assert_eq!(payload.node_ids_remove, vec![NodeId::from(PrincipalId::new_node_test_id(2))]);
+    }
+
+    #[test]
+    fn simulated_action_is_encoded_like_the_request() {
+        let proposal = IcAdminProposal::new(
+            IcAdminProposalCommand::DeployHostosToSomeNodes {
+                nodes: vec![PrincipalId::new_node_test_id(1), PrincipalId::new_node_test_id(2)],
+                version: "abc".to_string(),
+            },
+            IcAdminProposalOptions::default(),
+        );
+        let native = NativeProposal::try_from_ic_admin(&proposal).unwrap().unwrap();
+        let (nns_function, payload) = NativeProposal::encode_simulated_action(native.simulation()).unwrap();
+
+        let request: MakeProposalRequest = native.into();
+        let Some(ProposalActionRequest::ExecuteNnsFunction(function)) = request.action else {
+            panic!("Expected an ExecuteNnsFunction action");
+        };
+        assert_eq!(function.nns_function, nns_function as i32);
+        assert_eq!(function.payload, payload);
+    }
+
+    #[test]
+    fn free_form_proposals_need_ic_admin() {
+        let proposal = IcAdminProposal::new(
+            IcAdminProposalCommand::Raw(vec!["propose-to-update-subnet".to_string()]),
+            IcAdminProposalOptions::default(),
+        );
+
+        assert!(NativeProposal::try_from_ic_admin(&proposal).unwrap().is_none());
+    }
+
+    #[test]
+    fn subnet_creation_is_not_built_natively() {
+        let proposal = IcAdminProposal::new(
+            IcAdminProposalCommand::CreateSubnet {
+                node_ids: vec![PrincipalId::new_node_test_id(1)],
+                replica_version: "abc".to_string(),
+                other_args: vec![],
+            },
+            IcAdminProposalOptions::default(),
+        );
+
+        let err = NativeProposal::try_from_ic_admin(&proposal).unwrap_err().to_string();
+        assert!(err.contains("--use-ic-admin"), "{}", err);
+    }
+
+    #[test]
+    fn revise_elected_versions_flags_are_parsed() {
+        let args = [
+            "--replica-version-to-elect",
+            "abc",
+            "--release-package-sha256-hex",
+            "123",
+            "--release-package-urls",
+            "https://a",
+            "https://b",
+            "--replica-versions-to-unelect",
+            "old1",
+            "--replica-versions-to-unelect",
+            "old2",
+        ]
+        .map(String::from);
+
+        assert_eq!(
+            ReviseElectedVersionsFlags::parse(&Artifact::GuestOs, &args),
+            Some(ReviseElectedVersionsFlags {
+                version_to_elect: Some("abc".to_string()),
+                release_package_sha256_hex: Some("123".to_string()),
+                release_package_urls: vec!["https://a".to_string(), "https://b".to_string()],
+                versions_to_unelect: vec!["old1".to_string(), "old2".to_string()],
+            })
+        );
+        assert_eq!(ReviseElectedVersionsFlags::parse(&Artifact::HostOs, &args), None);
+
+        let with_measurements = ["--replica-version-to-elect", "abc", "--guest-launch-measurements-path", "/tmp/m.json"].map(String::from);
+        assert_eq!(ReviseElectedVersionsFlags::parse(&Artifact::GuestOs, &with_measurements), None);
+    }
+}
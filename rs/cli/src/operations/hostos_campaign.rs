@@ -0,0 +1,300 @@
+use std::sync::Arc;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use ic_base_types::PrincipalId;
+use ic_management_backend::health::HealthStatusQuerier;
+use ic_management_backend::proposal::ProposalAgent;
+use ic_management_types::{HealthStatus, Node, Subnet};
+use indexmap::IndexMap;
+use itertools::Itertools;
+
+use crate::commands::hostos::rollout_from_node_group::{NodeAssignment, NodeOwner};
+use crate::operations::hostos_rollout::{HostosRollout, HostosRolloutResponse, NodeGroup, NodeGroupUpdate, NumberOfNodes};
+
+/// Guards against wave sizes that never complete a group.
+const MAX_WAVES: usize = 1000;
+
+/// Node groups in the order a campaign rolls out to them: DFINITY nodes before the
+/// others, and unassigned nodes before the nodes in subnets.
+pub const CAMPAIGN_GROUPS: [NodeGroup; 4] = [
+    NodeGroup {
+        assignment: NodeAssignment::Unassigned,
+        owner: NodeOwner::Dfinity,
+    },
+    NodeGroup {
+        assignment: NodeAssignment::Assigned,
+        owner: NodeOwner::Dfinity,
+    },
+    NodeGroup {
+        assignment: NodeAssignment::Unassigned,
+        owner: NodeOwner::Others,
+    },
+    NodeGroup {
+        assignment: NodeAssignment::Assigned,
+        owner: NodeOwner::Others,
+    },
+];
+
+pub struct CampaignSettings {
+    pub version: String,
+    pub nodes_in_group: NumberOfNodes,
+    pub only: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Limit of nodes of a subnet updated in the same wave, by default the
+    /// number of nodes the subnet can lose without stalling
+    pub max_nodes_per_subnet: Option<usize>,
+}
+
+pub struct CampaignWave {
+    pub group: NodeGroup,
+    pub nodes: Vec<PrincipalId>,
+    pub nodes_per_subnet: IndexMap<PrincipalId, usize>,
+    pub violations: Vec<SubnetLimitViolation>,
+    pub date: Option<NaiveDate>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SubnetLimitViolation {
+    pub subnet_id: PrincipalId,
+    pub nodes: usize,
+    pub limit: usize,
+}
+
+/// Nodes of a group that the campaign does not update.
+pub struct LeftBehind {
+    pub group: NodeGroup,
+    pub nodes: usize,
+    pub reason: String,
+}
+
+pub struct Campaign {
+    pub waves: Vec<CampaignWave>,
+    pub left_behind: Vec<LeftBehind>,
+}
+
+impl Campaign {
+    /// Runs the HostOS rollout wave after wave against a copy of the registry in which every
+    /// wave is adopted, until no group has nodes left to update. Nodes in open HostOS
+    /// proposals are skipped like in a real rollout.
+    pub async fn simulate(
+        settings: &CampaignSettings,
+        nodes: &IndexMap<PrincipalId, Node>,
+        subnets: Arc<IndexMap<PrincipalId, Subnet>>,
+        nodes_health: &IndexMap<PrincipalId, HealthStatus>,
+        proposal_agent: Arc<dyn ProposalAgent>,
+        health_client: Arc<dyn HealthStatusQuerier>,
+    ) -> anyhow::Result<Self> {
+        let open_proposals = proposal_agent.list_open_update_nodes_hostos_versions_proposals().await?;
+        let mut virtual_nodes = nodes.clone();
+        let mut waves = vec![];
+        let mut left_behind = vec![];
+        for group in CAMPAIGN_GROUPS {
+            loop {
+                let rollout = HostosRollout::new(
+                    Arc::new(virtual_nodes.clone()),
+                    subnets.clone(),
+                    proposal_agent.clone(),
+                    &settings.version,
+                    &settings.only,
+                    &settings.exclude,
+                    health_client.clone(),
+                );
+                let remaining = rollout
+                    .grouped_nodes
+                    .get(&group)
+                    .map(|nodes| nodes.iter().filter(|n| n.hostos_version != settings.version).count())
+                    .unwrap_or_default();
+                if remaining == 0 {
+                    break;
+                }
+                if waves.len() >= MAX_WAVES {
+                    anyhow::bail!("The campaign needs more than {} waves, use larger waves", MAX_WAVES);
+                }
+
+                let update = NodeGroupUpdate {
+                    node_group: group,
+                    maybe_number_nodes: Some(settings.nodes_in_group),
+                };
+                let reason = match rollout
+                    .with_nodes_health_and_open_proposals(nodes_health.clone(), open_proposals.clone(), update)
+                    .await?
+                {
+                    HostosRolloutResponse::Ok(selected, _) if !selected.is_empty() => {
+                        for node in &selected {
+                            if let Some(node) = virtual_nodes.get_mut(&node.principal) {
+                                node.hostos_version = settings.version.clone();
+                            }
+                        }
+                        waves.push(CampaignWave::new(group, &selected, &subnets, settings.max_nodes_per_subnet));
+                        continue;
+                    }
+                    HostosRolloutResponse::Ok(_, _) => "The wave size selects no node".to_string(),
+                    HostosRolloutResponse::None(reasons) => reasons.iter().map(|(_, reason)| reason.to_string()).join("; "),
+                };
+                left_behind.push(LeftBehind {
+                    group,
+                    nodes: remaining,
+                    reason,
+                });
+                break;
+            }
+        }
+        Ok(Self { waves, left_behind })
+    }
+
+    /// Dates the waves on the given rollout days, a fixed number of waves per day.
+    pub fn schedule(&mut self, days: impl Iterator<Item = NaiveDate>, waves_per_day: usize) {
+        for (wave, date) in self
+            .waves
+            .iter_mut()
+            .zip(days.flat_map(|day| std::iter::repeat_n(day, waves_per_day.max(1))))
+        {
+            wave.date = Some(date);
+        }
+    }
+
+    pub fn completion_date(&self) -> Option<NaiveDate> {
+        self.waves.iter().filter_map(|w| w.date).max()
+    }
+}
+
+impl CampaignWave {
+    fn new(group: NodeGroup, selected: &[Node], subnets: &IndexMap<PrincipalId, Subnet>, max_nodes_per_subnet: Option<usize>) -> Self {
+        let nodes_per_subnet = selected
+            .iter()
+            .filter_map(|n| n.subnet_id)
+            .counts()
+            .into_iter()
+            .sorted()
+            .collect::<IndexMap<_, _>>();
+        let violations = nodes_per_subnet
+            .iter()
+            .filter_map(|(subnet_id, nodes)| {
+                let limit =
+                    max_nodes_per_subnet.unwrap_or_else(|| subnets.get(subnet_id).map(|s| s.nodes.len().saturating_sub(1) / 3).unwrap_or_default());
+                (*nodes > limit).then_some(SubnetLimitViolation {
+                    subnet_id: *subnet_id,
+                    nodes: *nodes,
+                    limit,
+                })
+            })
+            .collect();
+        Self {
+            group,
+            nodes: selected.iter().map(|n| n.principal).collect(),
+            nodes_per_subnet,
+            violations,
+            date: None,
+        }
+    }
+}
+
+/// Working days from the given date on, skipping the excluded days.
+pub fn rollout_calendar(start: NaiveDate, exclude_days: Vec<NaiveDate>) -> impl Iterator<Item = NaiveDate> {
+    start
+        .iter_days()
+        .filter(move |d| !matches!(d.weekday(), Weekday::Sat | Weekday::Sun) && !exclude_days.contains(d))
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_base_types::NodeId;
+    use ic_management_backend::{health::MockHealthStatusQuerier, proposal::MockProposalAgent};
+    use ic_management_types::{NodeFeatures, UpdateNodesHostosVersionsProposal};
+
+    use super::*;
+
+    fn node(number: u64, subnet_id: Option<PrincipalId>, dfinity_owned: bool) -> Node {
+        let node = Node::new_test_node(number, NodeFeatures::new_test_feature_set(&format!("n{}", number)), dfinity_owned);
+        let node = Node {
+            hostos_version: "old".to_string(),
+            ..node
+        };
+        match subnet_id {
+            Some(subnet_id) => node.with_subnet_id(subnet_id),
+            None => node,
+        }
+    }
+
+    #[tokio::test]
+    async fn simulate_until_every_group_is_updated() {
+        let subnet_id = PrincipalId::new_subnet_test_id(0);
+        let subnet_nodes = (0..7).map(|i| node(i, Some(subnet_id), i < 3)).collect_vec();
+        let nodes = subnet_nodes
+            .iter()
+            .cloned()
+            .chain((10..14).map(|i| node(i, None, i < 12)))
+            .map(|n| (n.principal, n))
+            .collect::<IndexMap<_, _>>();
+        let subnets = Arc::new(IndexMap::from([(
+            subnet_id,
+            Subnet {
+                principal: subnet_id,
+                nodes: subnet_nodes,
+                ..Default::default()
+            },
+        )]));
+        let mut nodes_health = nodes.keys().map(|n| (*n, HealthStatus::Healthy)).collect::<IndexMap<_, _>>();
+        nodes_health.insert(PrincipalId::new_node_test_id(13), HealthStatus::Dead);
+
+        let settings = CampaignSettings {
+            version: "new".to_string(),
+            nodes_in_group: NumberOfNodes::Absolute(3),
+            only: vec![],
+            exclude: vec![],
+            max_nodes_per_subnet: None,
+        };
+        // Node 11 is already in an open proposal, so the campaign leaves it to that proposal
+        let mut proposal_agent = MockProposalAgent::new();
+        proposal_agent.expect_list_open_update_nodes_hostos_versions_proposals().returning(|| {
+            Box::pin(async {
+                Ok(vec![UpdateNodesHostosVersionsProposal {
+                    proposal_id: 1,
+                    hostos_version_id: "new".to_string(),
+                    node_ids: vec![NodeId::from(PrincipalId::new_node_test_id(11))],
+                }])
+            })
+        });
+        let mut campaign = Campaign::simulate(
+            &settings,
+            &nodes,
+            subnets,
+            &nodes_health,
+            Arc::new(proposal_agent),
+            Arc::new(MockHealthStatusQuerier::new()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            campaign.waves.iter().map(|w| (w.group, w.nodes.len())).collect_vec(),
+            vec![
+                (CAMPAIGN_GROUPS[0], 1),
+                (CAMPAIGN_GROUPS[1], 3),
+                (CAMPAIGN_GROUPS[2], 1),
+                (CAMPAIGN_GROUPS[3], 3),
+                (CAMPAIGN_GROUPS[3], 1),
+            ]
+        );
+        // A subnet of 7 nodes tolerates 2 faulty nodes
+        assert_eq!(
+            campaign.waves[1].violations,
+            vec![SubnetLimitViolation {
+                subnet_id,
+                nodes: 3,
+                limit: 2
+            }]
+        );
+        assert!(campaign.waves[4].violations.is_empty());
+        assert_eq!(
+            campaign.left_behind.iter().map(|l| (l.group, l.nodes)).collect_vec(),
+            vec![(CAMPAIGN_GROUPS[0], 1), (CAMPAIGN_GROUPS[2], 1)]
+        );
+
+        // Starting on a Friday, with the Monday excluded
+        let friday = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        campaign.schedule(rollout_calendar(friday, vec![NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()]), 2);
+        assert_eq!(campaign.waves[2].date, NaiveDate::from_ymd_opt(2024, 3, 5));
+        assert_eq!(campaign.completion_date(), NaiveDate::from_ymd_opt(2024, 3, 6));
+    }
+}
@@ -1,16 +1,20 @@
 use anyhow::anyhow;
 use async_recursion::async_recursion;
+use chrono::Utc;
 use futures_util::future::try_join;
 use ic_base_types::{NodeId, PrincipalId};
 use ic_management_backend::health::HealthStatusQuerier;
-use ic_management_backend::proposal::ProposalAgent;
+use ic_management_backend::prometheus;
+use ic_management_backend::proposal::{MAX_PROPOSAL_OPEN_TIME, ProposalAgent};
 use ic_management_types::{HealthStatus, Node, Subnet, UpdateNodesHostosVersionsProposal};
 use indexmap::IndexMap;
+use itertools::Itertools;
 use log::{debug, info, warn};
 use std::sync::Arc;
 use std::{fmt::Display, str::FromStr};
 
 use crate::commands::hostos::rollout_from_node_group::{NodeAssignment, NodeOwner};
+use crate::operations::hostos_rollout_gates::{HostosRolloutGates, UpgradedNode, upgrade_times};
 
 pub enum HostosRolloutResponse {
     Ok(Vec<Node>, Option<Vec<HostosRolloutSubnetAffected>>),
@@ -28,6 +32,7 @@ pub enum HostosRolloutReason {
     NoNodeHealthy,
     NoNodeWithoutProposal,
     AllAlreadyUpdated,
+    GatesClosed(String),
 }
 
 impl Display for HostosRolloutReason {
@@ -36,6 +41,7 @@ impl Display for HostosRolloutReason {
             Self::NoNodeHealthy => write!(f, "No healthy node found in the group"),
             Self::NoNodeWithoutProposal => write!(f, "No node without open proposals found in the group"),
             Self::AllAlreadyUpdated => write!(f, "All candidate nodes have been already updated"),
+            Self::GatesClosed(explanation) => write!(f, "The nodes already on the new version do not pass the rollout gates: {}", explanation),
         }
     }
 }
@@ -137,6 +143,7 @@ pub struct HostosRollout {
     pub exclude_filter: Vec<String>,
     pub version: String,
     health_client: Arc<dyn HealthStatusQuerier>,
+    gates: HostosRolloutGates,
 }
 impl HostosRollout {
     pub fn new(
@@ -180,9 +187,14 @@ impl HostosRollout {
             exclude_filter: exclude_filter.to_vec(),
             version: rollout_version.to_string(),
             health_client,
+            gates: HostosRolloutGates::default(),
         }
     }
 
+    pub fn with_gates(self, gates: HostosRolloutGates) -> Self {
+        Self { gates, ..self }
+    }
+
     async fn nodes_different_version(&self, nodes: Vec<Node>) -> Vec<Node> {
         nodes.into_iter().filter(|n| n.hostos_version != self.version).collect::<Vec<_>>()
     }
@@ -355,8 +367,10 @@ impl HostosRollout {
         }
     }
 
+    /// Selects the nodes to update from the given health and open proposals, without
+    /// fetching them or checking the rollout gates.
     #[async_recursion]
-    async fn with_nodes_health_and_open_proposals(
+    pub async fn with_nodes_health_and_open_proposals(
         &self,
         nodes_health: IndexMap<PrincipalId, HealthStatus>,
         nodes_with_open_proposals: Vec<UpdateNodesHostosVersionsProposal>,
@@ -510,9 +524,59 @@ impl HostosRollout {
             );
         }
 
+        if let Some(explanation) = self.closed_gates(&nodes_on_the_new_version, &nodes_health).await? {
+            warn!("Not selecting more nodes for {}: {}", update_group.node_group, explanation);
+            return Ok(HostosRolloutResponse::None(vec![(
+                update_group.node_group,
+                HostosRolloutReason::GatesClosed(explanation),
+            )]));
+        }
+
         self.with_nodes_health_and_open_proposals(nodes_health, nodes_with_open_proposals, update_group)
             .await
     }
+
+    /// Checks the nodes already on the new version against the rollout gates, and explains
+    /// why no more nodes should be selected if any gate is closed.
+    async fn closed_gates(
+        &self,
+        nodes_on_the_new_version: &[Node],
+        nodes_health: &IndexMap<PrincipalId, HealthStatus>,
+    ) -> anyhow::Result<Option<String>> {
+        let upgrade_times = if self.gates.needs_upgrade_times() {
+            // Only upgrades executed within the bake time matter
+            let since = Utc::now() - chrono::Duration::from_std(self.gates.bake_time + MAX_PROPOSAL_OPEN_TIME)?;
+            upgrade_times(
+                &self
+                    .proposal_agent
+                    .list_update_nodes_hostos_versions_proposals(since.timestamp().max(0) as u64)
+                    .await?,
+                &self.version,
+            )
+        } else {
+            IndexMap::new()
+        };
+        let hosts_up = match &self.gates.prometheus_network {
+            Some(network) => Some(prometheus::hosts_up(&prometheus::client(network), network).await?),
+            None => None,
+        };
+
+        let upgraded = nodes_on_the_new_version
+            .iter()
+            .map(|node| UpgradedNode {
+                principal: node.principal,
+                upgraded_at: upgrade_times.get(&node.principal).copied(),
+                health: nodes_health.get(&node.principal).cloned().unwrap_or(HealthStatus::Unknown),
+                host_up: hosts_up.as_ref().map(|up| up.get(&node.principal).copied().unwrap_or(false)),
+            })
+            .collect::<Vec<_>>();
+        let failures = self.gates.evaluate(&upgraded, Utc::now());
+        if failures.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(failures.iter().join("; ")))
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,202 @@
+use std::{fmt::Display, time::Duration};
+
+use chrono::{DateTime, Utc};
+use ic_management_backend::proposal::HostosUpdateProposal;
+use ic_management_types::{HealthStatus, Network};
+use ic_types::PrincipalId;
+use indexmap::IndexMap;
+use itertools::Itertools;
+
+/// Conditions that the nodes already running the rollout version must meet
+/// before more nodes are selected for the next wave.
+#[derive(Clone, Debug)]
+pub struct HostosRolloutGates {
+    /// How long nodes must have been running the new version
+    pub bake_time: Duration,
+    /// Largest fraction of the upgraded nodes that may be failing
+    pub max_failure_ratio: f64,
+    /// If set, the host of upgraded nodes must also be reported as up by the Prometheus of this network
+    pub prometheus_network: Option<Network>,
+}
+
+impl Default for HostosRolloutGates {
+    /// Gates that are always open.
+    fn default() -> Self {
+        Self {
+            bake_time: Duration::ZERO,
+            max_failure_ratio: 1.0,
+            prometheus_network: None,
+        }
+    }
+}
+
+/// A node already running the rollout version, as seen by the gates.
+#[derive(Clone, Debug)]
+pub struct UpgradedNode {
+    pub principal: PrincipalId,
+    /// When the proposal upgrading the node was executed, if known
+    pub upgraded_at: Option<DateTime<Utc>>,
+    pub health: HealthStatus,
+    /// Whether Prometheus reports the host as up, if checked
+    pub host_up: Option<bool>,
+}
+
+impl UpgradedNode {
+    /// A node whose health is unknown is not failing, unless Prometheus reports its host as down.
+    fn is_failing(&self) -> bool {
+        !matches!(self.health, HealthStatus::Healthy | HealthStatus::Unknown) || self.host_up == Some(false)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GateFailure {
+    Baking {
+        nodes: usize,
+        ready_at: DateTime<Utc>,
+    },
+    TooManyFailures {
+        failing: Vec<PrincipalId>,
+        upgraded: usize,
+        max_failure_ratio: f64,
+    },
+}
+
+impl Display for GateFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Baking { nodes, ready_at } => write!(
+                f,
+                "{} nodes were upgraded less than the bake time ago, more nodes can be selected after {}",
+                nodes, ready_at
+            ),
+            Self::TooManyFailures {
+                failing,
+                upgraded,
+                max_failure_ratio,
+            } => write!(
+                f,
+                "{} of the {} upgraded nodes ({:.1}%) are failing, more than the allowed {:.1}%: {}",
+                failing.len(),
+                upgraded,
+                failing.len() as f64 / *upgraded as f64 * 100.0,
+                max_failure_ratio * 100.0,
+                failing.iter().join(", ")
+            ),
+        }
+    }
+}
+
+impl HostosRolloutGates {
+    /// Whether the gates need to know when the nodes were upgraded.
+    pub fn needs_upgrade_times(&self) -> bool {
+        !self.bake_time.is_zero()
+    }
+
+    /// Returns why no more nodes should be selected, or nothing if the gates are open.
+    pub fn evaluate(&self, upgraded: &[UpgradedNode], now: DateTime<Utc>) -> Vec<GateFailure> {
+        let mut failures = vec![];
+        let bake_time = chrono::Duration::from_std(self.bake_time).unwrap_or(chrono::Duration::MAX);
+        let baking = upgraded
+            .iter()
+            .filter_map(|n| n.upgraded_at)
+            .filter(|upgraded_at| now - *upgraded_at < bake_time)
+            .collect_vec();
+        if let Some(last) = baking.iter().max() {
+            failures.push(GateFailure::Baking {
+                nodes: baking.len(),
+                ready_at: last.checked_add_signed(bake_time).unwrap_or(DateTime::<Utc>::MAX_UTC),
+            });
+        }
+
+        let failing = upgraded.iter().filter(|n| n.is_failing()).map(|n| n.principal).collect_vec();
+        if !upgraded.is_empty() && failing.len() as f64 / upgraded.len() as f64 > self.max_failure_ratio {
+            failures.push(GateFailure::TooManyFailures {
+                failing,
+                upgraded: upgraded.len(),
+                max_failure_ratio: self.max_failure_ratio,
+            });
+        }
+        failures
+    }
+}
+
+/// When each node was last upgraded to the given version, according to the executed proposals.
+pub fn upgrade_times(proposals: &[HostosUpdateProposal], version: &str) -> IndexMap<PrincipalId, DateTime<Utc>> {
+    proposals
+        .iter()
+        .filter(|p| p.info.executed && p.payload.hostos_version_id.as_deref() == Some(version))
+        .filter_map(|p| DateTime::from_timestamp(p.info.executed_timestamp_seconds as i64, 0).map(|executed_at| (p, executed_at)))
+        .flat_map(|(p, executed_at)| p.payload.node_ids.iter().map(move |node_id| (node_id.get(), executed_at)))
+        .fold(IndexMap::new(), |mut acc, (node, executed_at)| {
+            let last = acc.entry(node).or_insert(executed_at);
+            *last = (*last).max(executed_at);
+            acc
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: u64, upgraded_at: Option<DateTime<Utc>>, health: HealthStatus) -> UpgradedNode {
+        UpgradedNode {
+            principal: PrincipalId::new_node_test_id(id),
+            upgraded_at,
+            health,
+            host_up: None,
+        }
+    }
+
+    #[test]
+    fn gates_require_bake_time() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let gates = HostosRolloutGates {
+            bake_time: Duration::from_secs(3600),
+            ..Default::default()
+        };
+        let old = now - chrono::Duration::hours(2);
+        let recent = now - chrono::Duration::minutes(10);
+
+        assert!(
+            gates
+                .evaluate(&[node(1, Some(old), HealthStatus::Healthy), node(2, None, HealthStatus::Healthy)], now)
+                .is_empty()
+        );
+        assert_eq!(
+            gates.evaluate(
+                &[node(1, Some(old), HealthStatus::Healthy), node(2, Some(recent), HealthStatus::Healthy)],
+                now
+            ),
+            vec![GateFailure::Baking {
+                nodes: 1,
+                ready_at: recent + chrono::Duration::hours(1)
+            }]
+        );
+        assert!(
+            HostosRolloutGates::default()
+                .evaluate(&[node(1, Some(now), HealthStatus::Dead)], now)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn gates_limit_failure_ratio() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let gates = HostosRolloutGates {
+            max_failure_ratio: 0.25,
+            ..Default::default()
+        };
+        let mut nodes = (0..4).map(|i| node(i, None, HealthStatus::Healthy)).collect_vec();
+        nodes[0].health = HealthStatus::Degraded;
+        assert!(gates.evaluate(&nodes, now).is_empty());
+
+        nodes[2].health = HealthStatus::Unknown;
+        assert!(gates.evaluate(&nodes, now).is_empty());
+
+        nodes[1].host_up = Some(false);
+        let failures = gates.evaluate(&nodes, now);
+        assert!(matches!(&failures[..], [GateFailure::TooManyFailures { failing, upgraded: 4, .. }] if failing.len() == 2));
+        assert!(failures[0].to_string().starts_with("2 of the 4 upgraded nodes (50.0%) are failing"));
+        assert!(gates.evaluate(&[], now).is_empty());
+    }
+}
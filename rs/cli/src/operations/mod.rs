@@ -1 +1,3 @@
+pub mod hostos_campaign;
 pub mod hostos_rollout;
+pub mod hostos_rollout_gates;
@@ -0,0 +1,404 @@
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use ic_canisters::governance::{GovernanceCanisterWrapper, SignedManageNeuron};
+use ic_nns_common::pb::v1::NeuronId;
+use ic_nns_governance::pb::v1::{
+    ManageNeuron,
+    manage_neuron::{Command, NeuronIdOrSubaccount},
+    proposal::Action,
+};
+use ic_nns_governance_api::MakeProposalRequest;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{native_proposals::NativeProposal, proposal_executors::ProposalSimulation};
+
+/// Bumped whenever the bundle format changes incompatibly.
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// How long a signed bundle can be submitted for. The IC rejects calls which expire
+/// more than 5 minutes in the future.
+pub const SIGNATURE_VALIDITY: Duration = Duration::from_secs(5 * 60);
+
+/// Where and on behalf of which neuron a proposal is prepared with `--prepare-only`.
+#[derive(Clone, Debug)]
+pub struct BundleTarget {
+    pub path: PathBuf,
+    pub network: String,
+    pub nns_urls: Vec<url::Url>,
+    pub registry_version: u64,
+    pub neuron_id: u64,
+}
+
+/// Everything needed to sign and submit a proposal, and to review it before signing.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BundleContent {
+    pub format_version: u32,
+    pub network: String,
+    pub nns_urls: Vec<url::Url>,
+    /// Registry version the proposal was computed against.
+    pub registry_version: u64,
+    pub neuron_id: u64,
+    pub prepared_at: DateTime<Utc>,
+    pub forum_post_link: Option<String>,
+    /// Human readable description of the proposal, checked against `manage_neuron_arg` when signing.
+    pub simulation: Option<ProposalSimulation>,
+    /// Hex encoded candid `ManageNeuron` argument which gets signed.
+    pub manage_neuron_arg: String,
+}
+
+/// A signed `manage_neuron` call, with binary fields hex encoded.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BundleSignature {
+    pub sender: String,
+    pub signed_at: DateTime<Utc>,
+    /// Nanoseconds since the UNIX epoch after which the IC rejects the call.
+    pub ingress_expiry: u64,
+    pub nonce: Option<String>,
+    pub request_id: String,
+    pub signed_update: String,
+    pub signed_request_status: String,
+    /// SHA-256 over the content hash and the fields above. Unkeyed, so it only detects accidental edits.
+    pub sha256: String,
+}
+
+/// A proposal prepared on an online machine, signed on an offline machine and submitted later.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ProposalBundle {
+    pub content: BundleContent,
+    /// SHA-256 of the JSON encoded `content`, to be compared out of band between the machines.
+    pub content_sha256: String,
+    pub signature: Option<BundleSignature>,
+}
+
+impl ProposalBundle {
+    pub fn prepare(
+        target: &BundleTarget,
+        request: MakeProposalRequest,
+        simulation: Option<ProposalSimulation>,
+        forum_post_link: Option<url::Url>,
+    ) -> anyhow::Result<Self> {
+        let request = MakeProposalRequest {
+            url: forum_post_link.as_ref().map(|u| u.to_string()).unwrap_or_default(),
+            ..request
+        };
+        let manage_neuron = GovernanceCanisterWrapper::make_proposal_command(NeuronId { id: target.neuron_id }, request.into());
+        let content = BundleContent {
+            format_version: BUNDLE_FORMAT_VERSION,
+            network: target.network.clone(),
+            nns_urls: target.nns_urls.clone(),
+            registry_version: target.registry_version,
+            neuron_id: target.neuron_id,
+            prepared_at: Utc::now(),
+            forum_post_link: forum_post_link.map(|u| u.to_string()),
+            simulation: simulation.map(|s| ProposalSimulation {
+                url: request_url(&manage_neuron).unwrap_or_default(),
+                ..s
+            }),
+            manage_neuron_arg: hex::encode(candid::encode_one(&manage_neuron)?),
+        };
+        Ok(Self {
+            content_sha256: sha256_hex(&serde_json::to_vec(&content)?),
+            content,
+            signature: None,
+        })
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let bundle: Self = serde_json::from_str(&fs_err::read_to_string(path)?)
+            .map_err(|e| anyhow::anyhow!("Failed to parse proposal bundle {}: {}", path.display(), e))?;
+        bundle.verify()?;
+        Ok(bundle)
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        fs_err::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Checks that the content is consistent with itself and that the signed call submits exactly
+    /// that content. The signature is not verified here, the IC rejects the call if it is invalid.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        if self.content.format_version != BUNDLE_FORMAT_VERSION {
+            anyhow::bail!(
+                "Unsupported proposal bundle format version {}, expected {}",
+                self.content.format_version,
+                BUNDLE_FORMAT_VERSION
+            );
+        }
+        let content_sha256 = sha256_hex(&serde_json::to_vec(&self.content)?);
+        if content_sha256 != self.content_sha256 {
+            anyhow::bail!(
+                "Proposal bundle content was modified: its SHA-256 is {} but the bundle was prepared with {}",
+                content_sha256,
+                self.content_sha256
+            );
+        }
+
+        let manage_neuron = self.manage_neuron()?;
+        let neuron_id = match &manage_neuron.neuron_id_or_subaccount {
+            Some(NeuronIdOrSubaccount::NeuronId(n)) => n.id,
+            other => anyhow::bail!("Unexpected neuron in proposal bundle: {:?}", other),
+        };
+        if neuron_id != self.content.neuron_id {
+            anyhow::bail!(
+                "Proposal bundle is for neuron {} but its payload is for neuron {}",
+                self.content.neuron_id,
+                neuron_id
+            );
+        }
+        if let Some(simulation) = &self.content.simulation {
+            let proposal = match &manage_neuron.command {
+                Some(Command::MakeProposal(proposal)) => proposal,
+                other => anyhow::bail!("Proposal bundle does not make a proposal: {:?}", other),
+            };
+            // The simulation is what the signer reviews, so the signed action has to be exactly the simulated one
+            let (nns_function, payload) = NativeProposal::encode_simulated_action(simulation)?;
+            let action_matches = match &proposal.action {
+                Some(Action::ExecuteNnsFunction(function)) => function.nns_function == nns_function as i32 && function.payload == payload,
+                _ => false,
+            };
+            if !action_matches
+                || proposal.title.as_deref() != Some(simulation.title.as_str())
+                || proposal.summary != simulation.summary
+                || proposal.url != simulation.url
+            {
+                anyhow::bail!("Proposal bundle description does not match its payload");
+            }
+        }
+
+        if let Some(signature) = &self.signature {
+            if signature.sha256 != signature_sha256(&self.content_sha256, signature)? {
+                anyhow::bail!("Proposal bundle signature was modified or was made for different content");
+            }
+            // `signed_update` is what gets submitted, so it has to be a call with exactly the reviewed
+            // content. Whether its signature is valid is only checked by the IC on submission.
+            self.signed_manage_neuron()?
+                .verify_signed_update()
+                .map_err(|e| anyhow::anyhow!("Proposal bundle signature was made for a different payload: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Fails if the registry changed since the proposal was prepared, since the proposal may no longer be valid.
+    pub fn check_registry_version(&self, current: u64, allow_changes: bool) -> anyhow::Result<()> {
+        if current == self.content.registry_version {
+            return Ok(());
+        }
+        let message = format!(
+            "The registry changed since the proposal was prepared (version {} -> {})",
+            self.content.registry_version, current
+        );
+        if allow_changes {
+            log::warn!("{}", message);
+            Ok(())
+        } else {
+            anyhow::bail!(
+                "{}. Prepare the proposal again, or pass --allow-registry-changes if the change is unrelated.",
+                message
+            )
+        }
+    }
+
+    pub fn sign(self, governance: &GovernanceCanisterWrapper) -> anyhow::Result<Self> {
+        let signed = governance.sign_manage_neuron(&self.manage_neuron()?, SIGNATURE_VALIDITY)?;
+        let mut signature = BundleSignature {
+            sender: signed.sender.to_text(),
+            signed_at: Utc::now(),
+            ingress_expiry: signed.ingress_expiry,
+            nonce: signed.nonce.map(hex::encode),
+            request_id: hex::encode(&signed.request_id),
+            signed_update: hex::encode(&signed.signed_update),
+            signed_request_status: hex::encode(&signed.signed_request_status),
+            sha256: String::new(),
+        };
+        signature.sha256 = signature_sha256(&self.content_sha256, &signature)?;
+
+        let bundle = Self {
+            signature: Some(signature),
+            ..self
+        };
+        bundle.verify()?;
+        Ok(bundle)
+    }
+
+    pub fn signed_manage_neuron(&self) -> anyhow::Result<SignedManageNeuron> {
+        let signature = self.signature.as_ref().ok_or_else(|| anyhow::anyhow!("Proposal bundle is not signed"))?;
+        Ok(SignedManageNeuron {
+            sender: candid::Principal::from_text(&signature.sender)?,
+            ingress_expiry: signature.ingress_expiry,
+            nonce: signature.nonce.as_ref().map(hex::decode).transpose()?,
+            arg: hex::decode(&self.content.manage_neuron_arg)?,
+            request_id: hex::decode(&signature.request_id)?,
+            signed_update: hex::decode(&signature.signed_update)?,
+            signed_request_status: hex::decode(&signature.signed_request_status)?,
+        })
+    }
+
+    /// Time until the signature expires, `None` if it already expired or the bundle is not signed.
+    pub fn signature_validity_left(&self) -> Option<Duration> {
+        let expiry = DateTime::from_timestamp_nanos(self.signature.as_ref()?.ingress_expiry as i64);
+        (expiry - Utc::now()).to_std().ok()
+    }
+
+    fn manage_neuron(&self) -> anyhow::Result<ManageNeuron> {
+        candid::decode_one(&hex::decode(&self.content.manage_neuron_arg)?).map_err(anyhow::Error::from)
+    }
+}
+
+impl Display for ProposalBundle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Network: {}", self.content.network)?;
+        writeln!(f, "Neuron: {}", self.content.neuron_id)?;
+        writeln!(f, "Registry version: {}", self.content.registry_version)?;
+        writeln!(f, "Prepared at: {}", self.content.prepared_at)?;
+        writeln!(f, "Forum post: {}", self.content.forum_post_link.as_deref().unwrap_or("None"))?;
+        writeln!(f, "Content SHA-256: {}", self.content_sha256)?;
+        if let Some(signature) = &self.signature {
+            writeln!(f, "Signed by {} at {}", signature.sender, signature.signed_at)?;
+        }
+        match (&self.content.simulation, self.manage_neuron()) {
+            (Some(simulation), _) => write!(f, "{}", simulation),
+            (
+                None,
+                Ok(ManageNeuron {
+                    command: Some(Command::MakeProposal(proposal)),
+                    ..
+                }),
+            ) => {
+                writeln!(f, "Title: {}", proposal.title.as_deref().unwrap_or_default())?;
+                writeln!(f, "Summary:\n{}", proposal.summary)?;
+                writeln!(f, "Action: {:#?}", proposal.action)
+            }
+            (None, _) => writeln!(f, "Payload: {}", self.content.manage_neuron_arg),
+        }
+    }
+}
+
+fn request_url(manage_neuron: &ManageNeuron) -> Option<String> {
+    match &manage_neuron.command {
+        Some(Command::MakeProposal(proposal)) => Some(proposal.url.clone()),
+        _ => None,
+    }
+}
+
+fn signature_sha256(content_sha256: &str, signature: &BundleSignature) -> anyhow::Result<String> {
+    let unhashed = BundleSignature {
+        sha256: String::new(),
+        ..signature.clone()
+    };
+    Ok(sha256_hex(&[content_sha256.as_bytes(), &serde_json::to_vec(&unhashed)?].concat()))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ic_admin::{IcAdminProposal, IcAdminProposalCommand, IcAdminProposalOptions};
+    use ic_types::PrincipalId;
+    use registry_canister::mutations::node_management::do_remove_nodes::RemoveNodesPayload;
+
+    fn bundle() -> ProposalBundle {
+        let target = BundleTarget {
+            path: PathBuf::from("bundle.json"),
+            network: "mainnet".to_string(),
+            nns_urls: vec![url::Url::parse("https://ic0.app").unwrap()],
+            registry_version: 100,
+            neuron_id: 40,
+        };
+        let proposal = IcAdminProposal::new(
+            IcAdminProposalCommand::RemoveNodes {
+                nodes: vec![PrincipalId::new_node_test_id(1)],
+            },
+            IcAdminProposalOptions {
+                title: Some("Title".to_string()),
+                summary: Some("Summary".to_string()),
+                motivation: None,
+            },
+        );
+        let native = NativeProposal::try_from_ic_admin(&proposal).unwrap().unwrap();
+        ProposalBundle::prepare(
+            &target,
+            native.clone().into(),
+            Some(native.simulation().clone()),
+            Some(url::Url::parse("https://forum.dfinity.org/t/1").unwrap()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn prepared_bundle_verifies() {
+        let bundle = bundle();
+
+        bundle.verify().unwrap();
+        assert_eq!(bundle.content.simulation.as_ref().unwrap().url, "https://forum.dfinity.org/t/1");
+    }
+
+    #[test]
+    fn tampered_bundle_is_rejected() {
+        let mut bundle = bundle();
+        bundle.content.neuron_id = 41;
+        assert!(bundle.verify().unwrap_err().to_string().contains("content was modified"));
+
+        // Even with a matching hash, the description has to match the signed payload
+        let mut bundle = self::bundle();
+        bundle.content.simulation.as_mut().unwrap().summary = "Something else".to_string();
+        bundle.content_sha256 = sha256_hex(&serde_json::to_vec(&bundle.content).unwrap());
+        assert!(bundle.verify().unwrap_err().to_string().contains("does not match its payload"));
+
+        // The same goes for the simulated payload
+        let mut bundle = self::bundle();
+        bundle.content.simulation.as_mut().unwrap().payload = serde_json::to_value(RemoveNodesPayload {
+            node_ids: vec![PrincipalId::new_node_test_id(2).into()],
+        })
+        .unwrap();
+        bundle.content_sha256 = sha256_hex(&serde_json::to_vec(&bundle.content).unwrap());
+        assert!(bundle.verify().unwrap_err().to_string().contains("does not match its payload"));
+    }
+
+    #[test]
+    fn swapped_signed_update_is_rejected() {
+        let client = ic_canisters::IcAgentCanisterClient::from_anonymous(url::Url::parse("https://ic0.app").unwrap()).unwrap();
+        let governance = GovernanceCanisterWrapper::from(client);
+        let signed = bundle().sign(&governance).unwrap();
+        signed.verify().unwrap();
+
+        // A valid call for other content, signed by the same identity, must not pass for this bundle
+        let mut other = bundle();
+        other.content.neuron_id = 41;
+        other.content.manage_neuron_arg = hex::encode(
+            candid::encode_one(ManageNeuron {
+                neuron_id_or_subaccount: Some(NeuronIdOrSubaccount::NeuronId(NeuronId { id: 41 })),
+                ..other.manage_neuron().unwrap()
+            })
+            .unwrap(),
+        );
+        other.content_sha256 = sha256_hex(&serde_json::to_vec(&other.content).unwrap());
+        let other = other.sign(&governance).unwrap();
+
+        let mut tampered = signed.clone();
+        let signature = tampered.signature.as_mut().unwrap();
+        signature.signed_update = other.signature.unwrap().signed_update;
+        signature.sha256 = signature_sha256(&tampered.content_sha256, signature).unwrap();
+        assert!(tampered.verify().unwrap_err().to_string().contains("different payload"));
+    }
+
+    #[test]
+    fn registry_changes_are_detected() {
+        let bundle = bundle();
+
+        assert!(bundle.check_registry_version(100, false).is_ok());
+        assert!(bundle.check_registry_version(101, false).is_err());
+        assert!(bundle.check_registry_version(101, true).is_ok());
+    }
+}
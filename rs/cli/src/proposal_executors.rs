@@ -1,8 +1,13 @@
-use std::fmt::{self, Debug, Display};
+use std::{
+    fmt::{self, Debug, Display},
+    path::PathBuf,
+};
 
 use futures::future::BoxFuture;
 use ic_nns_governance_api::{MakeProposalRequest, manage_neuron_response::MakeProposalResponse};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use url::Url;
 
 /// A struct representing a response to a submitted proposal,
@@ -100,6 +105,11 @@ pub trait ProducesProposalResult {
 /// also has the ability to deserialize to a ProposalResponseWithId.
 pub trait ProposableViaGovernanceCanister: Debug + Send + Sync + Clone + Into<MakeProposalRequest> {
     type ProposalResult: TryFrom<u64> + TryInto<ProposalResponseWithId>;
+
+    /// Structured description of the proposal, shown instead of the raw request when simulating.
+    fn simulation(&self) -> Option<ProposalSimulation> {
+        None
+    }
 }
 
 /// It so happens that any MakeProposalRequest protobuf can be turned
@@ -114,12 +124,126 @@ impl ProposableViaGovernanceCanister for MakeProposalRequest {
 pub trait ProposalExecution: Send + Sync {
     fn simulate(&self, forum_post_link_description: Option<String>) -> BoxFuture<'_, anyhow::Result<()>>;
 
+    /// Structured description of the proposal that would be submitted, if the executor builds
+    /// the payload itself. Proposals submitted through ic-admin return `None`.
+    fn simulation(&self) -> Option<ProposalSimulation> {
+        None
+    }
+
+    /// Writes the proposal to a bundle for offline signing instead of submitting it, if the execution
+    /// was created with `--prepare-only`. Returns the path of the bundle.
+    fn prepare_bundle(&self, _forum_post_link: Option<Url>) -> anyhow::Result<Option<PathBuf>> {
+        Ok(None)
+    }
+
     /// Runs the proposal in forrealz mode.  Result is returned and logged at debug level.
     fn submit<'a, 'b>(&'a self, forum_post_link: Option<Url>) -> BoxFuture<'b, anyhow::Result<ProposalResponseWithId>>
     where
         'a: 'b;
 }
 
+/// A proposal that would be submitted, with its decoded payload.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ProposalSimulation {
+    pub action: String,
+    pub title: String,
+    pub summary: String,
+    pub url: String,
+    pub payload: Value,
+}
+
+impl ProposalSimulation {
+    /// Payload fields which differ from `base`, for example the payload of an already submitted proposal.
+    pub fn diff(&self, base: &Value) -> Vec<PayloadChange> {
+        payload_diff(base, &self.payload)
+    }
+}
+
+impl Display for ProposalSimulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Action: {}", self.action)?;
+        writeln!(f, "Title: {}", self.title)?;
+        writeln!(f, "Summary:\n{}", self.summary)?;
+        writeln!(f, "Payload:")?;
+        for change in self.diff(&Value::Null) {
+            writeln!(f, "  {}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single leaf of a JSON payload which differs between two payloads.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct PayloadChange {
+    /// Path to the field, such as `node_ids_add[0]`.
+    pub path: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl Display for PayloadChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.before, &self.after) {
+            (None, Some(after)) => write!(f, "{}: {}", self.path, after),
+            (Some(before), None) => write!(f, "{}: {} -> (removed)", self.path, before),
+            (Some(before), Some(after)) => write!(f, "{}: {} -> {}", self.path, before, after),
+            (None, None) => write!(f, "{}", self.path),
+        }
+    }
+}
+
+/// Compares two JSON payloads leaf by leaf. Objects are compared by key and arrays by index,
+/// null and missing values are treated the same.
+pub fn payload_diff(before: &Value, after: &Value) -> Vec<PayloadChange> {
+    let mut changes = vec![];
+    diff_values("", before, after, &mut changes);
+    changes
+}
+
+fn diff_values(path: &str, before: &Value, after: &Value, changes: &mut Vec<PayloadChange>) {
+    let nested = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match (before, after) {
+        (Value::Object(b), Value::Object(a)) => {
+            let mut keys = b.keys().chain(a.keys()).collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                diff_values(
+                    &nested(key),
+                    b.get(key).unwrap_or(&Value::Null),
+                    a.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        (Value::Array(b), Value::Array(a)) => {
+            for i in 0..b.len().max(a.len()) {
+                diff_values(
+                    &format!("{}[{}]", path, i),
+                    b.get(i).unwrap_or(&Value::Null),
+                    a.get(i).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        (Value::Null, Value::Object(_) | Value::Array(_)) => diff_values(path, &empty_like(after), after, changes),
+        (Value::Object(_) | Value::Array(_), Value::Null) => diff_values(path, before, &empty_like(before), changes),
+        (b, a) if b != a => changes.push(PayloadChange {
+            path: path.to_string(),
+            before: (!b.is_null()).then(|| b.clone()),
+            after: (!a.is_null()).then(|| a.clone()),
+        }),
+        _ => {}
+    }
+}
+
+fn empty_like(value: &Value) -> Value {
+    match value {
+        Value::Array(_) => Value::Array(vec![]),
+        _ => Value::Object(Default::default()),
+    }
+}
+
 #[cfg(test)]
 #[allow(dead_code)]
 mod tests {
@@ -143,4 +267,23 @@ proposal 123456
         let parsed = ProposalResponseWithId::try_from(text.to_string()).unwrap();
         assert_eq!(parsed.0, 123456)
     }
+
+    #[test]
+    fn payload_diff_test() {
+        let before = serde_json::json!({"subnet_id": "a", "nodes": ["n1", "n2"], "config": {"x": 1}});
+        let after = serde_json::json!({"subnet_id": "a", "nodes": ["n1", "n3", "n4"], "config": null, "version": "v"});
+
+        let changes = payload_diff(&before, &after);
+
+        assert_eq!(
+            changes.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            vec![
+                "config.x: 1 -> (removed)",
+                "nodes[1]: \"n2\" -> \"n3\"",
+                "nodes[2]: \"n4\"",
+                "version: \"v\"",
+            ]
+        );
+        assert!(payload_diff(&after, &after).is_empty());
+    }
 }
@@ -0,0 +1,384 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use ic_types::PrincipalId;
+use itertools::Itertools;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use url::Url;
+
+use crate::{
+    ic_admin::{IcAdminProposal, IcAdminProposalCommand},
+    proposal_executors::{ProposalExecution, ProposalResponseWithId, ProposalSimulation},
+};
+
+/// Proposals submitted within this period are checked for duplicates and conflicts.
+pub const CONFLICT_WINDOW: Duration = Duration::from_secs(60 * 60 * 24 * 14);
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Display, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ProposalOutcome {
+    /// Simulated in dry-run or before confirmation
+    Simulated,
+    /// Written to a bundle for offline signing
+    Prepared,
+    /// Accepted by the governance canister
+    Submitted,
+    /// Rejected by the governance canister or ic-admin
+    Failed,
+}
+
+/// A proposal simulated or submitted by `dre` from this workstation.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ProposalRecord {
+    pub recorded_at: DateTime<Utc>,
+    pub network: String,
+    pub outcome: ProposalOutcome,
+    /// The ic-admin subcommand, also for proposals submitted natively, or `motion`
+    pub command: String,
+    pub args: Vec<String>,
+    /// Subnets and nodes the proposal changes
+    #[serde(default)]
+    pub targets: Vec<PrincipalId>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub forum_url: Option<Url>,
+    #[serde(default)]
+    pub neuron_id: Option<u64>,
+    #[serde(default)]
+    pub registry_version: Option<u64>,
+    #[serde(default)]
+    pub proposal_id: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl ProposalRecord {
+    pub fn new(network: &str, proposal: &IcAdminProposal, neuron_id: Option<u64>, registry_version: Option<u64>) -> Self {
+        let (command, args) = proposal.command.args();
+        Self {
+            recorded_at: Utc::now(),
+            network: network.to_string(),
+            outcome: ProposalOutcome::Simulated,
+            command,
+            args,
+            targets: targets(&proposal.command),
+            title: proposal.options.title.clone(),
+            summary: proposal.options.summary.clone(),
+            forum_url: None,
+            neuron_id,
+            registry_version,
+            proposal_id: None,
+            error: None,
+        }
+    }
+
+    fn with_outcome(&self, outcome: ProposalOutcome) -> Self {
+        Self {
+            recorded_at: Utc::now(),
+            outcome,
+            ..self.clone()
+        }
+    }
+
+    /// Whether the proposal may already be in the NNS, waiting for votes or executed.
+    fn is_in_flight(&self) -> bool {
+        matches!(self.outcome, ProposalOutcome::Submitted | ProposalOutcome::Prepared)
+    }
+
+    fn is_duplicate_of(&self, other: &Self) -> bool {
+        self.network == other.network && self.command == other.command && self.args == other.args
+    }
+
+    fn conflicts_with(&self, other: &Self) -> bool {
+        self.network == other.network && self.targets.iter().any(|t| other.targets.contains(t))
+    }
+}
+
+/// Subnets and nodes changed by the proposal, used to find proposals conflicting with each other.
+fn targets(command: &IcAdminProposalCommand) -> Vec<PrincipalId> {
+    match command {
+        IcAdminProposalCommand::ChangeSubnetMembership {
+            subnet_id,
+            node_ids_add,
+            node_ids_remove,
+        } => [vec![*subnet_id], node_ids_add.clone(), node_ids_remove.clone()].concat(),
+        IcAdminProposalCommand::DeployGuestosToAllSubnetNodes { subnet, .. } => vec![*subnet],
+        IcAdminProposalCommand::DeployHostosToSomeNodes { nodes, .. }
+        | IcAdminProposalCommand::RemoveNodes { nodes }
+        | IcAdminProposalCommand::AddApiBoundaryNodes { nodes, .. }
+        | IcAdminProposalCommand::RemoveApiBoundaryNodes { nodes }
+        | IcAdminProposalCommand::DeployGuestosToSomeApiBoundaryNodes { nodes, .. } => nodes.clone(),
+        IcAdminProposalCommand::CreateSubnet { node_ids, .. } => node_ids.clone(),
+        IcAdminProposalCommand::SetAuthorizedSubnetworks { subnets } => subnets.clone(),
+        IcAdminProposalCommand::DeployGuestosToAllUnassignedNodes { .. }
+        | IcAdminProposalCommand::ReviseElectedVersions { .. }
+        | IcAdminProposalCommand::Raw(_) => vec![],
+    }
+}
+
+/// Append-only log of the proposals made with `dre`, one JSON record per line, kept in the local store.
+///
+/// A plain file rather than an embedded database: records are only appended and read back in full,
+/// and concurrent `dre` invocations can share it without locking.
+#[derive(Clone, Debug)]
+pub struct ProposalHistory {
+    path: PathBuf,
+}
+
+impl ProposalHistory {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, record: &ProposalRecord) -> anyhow::Result<()> {
+        // A single write of a whole line, so that concurrent `dre` invocations don't interleave records
+        let line = format!("{}\n", serde_json::to_string(record)?);
+        let mut file = fs_err::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// All records, oldest first. Lines that cannot be parsed are skipped.
+    pub fn records(&self) -> anyhow::Result<Vec<ProposalRecord>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let contents = fs_err::read_to_string(&self.path)?;
+        Ok(contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(i, line)| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    warn!("Skipping invalid record on line {} of {}: {}", i + 1, self.path.display(), e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Proposals submitted recently that are identical to the given one.
+    pub fn duplicates_of(&self, record: &ProposalRecord) -> anyhow::Result<Vec<ProposalRecord>> {
+        Ok(self.recent_in_flight(record)?.into_iter().filter(|r| r.is_duplicate_of(record)).collect())
+    }
+
+    /// Proposals submitted recently that change some of the subnets or nodes of the given one, without being identical to it.
+    pub fn conflicts_with(&self, record: &ProposalRecord) -> anyhow::Result<Vec<ProposalRecord>> {
+        Ok(self
+            .recent_in_flight(record)?
+            .into_iter()
+            .filter(|r| !r.is_duplicate_of(record) && r.conflicts_with(record))
+            .collect())
+    }
+
+    fn recent_in_flight(&self, record: &ProposalRecord) -> anyhow::Result<Vec<ProposalRecord>> {
+        let since = record.recorded_at - chrono::Duration::from_std(CONFLICT_WINDOW)?;
+        Ok(self
+            .records()?
+            .into_iter()
+            .filter(|r| r.is_in_flight() && r.recorded_at >= since)
+            .collect())
+    }
+
+    /// Warns about recent proposals from this workstation that duplicate or conflict with the given one.
+    pub fn warn_about_conflicts(&self, record: &ProposalRecord) -> anyhow::Result<()> {
+        for duplicate in self.duplicates_of(record)? {
+            warn!(
+                "An identical `{}` proposal was {} on {}{}",
+                duplicate.command,
+                duplicate.outcome,
+                duplicate.recorded_at.format("%Y-%m-%d %H:%M UTC"),
+                duplicate.proposal_id.map(|id| format!(" as proposal {}", id)).unwrap_or_default()
+            );
+        }
+        for conflict in self.conflicts_with(record)? {
+            warn!(
+                "A `{}` proposal {} on {}{} also changes {}",
+                conflict.command,
+                conflict.outcome,
+                conflict.recorded_at.format("%Y-%m-%d %H:%M UTC"),
+                conflict.proposal_id.map(|id| format!(" as proposal {}", id)).unwrap_or_default(),
+                conflict.targets.iter().filter(|t| record.targets.contains(t)).join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Records the simulation and the outcome of the submission of a proposal in the proposal history.
+pub struct RecordedProposalExecution {
+    inner: Box<dyn ProposalExecution>,
+    history: ProposalHistory,
+    record: ProposalRecord,
+    conflicts_checked: AtomicBool,
+}
+
+impl RecordedProposalExecution {
+    pub fn new(inner: Box<dyn ProposalExecution>, history: ProposalHistory, record: ProposalRecord) -> Self {
+        Self {
+            inner,
+            history,
+            record,
+            conflicts_checked: AtomicBool::new(false),
+        }
+    }
+
+    // Simulation is skipped with --yes, so the check is done by whichever of simulate and submit comes first
+    fn warn_about_conflicts(&self) {
+        if self.conflicts_checked.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        if let Err(e) = self.history.warn_about_conflicts(&self.record) {
+            warn!("Failed to check the proposal history for conflicting proposals: {:?}", e);
+        }
+    }
+
+    // The history must never prevent a proposal from being made
+    fn append(&self, record: ProposalRecord) {
+        if let Err(e) = self.history.append(&record) {
+            warn!("Failed to record the proposal in {}: {:?}", self.history.path().display(), e);
+        }
+    }
+}
+
+impl ProposalExecution for RecordedProposalExecution {
+    fn simulate(&self, forum_post_link_description: Option<String>) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.inner.simulate(forum_post_link_description).await?;
+            self.warn_about_conflicts();
+            self.append(self.record.with_outcome(ProposalOutcome::Simulated));
+            Ok(())
+        })
+    }
+
+    fn simulation(&self) -> Option<ProposalSimulation> {
+        self.inner.simulation()
+    }
+
+    fn prepare_bundle(&self, forum_post_link: Option<Url>) -> anyhow::Result<Option<PathBuf>> {
+        let path = self.inner.prepare_bundle(forum_post_link.clone())?;
+        if path.is_some() {
+            self.append(ProposalRecord {
+                forum_url: forum_post_link,
+                ..self.record.with_outcome(ProposalOutcome::Prepared)
+            });
+        }
+        Ok(path)
+    }
+
+    fn submit<'a, 'b>(&'a self, forum_post_link: Option<Url>) -> BoxFuture<'b, anyhow::Result<ProposalResponseWithId>>
+    where
+        'a: 'b,
+    {
+        Box::pin(async move {
+            self.warn_about_conflicts();
+            let res = self.inner.submit(forum_post_link.clone()).await;
+            let record = match &res {
+                Ok(response) => ProposalRecord {
+                    proposal_id: Some(response.clone().into()),
+                    ..self.record.with_outcome(ProposalOutcome::Submitted)
+                },
+                Err(e) => ProposalRecord {
+                    error: Some(e.to_string()),
+                    ..self.record.with_outcome(ProposalOutcome::Failed)
+                },
+            };
+            self.append(ProposalRecord {
+                forum_url: forum_post_link,
+                ..record
+            });
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::ic_admin::IcAdminProposalOptions;
+
+    use super::*;
+
+    fn record(node_ids_remove: &[&str], outcome: ProposalOutcome) -> ProposalRecord {
+        let proposal = IcAdminProposal::new(
+            IcAdminProposalCommand::ChangeSubnetMembership {
+                subnet_id: PrincipalId::from_str("tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe").unwrap(),
+                node_ids_add: vec![],
+                node_ids_remove: node_ids_remove.iter().map(|n| PrincipalId::from_str(n).unwrap()).collect(),
+            },
+            IcAdminProposalOptions {
+                summary: Some("Replace a node".to_string()),
+                ..Default::default()
+            },
+        );
+        ProposalRecord::new("mainnet", &proposal, Some(40), Some(1234)).with_outcome(outcome)
+    }
+
+    #[test]
+    fn duplicates_and_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = ProposalHistory::new(dir.path().join("proposals.jsonl"));
+        let node_1 = "x33ed-h457x-bsgyx-oqxqf-6pzwv-wkhzr-rm2j3-npodi-purzm-n66cg-gae";
+        let node_2 = "bkfrj-6k62g-dycql-7h53p-atvkj-zg4to-gaogh-netha-ptybj-ntsgw-rqe";
+
+        history.append(&record(&[node_1], ProposalOutcome::Simulated)).unwrap();
+        history.append(&record(&[node_1], ProposalOutcome::Submitted)).unwrap();
+        assert_eq!(history.records().unwrap().len(), 2);
+
+        // Only submitted proposals count
+        let same = record(&[node_1], ProposalOutcome::Simulated);
+        assert_eq!(history.duplicates_of(&same).unwrap().len(), 1);
+        assert!(history.conflicts_with(&same).unwrap().is_empty());
+
+        // Same subnet, different nodes
+        let other = record(&[node_2], ProposalOutcome::Simulated);
+        assert!(history.duplicates_of(&other).unwrap().is_empty());
+        assert_eq!(history.conflicts_with(&other).unwrap().len(), 1);
+
+        // Different network
+        let other_network = ProposalRecord {
+            network: "staging".to_string(),
+            ..same
+        };
+        assert!(history.duplicates_of(&other_network).unwrap().is_empty());
+        assert!(history.conflicts_with(&other_network).unwrap().is_empty());
+    }
+
+    #[test]
+    fn old_and_invalid_records_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = ProposalHistory::new(dir.path().join("proposals.jsonl"));
+        let node = "x33ed-h457x-bsgyx-oqxqf-6pzwv-wkhzr-rm2j3-npodi-purzm-n66cg-gae";
+
+        let mut old = record(&[node], ProposalOutcome::Submitted);
+        old.recorded_at -= chrono::Duration::from_std(CONFLICT_WINDOW).unwrap() + chrono::Duration::hours(1);
+        history.append(&old).unwrap();
+        fs_err::OpenOptions::new()
+            .append(true)
+            .open(history.path())
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+
+        assert_eq!(history.records().unwrap(), vec![old]);
+        assert!(history.duplicates_of(&record(&[node], ProposalOutcome::Simulated)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+use decentralization::provider_clusters::ProviderClusters;
+use futures::future::BoxFuture;
+use mockall::automock;
+
+use crate::github_file_fetcher::GithubFileFetcher;
+
+#[automock]
+pub trait ProviderClustersFetcher: Sync + Send {
+    fn fetch(&self) -> BoxFuture<'_, anyhow::Result<ProviderClusters>>;
+}
+
+pub struct ProviderClustersFetcherImpl {
+    file: GithubFileFetcher,
+}
+
+impl ProviderClustersFetcherImpl {
+    pub fn new(local_copy: PathBuf, use_local_file: bool) -> anyhow::Result<Self> {
+        Ok(Self {
+            file: GithubFileFetcher::new("provider_clusters.yaml", "provider clusters", local_copy, use_local_file)?,
+        })
+    }
+
+    fn parse(&self, contents: &[u8]) -> anyhow::Result<ProviderClusters> {
+        self.file.parse_or_default(contents, ProviderClusters::parse)
+    }
+}
+
+impl ProviderClustersFetcher for ProviderClustersFetcherImpl {
+    fn fetch(&self) -> BoxFuture<'_, anyhow::Result<ProviderClusters>> {
+        Box::pin(self.file.fetch(|contents| self.parse(contents)))
+    }
+}
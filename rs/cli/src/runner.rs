@@ -5,12 +5,23 @@ use std::sync::Arc;
 
 use ahash::AHashMap;
 use decentralization::SubnetChangeResponse;
+use decentralization::business_rules::BusinessRules;
+use decentralization::network::CapacityPlan;
+use decentralization::network::CapacityPlanner;
 use decentralization::network::CordonedFeature;
 use decentralization::network::DecentralizedSubnet;
+use decentralization::network::HypotheticalNodes;
+use decentralization::network::MigrationPlan;
+use decentralization::network::MigrationPlanner;
+use decentralization::network::MigrationTarget;
 use decentralization::network::NetworkHealRequest;
+use decentralization::network::OutageScope;
+use decentralization::network::OutageSimulation;
+use decentralization::network::OutageSimulator;
 use decentralization::network::SubnetChange;
 use decentralization::network::SubnetChangeRequest;
 use decentralization::network::SubnetQueryBy;
+use decentralization::report::SubnetChangeReport;
 use decentralization::subnets::NodesRemover;
 use futures::TryFutureExt;
 use futures::future::try_join3;
@@ -50,6 +61,7 @@ use crate::ic_admin::{IcAdminProposalCommand, IcAdminProposalOptions};
 use crate::operations::hostos_rollout::HostosRollout;
 use crate::operations::hostos_rollout::HostosRolloutResponse;
 use crate::operations::hostos_rollout::NodeGroupUpdate;
+use crate::operations::hostos_rollout_gates::HostosRolloutGates;
 
 pub struct Runner {
     registry: Arc<dyn LazyRegistry>,
@@ -60,6 +72,7 @@ pub struct Runner {
     artifact_downloader: Arc<dyn ArtifactDownloader>,
     cordoned_features_fetcher: Arc<dyn CordonedFeatureFetcher>,
     health_client: Arc<dyn HealthStatusQuerier>,
+    business_rules: BusinessRules,
 }
 
 impl Runner {
@@ -72,6 +85,7 @@ impl Runner {
         artifact_downloader: Arc<dyn ArtifactDownloader>,
         cordoned_features_fetcher: Arc<dyn CordonedFeatureFetcher>,
         health_client: Arc<dyn HealthStatusQuerier>,
+        business_rules: BusinessRules,
     ) -> Self {
         Self {
             registry,
@@ -82,6 +96,7 @@ impl Runner {
             artifact_downloader,
             cordoned_features_fetcher,
             health_client,
+            business_rules,
         }
     }
 
@@ -173,6 +188,7 @@ impl Runner {
                     vec![]
                 }),
                 &all_nodes,
+                self.business_rules.clone(),
             )
             .await?;
         let subnet_creation_data = SubnetChangeResponse::new(&subnet_creation_data, &health_of_nodes, Some(motivation.clone()));
@@ -372,6 +388,7 @@ impl Runner {
         version: &String,
         only: &[String],
         exclude: &[String],
+        gates: HostosRolloutGates,
     ) -> anyhow::Result<Option<(Vec<PrincipalId>, String)>> {
         let elected_versions = self.registry.elected_hostos().await.unwrap();
         if !elected_versions.contains(&version.to_string()) {
@@ -389,7 +406,8 @@ impl Runner {
             only,
             exclude,
             self.health_client.clone(),
-        );
+        )
+        .with_gates(gates);
 
         match hostos_rollout.execute(node_group).await? {
             HostosRolloutResponse::Ok(nodes_to_update, maybe_subnets_affected) => {
@@ -488,9 +506,10 @@ impl Runner {
         let (mut node_removals, motivation) = nodes_remover.remove_nodes(healths, nodes_with_proposals);
         node_removals.sort_by_key(|nr| nr.reason.message());
 
+        let features = NodeFeature::variants_for(node_removals.iter().map(|nr| nr.node.get_features()));
         let headers = vec!["Principal".to_string()]
             .into_iter()
-            .chain(NodeFeature::variants().iter().map(|nf| nf.to_string()))
+            .chain(features.iter().map(|nf| nf.to_string()))
             .chain(vec!["Hostname".to_string()].into_iter())
             .chain(vec!["Reason".to_string()].into_iter())
             .collect::<Vec<_>>();
@@ -509,8 +528,8 @@ impl Runner {
         for nr in &node_removals {
             let mut row = tabular::Row::new();
             row.add_cell(nr.node.principal);
-            for nf in NodeFeature::variants() {
-                row.add_cell(nr.node.get_feature(&nf).expect("Feature should exist"));
+            for nf in &features {
+                row.add_cell(nr.node.get_feature(nf).expect("Feature should exist"));
             }
             row.add_cell(nr.node.hostname.clone().unwrap_or_else(|| "N/A".to_string()));
             row.add_cell(nr.reason.message());
@@ -566,7 +585,7 @@ impl Runner {
             vec![]
         });
 
-        let subnets_change_responses = NetworkHealRequest::new(subnets_without_proposals)
+        let subnets_change_responses = NetworkHealRequest::new(subnets_without_proposals, self.business_rules.clone())
             .fix_and_optimize(
                 available_nodes,
                 &health_of_nodes,
@@ -597,6 +616,191 @@ impl Runner {
         Ok(changes.into_iter().map(|maybe_change| maybe_change.unwrap()).collect_vec())
     }
 
+    pub async fn network_plan(
+        &self,
+        omit_subnets: &[String],
+        omit_nodes: &[String],
+        target: &MigrationTarget,
+        max_steps: usize,
+    ) -> anyhow::Result<MigrationPlan> {
+        let subnets_without_proposals = filter_subnets_without_proposals(self.get_subnets(omit_subnets).await?);
+        let (all_nodes, available_nodes, health_of_nodes) = try_join3(
+            self.registry.nodes(),
+            self.registry.available_nodes().map_err(anyhow::Error::from),
+            self.health_client.nodes(),
+        )
+        .await?;
+        let all_nodes = all_nodes.values().cloned().collect::<Vec<Node>>();
+        let available_nodes = available_nodes
+            .into_iter()
+            .filter(|n| !omit_nodes.iter().any(|s| n.principal.to_string().contains(s)))
+            .collect::<Vec<Node>>();
+
+        let cordoned_features = self.cordoned_features_fetcher.fetch().await.unwrap_or_else(|e| {
+            warn!("Failed to fetch cordoned features with error: {:?}", e);
+            warn!("Will continue running as if no features were cordoned");
+            vec![]
+        });
+
+        Ok(MigrationPlanner::new(
+            subnets_without_proposals,
+            available_nodes,
+            health_of_nodes,
+            cordoned_features,
+            all_nodes,
+            self.business_rules.clone(),
+        )
+        .with_max_steps(max_steps)
+        .plan(target)?)
+    }
+
+    /// Proposals for the first wave of the plan. Later waves depend on the first one being
+    /// executed, so they should be planned again once that happened.
+    pub async fn network_plan_first_wave_proposals(&self, plan: &MigrationPlan) -> anyhow::Result<Vec<IcAdminProposal>> {
+        let mut proposals = vec![];
+        for step in plan.waves().into_iter().next().unwrap_or_default() {
+            proposals.push(
+                self.run_membership_change(&step.change, replace_proposal_options(&step.change).await?)
+                    .await?,
+            );
+        }
+        Ok(proposals)
+    }
+
+    /// Where the provided kinds of new nodes would improve the decentralization of the subnets,
+    /// compared to what the healthy available nodes already allow.
+    pub async fn capacity_plan(
+        &self,
+        omit_subnets: &[String],
+        kinds: Vec<HypotheticalNodes>,
+        max_nodes: usize,
+        min_gain: f64,
+    ) -> anyhow::Result<CapacityPlan> {
+        let subnets = self
+            .get_subnets(omit_subnets)
+            .await?
+            .iter()
+            .map(|(subnet_id, subnet)| {
+                (
+                    *subnet_id,
+                    DecentralizedSubnet::from(subnet).with_business_rules(self.business_rules.clone()),
+                )
+            })
+            .collect();
+        let (available_nodes, health_of_nodes) =
+            try_join(self.registry.available_nodes().map_err(anyhow::Error::from), self.health_client.nodes()).await?;
+        let available_nodes = available_nodes
+            .into_iter()
+            .filter(|n| health_of_nodes.get(&n.principal) == Some(&HealthStatus::Healthy))
+            .collect::<Vec<Node>>();
+
+        Ok(CapacityPlanner::new(subnets, available_nodes, kinds)
+            .with_max_nodes(max_nodes)
+            .with_min_gain(min_gain)
+            .plan()?)
+    }
+
+    /// Which subnets an outage of the nodes in the scope would stall, and the proposals that
+    /// would heal them with the remaining available nodes.
+    pub async fn outage_simulation(&self, omit_subnets: &[String], scope: OutageScope) -> anyhow::Result<OutageSimulation> {
+        let subnets = self.get_subnets(omit_subnets).await?;
+        let (all_nodes, available_nodes, health_of_nodes) = try_join3(
+            self.registry.nodes(),
+            self.registry.available_nodes().map_err(anyhow::Error::from),
+            self.health_client.nodes(),
+        )
+        .await?;
+
+        Ok(OutageSimulator::new(
+            subnets,
+            all_nodes.values().cloned().collect(),
+            available_nodes,
+            health_of_nodes,
+            self.business_rules.clone(),
+        )
+        .simulate(scope)
+        .await?)
+    }
+
+    /// Proposals for the next wave of a persisted plan, i.e. the first wave with steps whose proposal
+    /// is not executed yet, together with that wave. Steps with an open proposal, recorded in the plan
+    /// or made by someone else for their subnet, are assumed to be in flight and skipped.
+    pub async fn network_plan_next_wave_proposals(
+        &self,
+        plan: &MigrationPlan,
+        executed_proposals: &IndexSet<u64>,
+    ) -> anyhow::Result<Option<(usize, Vec<IcAdminProposal>)>> {
+        let Some((wave, steps)) = plan.next_wave(executed_proposals) else {
+            info!("Every step of the migration plan is executed");
+            return Ok(None);
+        };
+        let subnets = self.registry.subnets_and_proposals().await?;
+        let available_nodes = self
+            .registry
+            .available_nodes()
+            .await?
+            .into_iter()
+            .map(|n| n.principal)
+            .collect::<IndexSet<_>>();
+
+        let mut proposals = vec![];
+        for step in steps {
+            let subnet_id = step.change.subnet_id.unwrap_or_default();
+            if let Some(proposal_id) = step.proposal_id {
+                info!(
+                    "Skipping step {} of wave {}, its proposal {} is still open",
+                    step.index + 1,
+                    wave,
+                    proposal_id
+                );
+                continue;
+            }
+            let subnet = subnets
+                .get(&subnet_id)
+                .ok_or_else(|| anyhow::anyhow!("Subnet {} of step {} no longer exists", subnet_id, step.index + 1))?;
+            if let Some(proposal) = &subnet.proposal {
+                info!(
+                    "Skipping step {} of wave {}, subnet {} has the open proposal {}",
+                    step.index + 1,
+                    wave,
+                    subnet_id,
+                    proposal.id
+                );
+                continue;
+            }
+            if let Some(node) = step
+                .change
+                .node_ids_removed
+                .iter()
+                .find(|node| !subnet.nodes.iter().any(|n| n.principal == **node))
+            {
+                return Err(anyhow::anyhow!(
+                    "Step {} removes node {}, which is no longer in subnet {}. The plan is outdated, plan the migration again",
+                    step.index + 1,
+                    node,
+                    subnet_id
+                ));
+            }
+            if let Some(node) = step.change.node_ids_added.iter().find(|node| !available_nodes.contains(*node)) {
+                return Err(anyhow::anyhow!(
+                    "Step {} adds node {}, which is no longer available. The plan is outdated, plan the migration again",
+                    step.index + 1,
+                    node
+                ));
+            }
+            proposals.push(
+                self.run_membership_change(&step.change, replace_proposal_options(&step.change).await?)
+                    .await?,
+            );
+        }
+        if proposals.is_empty() {
+            info!("Wave {} of {} is waiting for its open proposals to be executed", wave, plan.num_waves());
+        } else {
+            info!("Proposing {} step(s) of wave {} of {}", proposals.len(), wave, plan.num_waves());
+        }
+        Ok(Some((wave, proposals)))
+    }
+
     async fn get_subnets(&self, omit_subnets: &[String]) -> anyhow::Result<IndexMap<PrincipalId, Subnet>> {
         let subnets = self.registry.subnets_and_proposals().await?;
         Ok(subnets
@@ -694,7 +898,7 @@ impl Runner {
         let mut best_change: Option<SubnetChangeResponse> = None;
 
         for subnet in subnets.values() {
-            let subnet = DecentralizedSubnet::from(subnet);
+            let subnet = DecentralizedSubnet::from(subnet).with_business_rules(self.business_rules.clone());
             let subnet_id_short = subnet.id.to_string().split_once('-').unwrap().0.to_string();
             let change_request = if ensure_assigned {
                 SubnetChangeRequest::new(subnet, available_nodes.to_vec(), vec![node.clone()], vec![], vec![]).resize(
@@ -959,7 +1163,8 @@ impl Runner {
             .registry
             .modify_subnet_nodes(SubnetQueryBy::SubnetId(*subnet))
             .await
-            .map_err(|e| anyhow::anyhow!(e))?;
+            .map_err(|e| anyhow::anyhow!(e))?
+            .with_business_rules(self.business_rules.clone());
 
         let change_request = match keep_nodes {
             Some(n) => change_request.keeping_from_used(n),
@@ -1169,7 +1374,11 @@ pub async fn replace_proposal_options(change: &SubnetChangeResponse) -> anyhow::
     Ok(ic_admin::IcAdminProposalOptions {
         title: Some(change_desc.clone()),
         summary: Some(format!("# {change_desc}")),
-        motivation: Some(format!("{}\n\n{}\n", change.motivation.as_ref().unwrap_or(&String::new()), change)),
+        motivation: Some(format!(
+            "{}\n\n{}\n",
+            change.motivation.as_ref().unwrap_or(&String::new()),
+            SubnetChangeReport::from(change).markdown()
+        )),
     })
 }
 
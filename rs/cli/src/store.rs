@@ -6,7 +6,7 @@ use ic_management_backend::{
     proposal::ProposalAgent,
     registry::sync_local_store_with_path,
 };
-use ic_management_types::Network;
+use ic_management_types::{Network, custom_features::CustomNodeFeatures};
 use ic_registry_local_registry::LocalRegistry;
 use log::{debug, info, warn};
 use std::os::unix::fs::PermissionsExt;
@@ -100,9 +100,13 @@ async fn find_github_release_tag_for_commit(commit: &str, deep_scan: bool) -> an
 
 use crate::{
     auth::Neuron,
+    business_rules_fetcher::{BusinessRulesFetcher, BusinessRulesFetcherImpl},
     cordoned_feature_fetcher::{CordonedFeatureFetcher, CordonedFeatureFetcherImpl},
     exe::args::IcAdminVersion,
     ic_admin::IcAdminImpl,
+    proposal_history::ProposalHistory,
+    provider_clusters_fetcher::{ProviderClustersFetcher, ProviderClustersFetcherImpl},
+    voting_rules::VoteLog,
 };
 
 #[derive(Clone)]
@@ -200,6 +204,7 @@ impl Store {
         network: &Network,
         proposal_agent: Arc<dyn ProposalAgent>,
         version_height: Option<u64>,
+        custom_node_features: Arc<CustomNodeFeatures>,
     ) -> anyhow::Result<Arc<dyn LazyRegistry>> {
         let registry_path = self.local_store_for_network(network)?;
 
@@ -220,6 +225,7 @@ impl Store {
             self.guest_labels_cache_path(network)?,
             self.health_client(network)?,
             version_height,
+            custom_node_features,
         )))
     }
 
@@ -441,9 +447,64 @@ impl Store {
         Ok(file)
     }
 
-    pub fn cordoned_features_fetcher(&self, local_file_path: Option<String>) -> anyhow::Result<Arc<dyn CordonedFeatureFetcher>> {
+    pub fn cordoned_features_fetcher(
+        &self,
+        local_file_path: Option<String>,
+        custom_node_features: &CustomNodeFeatures,
+    ) -> anyhow::Result<Arc<dyn CordonedFeatureFetcher>> {
         let file = self.cordoned_features_file(local_file_path.clone())?;
-        Ok(Arc::new(CordonedFeatureFetcherImpl::new(
+        Ok(Arc::new(
+            CordonedFeatureFetcherImpl::new(file, self.is_offline() || local_file_path.is_some())?.with_custom_features(custom_node_features),
+        ))
+    }
+
+    fn business_rules_file(&self, file_path: Option<String>) -> anyhow::Result<PathBuf> {
+        let file = match file_path {
+            Some(path) => std::path::PathBuf::from(path).canonicalize()?,
+            None => {
+                let file = self.path().join("business_rules.yaml");
+
+                if !file.exists() {
+                    info!("Business rules file was missing. Creating on path `{}`...", file.display());
+                    fs_err::write(&file, "")?;
+                }
+
+                file
+            }
+        };
+
+        Ok(file)
+    }
+
+    pub fn business_rules_fetcher(&self, local_file_path: Option<String>) -> anyhow::Result<Arc<dyn BusinessRulesFetcher>> {
+        let file = self.business_rules_file(local_file_path.clone())?;
+        Ok(Arc::new(BusinessRulesFetcherImpl::new(
+            file,
+            self.is_offline() || local_file_path.is_some(),
+        )?))
+    }
+
+    fn provider_clusters_file(&self, file_path: Option<String>) -> anyhow::Result<PathBuf> {
+        let file = match file_path {
+            Some(path) => std::path::PathBuf::from(path).canonicalize()?,
+            None => {
+                let file = self.path().join("provider_clusters.yaml");
+
+                if !file.exists() {
+                    info!("Provider clusters file was missing. Creating on path `{}`...", file.display());
+                    fs_err::write(&file, "")?;
+                }
+
+                file
+            }
+        };
+
+        Ok(file)
+    }
+
+    pub fn provider_clusters_fetcher(&self, local_file_path: Option<String>) -> anyhow::Result<Arc<dyn ProviderClustersFetcher>> {
+        let file = self.provider_clusters_file(local_file_path.clone())?;
+        Ok(Arc::new(ProviderClustersFetcherImpl::new(
             file,
             self.is_offline() || local_file_path.is_some(),
         )?))
@@ -466,6 +527,35 @@ impl Store {
         Ok(file)
     }
 
+    fn history_dir(&self, network: &Network) -> anyhow::Result<PathBuf> {
+        let dir = self.path().join("history").join(&network.name);
+
+        if !dir.exists() {
+            debug!(
+                "Directory for proposal history for network {} doesn't exist. Creating on path `{}`",
+                network.name,
+                dir.display()
+            );
+            fs_err::create_dir_all(&dir)?
+        }
+
+        Ok(dir)
+    }
+
+    pub fn proposal_history(&self, network: &Network) -> anyhow::Result<ProposalHistory> {
+        Ok(ProposalHistory::new(self.history_dir(network)?.join("proposals.jsonl")))
+    }
+
+    pub fn vote_log(&self, network: &Network) -> anyhow::Result<VoteLog> {
+        Ok(VoteLog::new(self.history_dir(network)?.join("votes.jsonl")))
+    }
+
+    pub fn rollout_state_file(&self, network: &Network, version: &str) -> anyhow::Result<PathBuf> {
+        let dir = self.history_dir(network)?.join("rollouts");
+        fs_err::create_dir_all(&dir)?;
+        Ok(dir.join(format!("guestos-{}.yaml", version)))
+    }
+
     pub fn health_client(&self, network: &Network) -> anyhow::Result<Arc<dyn HealthStatusQuerier>> {
         let file = self.node_health_file(network)?;
 
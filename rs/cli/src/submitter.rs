@@ -1,5 +1,6 @@
 use clap::Args as ClapArgs;
-use log::warn;
+use ic_nns_governance::pb::v1::Topic;
+use log::{info, warn};
 
 use crate::{
     confirm::{ConfirmationModeOptions, HowToProceed},
@@ -56,37 +57,62 @@ impl Submitter {
         if let HowToProceed::DryRun = self.mode {
             Ok(None)
         } else {
-            let forum_post = ForumContext::from(&self.forum_parameters).client()?.forum_post(kind).await?;
-            let res = execution.submit(forum_post.url()).await;
-            match res {
-                Ok(res) => {
-                    match forum_post.add_proposal_url(res.clone().into()).await {
-                        Ok(_) => (),
-                        Err(e) => {
-                            if let Some(forum_post_url) = forum_post.url() {
-                                warn!("Failed to add the proposal URL to forum post {}: {}", forum_post_url, e)
-                            } else {
-                                warn!("Failed to add the proposal URL to forum post: {}", e)
-                            }
+            self.submit(execution.as_ref(), kind).await
+        }
+    }
+
+    /// Creates the forum post for a proposal and submits it, without simulating it or asking
+    /// for confirmation.  Callers are expected to have done both already.
+    ///
+    /// Returns None if the proposal was written to a bundle for offline signing instead.
+    pub async fn submit(&self, execution: &dyn ProposalExecution, kind: ForumPostKind) -> anyhow::Result<Option<ProposalResponseWithId>> {
+        let forum_post = ForumContext::from(&self.forum_parameters).client()?.forum_post(kind).await?;
+        if let Some(path) = execution.prepare_bundle(forum_post.url())? {
+            info!(
+                "Proposal bundle written to {}. Sign it with `dre sign-bundle` and submit it with `dre submit-bundle`.",
+                path.display()
+            );
+            return Ok(None);
+        }
+        let res = execution.submit(forum_post.url()).await;
+        match res {
+            Ok(res) => {
+                match forum_post.add_proposal_url(res.clone().into()).await {
+                    Ok(_) => (),
+                    Err(e) => {
+                        if let Some(forum_post_url) = forum_post.url() {
+                            warn!("Failed to add the proposal URL to forum post {}: {}", forum_post_url, e)
+                        } else {
+                            warn!("Failed to add the proposal URL to forum post: {}", e)
                         }
-                    };
-                    Ok(Some(res))
-                }
-                Err(e) => {
-                    if let Some(forum_post_url) = forum_post.url() {
-                        // Here we would ask the forum post code to delete the post since
-                        // the submission has failed... that is, if we had that feature.
-                        warn!(
-                            "Forum post {} may have been created for this proposal, but proposal submission failed.  Please delete the forum post if necessary, as it now serves no purpose.",
-                            forum_post_url
-                        );
-                    };
-                    Err(e)
-                }
+                    }
+                };
+                Ok(Some(res))
+            }
+            Err(e) => {
+                if let Some(forum_post_url) = forum_post.url() {
+                    // Here we would ask the forum post code to delete the post since
+                    // the submission has failed... that is, if we had that feature.
+                    warn!(
+                        "Forum post {} may have been created for this proposal, but proposal submission failed.  Please delete the forum post if necessary, as it now serves no purpose.",
+                        forum_post_url
+                    );
+                };
+                Err(e)
             }
         }
     }
 
+    /// Creates a forum topic grouping several proposals, which are then posted in it with
+    /// [ForumPostKind::PostInTopic].  Returns the topic URL, if any.
+    pub async fn create_forum_topic(&self, title: String, body: String, topics: Vec<Topic>) -> anyhow::Result<Option<url::Url>> {
+        let topic = ForumContext::from(&self.forum_parameters)
+            .client()?
+            .forum_post(ForumPostKind::Topic { title, body, topics })
+            .await?;
+        Ok(topic.url())
+    }
+
     /// Submits a proposal (maybe in dry-run mode) with confirmation from the user, by calling
     /// Self.propose().
     ///
@@ -6,7 +6,8 @@ use anyhow::Ok;
 use anyhow::anyhow;
 use decentralization::{
     SubnetChangeResponse,
-    network::{DecentralizedSubnet, SubnetQueryBy},
+    business_rules::BusinessRules,
+    network::{DecentralizedSubnet, SelectionStrategy, SubnetQueryBy},
 };
 use futures::TryFutureExt;
 use ic_management_backend::health::HealthStatusQuerier;
@@ -45,6 +46,8 @@ pub struct SubnetManager {
     registry_instance: Arc<dyn LazyRegistry>,
     cordoned_features_fetcher: Arc<dyn CordonedFeatureFetcher>,
     health_client: Arc<dyn HealthStatusQuerier>,
+    selection_strategy: SelectionStrategy,
+    business_rules: BusinessRules,
 }
 
 impl SubnetManager {
@@ -58,6 +61,8 @@ impl SubnetManager {
             registry_instance,
             cordoned_features_fetcher,
             health_client,
+            selection_strategy: SelectionStrategy::default(),
+            business_rules: BusinessRules::default(),
         }
     }
 
@@ -68,6 +73,14 @@ impl SubnetManager {
         }
     }
 
+    pub fn with_selection_strategy(self, selection_strategy: SelectionStrategy) -> Self {
+        Self { selection_strategy, ..self }
+    }
+
+    pub fn with_business_rules(self, business_rules: BusinessRules) -> Self {
+        Self { business_rules, ..self }
+    }
+
     fn target(&self) -> anyhow::Result<SubnetTarget> {
         self.subnet_target
             .clone()
@@ -174,9 +187,11 @@ impl SubnetManager {
             .registry_instance
             .modify_subnet_nodes(subnet_query_by.clone())
             .await?
+            .with_business_rules(self.business_rules.clone())
             .excluding_from_available(exclude.clone().unwrap_or_default())
             .adding_from_available(only.clone())
-            .adding_from_available(include.clone().unwrap_or_default());
+            .adding_from_available(include.clone().unwrap_or_default())
+            .with_selection_strategy(self.selection_strategy);
 
         let mut node_ids_unhealthy = HashSet::new();
         if heal {
@@ -241,9 +256,11 @@ impl SubnetManager {
         let change = registry
             .modify_subnet_nodes(SubnetQueryBy::SubnetId(request.subnet))
             .await?
+            .with_business_rules(self.business_rules.clone())
             .excluding_from_available(request.exclude.clone().unwrap_or_default())
             .adding_from_available(request.only.clone().unwrap_or_default())
             .adding_from_available(request.add_nodes.clone().unwrap_or_default())
+            .with_selection_strategy(self.selection_strategy)
             .resize(
                 request.add,
                 request.remove,
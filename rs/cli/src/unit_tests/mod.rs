@@ -5,6 +5,7 @@ mod cordoned_feature_fetcher;
 mod add_nodes;
 mod args_parse;
 mod health_client;
+mod native_proposals;
 mod node_labels;
 mod registry_versions;
 mod replace;
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use crate::artifact_downloader::MockArtifactDownloader;
+use crate::auth::Neuron;
+use crate::cordoned_feature_fetcher::MockCordonedFeatureFetcher;
+use crate::ctx::tests::get_mocked_ctx;
+use crate::ic_admin::{IcAdminProposal, IcAdminProposalCommand, IcAdminProposalOptions, MockIcAdmin};
+use ic_management_backend::health::MockHealthStatusQuerier;
+use ic_management_backend::{lazy_git::MockLazyGit, lazy_registry::MockLazyRegistry, proposal::MockProposalAgent};
+use ic_management_types::Network;
+use ic_types::PrincipalId;
+
+#[tokio::test]
+async fn subnet_creation_fails_without_ic_admin() {
+    let mut ic_admin = MockIcAdmin::new();
+    // Subnet creation must not silently fall back to ic-admin
+    ic_admin.expect_submit_proposal().never();
+    ic_admin.expect_simulate_proposal().never();
+
+    let ctx = get_mocked_ctx(
+        Network::mainnet_unchecked().unwrap(),
+        Neuron::anonymous_neuron(),
+        Arc::new(MockLazyRegistry::new()),
+        Arc::new(ic_admin),
+        Arc::new(MockLazyGit::new()),
+        Arc::new(MockProposalAgent::new()),
+        Arc::new(MockArtifactDownloader::new()),
+        Arc::new(MockCordonedFeatureFetcher::new()),
+        Arc::new(MockHealthStatusQuerier::new()),
+    )
+    .with_native_proposals();
+
+    let proposal = IcAdminProposal::new(
+        IcAdminProposalCommand::CreateSubnet {
+            node_ids: vec![PrincipalId::new_node_test_id(1)],
+            replica_version: "abc".to_string(),
+            other_args: vec![],
+        },
+        IcAdminProposalOptions::default(),
+    );
+
+    let Err(err) = ctx.proposal_execution(proposal).await else {
+        panic!("Expected the subnet creation to fail without ic-admin");
+    };
+    assert!(err.to_string().contains("--use-ic-admin"), "{}", err);
+}
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use decentralization::{
     SubnetChangeResponse,
+    business_rules::BusinessRules,
     network::{CordonedFeature, DecentralizedSubnet},
 };
 use ic_management_backend::{health::MockHealthStatusQuerier, lazy_registry::MockLazyRegistry};
@@ -51,6 +52,7 @@ fn subnet(id: u64, nodes: &[Node]) -> DecentralizedSubnet {
         removed_nodes: vec![],
         comment: None,
         run_log: vec![],
+        business_rules: BusinessRules::default(),
     }
 }
 
@@ -0,0 +1,388 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Datelike, Timelike, Utc, Weekday};
+use ic_nns_governance::pb::v1::{NnsFunction, Topic};
+use ic_nns_governance_api::{ProposalInfo, proposal::Action};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+/// What `dre vote` does with a proposal matched by a rule.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum VoteAction {
+    Yes,
+    No,
+    /// Do not vote, send a critical desktop notification instead
+    AbstainAndAlert,
+    /// Do not vote, alert and queue the proposal for a human to vote on
+    Manual,
+}
+
+/// Checks on the payload of a proposal, which require decoding it and looking at the registry.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PayloadCondition {
+    /// Deploys a GuestOS or HostOS version that is elected in the registry
+    ElectedVersion,
+    /// Changes the membership of a subnet without reducing its Nakamoto score
+    NakamotoNotReduced,
+}
+
+/// Hours of the day, in UTC, on which a rule applies. The end hour is exclusive.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TimeWindow {
+    /// Days of the week, all days if empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl TimeWindow {
+    fn contains(&self, at: DateTime<Utc>) -> bool {
+        (self.days.is_empty() || self.days.contains(&at.weekday())) && (self.start_hour..self.end_hour).contains(&at.hour())
+    }
+}
+
+/// A rule matches a proposal if all of its conditions hold. Empty lists match everything.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct VotingRule {
+    pub name: String,
+    /// Neuron IDs of the proposers
+    #[serde(default)]
+    pub proposers: Vec<u64>,
+    /// Topic names, for example `IcOsVersionDeployment`
+    #[serde(default)]
+    pub topics: Vec<String>,
+    /// NNS function names, for example `DeployGuestosToAllSubnetNodes`
+    #[serde(default)]
+    pub nns_functions: Vec<String>,
+    #[serde(default)]
+    pub conditions: Vec<PayloadCondition>,
+    #[serde(default)]
+    pub time_window: Option<TimeWindow>,
+    pub action: VoteAction,
+}
+
+impl VotingRule {
+    /// Whether the rule matches the proposal, regardless of its time window.
+    fn matches(&self, proposal: &ProposalFacts) -> bool {
+        (self.proposers.is_empty() || proposal.proposer.is_some_and(|p| self.proposers.contains(&p)))
+            && (self.topics.is_empty() || self.topics.contains(&proposal.topic))
+            && (self.nns_functions.is_empty() || proposal.nns_function.as_ref().is_some_and(|f| self.nns_functions.contains(f)))
+            && self.conditions.iter().all(|c| proposal.conditions.get(c).copied().unwrap_or(false))
+    }
+}
+
+/// Result of evaluating the voting rules on a proposal at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Evaluation<'a> {
+    /// The rule decides the vote now
+    Decided(&'a VotingRule),
+    /// The first matching rule has a time window that is closed now, later rules are not evaluated
+    /// and the proposal waits for the window to open
+    Deferred(&'a VotingRule),
+    /// No rule matches the proposal
+    NoMatch,
+}
+
+/// Ordered voting rules, the first rule matching a proposal decides the vote.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct VotingRules {
+    pub rules: Vec<VotingRule>,
+}
+
+impl VotingRules {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let rules: Self = serde_yaml::from_str(&fs_err::read_to_string(path)?)
+            .map_err(|e| anyhow::anyhow!("Failed to parse voting rules {}: {}", path.display(), e))?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    /// The rule `dre vote` applies without a rules file: vote yes on the given topics from the given proposers.
+    pub fn accepting(proposers: &[u64], topics: &[i32]) -> anyhow::Result<Self> {
+        Ok(Self {
+            rules: vec![VotingRule {
+                name: "accepted-neurons-and-topics".to_string(),
+                proposers: proposers.to_vec(),
+                topics: topics
+                    .iter()
+                    .map(|t| Topic::try_from(*t).map(topic_name).map_err(|_| anyhow::anyhow!("Unknown topic {}", t)))
+                    .collect::<anyhow::Result<_>>()?,
+                nns_functions: vec![],
+                conditions: vec![],
+                time_window: None,
+                action: VoteAction::Yes,
+            }],
+        })
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.rules.is_empty() {
+            anyhow::bail!("The voting rules file has no rules");
+        }
+        if let Some(name) = self.rules.iter().map(|r| &r.name).duplicates().next() {
+            anyhow::bail!("Voting rule `{}` is defined more than once", name);
+        }
+        let topics = (0..100).filter_map(|t| Topic::try_from(t).ok()).map(topic_name).collect_vec();
+        let nns_functions = (0..200)
+            .filter_map(|f| NnsFunction::try_from(f).ok())
+            .map(nns_function_name)
+            .collect_vec();
+        for rule in &self.rules {
+            if let Some(topic) = rule.topics.iter().find(|t| !topics.contains(t)) {
+                anyhow::bail!(
+                    "Voting rule `{}` has unknown topic `{}`. Known topics: {}",
+                    rule.name,
+                    topic,
+                    topics.join(", ")
+                );
+            }
+            if let Some(function) = rule.nns_functions.iter().find(|f| !nns_functions.contains(f)) {
+                anyhow::bail!("Voting rule `{}` has unknown NNS function `{}`", rule.name, function);
+            }
+            if let Some(window) = rule.time_window.as_ref().filter(|w| w.start_hour >= w.end_hour || w.end_hour > 24) {
+                anyhow::bail!(
+                    "Voting rule `{}` has an invalid time window {}-{}",
+                    rule.name,
+                    window.start_hour,
+                    window.end_hour
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Payload conditions used by any rule, the only ones that have to be evaluated.
+    pub fn conditions(&self) -> BTreeSet<PayloadCondition> {
+        self.rules.iter().flat_map(|r| r.conditions.iter().copied()).collect()
+    }
+
+    pub fn evaluate(&self, proposal: &ProposalFacts, at: DateTime<Utc>) -> Evaluation<'_> {
+        match self.rules.iter().find(|r| r.matches(proposal)) {
+            None => Evaluation::NoMatch,
+            Some(rule) if rule.time_window.as_ref().is_none_or(|w| w.contains(at)) => Evaluation::Decided(rule),
+            Some(rule) => Evaluation::Deferred(rule),
+        }
+    }
+}
+
+fn topic_name(topic: Topic) -> String {
+    format!("{:?}", topic)
+}
+
+fn nns_function_name(function: NnsFunction) -> String {
+    format!("{:?}", function)
+}
+
+/// What the voting rules look at in a proposal.
+#[derive(Clone, Debug, Default)]
+pub struct ProposalFacts {
+    pub proposal_id: u64,
+    pub title: String,
+    pub proposer: Option<u64>,
+    pub topic: String,
+    pub nns_function: Option<String>,
+    /// Results of the evaluated payload conditions, a missing condition does not hold
+    pub conditions: BTreeMap<PayloadCondition, bool>,
+}
+
+impl ProposalFacts {
+    pub fn new(proposal: &ProposalInfo) -> Self {
+        let body = proposal.proposal.as_ref();
+        Self {
+            proposal_id: proposal.id.map(|id| id.id).unwrap_or_default(),
+            title: body.and_then(|p| p.title.clone()).unwrap_or_default(),
+            proposer: proposal.proposer.map(|p| p.id),
+            topic: Topic::try_from(proposal.topic)
+                .map(topic_name)
+                .unwrap_or_else(|_| proposal.topic.to_string()),
+            nns_function: match body.and_then(|p| p.action.as_ref()) {
+                Some(Action::ExecuteNnsFunction(f)) => Some(
+                    NnsFunction::try_from(f.nns_function)
+                        .map(nns_function_name)
+                        .unwrap_or_else(|_| f.nns_function.to_string()),
+                ),
+                _ => None,
+            },
+            conditions: BTreeMap::new(),
+        }
+    }
+}
+
+/// A vote decided by the rules, recorded in the vote log.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct VoteDecision {
+    pub decided_at: DateTime<Utc>,
+    pub proposal_id: u64,
+    pub title: String,
+    pub proposer: Option<u64>,
+    pub topic: String,
+    pub nns_function: Option<String>,
+    pub rule: String,
+    pub action: VoteAction,
+    pub dry_run: bool,
+    /// Response of the governance canister, or why no vote was cast
+    pub result: Option<String>,
+}
+
+impl VoteDecision {
+    pub fn new(proposal: &ProposalFacts, rule: &VotingRule, dry_run: bool) -> Self {
+        Self {
+            decided_at: Utc::now(),
+            proposal_id: proposal.proposal_id,
+            title: proposal.title.clone(),
+            proposer: proposal.proposer,
+            topic: proposal.topic.clone(),
+            nns_function: proposal.nns_function.clone(),
+            rule: rule.name.clone(),
+            action: rule.action,
+            dry_run,
+            result: None,
+        }
+    }
+}
+
+/// Append-only log of the votes decided by `dre vote`, one JSON record per line.
+pub struct VoteLog {
+    path: PathBuf,
+}
+
+impl VoteLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, decision: &VoteDecision) -> anyhow::Result<()> {
+        let line = format!("{}\n", serde_json::to_string(decision)?);
+        let mut file = fs_err::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const RULES: &str = r#"
+rules:
+  - name: nakamoto-preserving-replacements
+    proposers: [40]
+    nns_functions: [ChangeSubnetMembership]
+    conditions: [nakamoto_not_reduced]
+    action: yes
+  - name: other-replacements
+    nns_functions: [ChangeSubnetMembership]
+    action: manual
+  - name: guestos-in-office-hours
+    topics: [IcOsVersionDeployment]
+    conditions: [elected_version]
+    time_window: { days: [Mon, Tue, Wed, Thu, Fri], start_hour: 8, end_hour: 18 }
+    action: yes
+  - name: guestos-otherwise
+    topics: [IcOsVersionDeployment]
+    action: abstain_and_alert
+"#;
+
+    fn facts(proposer: u64, topic: Topic, nns_function: NnsFunction, conditions: &[(PayloadCondition, bool)]) -> ProposalFacts {
+        ProposalFacts {
+            proposer: Some(proposer),
+            topic: topic_name(topic),
+            nns_function: Some(nns_function_name(nns_function)),
+            conditions: conditions.iter().cloned().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_decides() {
+        let rules: VotingRules = serde_yaml::from_str(RULES).unwrap();
+        rules.validate().unwrap();
+        assert_eq!(
+            rules.conditions().into_iter().collect_vec(),
+            vec![PayloadCondition::ElectedVersion, PayloadCondition::NakamotoNotReduced]
+        );
+
+        let tuesday_noon = Utc.with_ymd_and_hms(2025, 6, 3, 12, 0, 0).unwrap();
+        let saturday_noon = Utc.with_ymd_and_hms(2025, 6, 7, 12, 0, 0).unwrap();
+        let decide = |facts: &ProposalFacts, at| match rules.evaluate(facts, at) {
+            Evaluation::Decided(rule) => Some(rule.name.as_str()),
+            _ => None,
+        };
+
+        let replacement = |not_reduced| {
+            facts(
+                40,
+                Topic::SubnetManagement,
+                NnsFunction::ChangeSubnetMembership,
+                &[(PayloadCondition::NakamotoNotReduced, not_reduced)],
+            )
+        };
+        assert_eq!(decide(&replacement(true), tuesday_noon), Some("nakamoto-preserving-replacements"));
+        assert_eq!(decide(&replacement(false), tuesday_noon), Some("other-replacements"));
+
+        let upgrade = facts(
+            80,
+            Topic::IcOsVersionDeployment,
+            NnsFunction::DeployGuestosToAllSubnetNodes,
+            &[(PayloadCondition::ElectedVersion, true)],
+        );
+        assert_eq!(decide(&upgrade, tuesday_noon), Some("guestos-in-office-hours"));
+        // Outside of office hours the upgrade waits for the window instead of falling through to the next rule
+        assert!(matches!(
+            rules.evaluate(&upgrade, saturday_noon),
+            Evaluation::Deferred(rule) if rule.name == "guestos-in-office-hours"
+        ));
+        let unelected = facts(
+            80,
+            Topic::IcOsVersionDeployment,
+            NnsFunction::DeployGuestosToAllSubnetNodes,
+            &[(PayloadCondition::ElectedVersion, false)],
+        );
+        assert_eq!(decide(&unelected, saturday_noon), Some("guestos-otherwise"));
+
+        let motion = facts(80, Topic::Governance, NnsFunction::Unspecified, &[]);
+        assert_eq!(rules.evaluate(&motion, tuesday_noon), Evaluation::NoMatch);
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        let unknown_topic = VotingRules {
+            rules: vec![VotingRule {
+                topics: vec!["NoSuchTopic".to_string()],
+                ..VotingRules::accepting(&[40], &[12]).unwrap().rules[0].clone()
+            }],
+        };
+        assert!(unknown_topic.validate().is_err());
+
+        let accepting = VotingRules::accepting(&[40], &[12]).unwrap();
+        assert!(accepting.validate().is_ok());
+        assert_eq!(
+            VotingRules {
+                rules: [accepting.rules.clone(), accepting.rules].concat()
+            }
+            .validate()
+            .unwrap_err()
+            .to_string(),
+            "Voting rule `accepted-neurons-and-topics` is defined more than once"
+        );
+    }
+}
@@ -0,0 +1,587 @@
+use crate::provider_clusters::ProviderClusters;
+use ahash::{AHashMap, AHashSet};
+use ic_base_types::PrincipalId;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The only policy schema version understood by this release.
+pub const BUSINESS_RULES_POLICY_VERSION: u32 = 1;
+
+/// Subnet types that can be referenced from the `subnet_types` section of the policy.
+pub const KNOWN_SUBNET_TYPES: &[&str] = &["application", "system", "verified_application", "cloud_engine"];
+
+/// Principal of the NNS subnet, the same on every network.
+pub(crate) const NNS_SUBNET: &str = "tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe";
+const UZR34_SUBNET: &str = "uzr34-akd3s-xrdag-3ql62-ocgoh-ld2ao-tamcv-54e7j-krwgb-2gm4z-oqe";
+const X33ED_SUBNET: &str = "x33ed-h457x-bsgyx-oqxqf-6pzwv-wkhzr-rm2j3-npodi-purzm-n66cg-gae";
+const PZP6E_SUBNET: &str = "pzp6e-ekpqk-3c5x7-2h6so-njoeq-mt45d-h3h6c-q3mxf-vpeq5-fk5o7-yae";
+const EUROPEAN_SUBNET: &str = "bkfrj-6k62g-dycql-7h53p-atvkj-zg4to-gaogh-netha-ptybj-ntsgw-rqe";
+const DFINITY_NODE_PROVIDER: &str = "bvcsg-3od6r-jnydw-eysln-aql7w-td5zn-ay5m6-sibd2-jzojt-anwag-mqe";
+
+// EU member states. The registry does not mark them, so they are listed here.
+const EU_COUNTRIES: &[&str] = &[
+    "AT", "BE", "BG", "CY", "CZ", "DE", "DK", "EE", "ES", "FI", "FR", "GR", "HR", "HU", "IE", "IT", "LT", "LU", "LV", "MT", "NL", "PL", "PT", "RO",
+    "SE", "SI", "SK",
+];
+// Countries accepted in the European subnet, besides the EU member states.
+const OTHER_EUROPEAN_COUNTRIES: &[&str] = &["CH", "UK"];
+
+/// Declarative description of the "business rules" that every subnet is checked against.
+///
+/// The policy is versioned and kept in `business_rules.yaml` at the root of the repository,
+/// so that the target topology can be adjusted without releasing a new `dre`.
+/// The `Default` implementation is used whenever no policy could be loaded. It is the same as that
+/// file, which a test checks, and both keep the rules that were hardcoded before the policy existed.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BusinessRulesPolicy {
+    pub version: u32,
+    /// Node provider which operates the DFINITY-owned nodes
+    pub dfinity_node_provider: String,
+    #[serde(default)]
+    pub penalties: PenaltyWeights,
+    /// Rules applied to every subnet, unless overridden
+    pub defaults: SubnetRules,
+    /// Overrides per subnet type, e.g. `system` or `application`
+    #[serde(default)]
+    pub subnet_types: IndexMap<String, SubnetRules>,
+    /// Overrides per subnet id, these take precedence over the subnet type overrides
+    #[serde(default)]
+    pub subnets: IndexMap<String, SubnetRules>,
+    /// Subnet id -> subnet type, filled in from the registry at runtime
+    #[serde(skip)]
+    subnet_type_of: AHashMap<PrincipalId, String>,
+}
+
+/// Weights of the individual penalties. Each weight is multiplied by the size of the violation.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct PenaltyWeights {
+    pub dfinity_owned_nodes: usize,
+    pub country_above_one_third: usize,
+    pub nodes_per_provider_or_data_center: usize,
+    pub nodes_per_country: usize,
+    pub country_not_allowed: usize,
+    pub single_node_provider_can_halt: usize,
+    pub feature_above_two_thirds: usize,
+    pub linked_providers: usize,
+    pub nodes_per_feature: usize,
+}
+
+impl Default for PenaltyWeights {
+    fn default() -> Self {
+        Self {
+            dfinity_owned_nodes: 1000,
+            country_above_one_third: 1000,
+            nodes_per_provider_or_data_center: 10,
+            nodes_per_country: 10,
+            country_not_allowed: 1000,
+            single_node_provider_can_halt: 10000,
+            feature_above_two_thirds: 1000,
+            linked_providers: 10,
+            nodes_per_feature: 10,
+        }
+    }
+}
+
+/// Limits for a subnet. Every field is optional so that overrides only need to list what they change.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SubnetRules {
+    /// Human readable name, used in the business rules messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dfinity_owned_nodes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_nodes_per_node_provider: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_nodes_per_data_center: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_nodes_per_data_center_owner: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_nodes_per_country: Option<usize>,
+    /// No single country may control 1/3 or more of the subnet nodes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country_share_below_one_third: Option<bool>,
+    /// Only nodes from these countries may join the subnet. Nodes from other countries are penalized
+    /// per node instead of through `max_nodes_per_country`, and the 2/3 country control check is skipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_countries: Option<Vec<String>>,
+    /// Only nodes from these countries are limited by `max_nodes_per_country`. All countries are limited if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_country_cap_countries: Option<Vec<String>>,
+    /// DFINITY nodes may exceed the per node provider / data center limits, up to `dfinity_owned_nodes`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dfinity_exempt_from_provider_limits: Option<bool>,
+    /// Limits per value of any node feature, including the custom features from the node features file,
+    /// e.g. `asn: 2`. Overrides are merged per feature.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub max_nodes_per_feature: IndexMap<String, usize>,
+}
+
+impl SubnetRules {
+    fn merged_with(&self, other: &SubnetRules) -> SubnetRules {
+        SubnetRules {
+            name: other.name.clone().or(self.name.clone()),
+            dfinity_owned_nodes: other.dfinity_owned_nodes.or(self.dfinity_owned_nodes),
+            max_nodes_per_node_provider: other.max_nodes_per_node_provider.or(self.max_nodes_per_node_provider),
+            max_nodes_per_data_center: other.max_nodes_per_data_center.or(self.max_nodes_per_data_center),
+            max_nodes_per_data_center_owner: other.max_nodes_per_data_center_owner.or(self.max_nodes_per_data_center_owner),
+            max_nodes_per_country: other.max_nodes_per_country.or(self.max_nodes_per_country),
+            country_share_below_one_third: other.country_share_below_one_third.or(self.country_share_below_one_third),
+            allowed_countries: other.allowed_countries.clone().or(self.allowed_countries.clone()),
+            per_country_cap_countries: other.per_country_cap_countries.clone().or(self.per_country_cap_countries.clone()),
+            dfinity_exempt_from_provider_limits: other.dfinity_exempt_from_provider_limits.or(self.dfinity_exempt_from_provider_limits),
+            max_nodes_per_feature: self
+                .max_nodes_per_feature
+                .iter()
+                .chain(other.max_nodes_per_feature.iter())
+                .map(|(feature, max_nodes)| (feature.clone(), *max_nodes))
+                .collect(),
+        }
+    }
+}
+
+/// The rules that apply to one particular subnet, after all overrides have been applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedSubnetRules {
+    pub name: Option<String>,
+    pub dfinity_owned_nodes: usize,
+    pub max_nodes_per_node_provider: usize,
+    pub max_nodes_per_data_center: usize,
+    pub max_nodes_per_data_center_owner: usize,
+    pub max_nodes_per_country: usize,
+    pub country_share_below_one_third: bool,
+    pub allowed_countries: Option<Vec<String>>,
+    pub per_country_cap_countries: Option<Vec<String>>,
+    pub dfinity_exempt_from_provider_limits: bool,
+    pub max_nodes_per_feature: IndexMap<String, usize>,
+}
+
+impl ResolvedSubnetRules {
+    pub fn is_country_allowed(&self, country: &str) -> bool {
+        self.allowed_countries
+            .as_ref()
+            .map(|allowed| allowed.iter().any(|c| c == country))
+            .unwrap_or(true)
+    }
+
+    /// True if `max_nodes_per_country` applies to the nodes of the country.
+    pub fn is_country_capped(&self, country: &str) -> bool {
+        self.per_country_cap_countries
+            .as_ref()
+            .map(|capped| capped.iter().any(|c| c == country))
+            .unwrap_or(true)
+    }
+}
+
+impl Default for BusinessRulesPolicy {
+    fn default() -> Self {
+        let country_one_third = |name: Option<&str>| SubnetRules {
+            name: name.map(String::from),
+            max_nodes_per_country: Some(3),
+            country_share_below_one_third: Some(true),
+            ..Default::default()
+        };
+        Self {
+            version: BUSINESS_RULES_POLICY_VERSION,
+            dfinity_node_provider: DFINITY_NODE_PROVIDER.to_string(),
+            penalties: PenaltyWeights::default(),
+            // As per the adopted target topology
+            // https://dashboard.internetcomputer.org/proposal/132136
+            defaults: SubnetRules {
+                name: None,
+                dfinity_owned_nodes: Some(1),
+                max_nodes_per_node_provider: Some(1),
+                max_nodes_per_data_center: Some(1),
+                max_nodes_per_data_center_owner: Some(1),
+                max_nodes_per_country: Some(2),
+                country_share_below_one_third: Some(false),
+                allowed_countries: None,
+                per_country_cap_countries: None,
+                dfinity_exempt_from_provider_limits: Some(false),
+                max_nodes_per_feature: IndexMap::new(),
+            },
+            subnet_types: IndexMap::new(),
+            subnets: IndexMap::from_iter([
+                (
+                    NNS_SUBNET.to_string(),
+                    SubnetRules {
+                        dfinity_owned_nodes: Some(3),
+                        // https://dashboard.internetcomputer.org/proposal/135700
+                        dfinity_exempt_from_provider_limits: Some(true),
+                        ..country_one_third(Some("NNS"))
+                    },
+                ),
+                (UZR34_SUBNET.to_string(), country_one_third(Some("tECDSA backup"))),
+                (X33ED_SUBNET.to_string(), country_one_third(Some("SNS"))),
+                (
+                    PZP6E_SUBNET.to_string(),
+                    SubnetRules {
+                        name: Some("Fiduciary".to_string()),
+                        max_nodes_per_country: Some(3),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    EUROPEAN_SUBNET.to_string(),
+                    SubnetRules {
+                        name: Some("European".to_string()),
+                        allowed_countries: Some(EU_COUNTRIES.iter().chain(OTHER_EUROPEAN_COUNTRIES).map(|c| c.to_string()).collect()),
+                        per_country_cap_countries: Some(EU_COUNTRIES.iter().map(|c| c.to_string()).collect()),
+                        ..Default::default()
+                    },
+                ),
+            ]),
+            subnet_type_of: AHashMap::new(),
+        }
+    }
+}
+
+impl BusinessRulesPolicy {
+    pub fn from_yaml(contents: &[u8]) -> anyhow::Result<Self> {
+        let policy: Self = serde_yaml::from_slice(contents).map_err(|e| anyhow::anyhow!("Failed to parse business rules policy: {}", e))?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    pub fn from_json(contents: &[u8]) -> anyhow::Result<Self> {
+        let policy: Self = serde_json::from_slice(contents).map_err(|e| anyhow::anyhow!("Failed to parse business rules policy: {}", e))?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Parses the policy as JSON if the contents look like JSON, otherwise as YAML.
+    pub fn parse(contents: &[u8]) -> anyhow::Result<Self> {
+        match contents.iter().find(|c| !c.is_ascii_whitespace()) {
+            Some(b'{') => Self::from_json(contents),
+            _ => Self::from_yaml(contents),
+        }
+    }
+
+    pub fn to_yaml(&self) -> anyhow::Result<String> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    /// Checks the policy for mistakes which the deserialization alone cannot catch.
+    /// All problems are collected and reported at once.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut errors = vec![];
+        if self.version != BUSINESS_RULES_POLICY_VERSION {
+            errors.push(format!(
+                "Unsupported policy version {}, expected {}",
+                self.version, BUSINESS_RULES_POLICY_VERSION
+            ));
+        }
+        if PrincipalId::from_str(&self.dfinity_node_provider).is_err() {
+            errors.push(format!(
+                "`dfinity_node_provider` is not a valid principal: {}",
+                self.dfinity_node_provider
+            ));
+        }
+        for (field, missing) in [
+            ("dfinity_owned_nodes", self.defaults.dfinity_owned_nodes.is_none()),
+            ("max_nodes_per_node_provider", self.defaults.max_nodes_per_node_provider.is_none()),
+            ("max_nodes_per_data_center", self.defaults.max_nodes_per_data_center.is_none()),
+            ("max_nodes_per_data_center_owner", self.defaults.max_nodes_per_data_center_owner.is_none()),
+            ("max_nodes_per_country", self.defaults.max_nodes_per_country.is_none()),
+        ] {
+            if missing {
+                errors.push(format!("`defaults.{}` must be set", field));
+            }
+        }
+        errors.extend(Self::validate_rules("defaults", &self.defaults));
+        for (subnet_type, rules) in &self.subnet_types {
+            if !KNOWN_SUBNET_TYPES.contains(&subnet_type.as_str()) {
+                errors.push(format!(
+                    "Unknown subnet type `{}`, expected one of: [{}]",
+                    subnet_type,
+                    KNOWN_SUBNET_TYPES.iter().join(", ")
+                ));
+            }
+            errors.extend(Self::validate_rules(&format!("subnet_types.{}", subnet_type), rules));
+        }
+        for (subnet_id, rules) in &self.subnets {
+            if PrincipalId::from_str(subnet_id).is_err() {
+                errors.push(format!("`subnets.{}` is not a valid subnet id", subnet_id));
+            }
+            errors.extend(Self::validate_rules(&format!("subnets.{}", subnet_id), rules));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Invalid business rules policy:\n{}",
+                errors.iter().map(|e| format!("- {}", e)).join("\n")
+            ))
+        }
+    }
+
+    fn validate_rules(path: &str, rules: &SubnetRules) -> Vec<String> {
+        let mut errors = vec![];
+        for (field, value) in [
+            ("max_nodes_per_node_provider", rules.max_nodes_per_node_provider),
+            ("max_nodes_per_data_center", rules.max_nodes_per_data_center),
+            ("max_nodes_per_data_center_owner", rules.max_nodes_per_data_center_owner),
+            ("max_nodes_per_country", rules.max_nodes_per_country),
+        ] {
+            if value == Some(0) {
+                errors.push(format!("`{}.{}` must be at least 1", path, field));
+            }
+        }
+        for (feature, max_nodes) in &rules.max_nodes_per_feature {
+            if *max_nodes == 0 {
+                errors.push(format!("`{}.max_nodes_per_feature.{}` must be at least 1", path, feature));
+            }
+        }
+        for (field, countries) in [
+            ("allowed_countries", &rules.allowed_countries),
+            ("per_country_cap_countries", &rules.per_country_cap_countries),
+        ] {
+            for country in countries.iter().flatten() {
+                if country.len() != 2 || !country.chars().all(|c| c.is_ascii_uppercase()) {
+                    errors.push(format!(
+                        "`{}.{}` contains `{}`, expected a two-letter upper-case country code",
+                        path, field, country
+                    ));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Registers the subnet type of each subnet, so that the `subnet_types` overrides can be applied.
+    pub fn with_subnet_types<'a>(mut self, subnets: impl IntoIterator<Item = &'a ic_management_types::Subnet>) -> Self {
+        self.subnet_type_of = subnets
+            .into_iter()
+            .map(|s| (s.principal, subnet_type_to_policy_key(&format!("{:?}", s.subnet_type))))
+            .collect();
+        self
+    }
+
+    pub fn dfinity_node_provider(&self) -> Option<PrincipalId> {
+        PrincipalId::from_str(&self.dfinity_node_provider).ok()
+    }
+
+    /// Resolves the rules for the subnet: `defaults`, then `subnet_types`, then `subnets`.
+    pub fn rules_for_subnet(&self, subnet_id: &PrincipalId) -> ResolvedSubnetRules {
+        let mut rules = self.defaults.clone();
+        if let Some(type_rules) = self.subnet_type_of.get(subnet_id).and_then(|t| self.subnet_types.get(t)) {
+            rules = rules.merged_with(type_rules);
+        }
+        if let Some(subnet_rules) = self.subnets.get(&subnet_id.to_string()) {
+            rules = rules.merged_with(subnet_rules);
+        }
+        ResolvedSubnetRules {
+            name: rules.name,
+            dfinity_owned_nodes: rules.dfinity_owned_nodes.unwrap_or(1),
+            max_nodes_per_node_provider: rules.max_nodes_per_node_provider.unwrap_or(1),
+            max_nodes_per_data_center: rules.max_nodes_per_data_center.unwrap_or(1),
+            max_nodes_per_data_center_owner: rules.max_nodes_per_data_center_owner.unwrap_or(1),
+            max_nodes_per_country: rules.max_nodes_per_country.unwrap_or(2),
+            country_share_below_one_third: rules.country_share_below_one_third.unwrap_or_default(),
+            allowed_countries: rules.allowed_countries,
+            per_country_cap_countries: rules.per_country_cap_countries,
+            dfinity_exempt_from_provider_limits: rules.dfinity_exempt_from_provider_limits.unwrap_or_default(),
+            max_nodes_per_feature: rules.max_nodes_per_feature,
+        }
+    }
+}
+
+// `SubnetType` debug names are CamelCase, e.g. `VerifiedApplication`, the policy uses snake_case.
+fn subnet_type_to_policy_key(debug_name: &str) -> String {
+    let mut key = String::new();
+    for (i, c) in debug_name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                key.push('_');
+            }
+            key.push(c.to_ascii_lowercase());
+        } else {
+            key.push(c);
+        }
+    }
+    key
+}
+
+/// The business rules policy, and the provider clusters, that the subnets are checked against.
+///
+/// Cheap to clone, so that every `DecentralizedSubnet` and `SubnetChange` can carry the rules it was built with.
+#[derive(Clone, PartialEq, Eq)]
+pub struct BusinessRules {
+    policy: Arc<BusinessRulesPolicy>,
+    provider_clusters: Arc<ProviderClusters>,
+    // Parsed once here, since the business rules look them up for every candidate subnet
+    linked_providers: Arc<AHashMap<String, AHashSet<PrincipalId>>>,
+}
+
+impl BusinessRules {
+    pub fn new(policy: BusinessRulesPolicy, provider_clusters: ProviderClusters) -> Self {
+        Self {
+            policy: Arc::new(policy),
+            linked_providers: Arc::new(provider_clusters.linked_providers()),
+            provider_clusters: Arc::new(provider_clusters),
+        }
+    }
+
+    pub fn policy(&self) -> &BusinessRulesPolicy {
+        &self.policy
+    }
+
+    pub fn provider_clusters(&self) -> &ProviderClusters {
+        &self.provider_clusters
+    }
+
+    /// Providers of the clusters, keyed by the cluster name.
+    pub fn linked_providers(&self) -> &AHashMap<String, AHashSet<PrincipalId>> {
+        &self.linked_providers
+    }
+}
+
+impl Default for BusinessRules {
+    fn default() -> Self {
+        Self::new(BusinessRulesPolicy::default(), ProviderClusters::default())
+    }
+}
+
+// The policy is long, and would be repeated in the debug output of every subnet.
+impl std::fmt::Debug for BusinessRules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BusinessRules")
+            .field("version", &self.policy.version)
+            .field("provider_clusters_version", &self.provider_clusters.version)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy_is_valid() {
+        BusinessRulesPolicy::default().validate().unwrap();
+    }
+
+    #[test]
+    fn repository_policy_matches_default() {
+        let policy = BusinessRulesPolicy::from_yaml(include_bytes!("../../../business_rules.yaml")).unwrap();
+        assert_eq!(policy, BusinessRulesPolicy::default());
+    }
+
+    #[test]
+    fn yaml_roundtrip() {
+        let policy = BusinessRulesPolicy::default();
+        let parsed = BusinessRulesPolicy::parse(policy.to_yaml().unwrap().as_bytes()).unwrap();
+        assert_eq!(parsed, policy);
+    }
+
+    #[test]
+    fn json_is_accepted() {
+        let json = serde_json::to_vec(&BusinessRulesPolicy::default()).unwrap();
+        assert_eq!(BusinessRulesPolicy::parse(&json).unwrap(), BusinessRulesPolicy::default());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let contents = br#"
+version: 1
+dfinity_node_provider: bvcsg-3od6r-jnydw-eysln-aql7w-td5zn-ay5m6-sibd2-jzojt-anwag-mqe
+defaults:
+  max_nodes_per_node_provder: 1
+"#;
+        assert!(BusinessRulesPolicy::from_yaml(contents).is_err());
+    }
+
+    #[test]
+    fn validation_reports_all_errors() {
+        let contents = br#"
+version: 2
+dfinity_node_provider: not-a-principal
+defaults:
+  dfinity_owned_nodes: 1
+  max_nodes_per_node_provider: 0
+  max_nodes_per_data_center: 1
+  max_nodes_per_data_center_owner: 1
+  max_nodes_per_country: 2
+subnet_types:
+  fancy: {}
+subnets:
+  not-a-subnet:
+    allowed_countries: [ch]
+"#;
+        let err = BusinessRulesPolicy::from_yaml(contents).unwrap_err().to_string();
+        for expected in [
+            "Unsupported policy version 2",
+            "`dfinity_node_provider` is not a valid principal",
+            "`defaults.max_nodes_per_node_provider` must be at least 1",
+            "Unknown subnet type `fancy`",
+            "`subnets.not-a-subnet` is not a valid subnet id",
+            "contains `ch`",
+        ] {
+            assert!(err.contains(expected), "`{}` not found in:\n{}", expected, err);
+        }
+    }
+
+    #[test]
+    fn overrides_are_applied_in_order() {
+        let subnet_id = PrincipalId::new_subnet_test_id(1);
+        let mut policy = BusinessRulesPolicy::default();
+        policy.subnet_types.insert(
+            "system".to_string(),
+            SubnetRules {
+                dfinity_owned_nodes: Some(2),
+                max_nodes_per_country: Some(4),
+                ..Default::default()
+            },
+        );
+        policy.subnets.insert(
+            subnet_id.to_string(),
+            SubnetRules {
+                max_nodes_per_country: Some(5),
+                ..Default::default()
+            },
+        );
+        policy.subnet_type_of.insert(subnet_id, "system".to_string());
+
+        let rules = policy.rules_for_subnet(&subnet_id);
+        assert_eq!(rules.dfinity_owned_nodes, 2);
+        assert_eq!(rules.max_nodes_per_country, 5);
+        assert_eq!(rules.max_nodes_per_node_provider, 1);
+
+        let other = policy.rules_for_subnet(&PrincipalId::new_subnet_test_id(2));
+        assert_eq!(other.dfinity_owned_nodes, 1);
+        assert_eq!(other.max_nodes_per_country, 2);
+    }
+
+    #[test]
+    fn feature_limits_are_merged_per_feature() {
+        let subnet_id = PrincipalId::new_subnet_test_id(1);
+        let mut policy = BusinessRulesPolicy::default();
+        policy.defaults.max_nodes_per_feature = IndexMap::from([("asn".to_string(), 2), ("jurisdiction_group".to_string(), 5)]);
+        policy.subnets.insert(
+            subnet_id.to_string(),
+            SubnetRules {
+                max_nodes_per_feature: IndexMap::from([("asn".to_string(), 1)]),
+                ..Default::default()
+            },
+        );
+
+        let rules = policy.rules_for_subnet(&subnet_id);
+        assert_eq!(
+            rules.max_nodes_per_feature,
+            IndexMap::from([("asn".to_string(), 1), ("jurisdiction_group".to_string(), 5)])
+        );
+    }
+
+    #[test]
+    fn subnet_type_keys() {
+        assert_eq!(subnet_type_to_policy_key("VerifiedApplication"), "verified_application");
+        assert_eq!(subnet_type_to_policy_key("System"), "system");
+    }
+}
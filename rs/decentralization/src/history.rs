@@ -0,0 +1,232 @@
+use crate::{business_rules::BusinessRules, nakamoto::NakamotoScore, network::DecentralizedSubnet};
+use ic_base_types::PrincipalId;
+use ic_management_types::{NodeFeature, Subnet};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// Decentralization metrics of a single subnet at a single registry version.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SubnetSnapshot {
+    pub registry_version: u64,
+    pub subnet_id: PrincipalId,
+    pub subnet_name: String,
+    pub node_ids: Vec<PrincipalId>,
+    pub nakamoto: IndexMap<NodeFeature, f64>,
+    pub nakamoto_avg_linear: f64,
+    pub penalty: usize,
+    pub penalty_reasons: Vec<String>,
+    pub feature_value_counts: IndexMap<NodeFeature, Vec<(String, usize)>>,
+    /// The proposal that changed the subnet membership at this registry version, if known.
+    pub proposal_id: Option<u64>,
+}
+
+impl SubnetSnapshot {
+    pub fn new(registry_version: u64, subnet: &Subnet, business_rules: &BusinessRules) -> anyhow::Result<Self> {
+        let score = NakamotoScore::new_from_nodes(&subnet.nodes);
+        let (penalty, penalty_reasons) =
+            DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&subnet.principal, &subnet.nodes, business_rules)?;
+        let features = score.features().cloned().collect::<Vec<_>>();
+
+        Ok(Self {
+            registry_version,
+            subnet_id: subnet.principal,
+            subnet_name: subnet.metadata.name.clone(),
+            node_ids: subnet.nodes.iter().map(|n| n.principal).sorted().collect(),
+            nakamoto: features.iter().filter_map(|f| score.score_feature(f).map(|s| (f.clone(), s))).collect(),
+            nakamoto_avg_linear: score.score_avg_linear(),
+            penalty,
+            penalty_reasons,
+            feature_value_counts: features.iter().map(|f| (f.clone(), score.feature_value_counts(f))).collect(),
+            proposal_id: None,
+        })
+    }
+
+    /// Nodes added and removed compared to an earlier snapshot of the same subnet.
+    pub fn membership_diff(&self, earlier: &SubnetSnapshot) -> (Vec<PrincipalId>, Vec<PrincipalId>) {
+        let added = self.node_ids.iter().filter(|n| !earlier.node_ids.contains(n)).copied().collect();
+        let removed = earlier.node_ids.iter().filter(|n| !self.node_ids.contains(n)).copied().collect();
+        (added, removed)
+    }
+
+    fn metric(&self, metric: &TrendMetric) -> f64 {
+        match metric {
+            TrendMetric::Nakamoto(feature) => self.nakamoto.get(feature).copied().unwrap_or_default(),
+            TrendMetric::BusinessRulesPenalty => self.penalty as f64,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TrendMetric {
+    Nakamoto(NodeFeature),
+    BusinessRulesPenalty,
+}
+
+impl TrendMetric {
+    /// The metrics of a snapshot, including the custom features of its nodes.
+    fn of(snapshot: &SubnetSnapshot) -> Vec<TrendMetric> {
+        snapshot
+            .nakamoto
+            .keys()
+            .cloned()
+            .map(TrendMetric::Nakamoto)
+            .chain(std::iter::once(TrendMetric::BusinessRulesPenalty))
+            .collect()
+    }
+
+    /// How much worse `after` is compared to `before`, zero or negative if it is not worse.
+    fn worsening(&self, before: f64, after: f64) -> f64 {
+        match self {
+            TrendMetric::Nakamoto(_) => before - after,
+            TrendMetric::BusinessRulesPenalty => after - before,
+        }
+    }
+}
+
+/// A subnet metric that is worse at the end of the observed range than at its start.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Regression {
+    pub subnet_id: PrincipalId,
+    pub metric: TrendMetric,
+    pub since_version: u64,
+    pub value_before: f64,
+    pub value_after: f64,
+    /// The registry version with the largest single worsening of the metric.
+    pub caused_by_version: u64,
+    pub caused_by_proposal: Option<u64>,
+}
+
+impl Display for Regression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let subnet = self.subnet_id.to_string().split('-').next().unwrap_or_default().to_string();
+        match &self.metric {
+            TrendMetric::Nakamoto(feature) => write!(
+                f,
+                "subnet {} lost {:.2} {} NC since version {} ({:.2} -> {:.2})",
+                subnet,
+                self.value_before - self.value_after,
+                feature,
+                self.since_version,
+                self.value_before,
+                self.value_after
+            )?,
+            TrendMetric::BusinessRulesPenalty => write!(
+                f,
+                "subnet {} business rules penalty increased by {} since version {} ({} -> {})",
+                subnet,
+                self.value_after - self.value_before,
+                self.since_version,
+                self.value_before,
+                self.value_after
+            )?,
+        }
+        match self.caused_by_proposal {
+            Some(proposal_id) => write!(f, ", caused by proposal {}", proposal_id),
+            None => write!(f, ", caused by the change at version {}", self.caused_by_version),
+        }
+    }
+}
+
+/// Compares the first and the last snapshot of every subnet and reports the metrics that got worse.
+pub fn find_regressions(snapshots: &[SubnetSnapshot]) -> Vec<Regression> {
+    let mut regressions = vec![];
+    let by_subnet = snapshots.iter().into_group_map_by(|s| s.subnet_id);
+
+    for subnet_id in by_subnet.keys().sorted() {
+        let series = by_subnet[subnet_id].iter().sorted_by_key(|s| s.registry_version).collect_vec();
+        let (Some(first), Some(last)) = (series.first(), series.last()) else {
+            continue;
+        };
+        for metric in TrendMetric::of(last) {
+            let (value_before, value_after) = (first.metric(&metric), last.metric(&metric));
+            if metric.worsening(value_before, value_after) <= 0. {
+                continue;
+            }
+            let cause = series
+                .iter()
+                .tuple_windows()
+                .max_by(|(a1, b1), (a2, b2)| {
+                    metric
+                        .worsening(a1.metric(&metric), b1.metric(&metric))
+                        .total_cmp(&metric.worsening(a2.metric(&metric), b2.metric(&metric)))
+                })
+                .map(|(_, cause)| cause)
+                .unwrap_or(last);
+            regressions.push(Regression {
+                subnet_id: *subnet_id,
+                metric,
+                since_version: first.registry_version,
+                value_before,
+                value_after,
+                caused_by_version: cause.registry_version,
+                caused_by_proposal: cause.proposal_id,
+            });
+        }
+    }
+
+    regressions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(version: u64, country_nc: f64, penalty: usize, proposal_id: Option<u64>) -> SubnetSnapshot {
+        SubnetSnapshot {
+            registry_version: version,
+            subnet_id: PrincipalId::new_subnet_test_id(1),
+            subnet_name: "test".to_string(),
+            node_ids: vec![],
+            nakamoto: IndexMap::from([(NodeFeature::Country, country_nc), (NodeFeature::NodeProvider, 5.)]),
+            nakamoto_avg_linear: 0.,
+            penalty,
+            penalty_reasons: vec![],
+            feature_value_counts: IndexMap::new(),
+            proposal_id,
+        }
+    }
+
+    #[test]
+    fn regression_is_attributed_to_largest_drop() {
+        let snapshots = vec![
+            snapshot(10, 4., 0, None),
+            snapshot(20, 3.5, 0, Some(100)),
+            snapshot(30, 3.75, 0, Some(101)),
+            snapshot(40, 3.5, 0, Some(102)),
+        ];
+
+        let regressions = find_regressions(&snapshots);
+
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].metric, TrendMetric::Nakamoto(NodeFeature::Country));
+        assert_eq!(regressions[0].since_version, 10);
+        assert_eq!(regressions[0].caused_by_proposal, Some(100));
+        assert_eq!(
+            regressions[0].to_string(),
+            format!(
+                "subnet {} lost 0.50 country NC since version 10 (4.00 -> 3.50), caused by proposal 100",
+                PrincipalId::new_subnet_test_id(1).to_string().split('-').next().unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn penalty_increase_is_a_regression() {
+        let snapshots = vec![snapshot(10, 4., 0, None), snapshot(20, 4., 10, None)];
+
+        let regressions = find_regressions(&snapshots);
+
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].metric, TrendMetric::BusinessRulesPenalty);
+        assert_eq!(regressions[0].caused_by_version, 20);
+    }
+
+    #[test]
+    fn improvements_are_not_regressions() {
+        let snapshots = vec![snapshot(10, 3., 10, None), snapshot(20, 4., 0, Some(100))];
+
+        assert!(find_regressions(&snapshots).is_empty());
+    }
+}
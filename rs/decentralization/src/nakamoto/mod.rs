@@ -8,6 +8,7 @@ use std::collections::VecDeque;
 use std::fmt::{Debug, Display, Formatter};
 use std::hash::Hasher;
 use std::iter::IntoIterator;
+use std::str::FromStr;
 
 use ic_management_types::{Node, NodeFeature, NodeFeatures};
 
@@ -19,6 +20,102 @@ thread_local! {
     pub static MEMOIZE_HIT_RATES: RefCell<VecDeque<u32>> = const { RefCell::new(VecDeque::new()) };
 }
 
+/// The fraction of subnet nodes that colluding actors need to control, strictly more than,
+/// to break a property of the subnet. The Nakamoto coefficient at a threshold is the number of
+/// actors that together control more than that fraction of the nodes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct NakamotoThreshold {
+    numerator: usize,
+    denominator: usize,
+}
+
+impl NakamotoThreshold {
+    /// More than 1/3 of the nodes can halt the subnet (liveness).
+    pub const ONE_THIRD: NakamotoThreshold = NakamotoThreshold {
+        numerator: 1,
+        denominator: 3,
+    };
+    /// More than 2/3 of the nodes can certify arbitrary state or forge threshold signatures (safety).
+    pub const TWO_THIRDS: NakamotoThreshold = NakamotoThreshold {
+        numerator: 2,
+        denominator: 3,
+    };
+
+    pub fn new(numerator: usize, denominator: usize) -> anyhow::Result<Self> {
+        if numerator == 0 || numerator >= denominator {
+            anyhow::bail!("Nakamoto threshold {}/{} must be strictly between 0 and 1", numerator, denominator);
+        }
+        Ok(Self { numerator, denominator })
+    }
+
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Self::ONE_THIRD => "liveness",
+            Self::TWO_THIRDS => "safety",
+            _ => "custom",
+        }
+    }
+
+    /// The largest number of nodes that may be controlled without crossing the threshold.
+    fn max_controlled_nodes(&self, total_nodes: usize) -> usize {
+        total_nodes * self.numerator / self.denominator
+    }
+
+    /// Whether controlling the given number of nodes crosses the threshold.
+    pub fn is_crossed_by(&self, controlled_nodes: usize, total_nodes: usize) -> bool {
+        controlled_nodes > self.max_controlled_nodes(total_nodes)
+    }
+}
+
+impl Ord for NakamotoThreshold {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.numerator * other.denominator)
+            .cmp(&(other.numerator * self.denominator))
+            .then(self.denominator.cmp(&other.denominator))
+    }
+}
+
+impl PartialOrd for NakamotoThreshold {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for NakamotoThreshold {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+impl FromStr for NakamotoThreshold {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (numerator, denominator) = s.split_once('/').ok_or_else(|| {
+            anyhow::anyhow!(
+                "Expected a Nakamoto threshold in the form `numerator/denominator`, e.g. `2/3`, found `{}`",
+                s
+            )
+        })?;
+        Self::new(numerator.trim().parse()?, denominator.trim().parse()?)
+    }
+}
+
+impl TryFrom<String> for NakamotoThreshold {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<NakamotoThreshold> for String {
+    fn from(value: NakamotoThreshold) -> Self {
+        value.to_string()
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 /// This struct keeps the Nakamoto coefficients for each feature that we track
 /// for the IC nodes https://crosstower.com/resources/education/nakamoto-coefficient/
@@ -44,6 +141,11 @@ pub struct NakamotoScore {
     /// Self.new_from_slice_node_features for implementation detail
     avg_log2: Option<f64>,
     min: f64,
+
+    /// Nakamoto coefficients at thresholds other than 1/3, which is kept in `coefficients`.
+    /// The 2/3 threshold is always included.
+    #[serde(default)]
+    threshold_coefficients: IndexMap<NakamotoThreshold, IndexMap<NodeFeature, f64>>,
 }
 
 impl NakamotoScore {
@@ -51,16 +153,17 @@ impl NakamotoScore {
     pub fn new_from_slice_node_features(slice_node_features: &[NodeFeatures]) -> Self {
         let mut features_to_nodes_map = IndexMap::new();
 
-        for feature in NodeFeature::variants() {
-            features_to_nodes_map.insert(feature, Vec::new());
+        let features = NodeFeature::variants_for(slice_node_features);
+        for feature in &features {
+            features_to_nodes_map.insert(feature.clone(), Vec::new());
         }
 
         // Convert a Vec<IndexMap<NodeFeature, Value>> into a Vec<IndexMap<NodeFeature,
         // Vec<Values>>
         for node_features in slice_node_features.iter() {
-            for feature in NodeFeature::variants() {
-                let curr = features_to_nodes_map.get_mut(&feature).unwrap();
-                curr.push(node_features.get(&feature));
+            for feature in &features {
+                let curr = features_to_nodes_map.get_mut(feature).unwrap();
+                curr.push(node_features.get(feature));
             }
         }
 
@@ -110,7 +213,11 @@ impl NakamotoScore {
 
         let controlled_nodes = nakamoto_calc.clone().map(|(f, n, _)| (f, n.1)).collect::<IndexMap<NodeFeature, usize>>();
 
-        let value_counts = nakamoto_calc.map(|(f, _, value_counts)| (f, value_counts)).collect();
+        let value_counts: IndexMap<NodeFeature, Vec<(String, usize)>> = nakamoto_calc.map(|(f, _, value_counts)| (f, value_counts)).collect();
+        let threshold_coefficients = IndexMap::from([(
+            NakamotoThreshold::TWO_THIRDS,
+            Self::coefficients_at(&value_counts, &NakamotoThreshold::TWO_THIRDS),
+        )]);
 
         NakamotoScore {
             coefficients: scores.clone(),
@@ -130,6 +237,7 @@ impl NakamotoScore {
                 .values()
                 .map(|x| if x.is_finite() { *x } else { 0. })
                 .fold(1.0 / 0.0, |acc, x| if x < acc { x } else { acc }),
+            threshold_coefficients,
         }
     }
 
@@ -188,6 +296,12 @@ impl NakamotoScore {
     ///    requirements
     /// 2) the number of nodes that the top actors control
     fn nakamoto(values: &[usize]) -> (usize, usize) {
+        Self::nakamoto_at(values, &NakamotoThreshold::ONE_THIRD)
+    }
+
+    /// Same as [NakamotoScore::nakamoto], but counts the actors needed to control
+    /// more than the provided fraction of the nodes.
+    fn nakamoto_at(values: &[usize], threshold: &NakamotoThreshold) -> (usize, usize) {
         let mut values = values.to_owned();
         let total_subnet_nodes: usize = values.iter().sum();
 
@@ -199,7 +313,10 @@ impl NakamotoScore {
         // things like certification / CUPs > need to use 2f+1 (even if we only
         // assume that f can be corrupt) because we want to be more > resilient
         // against non-deterministic execution.
-        let max_malicious_nodes = total_subnet_nodes / 3;
+        //
+        // The 2/3 threshold (2f + 1 nodes) is the one relevant for safety, e.g. for forging
+        // threshold signatures or certifying an arbitrary state.
+        let max_malicious_nodes = threshold.max_controlled_nodes(total_subnet_nodes);
 
         // Reverse sort, go from actor with most to fewest repetitions.
         // The ultimate nakamoto coefficient is the number of different actors necessary
@@ -220,6 +337,59 @@ impl NakamotoScore {
         (sum_actors, sum_nodes)
     }
 
+    fn coefficients_at(value_counts: &IndexMap<NodeFeature, Vec<(String, usize)>>, threshold: &NakamotoThreshold) -> IndexMap<NodeFeature, f64> {
+        value_counts
+            .iter()
+            .map(|(feature, counts)| {
+                let counts = counts.iter().map(|(_, count)| *count).collect::<Vec<_>>();
+                (feature.clone(), Self::nakamoto_at(&counts, threshold).0 as f64)
+            })
+            .collect()
+    }
+
+    /// Adds the Nakamoto coefficients at the provided thresholds, so that they are
+    /// serialized and displayed together with the 1/3 and 2/3 ones.
+    pub fn with_thresholds(mut self, thresholds: &[NakamotoThreshold]) -> Self {
+        for threshold in thresholds.iter().filter(|t| **t != NakamotoThreshold::ONE_THIRD) {
+            if !self.threshold_coefficients.contains_key(threshold) {
+                let coefficients = Self::coefficients_at(&self.value_counts, threshold);
+                self.threshold_coefficients.insert(*threshold, coefficients);
+            }
+        }
+        self.threshold_coefficients.sort_keys();
+        self
+    }
+
+    /// All thresholds with known coefficients, in increasing order. Always starts with 1/3.
+    pub fn thresholds(&self) -> Vec<NakamotoThreshold> {
+        std::iter::once(NakamotoThreshold::ONE_THIRD)
+            .chain(self.threshold_coefficients.keys().copied())
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    /// Nakamoto coefficients of all features at the provided threshold.
+    pub fn scores_individual_at(&self, threshold: &NakamotoThreshold) -> IndexMap<NodeFeature, f64> {
+        if *threshold == NakamotoThreshold::ONE_THIRD {
+            return self.coefficients.clone();
+        }
+        match self.threshold_coefficients.get(threshold) {
+            Some(coefficients) => coefficients.clone(),
+            None => Self::coefficients_at(&self.value_counts, threshold),
+        }
+    }
+
+    pub fn score_feature_at(&self, feature: &NodeFeature, threshold: &NakamotoThreshold) -> Option<f64> {
+        self.scores_individual_at(threshold).get(feature).copied()
+    }
+
+    /// An average of the linear nakamoto scores over all features, at the provided threshold
+    pub fn score_avg_linear_at(&self, threshold: &NakamotoThreshold) -> f64 {
+        let coefficients = self.scores_individual_at(threshold);
+        coefficients.values().sum::<f64>() / coefficients.len() as f64
+    }
+
     /// An average of the linear nakamoto scores over all features
     pub fn score_avg_linear(&self) -> f64 {
         self.avg_linear
@@ -230,6 +400,11 @@ impl NakamotoScore {
         self.avg_log2
     }
 
+    /// Features of the score, including the custom features of the nodes.
+    pub fn features(&self) -> impl Iterator<Item = &NodeFeature> {
+        self.coefficients.keys()
+    }
+
     /// Get a Map with all the features and the corresponding Nakamoto score
     pub fn scores_individual(&self) -> IndexMap<NodeFeature, f64> {
         self.coefficients.clone()
@@ -403,7 +578,7 @@ impl NakamotoScore {
 
         // If the worst feature is the same for both candidates
         // => prefer candidates that maximizes all features
-        for feature in NodeFeature::variants() {
+        for feature in self.coefficients.keys().cloned() {
             if feature == NodeFeature::Continent {
                 // Skip the continent feature as it is not used in the Nakamoto score
                 continue;
@@ -488,6 +663,7 @@ impl Display for NakamotoScore {
 mod tests {
     use std::str::FromStr;
 
+    use crate::business_rules::BusinessRules;
     use crate::network::{DecentralizedSubnet, NetworkHealRequest, NetworkHealSubnet, SubnetChangeRequest};
     use ic_base_types::PrincipalId;
     use ic_management_types::HealthStatus;
@@ -548,10 +724,61 @@ mod tests {
             avg_linear: 1.,
             avg_log2: Some(0.),
             min: 1.,
+            threshold_coefficients: IndexMap::new(),
         };
         assert_eq!(score, score_expected);
     }
 
+    #[test]
+    fn computes_nakamoto_scores_at_thresholds() {
+        let two_thirds = NakamotoThreshold::TWO_THIRDS;
+        // 13 nodes with different actors: > 8 nodes needed for 2/3
+        assert_eq!(
+            (9, 9),
+            NakamotoScore::nakamoto_at(&std::iter::repeat_n(1, 13).collect::<Vec<usize>>(), &two_thirds)
+        );
+        assert_eq!((3, 10), NakamotoScore::nakamoto_at(&[1, 1, 2, 3, 5, 1], &two_thirds)); // top actors control 10/13 nodes
+        let half = NakamotoThreshold::new(1, 2).unwrap();
+        assert_eq!((2, 8), NakamotoScore::nakamoto_at(&[1, 1, 2, 3, 5, 1], &half)); // top actors control 8/13 nodes
+        for actors in 1..100 {
+            assert_eq!(
+                NakamotoScore::nakamoto(&std::iter::repeat_n(1, actors).collect::<Vec<usize>>()),
+                NakamotoScore::nakamoto_at(&std::iter::repeat_n(1, actors).collect::<Vec<usize>>(), &NakamotoThreshold::ONE_THIRD)
+            );
+        }
+    }
+
+    #[test]
+    fn nakamoto_thresholds_parse() {
+        assert_eq!("2/3".parse::<NakamotoThreshold>().unwrap(), NakamotoThreshold::TWO_THIRDS);
+        assert_eq!(" 1 / 3 ".parse::<NakamotoThreshold>().unwrap(), NakamotoThreshold::ONE_THIRD);
+        assert!("3/3".parse::<NakamotoThreshold>().is_err());
+        assert!("0/3".parse::<NakamotoThreshold>().is_err());
+        assert!("1:3".parse::<NakamotoThreshold>().is_err());
+        assert!(NakamotoThreshold::new(1, 2).unwrap() > NakamotoThreshold::ONE_THIRD);
+        assert!(NakamotoThreshold::new(1, 2).unwrap() < NakamotoThreshold::TWO_THIRDS);
+    }
+
+    #[test]
+    fn score_includes_safety_and_custom_thresholds() {
+        let nodes = new_test_nodes("foo", 13, 0);
+        let three_quarters = NakamotoThreshold::new(3, 4).unwrap();
+        let score = NakamotoScore::new_from_nodes(&nodes).with_thresholds(&[three_quarters]);
+
+        assert_eq!(
+            score.thresholds(),
+            vec![NakamotoThreshold::ONE_THIRD, NakamotoThreshold::TWO_THIRDS, three_quarters]
+        );
+        assert_eq!(score.score_feature_at(&NodeFeature::Country, &NakamotoThreshold::ONE_THIRD), Some(5.));
+        assert_eq!(score.score_feature_at(&NodeFeature::Country, &NakamotoThreshold::TWO_THIRDS), Some(9.));
+        assert_eq!(score.score_feature_at(&NodeFeature::Country, &three_quarters), Some(10.));
+
+        let serialized = serde_json::to_value(&score).unwrap();
+        assert!(serialized["threshold_coefficients"]["2/3"].is_object());
+        let deserialized: NakamotoScore = serde_json::from_value(serialized).unwrap();
+        assert_eq!(deserialized.thresholds(), score.thresholds());
+    }
+
     /// Generate a new Vec<Node> of len num_nodes, out of which
     /// num_dfinity_nodes are DFINITY-owned
     fn new_test_nodes(feat_prefix: &str, num_nodes: usize, num_dfinity_nodes: usize) -> Vec<Node> {
@@ -599,6 +826,7 @@ mod tests {
             removed_nodes: Vec::new(),
             comment: None,
             run_log: Vec::new(),
+            business_rules: BusinessRules::default(),
         }
     }
 
@@ -617,6 +845,7 @@ mod tests {
             removed_nodes: Vec::new(),
             comment: None,
             run_log: Vec::new(),
+            business_rules: BusinessRules::default(),
         }
     }
 
@@ -871,6 +1100,7 @@ mod tests {
             removed_nodes: Vec::new(),
             comment: None,
             run_log: Vec::new(),
+            business_rules: BusinessRules::default(),
         };
 
         let available_nodes = serde_json::from_str::<Vec<ic_management_types::Node>>(include_str!("../../test_data/available-nodes.json"))
@@ -943,6 +1173,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_european_subnet_country_limit_excludes_swiss_and_uk_nodes() {
+        // Switzerland and the UK are allowed in the European subnet, but only the EU members are limited per country
+        let european_subnet_id = PrincipalId::from_str("bkfrj-6k62g-dycql-7h53p-atvkj-zg4to-gaogh-netha-ptybj-ntsgw-rqe").unwrap();
+        for country in ["CH", "UK"] {
+            let subnet = new_test_subnet_with_overrides(0, 0, 7, 1, (&NodeFeature::Country, &["AT", "BE", "DE", "ES", country, country, country]))
+                .with_subnet_id(european_subnet_id);
+            assert_eq!(subnet.check_business_rules().unwrap(), (0, vec![]));
+        }
+
+        let subnet = new_test_subnet_with_overrides(0, 0, 7, 1, (&NodeFeature::Country, &["AT", "BE", "DE", "ES", "FR", "FR", "FR"]))
+            .with_subnet_id(european_subnet_id);
+        assert_eq!(
+            subnet.check_business_rules().unwrap(),
+            (
+                10,
+                vec!["Country FR controls 3 of nodes, which is higher than target of 2 for the subnet. Applying penalty of 10.".to_string()]
+            )
+        );
+    }
+
     #[test]
     fn test_network_heal_subnets_ord() {
         let not_important_small = new_test_subnet(0, 13, 0)
@@ -1008,7 +1259,7 @@ mod tests {
 
         important.insert(subnet.principal, subnet);
 
-        let network_heal_response = NetworkHealRequest::new(important.clone())
+        let network_heal_response = NetworkHealRequest::new(important.clone(), BusinessRules::default())
             .fix_and_optimize(nodes_available.clone(), &health_of_nodes, vec![], &all_nodes, false, false, true)
             .await
             .unwrap();
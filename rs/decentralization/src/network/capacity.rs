@@ -0,0 +1,514 @@
+use super::*;
+use crate::nakamoto::NakamotoScore;
+use ic_management_types::NodeFeatures;
+use log::{info, warn};
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+/// Node ids of hypothetical nodes start here, far away from the ids of the test nodes.
+const HYPOTHETICAL_NODE_ID_BASE: u64 = 1 << 48;
+
+/// A kind of node that a node provider considers onboarding, as described in a capacity plan file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HypotheticalNodes {
+    pub node_provider: String,
+    pub data_center: String,
+    pub data_center_owner: String,
+    pub country: String,
+    /// City or state of the data center, the data center itself if not provided
+    #[serde(default)]
+    pub area: Option<String>,
+    #[serde(default)]
+    pub continent: Option<String>,
+    /// Values of the custom node features, e.g. `asn`
+    #[serde(default)]
+    pub features: IndexMap<String, String>,
+    /// How many such nodes could be onboarded, unlimited if not provided
+    #[serde(default)]
+    pub count: Option<usize>,
+}
+
+impl HypotheticalNodes {
+    fn node(&self, id: u64) -> Node {
+        let principal = PrincipalId::new_node_test_id(HYPOTHETICAL_NODE_ID_BASE + id);
+        let feature_map = [
+            (NodeFeature::NodeId, principal.to_string()),
+            (NodeFeature::NodeOperator, format!("{}/{}", self.node_provider, self.data_center)),
+            (NodeFeature::NodeProvider, self.node_provider.clone()),
+            (NodeFeature::DataCenter, self.data_center.clone()),
+            (NodeFeature::DataCenterOwner, self.data_center_owner.clone()),
+            (NodeFeature::Area, self.area.clone().unwrap_or_else(|| self.data_center.clone())),
+            (NodeFeature::Country, self.country.clone()),
+            (NodeFeature::Continent, self.continent.clone().unwrap_or_else(|| "unknown".to_string())),
+        ]
+        .into_iter()
+        .chain(
+            self.features
+                .iter()
+                .map(|(name, value)| (NodeFeature::Custom(name.clone()), value.clone())),
+        )
+        .collect();
+        Node {
+            principal,
+            cached_features: OnceLock::from(NodeFeatures { feature_map }),
+            dfinity_owned: Some(false),
+            ..Default::default()
+        }
+    }
+}
+
+impl Display for HypotheticalNodes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} in {} ({}, {}, {})",
+            self.node_provider,
+            self.data_center,
+            self.area.as_deref().unwrap_or(&self.data_center),
+            self.country,
+            self.data_center_owner
+        )
+    }
+}
+
+/// What one new node would change in a subnet, replacing the member for which that helps most.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapacityImprovement {
+    /// Index of the node kind in [`CapacityPlan::kinds`]
+    pub kind: usize,
+    pub subnet_id: PrincipalId,
+    pub replaced_node: PrincipalId,
+    pub penalties_before: usize,
+    /// Best the subnet can get by replacing one node with one of the nodes already available
+    pub penalties_with_available_nodes: usize,
+    pub penalties_after: usize,
+    /// Nakamoto coefficients, averaged over the features
+    pub nakamoto_before: f64,
+    pub nakamoto_with_available_nodes: f64,
+    pub nakamoto_after: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CapacityPlan {
+    pub kinds: Vec<HypotheticalNodes>,
+    /// Subnets that a single node of each kind would improve, on the current network
+    pub improvements: Vec<CapacityImprovement>,
+    /// New nodes placed one after the other, each where it improves a subnet most, until no
+    /// further node improves any subnet
+    pub absorption: Vec<CapacityImprovement>,
+    /// True if the absorption stopped at the maximum number of nodes rather than for lack of improvements
+    pub max_nodes_reached: bool,
+    /// Sum of the business rules penalties of all subnets, before and after the absorption
+    pub network_penalty_before: usize,
+    pub network_penalty_after: usize,
+}
+
+impl CapacityPlan {
+    /// Number of nodes of the kind placed by the absorption.
+    pub fn absorbed(&self, kind: usize) -> usize {
+        self.absorption.iter().filter(|step| step.kind == kind).count()
+    }
+}
+
+impl Display for CapacityPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (kind, nodes) in self.kinds.iter().enumerate() {
+            let improvements = self.improvements.iter().filter(|i| i.kind == kind).collect_vec();
+            writeln!(f, "Node of {}: improves {} subnet(s)", nodes, improvements.len())?;
+            for i in improvements {
+                writeln!(
+                    f,
+                    "  subnet {} replacing {}: penalty {} -> {} (available nodes: {}), Nakamoto {:.2} -> {:.2} (available nodes: {:.2})",
+                    i.subnet_id,
+                    i.replaced_node,
+                    i.penalties_before,
+                    i.penalties_after,
+                    i.penalties_with_available_nodes,
+                    i.nakamoto_before,
+                    i.nakamoto_after,
+                    i.nakamoto_with_available_nodes
+                )?;
+            }
+        }
+
+        writeln!(f, "\nNew nodes placed one at a time where they help most:")?;
+        for (index, step) in self.absorption.iter().enumerate() {
+            writeln!(
+                f,
+                "  {}. {} into subnet {}: penalty {} -> {}, Nakamoto {:+.2}",
+                index + 1,
+                self.kinds[step.kind],
+                step.subnet_id,
+                step.penalties_before,
+                step.penalties_after,
+                step.nakamoto_after - step.nakamoto_before
+            )?;
+        }
+        writeln!(
+            f,
+            "\nTotal business rules penalty {} -> {}",
+            self.network_penalty_before, self.network_penalty_after
+        )?;
+        for (kind, nodes) in self.kinds.iter().enumerate() {
+            writeln!(f, "The network absorbs {} node(s) of {}", self.absorbed(kind), nodes)?;
+        }
+        if self.max_nodes_reached {
+            writeln!(
+                f,
+                "Stopped at the maximum of {} nodes, returns may not have diminished yet",
+                self.absorption.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Business rules penalty and Nakamoto score of a subnet with some set of nodes.
+#[derive(Clone, Debug)]
+struct Outcome {
+    penalties: usize,
+    score: NakamotoScore,
+}
+
+impl Outcome {
+    fn of(subnet: &DecentralizedSubnet, nodes: &[Node]) -> Result<Self, NetworkError> {
+        Ok(Self {
+            penalties: DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&subnet.id, nodes, &subnet.business_rules)
+                .map_err(|e| NetworkError::ResizeFailed(e.to_string()))?
+                .0,
+            score: NakamotoScore::new_from_nodes(nodes),
+        })
+    }
+
+    /// Lower penalties first, then a higher mean Nakamoto coefficient by more than `min_gain`.
+    fn is_better_than(&self, other: &Outcome, min_gain: f64) -> bool {
+        self.penalties < other.penalties
+            || (self.penalties == other.penalties && self.score.score_avg_linear() - other.score.score_avg_linear() > min_gain)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Replacement {
+    removed: PrincipalId,
+    outcome: Outcome,
+}
+
+/// A subnet during the planning, with its outcome now and with the best available node.
+struct SubnetState {
+    subnet: DecentralizedSubnet,
+    current: Outcome,
+    with_available_nodes: Outcome,
+}
+
+impl SubnetState {
+    /// What the new nodes have to beat: the subnet as it is, or with its best replacement from the available nodes.
+    fn baseline(&self) -> &Outcome {
+        if self.with_available_nodes.is_better_than(&self.current, 0.) {
+            &self.with_available_nodes
+        } else {
+            &self.current
+        }
+    }
+}
+
+/// Finds where new nodes of a provider would improve the decentralization of the network.
+///
+/// Each new node replaces one member of a subnet. A new node only counts as an improvement
+/// if the subnet gets better than with any of the nodes that are already available.
+pub struct CapacityPlanner {
+    subnets: IndexMap<PrincipalId, DecentralizedSubnet>,
+    available_nodes: Vec<Node>,
+    kinds: Vec<HypotheticalNodes>,
+    max_nodes: usize,
+    min_gain: f64,
+}
+
+impl CapacityPlanner {
+    const DEFAULT_MAX_NODES: usize = 100;
+
+    pub fn new(subnets: IndexMap<PrincipalId, DecentralizedSubnet>, available_nodes: Vec<Node>, kinds: Vec<HypotheticalNodes>) -> Self {
+        Self {
+            subnets,
+            available_nodes,
+            kinds,
+            max_nodes: Self::DEFAULT_MAX_NODES,
+            min_gain: 0.,
+        }
+    }
+
+    pub fn with_max_nodes(self, max_nodes: usize) -> Self {
+        Self { max_nodes, ..self }
+    }
+
+    /// Smallest increase of the mean Nakamoto coefficient that counts as an improvement,
+    /// if the business rules penalty stays the same
+    pub fn with_min_gain(self, min_gain: f64) -> Self {
+        Self { min_gain, ..self }
+    }
+
+    pub fn plan(&self) -> Result<CapacityPlan, NetworkError> {
+        // Available nodes with the same features are interchangeable for the evaluation
+        let available_nodes = self
+            .available_nodes
+            .iter()
+            .unique_by(|n| {
+                (
+                    n.get_features()
+                        .feature_map
+                        .into_iter()
+                        .filter(|(f, _)| *f != NodeFeature::NodeId)
+                        .collect_vec(),
+                    n.dfinity_owned,
+                    n.operator.provider.principal,
+                )
+            })
+            .cloned()
+            .collect_vec();
+        info!(
+            "Evaluating {} kind(s) of new nodes against {} subnets and {} distinct available nodes",
+            self.kinds.len(),
+            self.subnets.len(),
+            available_nodes.len()
+        );
+
+        let mut states = IndexMap::new();
+        for (subnet_id, subnet) in &self.subnets {
+            let current = Outcome::of(subnet, &subnet.nodes)?;
+            let with_available_nodes = Self::best_replacement(subnet, &available_nodes, &AHashSet::new())?
+                .map(|r| r.outcome)
+                .unwrap_or_else(|| current.clone());
+            states.insert(
+                *subnet_id,
+                SubnetState {
+                    subnet: subnet.clone(),
+                    current,
+                    with_available_nodes,
+                },
+            );
+        }
+        let network_penalty_before = states.values().map(|s| s.current.penalties).sum();
+
+        let mut next_node_id = 0;
+        let mut new_node = |kind: &HypotheticalNodes| {
+            next_node_id += 1;
+            kind.node(next_node_id)
+        };
+
+        // Best replacement with one node of each kind, for every subnet
+        let mut candidates: IndexMap<(usize, PrincipalId), Option<(Node, Replacement)>> = IndexMap::new();
+        let mut new_node_ids = AHashSet::new();
+        for (kind_index, kind) in self.kinds.iter().enumerate() {
+            for (subnet_id, state) in &states {
+                let node = new_node(kind);
+                let candidate = self.improving_replacement(state, &node, &new_node_ids)?.map(|r| (node, r));
+                candidates.insert((kind_index, *subnet_id), candidate);
+            }
+        }
+        let improvements = candidates
+            .iter()
+            .filter_map(|((kind, subnet_id), candidate)| {
+                candidate
+                    .as_ref()
+                    .map(|(_, replacement)| Self::improvement(*kind, &states[subnet_id], replacement))
+            })
+            .sorted_by(|a, b| {
+                (b.penalties_before.saturating_sub(b.penalties_after))
+                    .cmp(&a.penalties_before.saturating_sub(a.penalties_after))
+                    .then((b.nakamoto_after - b.nakamoto_before).total_cmp(&(a.nakamoto_after - a.nakamoto_before)))
+            })
+            .collect_vec();
+
+        let mut absorption: Vec<CapacityImprovement> = vec![];
+        let mut max_nodes_reached = false;
+        loop {
+            let absorbed = absorption.iter().counts_by(|step| step.kind);
+            let best = candidates
+                .iter()
+                .filter(|((kind, _), _)| {
+                    self.kinds[*kind]
+                        .count
+                        .is_none_or(|count| absorbed.get(kind).copied().unwrap_or_default() < count)
+                })
+                .filter_map(|(key, candidate)| candidate.as_ref().map(|(_, replacement)| (*key, replacement)))
+                .max_by(|(a_key, a), (b_key, b)| {
+                    let a_baseline = states[&a_key.1].baseline();
+                    let b_baseline = states[&b_key.1].baseline();
+                    (a_baseline.penalties.saturating_sub(a.outcome.penalties))
+                        .cmp(&b_baseline.penalties.saturating_sub(b.outcome.penalties))
+                        .then(
+                            (a.outcome.score.score_avg_linear() - a_baseline.score.score_avg_linear())
+                                .total_cmp(&(b.outcome.score.score_avg_linear() - b_baseline.score.score_avg_linear())),
+                        )
+                        // Prefer the kinds and subnets that come first on ties
+                        .then(b_key.cmp(a_key))
+                })
+                .map(|(key, _)| key);
+            let Some((kind, subnet_id)) = best else {
+                break;
+            };
+            if absorption.len() >= self.max_nodes {
+                warn!("Reached the maximum of {} new nodes in the capacity plan", self.max_nodes);
+                max_nodes_reached = true;
+                break;
+            }
+
+            let (node, replacement) = candidates[&(kind, subnet_id)].clone().expect("the best candidate exists");
+            let state = states.get_mut(&subnet_id).expect("subnet of the candidate exists");
+            absorption.push(Self::improvement(kind, state, &replacement));
+            let nodes = state
+                .subnet
+                .nodes
+                .iter()
+                .filter(|n| n.principal != replacement.removed)
+                .cloned()
+                .chain(std::iter::once(node.clone()))
+                .collect_vec();
+            new_node_ids.insert(node.principal);
+            state.subnet =
+                DecentralizedSubnet::new_with_subnet_id_and_nodes(subnet_id, nodes).with_business_rules(state.subnet.business_rules.clone());
+            state.current = replacement.outcome;
+            state.with_available_nodes = Self::best_replacement(&state.subnet, &available_nodes, &new_node_ids)?
+                .map(|r| r.outcome)
+                .unwrap_or_else(|| state.current.clone());
+
+            // Only the candidates of the changed subnet need to be evaluated again
+            for (kind_index, kind) in self.kinds.iter().enumerate() {
+                let node = new_node(kind);
+                let candidate = self.improving_replacement(&states[&subnet_id], &node, &new_node_ids)?.map(|r| (node, r));
+                candidates.insert((kind_index, subnet_id), candidate);
+            }
+        }
+
+        Ok(CapacityPlan {
+            kinds: self.kinds.clone(),
+            improvements,
+            absorption,
+            max_nodes_reached,
+            network_penalty_before,
+            network_penalty_after: states.values().map(|s| s.current.penalties).sum(),
+        })
+    }
+
+    fn improvement(kind: usize, state: &SubnetState, replacement: &Replacement) -> CapacityImprovement {
+        let baseline = state.baseline();
+        CapacityImprovement {
+            kind,
+            subnet_id: state.subnet.id,
+            replaced_node: replacement.removed,
+            penalties_before: state.current.penalties,
+            penalties_with_available_nodes: baseline.penalties,
+            penalties_after: replacement.outcome.penalties,
+            nakamoto_before: state.current.score.score_avg_linear(),
+            nakamoto_with_available_nodes: baseline.score.score_avg_linear(),
+            nakamoto_after: replacement.outcome.score.score_avg_linear(),
+        }
+    }
+
+    /// The best replacement with the new node, if it beats the subnet as it is and with the available nodes.
+    fn improving_replacement(
+        &self,
+        state: &SubnetState,
+        node: &Node,
+        new_node_ids: &AHashSet<PrincipalId>,
+    ) -> Result<Option<Replacement>, NetworkError> {
+        Ok(Self::best_replacement(&state.subnet, std::slice::from_ref(node), new_node_ids)?
+            .filter(|r| r.outcome.is_better_than(state.baseline(), self.min_gain) && r.outcome.penalties <= state.baseline().penalties))
+    }
+
+    /// Replaces each member of the subnet, except the `fixed` ones, with each of the candidates and
+    /// returns the best resulting subnet.
+    fn best_replacement(
+        subnet: &DecentralizedSubnet,
+        candidates: &[Node],
+        fixed: &AHashSet<PrincipalId>,
+    ) -> Result<Option<Replacement>, NetworkError> {
+        let mut best: Option<Replacement> = None;
+        for candidate in candidates {
+            for removed in subnet.nodes.iter().filter(|n| !fixed.contains(&n.principal)) {
+                let nodes = subnet
+                    .nodes
+                    .iter()
+                    .filter(|n| n.principal != removed.principal)
+                    .cloned()
+                    .chain(std::iter::once(candidate.clone()))
+                    .collect_vec();
+                let outcome = Outcome::of(subnet, &nodes)?;
+                if best.as_ref().is_none_or(|b| outcome.is_better_than(&b.outcome, 0.)) {
+                    best = Some(Replacement {
+                        removed: removed.principal,
+                        outcome,
+                    });
+                }
+            }
+        }
+        Ok(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subnet_in_one_country(subnet_number: u64, size: u64) -> DecentralizedSubnet {
+        let nodes = (0..size)
+            .map(|i| {
+                let features =
+                    NodeFeatures::new_test_feature_set(&format!("s{}n{}", subnet_number, i)).with_feature_value(&NodeFeature::Country, "CH");
+                Node::new_test_node(subnet_number * 100 + i, features, false)
+            })
+            .collect_vec();
+        DecentralizedSubnet::new_with_subnet_id_and_nodes(PrincipalId::new_subnet_test_id(subnet_number), nodes)
+    }
+
+    fn nodes_in(country: &str, count: Option<usize>) -> HypotheticalNodes {
+        HypotheticalNodes {
+            node_provider: format!("provider-{}", country),
+            data_center: format!("dc-{}", country),
+            data_center_owner: format!("owner-{}", country),
+            country: country.to_string(),
+            area: None,
+            continent: None,
+            features: IndexMap::new(),
+            count,
+        }
+    }
+
+    #[test]
+    fn new_nodes_in_another_country_are_absorbed_until_returns_diminish() {
+        let subnet = subnet_in_one_country(1, 7);
+        let planner = CapacityPlanner::new(IndexMap::from([(subnet.id, subnet.clone())]), vec![], vec![nodes_in("DE", None)]);
+        let plan = planner.plan().unwrap();
+
+        assert_eq!(plan.improvements.len(), 1);
+        let first = &plan.improvements[0];
+        assert!(first.penalties_after < first.penalties_before);
+        assert_eq!(plan.absorption[0], *first);
+        // Nodes of a single provider and country stop helping long before they replace the whole subnet
+        assert!(!plan.absorption.is_empty() && plan.absorption.len() < 7);
+        assert!(!plan.max_nodes_reached);
+        assert!(plan.network_penalty_after < plan.network_penalty_before);
+        assert!(
+            plan.absorption
+                .iter()
+                .all(|s| s.penalties_after < s.penalties_before || s.nakamoto_after > s.nakamoto_before)
+        );
+
+        let limited = CapacityPlanner::new(IndexMap::from([(subnet.id, subnet.clone())]), vec![], vec![nodes_in("DE", Some(1))])
+            .plan()
+            .unwrap();
+        assert_eq!(limited.absorbed(0), 1);
+    }
+
+    #[test]
+    fn new_nodes_must_beat_the_available_nodes() {
+        let subnet = subnet_in_one_country(1, 7);
+        let available = nodes_in("DE", None).node(1000);
+        let plan = CapacityPlanner::new(IndexMap::from([(subnet.id, subnet)]), vec![available], vec![nodes_in("DE", None)])
+            .plan()
+            .unwrap();
+
+        // A node in DE is already available, so new nodes in DE do not improve the subnet any further
+        assert!(plan.improvements.is_empty());
+        assert!(plan.absorption.is_empty());
+    }
+}
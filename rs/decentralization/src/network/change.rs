@@ -1,4 +1,5 @@
 use super::*;
+use crate::business_rules::BusinessRules;
 use crate::nakamoto::NakamotoScore;
 
 #[derive(Debug, Clone, Default)]
@@ -12,15 +13,19 @@ pub struct SubnetChange {
     pub penalties_after_change: (usize, Vec<String>),
     pub comment: Option<String>,
     pub run_log: Vec<String>,
+    /// Policy that the penalties before and after the change are checked against
+    pub business_rules: BusinessRules,
 }
 
 impl SubnetChange {
     pub fn with_nodes(self, nodes_to_add: &[Node]) -> Self {
         let new_nodes = [self.new_nodes, nodes_to_add.to_vec()].concat();
-        let penalties_before_change = DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet_id, &self.old_nodes)
-            .expect("Business rules check before should succeed");
-        let penalties_after_change = DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet_id, &new_nodes)
-            .expect("Business rules check after should succeed");
+        let penalties_before_change =
+            DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet_id, &self.old_nodes, &self.business_rules)
+                .expect("Business rules check before should succeed");
+        let penalties_after_change =
+            DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet_id, &new_nodes, &self.business_rules)
+                .expect("Business rules check after should succeed");
         Self {
             new_nodes,
             added_nodes: nodes_to_add.to_vec(),
@@ -34,10 +39,12 @@ impl SubnetChange {
         let nodes_to_rm = AHashSet::from_iter(nodes_to_remove);
         self.removed_nodes.extend(nodes_to_remove.to_vec());
         self.new_nodes.retain(|n| !nodes_to_rm.contains(n));
-        self.penalties_before_change = DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet_id, &self.old_nodes)
-            .expect("Business rules check before should succeed");
-        self.penalties_after_change = DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet_id, &self.new_nodes)
-            .expect("Business rules check after should succeed");
+        self.penalties_before_change =
+            DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet_id, &self.old_nodes, &self.business_rules)
+                .expect("Business rules check before should succeed");
+        self.penalties_after_change =
+            DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet_id, &self.new_nodes, &self.business_rules)
+                .expect("Business rules check after should succeed");
         self
     }
 
@@ -57,6 +64,7 @@ impl SubnetChange {
             removed_nodes: Vec::new(),
             comment: self.comment.clone(),
             run_log: Vec::new(),
+            business_rules: self.business_rules.clone(),
         }
     }
 
@@ -68,6 +76,7 @@ impl SubnetChange {
             removed_nodes: self.removed_nodes.clone(),
             comment: self.comment.clone(),
             run_log: self.run_log.clone(),
+            business_rules: self.business_rules.clone(),
         }
     }
 }
@@ -0,0 +1,445 @@
+use super::*;
+use crate::business_rules::{BusinessRules, ResolvedSubnetRules};
+use crate::nakamoto::NakamotoScore;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+/// How the nodes to add to or remove from a subnet are selected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectionStrategy {
+    /// Pick the best node one at a time. Fast, but can miss changes where
+    /// several nodes have to be swapped together to improve the subnet.
+    #[default]
+    Greedy,
+    /// Search jointly for the best set of nodes to remove and add, minimizing the
+    /// business rules penalty first and maximizing the Nakamoto score second.
+    /// The search stops after `time_budget`, and the greedy result is used if
+    /// the search could not improve on it.
+    Exact { time_budget: Duration },
+}
+
+/// Comparison of the greedy selection and the exact search.
+#[derive(Clone, Debug)]
+pub struct OptimalityReport {
+    pub greedy_penalty: usize,
+    pub greedy_score: NakamotoScore,
+    pub best_penalty: usize,
+    pub best_score: NakamotoScore,
+    /// The whole search space was explored, so the best result is proven optimal
+    pub exhaustive: bool,
+    pub evaluated: usize,
+    pub elapsed: Duration,
+}
+
+impl OptimalityReport {
+    /// True if the exact search found a strictly better subnet than the greedy selection
+    pub fn improves_on_greedy(&self) -> bool {
+        compare_results(self.best_penalty, &self.best_score, self.greedy_penalty, &self.greedy_score) == Ordering::Greater
+    }
+}
+
+impl Display for OptimalityReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Exact search {} after evaluating {} candidate subnets in {:.1}s",
+            if self.exhaustive {
+                "explored the whole search space"
+            } else {
+                "ran out of the time budget"
+            },
+            self.evaluated,
+            self.elapsed.as_secs_f64()
+        )?;
+        writeln!(f, "Greedy result: penalty {}, {}", self.greedy_penalty, self.greedy_score)?;
+        writeln!(f, "Best result:   penalty {}, {}", self.best_penalty, self.best_score)?;
+        if self.improves_on_greedy() {
+            write!(
+                f,
+                "Greedy result was {} penalty points from the {}: {}",
+                self.greedy_penalty as i64 - self.best_penalty as i64,
+                if self.exhaustive { "optimum" } else { "best result found" },
+                self.best_score.describe_difference_from(&self.greedy_score).1
+            )
+        } else if self.exhaustive {
+            write!(f, "Greedy result is optimal")
+        } else {
+            write!(f, "No better result than the greedy one was found")
+        }
+    }
+}
+
+// Ordering of two candidate subnets, `Greater` means the first one is better.
+fn compare_results(penalty_a: usize, score_a: &NakamotoScore, penalty_b: usize, score_b: &NakamotoScore) -> Ordering {
+    penalty_b.cmp(&penalty_a).then_with(|| score_a.cmp(score_b))
+}
+
+// Nodes with the same features are interchangeable as far as the Nakamoto score
+// and the business rules are concerned, so only one of them has to be tried.
+fn node_signature(node: &Node) -> (Vec<String>, bool) {
+    (
+        node.get_features()
+            .feature_map
+            .into_iter()
+            .filter(|(f, _)| *f != NodeFeature::NodeId)
+            .map(|(_, value)| value)
+            .collect(),
+        node.dfinity_owned.unwrap_or_default(),
+    )
+}
+
+fn group_equivalent_nodes(nodes: &[Node]) -> Vec<Vec<Node>> {
+    nodes
+        .iter()
+        .cloned()
+        .sorted_by_key(|n| (node_signature(n), n.principal))
+        .chunk_by(node_signature)
+        .into_iter()
+        .map(|(_, group)| group.collect())
+        .collect()
+}
+
+/// Lower bound for the business rules penalty of any subnet of `final_len` nodes which contains `nodes`
+/// and `remaining` more nodes. Only the parts of the business rules which can't decrease by adding
+/// nodes are taken into account, the rest is bounded by zero.
+fn penalty_lower_bound(business_rules: &BusinessRules, rules: &ResolvedSubnetRules, nodes: &[Node], final_len: usize, remaining: usize) -> usize {
+    let weights = &business_rules.policy().penalties;
+    let mut bound = 0;
+
+    let dfinity_owned = nodes.iter().filter(|n| n.dfinity_owned.unwrap_or_default()).count();
+    let target = rules.dfinity_owned_nodes;
+    if dfinity_owned > target {
+        bound += (dfinity_owned - target) * weights.dfinity_owned_nodes;
+    } else if dfinity_owned + remaining < target {
+        bound += (target - dfinity_owned - remaining) * weights.dfinity_owned_nodes;
+    }
+
+    let counts = |feature: &NodeFeature| nodes.iter().map(|n| n.get_feature(feature).unwrap_or_default()).counts();
+    for (feature, max_nodes) in [
+        (NodeFeature::NodeProvider, rules.max_nodes_per_node_provider),
+        (NodeFeature::DataCenter, rules.max_nodes_per_data_center),
+        (NodeFeature::DataCenterOwner, rules.max_nodes_per_data_center_owner),
+    ] {
+        for count in counts(&feature).into_values().filter(|count| *count > max_nodes) {
+            // DFINITY nodes may be exempt from the limit, as long as there aren't too many of them
+            if rules.dfinity_exempt_from_provider_limits && count <= target {
+                continue;
+            }
+            bound += (count - max_nodes) * weights.nodes_per_provider_or_data_center;
+        }
+    }
+
+    let known_features = NodeFeature::variants_for(nodes.iter().map(Node::get_features));
+    for (feature, max_nodes) in &rules.max_nodes_per_feature {
+        let Ok(feature) = NodeFeature::parse_known(feature, &known_features) else {
+            continue;
+        };
+        for count in counts(&feature).into_values().filter(|count| count > max_nodes) {
+            bound += (count - max_nodes) * weights.nodes_per_feature;
+        }
+    }
+
+    for (country, count) in counts(&NodeFeature::Country) {
+        if !rules.is_country_allowed(&country) {
+            bound += count * weights.country_not_allowed;
+        } else if rules.is_country_capped(&country) && count > rules.max_nodes_per_country {
+            bound += (count - rules.max_nodes_per_country) * weights.nodes_per_country;
+        }
+        if rules.country_share_below_one_third && count > final_len / 3 {
+            bound += (count - final_len / 3) * weights.country_above_one_third;
+        }
+    }
+
+    for providers in business_rules.linked_providers().values() {
+        let count = nodes.iter().filter(|n| providers.contains(&n.operator.provider.principal)).count();
+        if count > 1 {
+            bound += (count - 1) * weights.linked_providers;
+        }
+    }
+
+    bound
+}
+
+struct ExactSearch<'a> {
+    subnet_id: PrincipalId,
+    business_rules: &'a BusinessRules,
+    rules: ResolvedSubnetRules,
+    deadline: Instant,
+    timed_out: bool,
+    evaluated: usize,
+    best: Option<(usize, NakamotoScore, Vec<Node>, Vec<Node>)>,
+}
+
+impl ExactSearch<'_> {
+    fn is_better(&self, penalty: usize, score: &NakamotoScore) -> bool {
+        match &self.best {
+            Some((best_penalty, best_score, _, _)) => compare_results(penalty, score, *best_penalty, best_score) == Ordering::Greater,
+            None => true,
+        }
+    }
+
+    fn search_additions(
+        &mut self,
+        nodes: &mut Vec<Node>,
+        removed: &[Node],
+        groups: &[Vec<Node>],
+        start: usize,
+        used: &mut [usize],
+        remaining: usize,
+    ) {
+        if self.timed_out {
+            return;
+        }
+        if Instant::now() >= self.deadline {
+            self.timed_out = true;
+            return;
+        }
+        let final_len = nodes.len() + remaining;
+        if let Some((best_penalty, _, _, _)) = &self.best {
+            if penalty_lower_bound(self.business_rules, &self.rules, nodes, final_len, remaining) > *best_penalty {
+                return;
+            }
+        }
+        if remaining == 0 {
+            self.evaluated += 1;
+            if let Ok((penalty, _)) = DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet_id, nodes, self.business_rules) {
+                let score = NakamotoScore::new_from_nodes(nodes.iter());
+                if self.is_better(penalty, &score) {
+                    let added = groups.iter().zip(used.iter()).flat_map(|(group, used)| group[..*used].to_vec()).collect();
+                    self.best = Some((penalty, score, removed.to_vec(), added));
+                }
+            }
+            return;
+        }
+        for i in start..groups.len() {
+            if used[i] == groups[i].len() {
+                continue;
+            }
+            nodes.push(groups[i][used[i]].clone());
+            used[i] += 1;
+            self.search_additions(nodes, removed, groups, i, used, remaining - 1);
+            used[i] -= 1;
+            nodes.pop();
+            if self.timed_out {
+                return;
+            }
+        }
+    }
+}
+
+/// Result of the exact search: the changed subnet and how it compares to the greedy one.
+pub struct ExactSearchResult {
+    pub subnet: DecentralizedSubnet,
+    pub report: OptimalityReport,
+}
+
+impl DecentralizedSubnet {
+    /// Jointly search for the `how_many_nodes_to_remove` nodes out of `removable_nodes` and the
+    /// `how_many_nodes_to_add` nodes out of `available_nodes` that give the subnet the lowest business
+    /// rules penalty, and then the highest Nakamoto score. The search is a branch and bound over groups
+    /// of equivalent nodes, and is seeded with the `greedy` result so that it never does worse.
+    pub fn subnet_with_exact_changes(
+        &self,
+        how_many_nodes_to_remove: usize,
+        how_many_nodes_to_add: usize,
+        removable_nodes: &[Node],
+        available_nodes: &[Node],
+        greedy: &DecentralizedSubnet,
+        time_budget: Duration,
+    ) -> Result<ExactSearchResult, NetworkError> {
+        let started = Instant::now();
+        let (greedy_penalty, _) = Self::check_business_rules_for_subnet_with_nodes(&self.id, &greedy.nodes, &self.business_rules)
+            .map_err(|e| NetworkError::ResizeFailed(e.to_string()))?;
+        let greedy_score = greedy.nakamoto_score();
+
+        let mut search = ExactSearch {
+            subnet_id: self.id,
+            business_rules: &self.business_rules,
+            rules: self.business_rules.policy().rules_for_subnet(&self.id),
+            deadline: started + time_budget,
+            timed_out: false,
+            evaluated: 0,
+            best: Some((greedy_penalty, greedy_score.clone(), vec![], vec![])),
+        };
+
+        let removal_groups = group_equivalent_nodes(removable_nodes);
+        for removed in removal_groups
+            .iter()
+            .flat_map(|group| group.iter().cloned())
+            .combinations(how_many_nodes_to_remove)
+            // Removing either of two equivalent nodes gives the same subnet
+            .unique_by(|removed| removed.iter().map(node_signature).sorted().collect_vec())
+        {
+            let mut nodes = self.nodes.iter().filter(|n| !removed.contains(n)).cloned().collect_vec();
+            // Nodes removed from the subnet may be added back, which results in a smaller change
+            let candidates = available_nodes.iter().chain(removed.iter()).cloned().collect_vec();
+            let groups = group_equivalent_nodes(&candidates);
+            let mut used = vec![0; groups.len()];
+            search.search_additions(&mut nodes, &removed, &groups, 0, &mut used, how_many_nodes_to_add);
+            if search.timed_out {
+                break;
+            }
+        }
+
+        let (best_penalty, best_score, removed, added) = search.best.clone().expect("search is seeded with the greedy result");
+        let report = OptimalityReport {
+            greedy_penalty,
+            greedy_score,
+            best_penalty,
+            best_score,
+            exhaustive: !search.timed_out,
+            evaluated: search.evaluated,
+            elapsed: started.elapsed(),
+        };
+
+        let subnet = if report.improves_on_greedy() {
+            let subnet = self.without_nodes(&removed)?.with_nodes(&added).without_duplicate_added_removed();
+            let (penalty, business_rules_log) = subnet.check_business_rules().map_err(|e| NetworkError::ResizeFailed(e.to_string()))?;
+            let comment = if penalty != 0 {
+                Some(format!(
+                    "Subnet change finished with the total penalty {}. Penalty causes:\n\n{}",
+                    penalty,
+                    business_rules_log.iter().map(|s| format!("- {}", s)).join("\n")
+                ))
+            } else {
+                None
+            };
+            Self { comment, ..subnet }
+        } else {
+            greedy.clone()
+        };
+        let mut run_log = subnet.run_log.clone();
+        run_log.extend(report.to_string().lines().map(String::from));
+
+        Ok(ExactSearchResult {
+            subnet: Self { run_log, ..subnet },
+            report,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::business_rules::BusinessRulesPolicy;
+    use crate::provider_clusters::ProviderClusters;
+    use ic_management_types::NodeFeatures;
+
+    fn test_nodes(node_number_start: u64, feature_values: &[&str], num_dfinity_nodes: usize) -> Vec<Node> {
+        feature_values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                Node::new_test_node(
+                    node_number_start + i as u64,
+                    NodeFeatures::new_test_feature_set(value),
+                    i < num_dfinity_nodes,
+                )
+            })
+            .collect()
+    }
+
+    fn test_subnet() -> DecentralizedSubnet {
+        DecentralizedSubnet::new_with_subnet_id_and_nodes(PrincipalId::new_subnet_test_id(1), test_nodes(0, &["a", "b", "c", "c", "d"], 1))
+    }
+
+    #[test]
+    fn exact_search_matches_brute_force() {
+        let subnet = test_subnet();
+        let available = test_nodes(100, &["a", "c", "e", "e", "f", "g", "g"], 0);
+        let all_nodes = subnet.nodes.iter().chain(available.iter()).cloned().collect_vec();
+        let greedy = subnet
+            .clone()
+            .subnet_with_fewer_nodes(1, &all_nodes)
+            .and_then(|s| s.subnet_with_more_nodes(2, &available, &all_nodes))
+            .unwrap();
+
+        let result = subnet
+            .subnet_with_exact_changes(1, 2, &subnet.nodes, &available, &greedy, Duration::from_secs(60))
+            .unwrap();
+        assert!(result.report.exhaustive);
+
+        let brute_force_best = subnet
+            .nodes
+            .iter()
+            .combinations(1)
+            .flat_map(|removed| {
+                let remaining = subnet.nodes.iter().filter(|n| !removed.contains(&n)).cloned().collect_vec();
+                available
+                    .iter()
+                    .chain(removed.into_iter())
+                    .cloned()
+                    .combinations(2)
+                    .map(move |added| remaining.iter().cloned().chain(added).collect_vec())
+            })
+            .map(|nodes| {
+                let (penalty, _) =
+                    DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&subnet.id, &nodes, &subnet.business_rules).unwrap();
+                (penalty, NakamotoScore::new_from_nodes(&nodes))
+            })
+            .max_by(|(penalty_a, score_a), (penalty_b, score_b)| compare_results(*penalty_a, score_a, *penalty_b, score_b))
+            .unwrap();
+
+        assert_eq!(
+            compare_results(
+                result.report.best_penalty,
+                &result.report.best_score,
+                brute_force_best.0,
+                &brute_force_best.1
+            ),
+            Ordering::Equal
+        );
+        assert_eq!(result.subnet.nodes.len(), subnet.nodes.len() + 1);
+    }
+
+    #[test]
+    fn exact_search_falls_back_to_greedy_when_out_of_time() {
+        let subnet = test_subnet();
+        let available = test_nodes(100, &["e", "f", "g"], 0);
+        let greedy = subnet.clone().subnet_with_more_nodes(1, &available, &available).unwrap();
+
+        let result = subnet
+            .subnet_with_exact_changes(0, 1, &subnet.nodes, &available, &greedy, Duration::ZERO)
+            .unwrap();
+
+        assert!(!result.report.exhaustive);
+        assert!(!result.report.improves_on_greedy());
+        assert_eq!(result.subnet.nodes, greedy.nodes);
+    }
+
+    #[test]
+    fn lower_bound_does_not_exceed_penalty() {
+        let subnet = test_subnet();
+        let rules = subnet.business_rules.policy().rules_for_subnet(&subnet.id);
+        let (penalty, _) = subnet.check_business_rules().unwrap();
+
+        for len in 0..=subnet.nodes.len() {
+            let partial = &subnet.nodes[..len];
+            let remaining = subnet.nodes.len() - len;
+            assert!(penalty_lower_bound(&subnet.business_rules, &rules, partial, subnet.nodes.len(), remaining) <= penalty);
+        }
+    }
+
+    #[test]
+    fn feature_limits_are_penalized_and_bounded() {
+        let subnet = test_subnet();
+        let mut policy = BusinessRulesPolicy::default();
+        let (penalty_without_limit, _) = subnet.check_business_rules().unwrap();
+
+        policy.defaults.max_nodes_per_feature = IndexMap::from([("area".to_string(), 1)]);
+        let rules = policy.rules_for_subnet(&subnet.id);
+        let subnet = subnet.with_business_rules(BusinessRules::new(policy.clone(), ProviderClusters::default()));
+        let (penalty, checks) = subnet.check_business_rules().unwrap();
+
+        assert_eq!(penalty, penalty_without_limit + policy.penalties.nodes_per_feature);
+        assert!(checks.iter().any(|c| c.starts_with("area c controls 2 of nodes")));
+        assert!(penalty_lower_bound(&subnet.business_rules, &rules, &subnet.nodes, subnet.nodes.len(), 0) <= penalty);
+    }
+
+    #[test]
+    fn equivalent_nodes_are_grouped() {
+        let groups = group_equivalent_nodes(&test_nodes(0, &["a", "b", "a", "a"], 0));
+        assert_eq!(groups.iter().map(|g| g.len()).sorted().collect_vec(), vec![1, 3]);
+    }
+}
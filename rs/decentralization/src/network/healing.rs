@@ -1,6 +1,7 @@
 use super::*;
 use crate::{
     SubnetChangeResponse,
+    business_rules::BusinessRules,
     subnets::{subnets_with_business_rules_violations, unhealthy_with_nodes},
 };
 use log::{info, warn};
@@ -39,11 +40,12 @@ impl Ord for NetworkHealSubnet {
 
 pub struct NetworkHealRequest {
     pub subnets: IndexMap<PrincipalId, ic_management_types::Subnet>,
+    pub business_rules: BusinessRules,
 }
 
 impl NetworkHealRequest {
-    pub fn new(subnets: IndexMap<PrincipalId, ic_management_types::Subnet>) -> Self {
-        Self { subnets }
+    pub fn new(subnets: IndexMap<PrincipalId, ic_management_types::Subnet>, business_rules: BusinessRules) -> Self {
+        Self { subnets, business_rules }
     }
 
     pub async fn fix_and_optimize(
@@ -65,7 +67,7 @@ impl NetworkHealRequest {
                         *subnet_id,
                         NetworkHealSubnet {
                             name: unhealthy_subnet.metadata.name.clone(),
-                            decentralized_subnet: DecentralizedSubnet::from(unhealthy_subnet),
+                            decentralized_subnet: DecentralizedSubnet::from(unhealthy_subnet).with_business_rules(self.business_rules.clone()),
                             unhealthy_nodes: unhealthy_nodes.clone(),
                             cordoned_nodes: vec![],
                         },
@@ -98,7 +100,7 @@ impl NetworkHealRequest {
                             *subnet_id,
                             NetworkHealSubnet {
                                 name: subnet.metadata.name.clone(),
-                                decentralized_subnet: DecentralizedSubnet::from(subnet.clone()),
+                                decentralized_subnet: DecentralizedSubnet::from(subnet.clone()).with_business_rules(self.business_rules.clone()),
                                 unhealthy_nodes: vec![],
                                 cordoned_nodes,
                             },
@@ -119,11 +121,11 @@ impl NetworkHealRequest {
 
         let mut optimized_subnets = BTreeSet::new();
         if optimize_for_business_rules_compliance {
-            for subnet in subnets_with_business_rules_violations(&self.subnets.values().cloned().collect::<Vec<_>>()) {
+            for subnet in subnets_with_business_rules_violations(&self.subnets.values().cloned().collect::<Vec<_>>(), &self.business_rules) {
                 optimized_subnets.insert(subnet.principal);
                 let network_heal_subnet = NetworkHealSubnet {
                     name: subnet.metadata.name.clone(),
-                    decentralized_subnet: DecentralizedSubnet::from(subnet),
+                    decentralized_subnet: DecentralizedSubnet::from(subnet).with_business_rules(self.business_rules.clone()),
                     unhealthy_nodes: vec![],
                     cordoned_nodes: vec![],
                 };
@@ -1,13 +1,21 @@
 // Re-export all public items from submodules
+mod capacity;
 mod change;
+mod exact;
 mod healing;
+mod outage;
+mod planner;
 mod request;
 mod subnet;
 mod traits;
 mod types;
 
+pub use capacity::*;
 pub use change::*;
+pub use exact::*;
 pub use healing::*;
+pub use outage::*;
+pub use planner::*;
 pub use request::*;
 pub use subnet::*;
 pub use traits::*;
@@ -0,0 +1,376 @@
+use super::*;
+use crate::{
+    business_rules::{BusinessRules, NNS_SUBNET},
+    nakamoto::NakamotoThreshold,
+};
+use log::{info, warn};
+use std::fmt::{Display, Formatter};
+
+/// The nodes that go down together in a simulated outage.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutageScope {
+    /// Nodes with the feature value, e.g. `data_center=zh1` or `country=CH`
+    FeatureValue(String),
+    /// Nodes of the providers in a cluster of linked providers
+    ProviderCluster(String),
+}
+
+impl OutageScope {
+    fn matcher(&self, business_rules: &BusinessRules) -> Result<Box<dyn Fn(&Node) -> bool + Send + Sync>, NetworkError> {
+        match self {
+            OutageScope::FeatureValue(value) => {
+                let value = value.clone();
+                Ok(Box::new(move |node: &Node| node.matches_feature_value(&value)))
+            }
+            OutageScope::ProviderCluster(name) => {
+                let clusters = business_rules.linked_providers();
+                let providers = clusters.get(name).cloned().ok_or_else(|| {
+                    NetworkError::IllegalRequest(format!(
+                        "Unknown provider cluster '{}', known clusters: {}",
+                        name,
+                        clusters.keys().sorted().join(", ")
+                    ))
+                })?;
+                Ok(Box::new(move |node: &Node| providers.contains(&node.operator.provider.principal)))
+            }
+        }
+    }
+}
+
+impl Display for OutageScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutageScope::FeatureValue(value) => write!(f, "nodes matching {}", value),
+            OutageScope::ProviderCluster(name) => write!(f, "nodes of provider cluster '{}'", name),
+        }
+    }
+}
+
+/// A node replacement proposal that brings a subnet back to healthy nodes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RescueProposal {
+    /// Proposals of the same round can be submitted together, a round waits for the previous one
+    pub round: usize,
+    pub subnet_id: PrincipalId,
+    pub node_ids_added: Vec<PrincipalId>,
+    pub node_ids_removed: Vec<PrincipalId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubnetOutageImpact {
+    pub subnet_id: PrincipalId,
+    pub name: String,
+    pub nodes: usize,
+    /// Members that go down in the outage
+    pub dead_nodes: Vec<PrincipalId>,
+    /// Members that were already unhealthy before the outage
+    pub unhealthy_nodes: Vec<PrincipalId>,
+    /// More than 1/3 of the members are down, the subnet stalls
+    pub liveness_lost: bool,
+    /// More than 2/3 of the members are down, the affected actor could also break safety
+    pub safety_lost: bool,
+    /// Members still down once the rescue proposals are adopted
+    pub nodes_down_after_rescue: usize,
+}
+
+impl SubnetOutageImpact {
+    pub fn healable(&self) -> bool {
+        self.nodes_down_after_rescue == 0
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutageSimulation {
+    pub scope: OutageScope,
+    /// Nodes that go down in the outage, in subnets or not
+    pub dead_nodes: usize,
+    /// Available nodes that go down in the outage and can no longer replace subnet members
+    pub dead_available_nodes: usize,
+    /// Subnets with members that go down, most affected first
+    pub subnets: Vec<SubnetOutageImpact>,
+    /// Proposals that replace the members that are down, with the remaining available nodes
+    pub rescue_proposals: Vec<RescueProposal>,
+}
+
+impl OutageSimulation {
+    /// If the NNS stalls, no proposal can be executed before the NNS is recovered.
+    pub fn nns_liveness_lost(&self) -> bool {
+        self.subnets.iter().any(|s| s.subnet_id.to_string() == NNS_SUBNET && s.liveness_lost)
+    }
+}
+
+impl Display for OutageSimulation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Outage of {}: {} node(s) down, {} of them available nodes",
+            self.scope, self.dead_nodes, self.dead_available_nodes
+        )?;
+        if self.subnets.is_empty() {
+            return writeln!(f, "No subnet is affected");
+        }
+        for subnet in &self.subnets {
+            writeln!(
+                f,
+                "  subnet {} ({}): {} of {} node(s) down{}, {}{}{}",
+                subnet.subnet_id,
+                subnet.name,
+                subnet.dead_nodes.len() + subnet.unhealthy_nodes.len(),
+                subnet.nodes,
+                if subnet.unhealthy_nodes.is_empty() {
+                    "".to_string()
+                } else {
+                    format!(" ({} already unhealthy)", subnet.unhealthy_nodes.len())
+                },
+                if subnet.liveness_lost { "liveness LOST" } else { "liveness kept" },
+                if subnet.safety_lost { ", safety LOST" } else { "" },
+                if subnet.healable() {
+                    format!(
+                        ", healed by {} proposal(s)",
+                        self.rescue_proposals.iter().filter(|p| p.subnet_id == subnet.subnet_id).count()
+                    )
+                } else {
+                    format!(", {} node(s) cannot be replaced", subnet.nodes_down_after_rescue)
+                }
+            )?;
+        }
+
+        writeln!(f, "\nRescue proposals, one round after the other:")?;
+        for proposal in &self.rescue_proposals {
+            writeln!(
+                f,
+                "  {}. subnet {}: remove {}, add {}",
+                proposal.round,
+                proposal.subnet_id,
+                proposal.node_ids_removed.iter().join(", "),
+                proposal.node_ids_added.iter().join(", ")
+            )?;
+        }
+        if self.nns_liveness_lost() {
+            writeln!(f, "\nThe NNS stalls: it must be recovered before any rescue proposal can be executed")?;
+        }
+        let stalled = self.subnets.iter().filter(|s| s.liveness_lost).count();
+        if stalled > 0 {
+            writeln!(
+                f,
+                "{} subnet(s) stall and need a subnet recovery for their membership changes to take effect",
+                stalled
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Simulates an outage of all nodes in a scope and the proposals that would heal the subnets.
+///
+/// The subnets are healed like `NetworkHealRequest` does, in rounds: a change replaces at most
+/// 1/6 of the members of a subnet, so a subnet that lost many nodes needs several proposals.
+pub struct OutageSimulator {
+    subnets: IndexMap<PrincipalId, ic_management_types::Subnet>,
+    all_nodes: Vec<Node>,
+    available_nodes: Vec<Node>,
+    health_of_nodes: IndexMap<PrincipalId, HealthStatus>,
+    business_rules: BusinessRules,
+    max_rounds: usize,
+}
+
+impl OutageSimulator {
+    const DEFAULT_MAX_ROUNDS: usize = 20;
+
+    pub fn new(
+        subnets: IndexMap<PrincipalId, ic_management_types::Subnet>,
+        all_nodes: Vec<Node>,
+        available_nodes: Vec<Node>,
+        health_of_nodes: IndexMap<PrincipalId, HealthStatus>,
+        business_rules: BusinessRules,
+    ) -> Self {
+        Self {
+            subnets,
+            all_nodes,
+            available_nodes,
+            health_of_nodes,
+            business_rules,
+            max_rounds: Self::DEFAULT_MAX_ROUNDS,
+        }
+    }
+
+    pub fn with_max_rounds(self, max_rounds: usize) -> Self {
+        Self { max_rounds, ..self }
+    }
+
+    pub async fn simulate(&self, scope: OutageScope) -> Result<OutageSimulation, NetworkError> {
+        let is_affected = scope.matcher(&self.business_rules)?;
+        let dead_nodes = self
+            .all_nodes
+            .iter()
+            .chain(self.subnets.values().flat_map(|s| s.nodes.iter()))
+            .filter(|n| is_affected(n))
+            .map(|n| n.principal)
+            .collect::<AHashSet<_>>();
+        info!("Simulating an outage of {} {}", dead_nodes.len(), scope);
+
+        let mut health_of_nodes = self.health_of_nodes.clone();
+        for node_id in &dead_nodes {
+            health_of_nodes.insert(*node_id, HealthStatus::Dead);
+        }
+        let is_down =
+            |health_of_nodes: &IndexMap<PrincipalId, HealthStatus>, node: &Node| health_of_nodes.get(&node.principal) != Some(&HealthStatus::Healthy);
+        let mut available_nodes = self
+            .available_nodes
+            .iter()
+            .filter(|n| !is_down(&health_of_nodes, n))
+            .cloned()
+            .collect_vec();
+        let dead_available_nodes = self.available_nodes.iter().filter(|n| dead_nodes.contains(&n.principal)).count();
+
+        let mut subnets = self
+            .subnets
+            .iter()
+            .filter(|(_, subnet)| subnet.nodes.iter().any(|n| dead_nodes.contains(&n.principal)))
+            .map(|(subnet_id, subnet)| (*subnet_id, subnet.clone()))
+            .collect::<IndexMap<_, _>>();
+        let mut impacts = subnets
+            .values()
+            .map(|subnet| {
+                let (dead, unhealthy): (Vec<_>, Vec<_>) = subnet
+                    .nodes
+                    .iter()
+                    .filter(|n| is_down(&health_of_nodes, n))
+                    .map(|n| n.principal)
+                    .partition(|node_id| dead_nodes.contains(node_id));
+                let down = dead.len() + unhealthy.len();
+                SubnetOutageImpact {
+                    subnet_id: subnet.principal,
+                    name: subnet.metadata.name.clone(),
+                    nodes: subnet.nodes.len(),
+                    dead_nodes: dead,
+                    unhealthy_nodes: unhealthy,
+                    liveness_lost: NakamotoThreshold::ONE_THIRD.is_crossed_by(down, subnet.nodes.len()),
+                    safety_lost: NakamotoThreshold::TWO_THIRDS.is_crossed_by(down, subnet.nodes.len()),
+                    nodes_down_after_rescue: down,
+                }
+            })
+            .collect_vec();
+
+        let mut rescue_proposals = vec![];
+        for round in 1..=self.max_rounds {
+            let subnets_down = subnets
+                .iter()
+                .filter(|(_, subnet)| subnet.nodes.iter().any(|n| is_down(&health_of_nodes, n)))
+                .map(|(subnet_id, subnet)| (*subnet_id, subnet.clone()))
+                .collect::<IndexMap<_, _>>();
+            if subnets_down.is_empty() {
+                break;
+            }
+            let changes = NetworkHealRequest::new(subnets_down, self.business_rules.clone())
+                .fix_and_optimize(available_nodes.clone(), &health_of_nodes, vec![], &self.all_nodes, false, false, true)
+                .await?;
+            let changes = changes.into_iter().filter(|c| !c.node_ids_added.is_empty()).collect_vec();
+            if changes.is_empty() {
+                break;
+            }
+            for change in changes {
+                let Some(subnet_id) = change.subnet_id else {
+                    continue;
+                };
+                available_nodes.retain(|n| !change.node_ids_added.contains(&n.principal));
+                if let Some(subnet) = subnets.get_mut(&subnet_id) {
+                    subnet.nodes = change.nodes_new.clone();
+                }
+                rescue_proposals.push(RescueProposal {
+                    round,
+                    subnet_id,
+                    node_ids_added: change.node_ids_added,
+                    node_ids_removed: change.node_ids_removed,
+                });
+            }
+        }
+
+        for impact in impacts.iter_mut() {
+            impact.nodes_down_after_rescue = subnets[&impact.subnet_id].nodes.iter().filter(|n| is_down(&health_of_nodes, n)).count();
+        }
+        if rescue_proposals.last().is_some_and(|p| p.round == self.max_rounds) && impacts.iter().any(|i| !i.healable()) {
+            warn!("Stopped the rescue of the subnets after {} rounds of proposals", self.max_rounds);
+        }
+        impacts.sort_by(|a, b| {
+            (b.safety_lost, b.liveness_lost, b.dead_nodes.len() + b.unhealthy_nodes.len()).cmp(&(
+                a.safety_lost,
+                a.liveness_lost,
+                a.dead_nodes.len() + a.unhealthy_nodes.len(),
+            ))
+        });
+
+        Ok(OutageSimulation {
+            scope,
+            dead_nodes: dead_nodes.len(),
+            dead_available_nodes,
+            subnets: impacts,
+            rescue_proposals,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_management_types::NodeFeatures;
+
+    fn simulator(spare_nodes: u64) -> OutageSimulator {
+        let subnet =
+            serde_json::from_str::<ic_management_types::Subnet>(include_str!("../../test_data/subnet-uzr34.json")).expect("failed to read test data");
+        let available_nodes = (0..spare_nodes)
+            .map(|i| Node::new_test_node(i, NodeFeatures::new_test_feature_set(&format!("spare {}", i)), i < 2))
+            .collect_vec();
+        let all_nodes = available_nodes.iter().chain(subnet.nodes.iter()).cloned().collect_vec();
+        let health_of_nodes = all_nodes.iter().map(|n| (n.principal, HealthStatus::Healthy)).collect();
+        OutageSimulator::new(
+            IndexMap::from([(subnet.principal, subnet)]),
+            all_nodes,
+            available_nodes,
+            health_of_nodes,
+            BusinessRules::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn outage_of_a_continent_stalls_the_subnet_and_is_healed_in_rounds() {
+        // 14 of the 28 nodes of the subnet are in Europe
+        let simulation = simulator(30)
+            .simulate(OutageScope::FeatureValue("continent=Europe".to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(simulation.dead_nodes, 14);
+        assert_eq!(simulation.dead_available_nodes, 0);
+        let impact = &simulation.subnets[0];
+        assert_eq!(impact.dead_nodes.len(), 14);
+        assert!(impact.liveness_lost);
+        assert!(!impact.safety_lost);
+        assert!(impact.healable());
+        // At most 28 / 6 = 4 nodes are replaced by each proposal
+        assert!(simulation.rescue_proposals.iter().all(|p| p.node_ids_removed.len() <= 4));
+        assert!(simulation.rescue_proposals.len() >= 4);
+        assert_eq!(
+            simulation.rescue_proposals.iter().map(|p| p.round).collect_vec(),
+            (1..=simulation.rescue_proposals.len()).collect_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn outage_without_enough_available_nodes() {
+        let simulation = simulator(2).simulate(OutageScope::FeatureValue("country=US".to_string())).await.unwrap();
+
+        let impact = &simulation.subnets[0];
+        // 9 of 28 nodes down is not more than 1/3
+        assert_eq!(impact.dead_nodes.len(), 9);
+        assert!(!impact.liveness_lost);
+        assert!(!impact.healable());
+
+        assert!(
+            simulator(2)
+                .simulate(OutageScope::ProviderCluster("no such cluster".to_string()))
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,553 @@
+use super::*;
+use crate::{SubnetChangeResponse, business_rules::BusinessRules, nakamoto::NakamotoScore};
+use log::{info, warn};
+use std::fmt::{Display, Formatter};
+
+/// The network state that a [`MigrationPlanner`] should reach.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MigrationTarget {
+    /// Every subnet passes the business rules (has a zero topology penalty).
+    BusinessRulesCompliant,
+    /// No node of the given node operator is assigned to a subnet.
+    OperatorUnassigned(PrincipalId),
+}
+
+impl Display for MigrationTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationTarget::BusinessRulesCompliant => write!(f, "every subnet passes the business rules"),
+            MigrationTarget::OperatorUnassigned(operator) => write!(f, "node operator {} fully unassigned", operator),
+        }
+    }
+}
+
+/// A single subnet membership change in a [`MigrationPlan`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MigrationStep {
+    /// Position of the step in the plan, starting from 0.
+    pub index: usize,
+    /// Steps in the same wave are independent of each other and can be proposed together.
+    /// Wave numbers start from 1.
+    pub wave: usize,
+    /// Indices of the steps which have to be executed before this one, either because they
+    /// change the same subnet or because they free up a node that this step adds.
+    pub depends_on: Vec<usize>,
+    pub change: SubnetChangeResponse,
+    /// Nakamoto score of all nodes assigned to subnets, before and after this step,
+    /// assuming all previous steps have been executed.
+    pub network_score_before: NakamotoScore,
+    pub network_score_after: NakamotoScore,
+    /// Sum of the business rules penalties of all subnets, before and after this step.
+    pub network_penalty_before: usize,
+    pub network_penalty_after: usize,
+    /// Proposal submitted for this step, recorded in the saved plan so that resuming the plan
+    /// does not depend on the subnet members, which later steps and other proposals change.
+    #[serde(default)]
+    pub proposal_id: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MigrationPlan {
+    pub target: MigrationTarget,
+    pub steps: Vec<MigrationStep>,
+    pub network_score_before: NakamotoScore,
+    pub network_score_after: NakamotoScore,
+    pub network_penalty_before: usize,
+    pub network_penalty_after: usize,
+    /// Subnets which still do not satisfy the target once all steps are executed.
+    pub unresolved_subnets: Vec<PrincipalId>,
+}
+
+impl MigrationPlan {
+    /// True if executing all steps reaches the target.
+    pub fn is_complete(&self) -> bool {
+        self.unresolved_subnets.is_empty()
+    }
+
+    /// Node features that the scores of the plan were computed with.
+    pub fn features(&self) -> impl Iterator<Item = &NodeFeature> {
+        self.network_score_before.features().chain(self.network_score_after.features())
+    }
+
+    pub fn num_waves(&self) -> usize {
+        self.steps.iter().map(|step| step.wave).max().unwrap_or_default()
+    }
+
+    /// Steps grouped by wave, in wave order.
+    pub fn waves(&self) -> Vec<Vec<&MigrationStep>> {
+        (1..=self.num_waves())
+            .map(|wave| self.steps.iter().filter(|step| step.wave == wave).collect())
+            .collect()
+    }
+
+    /// The first wave with steps whose proposal is not executed yet, together with those steps.
+    /// Steps with a recorded proposal that is still open are included, since they are in flight.
+    /// Returns `None` once every step is executed.
+    pub fn next_wave(&self, executed_proposals: &IndexSet<u64>) -> Option<(usize, Vec<&MigrationStep>)> {
+        self.waves().into_iter().enumerate().find_map(|(wave, steps)| {
+            let pending = steps
+                .into_iter()
+                .filter(|step| !step.proposal_id.is_some_and(|id| executed_proposals.contains(&id)))
+                .collect_vec();
+            (!pending.is_empty()).then_some((wave + 1, pending))
+        })
+    }
+
+    /// Records the proposal submitted for the step of the subnet in the given wave.
+    pub fn record_proposal(&mut self, wave: usize, subnet_id: PrincipalId, proposal_id: u64) {
+        if let Some(step) = self
+            .steps
+            .iter_mut()
+            .find(|step| step.wave == wave && step.change.subnet_id == Some(subnet_id))
+        {
+            step.proposal_id = Some(proposal_id);
+        }
+    }
+
+    /// IDs of the proposals recorded for the steps so far.
+    pub fn proposal_ids(&self) -> Vec<u64> {
+        self.steps.iter().filter_map(|step| step.proposal_id).collect()
+    }
+}
+
+impl Display for MigrationPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Migration plan: {}", self.target)?;
+        writeln!(
+            f,
+            "{} step(s) in {} wave(s). Network Nakamoto coefficient (avg linear) {:.4} -> {:.4}, total business rules penalty {} -> {}",
+            self.steps.len(),
+            self.num_waves(),
+            self.network_score_before.score_avg_linear(),
+            self.network_score_after.score_avg_linear(),
+            self.network_penalty_before,
+            self.network_penalty_after,
+        )?;
+        for (wave, steps) in self.waves().into_iter().enumerate() {
+            writeln!(f, "\nWave {}:", wave + 1)?;
+            for step in steps {
+                writeln!(
+                    f,
+                    "  Step {}: subnet {} removes {} and adds {} node(s), penalty {} -> {}, subnet Nakamoto {:.4} -> {:.4}, network Nakamoto {:.4} -> {:.4}{}",
+                    step.index + 1,
+                    step.change.subnet_id.unwrap_or_default(),
+                    step.change.node_ids_removed.len(),
+                    step.change.node_ids_added.len(),
+                    step.change.penalties_before_change.0,
+                    step.change.penalties_after_change.0,
+                    step.change.score_before.score_avg_linear(),
+                    step.change.score_after.score_avg_linear(),
+                    step.network_score_before.score_avg_linear(),
+                    step.network_score_after.score_avg_linear(),
+                    if step.depends_on.is_empty() {
+                        "".to_string()
+                    } else {
+                        format!(", after step(s) {}", step.depends_on.iter().map(|i| i + 1).join(", "))
+                    }
+                )?;
+            }
+        }
+        if !self.is_complete() {
+            writeln!(
+                f,
+                "\nThe target is not reached for subnet(s): {}",
+                self.unresolved_subnets.iter().map(|s| s.to_string()).join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Plans a sequence of subnet membership changes towards a [`MigrationTarget`].
+///
+/// Unlike [`NetworkHealRequest`], which looks at every subnet in isolation, the planner
+/// simulates the network after every change. Nodes removed from a subnet go back to the
+/// pool of available nodes, so later changes in other subnets can pick them up.
+pub struct MigrationPlanner {
+    subnets: IndexMap<PrincipalId, ic_management_types::Subnet>,
+    available_nodes: Vec<Node>,
+    health_of_nodes: IndexMap<PrincipalId, HealthStatus>,
+    cordoned_features: Vec<CordonedFeature>,
+    all_nodes: Vec<Node>,
+    business_rules: BusinessRules,
+    max_steps: usize,
+}
+
+impl MigrationPlanner {
+    const DEFAULT_MAX_STEPS: usize = 50;
+
+    pub fn new(
+        subnets: IndexMap<PrincipalId, ic_management_types::Subnet>,
+        available_nodes: Vec<Node>,
+        health_of_nodes: IndexMap<PrincipalId, HealthStatus>,
+        cordoned_features: Vec<CordonedFeature>,
+        all_nodes: Vec<Node>,
+        business_rules: BusinessRules,
+    ) -> Self {
+        Self {
+            subnets,
+            available_nodes,
+            health_of_nodes,
+            cordoned_features,
+            all_nodes,
+            business_rules,
+            max_steps: Self::DEFAULT_MAX_STEPS,
+        }
+    }
+
+    pub fn with_max_steps(self, max_steps: usize) -> Self {
+        Self { max_steps, ..self }
+    }
+
+    pub fn plan(&self, target: &MigrationTarget) -> Result<MigrationPlan, NetworkError> {
+        let mut subnets: IndexMap<PrincipalId, DecentralizedSubnet> = self
+            .subnets
+            .iter()
+            .map(|(id, subnet)| (*id, DecentralizedSubnet::from(subnet).with_business_rules(self.business_rules.clone())))
+            .collect();
+        let mut available_nodes = self
+            .available_nodes
+            .iter()
+            .filter(|n| Self::may_be_assigned(target, n))
+            .cloned()
+            .collect_vec();
+
+        let (network_score_before, network_penalty_before) = Self::network_state(&subnets)?;
+        let mut network_score = network_score_before.clone();
+        let mut network_penalty = network_penalty_before;
+
+        let mut steps: Vec<MigrationStep> = vec![];
+        let mut last_step_for_subnet: AHashMap<PrincipalId, usize> = AHashMap::new();
+        let mut freed_by_step: AHashMap<PrincipalId, usize> = AHashMap::new();
+        // Subnets for which no suitable change was found with the current pool of available nodes.
+        // Retried whenever some other step returns nodes to the pool.
+        let mut stalled: AHashSet<PrincipalId> = AHashSet::new();
+
+        'rounds: loop {
+            let pending = self.pending_subnets(target, &subnets)?;
+            let pending = pending.into_iter().filter(|id| !stalled.contains(id)).collect_vec();
+            if pending.is_empty() {
+                break;
+            }
+
+            let mut progressed = false;
+            for subnet_id in pending {
+                if steps.len() >= self.max_steps {
+                    warn!("Reached the maximum of {} steps in the migration plan", self.max_steps);
+                    break 'rounds;
+                }
+                let subnet = subnets.get(&subnet_id).expect("pending subnet must exist").clone();
+                let Some(change) = self.plan_subnet_change(target, &subnet, &available_nodes) else {
+                    stalled.insert(subnet_id);
+                    continue;
+                };
+                progressed = true;
+
+                let index = steps.len();
+                let depends_on = last_step_for_subnet
+                    .get(&subnet_id)
+                    .into_iter()
+                    .chain(change.added_nodes.iter().filter_map(|n| freed_by_step.get(&n.principal)))
+                    .copied()
+                    .sorted()
+                    .dedup()
+                    .collect_vec();
+                let wave = depends_on.iter().map(|i| steps[*i].wave).max().map_or(1, |wave| wave + 1);
+
+                available_nodes.retain(|n| !change.added_nodes.contains(n));
+                for node in change.removed_nodes.iter().filter(|n| Self::may_be_assigned(target, n)) {
+                    available_nodes.push(node.clone());
+                    freed_by_step.insert(node.principal, index);
+                }
+                if !change.removed_nodes.is_empty() {
+                    stalled.clear();
+                }
+                subnets.insert(
+                    subnet_id,
+                    DecentralizedSubnet::new_with_subnet_id_and_nodes(subnet_id, change.new_nodes.clone())
+                        .with_business_rules(self.business_rules.clone()),
+                );
+                last_step_for_subnet.insert(subnet_id, index);
+
+                let (network_score_after, network_penalty_after) = Self::network_state(&subnets)?;
+                let motivation = format!(
+                    "Step {} of the migration plan towards: {}. Subnet topology penalty {} => {}.",
+                    index + 1,
+                    target,
+                    change.penalties_before_change.0,
+                    change.penalties_after_change.0
+                );
+                info!(
+                    "Migration step {} (wave {}): subnet {} removes {} and adds {} node(s)",
+                    index + 1,
+                    wave,
+                    subnet_id,
+                    change.removed_nodes.len(),
+                    change.added_nodes.len()
+                );
+                steps.push(MigrationStep {
+                    index,
+                    wave,
+                    depends_on,
+                    change: SubnetChangeResponse::new(&change, &self.health_of_nodes, Some(motivation)),
+                    network_score_before: network_score.clone(),
+                    network_score_after: network_score_after.clone(),
+                    network_penalty_before: network_penalty,
+                    network_penalty_after,
+                    proposal_id: None,
+                });
+                network_score = network_score_after;
+                network_penalty = network_penalty_after;
+            }
+
+            if !progressed {
+                break;
+            }
+        }
+
+        Ok(MigrationPlan {
+            target: target.clone(),
+            steps,
+            network_score_before,
+            network_score_after: network_score,
+            network_penalty_before,
+            network_penalty_after: network_penalty,
+            unresolved_subnets: self.pending_subnets(target, &subnets)?,
+        })
+    }
+
+    /// Nodes which the target forbids from being assigned to a subnet are never picked
+    /// as replacements.
+    fn may_be_assigned(target: &MigrationTarget, node: &Node) -> bool {
+        match target {
+            MigrationTarget::BusinessRulesCompliant => true,
+            MigrationTarget::OperatorUnassigned(operator) => node.get_feature(&NodeFeature::NodeOperator) != Some(operator.to_string()),
+        }
+    }
+
+    /// Subnets that do not satisfy the target yet, most important first.
+    fn pending_subnets(
+        &self,
+        target: &MigrationTarget,
+        subnets: &IndexMap<PrincipalId, DecentralizedSubnet>,
+    ) -> Result<Vec<PrincipalId>, NetworkError> {
+        let mut pending = vec![];
+        for (subnet_id, subnet) in subnets {
+            let is_pending = match target {
+                MigrationTarget::BusinessRulesCompliant => {
+                    subnet.check_business_rules().map_err(|e| NetworkError::ResizeFailed(e.to_string()))?.0 > 0
+                }
+                MigrationTarget::OperatorUnassigned(_) => subnet.nodes.iter().any(|n| !Self::may_be_assigned(target, n)),
+            };
+            if is_pending {
+                pending.push(NetworkHealSubnet {
+                    name: self.subnets.get(subnet_id).map(|s| s.metadata.name.clone()).unwrap_or_default(),
+                    decentralized_subnet: subnet.clone(),
+                    unhealthy_nodes: vec![],
+                    cordoned_nodes: vec![],
+                });
+            }
+        }
+        Ok(pending
+            .into_iter()
+            .sorted_by(|a, b| b.cmp(a))
+            .map(|subnet| subnet.decentralized_subnet.id)
+            .collect())
+    }
+
+    /// Finds the change that brings the subnet closer to the target, or None if there is
+    /// no such change with the given pool of available nodes.
+    fn plan_subnet_change(&self, target: &MigrationTarget, subnet: &DecentralizedSubnet, available_nodes: &[Node]) -> Option<SubnetChange> {
+        // Same limit as in healing: replacing more than 1/6 of the nodes at once risks stalling the subnet.
+        let max_replaceable_nodes = (subnet.nodes.len() / 6).max(1);
+        let change_req = SubnetChangeRequest {
+            subnet: subnet.clone(),
+            available_nodes: available_nodes.to_vec(),
+            ..Default::default()
+        };
+
+        let change = match target {
+            MigrationTarget::OperatorUnassigned(_) => {
+                let nodes_to_remove = subnet
+                    .nodes
+                    .iter()
+                    .filter(|n| !Self::may_be_assigned(target, n))
+                    .take(max_replaceable_nodes)
+                    .cloned()
+                    .collect_vec();
+                change_req
+                    .optimize(
+                        0,
+                        &nodes_to_remove,
+                        &self.health_of_nodes,
+                        self.cordoned_features.clone(),
+                        &self.all_nodes,
+                    )
+                    .map_err(|e| warn!("Subnet {}: {}", subnet.id, e))
+                    .ok()?
+            }
+            MigrationTarget::BusinessRulesCompliant => (1..=max_replaceable_nodes)
+                .filter_map(|num_nodes_to_optimize| {
+                    change_req
+                        .clone()
+                        .optimize(
+                            num_nodes_to_optimize,
+                            &[],
+                            &self.health_of_nodes,
+                            self.cordoned_features.clone(),
+                            &self.all_nodes,
+                        )
+                        .map_err(|e| warn!("Subnet {}: {}", subnet.id, e))
+                        .ok()
+                })
+                .filter(|change| change.penalties_after_change.0 < change.penalties_before_change.0)
+                // Lowest penalty first, then the best Nakamoto score, then the fewest replaced nodes
+                .min_by(|a, b| {
+                    a.penalties_after_change
+                        .0
+                        .cmp(&b.penalties_after_change.0)
+                        .then_with(|| NakamotoScore::new_from_nodes(&b.new_nodes).cmp(&NakamotoScore::new_from_nodes(&a.new_nodes)))
+                        .then_with(|| a.removed_nodes.len().cmp(&b.removed_nodes.len()))
+                })?,
+        };
+
+        if change.removed_nodes.is_empty() && change.added_nodes.is_empty() {
+            return None;
+        }
+        Some(change)
+    }
+
+    fn network_state(subnets: &IndexMap<PrincipalId, DecentralizedSubnet>) -> Result<(NakamotoScore, usize), NetworkError> {
+        let score = NakamotoScore::new_from_nodes(subnets.values().flat_map(|s| s.nodes.iter()));
+        let mut penalty = 0;
+        for subnet in subnets.values() {
+            penalty += subnet.check_business_rules().map_err(|e| NetworkError::ResizeFailed(e.to_string()))?.0;
+        }
+        Ok((score, penalty))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_management_types::{NodeFeatures, Subnet};
+
+    fn new_test_node(num: u64, operator: &str) -> Node {
+        Node::new_test_node(
+            num,
+            NodeFeatures::new_test_feature_set(&format!("feat-{}", num)).with_feature_value(&NodeFeature::NodeOperator, operator),
+            false,
+        )
+    }
+
+    fn new_test_planner(subnet_nodes: Vec<Node>, available_nodes: Vec<Node>) -> MigrationPlanner {
+        let subnet_id = PrincipalId::new_subnet_test_id(1);
+        let subnet_nodes = subnet_nodes.into_iter().map(|n| n.with_subnet_id(subnet_id)).collect_vec();
+        let all_nodes = subnet_nodes.iter().chain(available_nodes.iter()).cloned().collect_vec();
+        let health_of_nodes = all_nodes.iter().map(|n| (n.principal, HealthStatus::Healthy)).collect();
+        let subnet = Subnet {
+            principal: subnet_id,
+            nodes: subnet_nodes,
+            ..Default::default()
+        };
+        MigrationPlanner::new(
+            IndexMap::from([(subnet_id, subnet)]),
+            available_nodes,
+            health_of_nodes,
+            vec![],
+            all_nodes,
+            BusinessRules::default(),
+        )
+    }
+
+    #[test]
+    fn operator_is_unassigned_in_dependent_steps() {
+        let operator = PrincipalId::new_user_test_id(42);
+        // 4 of 12 nodes belong to the operator, but at most 2 nodes may be replaced at once
+        let subnet_nodes = (0..12)
+            .map(|i| new_test_node(i, &if i < 4 { operator.to_string() } else { format!("op-{}", i) }))
+            .collect_vec();
+        let available_nodes = (100..110).map(|i| new_test_node(i, &format!("op-{}", i))).collect_vec();
+
+        let plan = new_test_planner(subnet_nodes, available_nodes)
+            .plan(&MigrationTarget::OperatorUnassigned(operator))
+            .unwrap();
+
+        assert!(plan.is_complete());
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(plan.num_waves(), 2);
+        assert!(plan.steps[0].depends_on.is_empty());
+        assert_eq!(plan.steps[1].depends_on, vec![0]);
+        assert_eq!(plan.steps[0].network_score_after, plan.steps[1].network_score_before);
+        assert_eq!(plan.network_score_after, plan.steps[1].network_score_after);
+        let removed = plan.steps.iter().flat_map(|s| s.change.node_ids_removed.clone()).collect_vec();
+        assert_eq!(removed.len(), 4);
+        assert!(removed.iter().all(|n| (0..4).any(|i| PrincipalId::new_node_test_id(i) == *n)));
+    }
+
+    #[test]
+    fn operator_nodes_are_not_used_as_replacements() {
+        let operator = PrincipalId::new_user_test_id(42);
+        let subnet_nodes = (0..12)
+            .map(|i| new_test_node(i, &if i == 0 { operator.to_string() } else { format!("op-{}", i) }))
+            .collect_vec();
+        let available_nodes = (100..103).map(|i| new_test_node(i, &operator.to_string())).collect_vec();
+
+        let plan = new_test_planner(subnet_nodes, available_nodes)
+            .plan(&MigrationTarget::OperatorUnassigned(operator))
+            .unwrap();
+
+        assert!(plan.steps.is_empty());
+        assert_eq!(plan.unresolved_subnets, vec![PrincipalId::new_subnet_test_id(1)]);
+    }
+
+    #[test]
+    fn max_steps_limits_the_plan() {
+        let operator = PrincipalId::new_user_test_id(42);
+        let subnet_nodes = (0..12)
+            .map(|i| new_test_node(i, &if i < 4 { operator.to_string() } else { format!("op-{}", i) }))
+            .collect_vec();
+        let available_nodes = (100..110).map(|i| new_test_node(i, &format!("op-{}", i))).collect_vec();
+
+        let plan = new_test_planner(subnet_nodes, available_nodes)
+            .with_max_steps(1)
+            .plan(&MigrationTarget::OperatorUnassigned(operator))
+            .unwrap();
+
+        assert_eq!(plan.steps.len(), 1);
+        assert!(!plan.is_complete());
+    }
+
+    #[test]
+    fn next_wave_follows_the_executed_proposals() {
+        let operator = PrincipalId::new_user_test_id(42);
+        let subnet_id = PrincipalId::new_subnet_test_id(1);
+        let subnet_nodes = (0..12)
+            .map(|i| new_test_node(i, &if i < 4 { operator.to_string() } else { format!("op-{}", i) }))
+            .collect_vec();
+        let available_nodes = (100..110).map(|i| new_test_node(i, &format!("op-{}", i))).collect_vec();
+
+        let mut plan = new_test_planner(subnet_nodes, available_nodes)
+            .plan(&MigrationTarget::OperatorUnassigned(operator))
+            .unwrap();
+        assert_eq!(plan.num_waves(), 2);
+        let mut executed = IndexSet::new();
+
+        let (wave, steps) = plan.next_wave(&executed).unwrap();
+        assert_eq!((wave, steps.len(), steps[0].index), (1, 1, 0));
+
+        // An open proposal keeps its wave pending
+        plan.record_proposal(1, subnet_id, 10);
+        let (wave, steps) = plan.next_wave(&executed).unwrap();
+        assert_eq!((wave, steps[0].proposal_id), (1, Some(10)));
+
+        executed.insert(10);
+        let (wave, steps) = plan.next_wave(&executed).unwrap();
+        assert_eq!((wave, steps.len(), steps[0].index), (2, 1, 1));
+
+        plan.record_proposal(2, subnet_id, 11);
+        executed.insert(11);
+        assert!(plan.next_wave(&executed).is_none());
+        assert_eq!(plan.proposal_ids(), vec![10, 11]);
+    }
+}
@@ -1,4 +1,5 @@
 use super::*;
+use crate::business_rules::BusinessRules;
 use log::info;
 
 #[derive(Default, Clone, Debug)]
@@ -8,6 +9,7 @@ pub struct SubnetChangeRequest {
     pub(crate) include_nodes: Vec<Node>,
     pub(crate) nodes_to_remove: Vec<Node>,
     pub(crate) nodes_to_keep: Vec<Node>,
+    pub(crate) selection_strategy: SelectionStrategy,
 }
 
 impl SubnetChangeRequest {
@@ -24,6 +26,7 @@ impl SubnetChangeRequest {
             include_nodes,
             nodes_to_remove,
             nodes_to_keep,
+            selection_strategy: SelectionStrategy::default(),
         }
     }
 
@@ -79,6 +82,17 @@ impl SubnetChangeRequest {
         self.subnet.clone()
     }
 
+    pub fn with_business_rules(self, business_rules: BusinessRules) -> Self {
+        Self {
+            subnet: self.subnet.with_business_rules(business_rules),
+            ..self
+        }
+    }
+
+    pub fn with_selection_strategy(self, selection_strategy: SelectionStrategy) -> Self {
+        Self { selection_strategy, ..self }
+    }
+
     pub fn with_custom_available_nodes(self, nodes: Vec<Node>) -> Self {
         Self {
             available_nodes: nodes,
@@ -182,6 +196,7 @@ impl SubnetChangeRequest {
             available_nodes.len(),
         );
 
+        let healthy_available_nodes = available_nodes.clone();
         let resized_subnet = if how_many_nodes_to_remove > 0 {
             self.subnet
                 .clone()
@@ -218,11 +233,36 @@ impl SubnetChangeRequest {
             .map_err(|e| NetworkError::ResizeFailed(e.to_string()))?
             .without_duplicate_added_removed();
 
-        let penalties_before_change = DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet.id, &old_nodes)
-            .map_err(|e| NetworkError::ResizeFailed(e.to_string()))?;
+        let resized_subnet = match self.selection_strategy {
+            SelectionStrategy::Greedy => resized_subnet,
+            SelectionStrategy::Exact { time_budget } => {
+                let base_subnet = self.subnet.clone().with_nodes(&self.include_nodes).without_nodes(&self.nodes_to_remove)?;
+                let removable_nodes = base_subnet
+                    .nodes
+                    .iter()
+                    .filter(|n| !self.include_nodes.contains(n) && !self.nodes_to_keep.contains(n))
+                    .cloned()
+                    .collect_vec();
+                let result = base_subnet.subnet_with_exact_changes(
+                    how_many_nodes_to_remove,
+                    how_many_nodes_to_add,
+                    &removable_nodes,
+                    &healthy_available_nodes,
+                    &resized_subnet,
+                    time_budget,
+                )?;
+                info!("{}", result.report);
+                result.subnet
+            }
+        };
+
+        let penalties_before_change =
+            DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet.id, &old_nodes, &self.subnet.business_rules)
+                .map_err(|e| NetworkError::ResizeFailed(e.to_string()))?;
 
-        let penalties_after_change = DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet.id, &resized_subnet.nodes)
-            .map_err(|e| NetworkError::ResizeFailed(e.to_string()))?;
+        let penalties_after_change =
+            DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&self.subnet.id, &resized_subnet.nodes, &self.subnet.business_rules)
+                .map_err(|e| NetworkError::ResizeFailed(e.to_string()))?;
 
         let subnet_change = SubnetChange {
             subnet_id: self.subnet.id,
@@ -234,6 +274,7 @@ impl SubnetChangeRequest {
             penalties_after_change,
             comment: resized_subnet.comment,
             run_log: resized_subnet.run_log,
+            business_rules: self.subnet.business_rules.clone(),
         };
         Ok(subnet_change)
     }
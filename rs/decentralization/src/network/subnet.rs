@@ -1,6 +1,6 @@
 use super::*;
+use crate::business_rules::BusinessRules;
 use crate::nakamoto::NakamotoScore;
-use crate::provider_clusters::get_linked_providers;
 use log::{debug, info};
 use rand::seq::IndexedRandom;
 use std::cmp::Ordering;
@@ -14,6 +14,9 @@ pub struct DecentralizedSubnet {
     pub removed_nodes: Vec<Node>,
     pub comment: Option<String>,
     pub run_log: Vec<String>,
+    /// Policy that the business rules of the subnet are checked against
+    #[serde(skip)]
+    pub business_rules: BusinessRules,
 }
 
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -33,6 +36,7 @@ impl DecentralizedSubnet {
             removed_nodes: vec![],
             comment: None,
             run_log: vec![],
+            business_rules: BusinessRules::default(),
         }
     }
 
@@ -40,6 +44,10 @@ impl DecentralizedSubnet {
         Self { id: subnet_id, ..self }
     }
 
+    pub fn with_business_rules(self, business_rules: BusinessRules) -> Self {
+        Self { business_rules, ..self }
+    }
+
     /// Return a new instance of a DecentralizedSubnet that does not contain the
     /// provided nodes.
     pub fn without_nodes(&self, nodes_to_remove: &[Node]) -> Result<Self, NetworkError> {
@@ -72,6 +80,7 @@ impl DecentralizedSubnet {
                     run_log
                 }
             },
+            business_rules: self.business_rules.clone(),
         })
     }
 
@@ -104,6 +113,7 @@ impl DecentralizedSubnet {
                     run_log
                 }
             },
+            business_rules: self.business_rules,
         }
     }
 
@@ -127,13 +137,18 @@ impl DecentralizedSubnet {
 
     /// Check the "business rules" for the current DecentralizedSubnet.
     pub fn check_business_rules(&self) -> anyhow::Result<(usize, Vec<String>)> {
-        Self::check_business_rules_for_subnet_with_nodes(&self.id, &self.nodes)
+        Self::check_business_rules_for_subnet_with_nodes(&self.id, &self.nodes, &self.business_rules)
     }
 
     /// Ensure "business rules" or constraints are met for the subnet id with provided list of nodes.
     /// For instance, there needs to be at least one DFINITY-owned node in each subnet.
-    /// For the mainnet NNS there needs to be at least 3 DFINITY-owned nodes.
-    pub fn check_business_rules_for_subnet_with_nodes(subnet_id: &PrincipalId, nodes: &[Node]) -> anyhow::Result<(usize, Vec<String>)> {
+    /// The rules are taken from the given policy, see [`crate::business_rules`].
+    pub fn check_business_rules_for_subnet_with_nodes(
+        subnet_id: &PrincipalId,
+        nodes: &[Node],
+        business_rules: &BusinessRules,
+    ) -> anyhow::Result<(usize, Vec<String>)> {
+        let policy = business_rules.policy();
         let mut checks = Vec::new();
         let mut penalties = 0;
         if nodes.len() <= 1 {
@@ -141,33 +156,23 @@ impl DecentralizedSubnet {
         }
 
         let nakamoto_scores = Self::_calc_nakamoto_score(nodes);
-        let subnet_id_str = subnet_id.to_string();
-        let is_european_subnet = &subnet_id_str == "bkfrj-6k62g-dycql-7h53p-atvkj-zg4to-gaogh-netha-ptybj-ntsgw-rqe";
-        let is_nns_subnet = &subnet_id_str == "tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe";
+        let rules = policy.rules_for_subnet(subnet_id);
+        let weights = &policy.penalties;
 
         let dfinity_owned_nodes_count: usize = nodes.iter().map(|n| n.dfinity_owned.unwrap_or_default() as usize).sum();
-        let target_dfinity_owned_nodes_count = if is_nns_subnet { 3 } else { 1 };
+        let target_dfinity_owned_nodes_count = rules.dfinity_owned_nodes;
 
         if dfinity_owned_nodes_count != target_dfinity_owned_nodes_count {
             checks.push(format!(
                 "Subnet should have {} DFINITY-owned node(s) for subnet recovery, got {}",
                 target_dfinity_owned_nodes_count, dfinity_owned_nodes_count
             ));
-            penalties += target_dfinity_owned_nodes_count.abs_diff(dfinity_owned_nodes_count) * 1000;
+            penalties += target_dfinity_owned_nodes_count.abs_diff(dfinity_owned_nodes_count) * weights.dfinity_owned_nodes;
         }
 
-        if subnet_id_str == *"uzr34-akd3s-xrdag-3ql62-ocgoh-ld2ao-tamcv-54e7j-krwgb-2gm4z-oqe"
-            || is_nns_subnet
-            || subnet_id_str == *"x33ed-h457x-bsgyx-oqxqf-6pzwv-wkhzr-rm2j3-npodi-purzm-n66cg-gae"
-        {
-            // We keep the backup of the ECDSA key on uzr34, and we don't want a single
-            // country to be able to extract that key.
-            // The tECDSA key can be extracted with 1/3 of the nodes.
-            // We should use the same NC requirements for uzr34 and the upcoming ECDSA
-            // subnet, since they'll both hold the same valuable key.
-            // Slack discussion: https://dfinity.slack.com/archives/C01DB8MQ5M1/p1668702249558389
-            // For different reasons, there is the same requirement for the NNS and the SNS
-            // subnet.
+        if rules.country_share_below_one_third {
+            // Subnets holding valuable keys (e.g. the tECDSA key backup) must not allow a single
+            // country to control 1/3 of the nodes, since that's enough to extract the key.
             let feature = NodeFeature::Country;
             let controlled_nodes_max = nodes.len() / 3;
             for (country, count) in nakamoto_scores
@@ -175,7 +180,7 @@ impl DecentralizedSubnet {
                 .iter()
                 .filter(|(_country, count)| *count > controlled_nodes_max)
             {
-                let penalty = (count - controlled_nodes_max) * 1000;
+                let penalty = (count - controlled_nodes_max) * weights.country_above_one_third;
                 checks.push(format!(
                     "Country {} controls {} of nodes, which is > {} (1/3 - 1) of subnet nodes. Applying penalty of {}.",
                     country, count, controlled_nodes_max, penalty
@@ -184,10 +189,7 @@ impl DecentralizedSubnet {
             }
         }
 
-        // As per the adopted target topology
-        // https://dashboard.internetcomputer.org/proposal/132136
-        let max_nodes_per_np_and_dc = 1;
-        let dfinity_np = "bvcsg-3od6r-jnydw-eysln-aql7w-td5zn-ay5m6-sibd2-jzojt-anwag-mqe";
+        let dfinity_np = policy.dfinity_node_provider.as_str();
         let dfinity_dcs = nodes
             .iter()
             .filter(|n| n.operator.provider.principal.to_string() == dfinity_np)
@@ -199,14 +201,17 @@ impl DecentralizedSubnet {
             .map(|n| n.operator.datacenter.clone().unwrap_or_default().owner.name)
             .collect::<AHashSet<_>>();
 
-        for feature in &[NodeFeature::NodeProvider, NodeFeature::DataCenter, NodeFeature::DataCenterOwner] {
+        for (feature, max_nodes) in &[
+            (NodeFeature::NodeProvider, rules.max_nodes_per_node_provider),
+            (NodeFeature::DataCenter, rules.max_nodes_per_data_center),
+            (NodeFeature::DataCenterOwner, rules.max_nodes_per_data_center_owner),
+        ] {
             for (name, count) in nakamoto_scores
                 .feature_value_counts(feature)
                 .iter()
-                .filter(|(_name, count)| *count > max_nodes_per_np_and_dc)
+                .filter(|(_name, count)| count > max_nodes)
             {
-                // DFINITY is allowed to have 3 nodes on the NNS subnet, exempt from the standard “1 node per DC/NP” rule: https://dashboard.internetcomputer.org/proposal/135700
-                if is_nns_subnet && *count <= target_dfinity_owned_nodes_count {
+                if rules.dfinity_exempt_from_provider_limits && *count <= target_dfinity_owned_nodes_count {
                     if feature == &NodeFeature::NodeProvider && name == dfinity_np {
                         continue;
                     }
@@ -217,31 +222,41 @@ impl DecentralizedSubnet {
                         continue;
                     }
                 }
-                if *count > max_nodes_per_np_and_dc {
-                    let penalty = (count - max_nodes_per_np_and_dc) * 10;
-                    checks.push(format!(
-                        "{} {} controls {} of nodes, which is higher than target of {} for the subnet. Applying penalty of {}.",
-                        feature, name, count, max_nodes_per_np_and_dc, penalty
-                    ));
-                    penalties += penalty;
-                }
+                let penalty = (count - max_nodes) * weights.nodes_per_provider_or_data_center;
+                checks.push(format!(
+                    "{} {} controls {} of nodes, which is higher than target of {} for the subnet. Applying penalty of {}.",
+                    feature, name, count, max_nodes, penalty
+                ));
+                penalties += penalty;
             }
         }
 
-        // As per the adopted target topology
-        // https://dashboard.internetcomputer.org/proposal/132136
-        let max_nodes_per_country = match subnet_id_str.as_str() {
-            "tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe"
-            | "x33ed-h457x-bsgyx-oqxqf-6pzwv-wkhzr-rm2j3-npodi-purzm-n66cg-gae"
-            | "pzp6e-ekpqk-3c5x7-2h6so-njoeq-mt45d-h3h6c-q3mxf-vpeq5-fk5o7-yae"
-            | "uzr34-akd3s-xrdag-3ql62-ocgoh-ld2ao-tamcv-54e7j-krwgb-2gm4z-oqe" => 3,
-            _ => 2,
-        };
+        for (feature_name, max_nodes) in &rules.max_nodes_per_feature {
+            // Custom features are only known when the node features file is loaded
+            let Ok(feature) = NodeFeature::parse_known(feature_name, nakamoto_scores.features()) else {
+                debug!("Skipping the limit for node feature {}, which is not loaded", feature_name);
+                continue;
+            };
+            for (name, count) in nakamoto_scores
+                .feature_value_counts(&feature)
+                .iter()
+                .filter(|(_name, count)| count > max_nodes)
+            {
+                let penalty = (count - max_nodes) * weights.nodes_per_feature;
+                checks.push(format!(
+                    "{} {} controls {} of nodes, which is higher than target of {} for the subnet. Applying penalty of {}.",
+                    feature, name, count, max_nodes, penalty
+                ));
+                penalties += penalty;
+            }
+        }
+
+        let max_nodes_per_country = rules.max_nodes_per_country;
         for (name, count) in nakamoto_scores.feature_value_counts(&NodeFeature::Country) {
-            if is_european_subnet && !Node::is_country_from_eu(name.as_str()) {
-                // European subnet is expected to be controlled by European countries
-            } else if count > max_nodes_per_country {
-                let penalty = (count - max_nodes_per_country) * 10;
+            if !rules.is_country_allowed(name.as_str()) {
+                // Nodes from countries outside of the allow-list are penalized below
+            } else if rules.is_country_capped(name.as_str()) && count > max_nodes_per_country {
+                let penalty = (count - max_nodes_per_country) * weights.nodes_per_country;
                 checks.push(format!(
                     "Country {} controls {} of nodes, which is higher than target of {} for the subnet. Applying penalty of {}.",
                     name, count, max_nodes_per_country, penalty
@@ -250,22 +265,19 @@ impl DecentralizedSubnet {
             }
         }
 
-        if is_european_subnet {
-            // European subnet should only take European nodes.
+        if rules.allowed_countries.is_some() {
             let country_counts = nakamoto_scores.feature_value_counts(&NodeFeature::Country);
-            let non_european_nodes_count = country_counts
+            let not_allowed_nodes_count = country_counts
                 .iter()
-                .filter_map(|(country, count)| {
-                    if Node::is_country_from_eu(country.as_str()) || country.as_str() == "CH" || country.as_str() == "UK" {
-                        None
-                    } else {
-                        Some(*count)
-                    }
-                })
+                .filter(|(country, _count)| !rules.is_country_allowed(country.as_str()))
+                .map(|(_country, count)| *count)
                 .sum::<usize>();
-            if non_european_nodes_count > 0 {
-                checks.push(format!("European subnet has {} non-European node(s)", non_european_nodes_count));
-                penalties += non_european_nodes_count * 1000;
+            if not_allowed_nodes_count > 0 {
+                checks.push(match &rules.name {
+                    Some(name) => format!("{} subnet has {} non-{} node(s)", name, not_allowed_nodes_count, name),
+                    None => format!("Subnet has {} node(s) from countries which are not allowed", not_allowed_nodes_count),
+                });
+                penalties += not_allowed_nodes_count * weights.country_not_allowed;
             }
         }
 
@@ -274,25 +286,26 @@ impl DecentralizedSubnet {
                 if score <= 1.0 && nodes.len() > 3 {
                     // We restrict to subnets with >3 nodes to be able to build subnet from scratch
                     checks.push("A single Node Provider can halt the subnet".to_string());
-                    penalties += 10000;
+                    penalties += weights.single_node_provider_can_halt;
                 }
             }
             None => return Err(anyhow::anyhow!("Missing the Nakamoto score for the Node Provider")),
         }
 
-        for feature in &NodeFeature::variants() {
+        for feature in nakamoto_scores.features() {
             match (nakamoto_scores.score_feature(feature), nakamoto_scores.controlled_nodes(feature)) {
                 (Some(score), Some(controlled_nodes)) => {
-                    let european_subnet_penalty = is_european_subnet && feature == &NodeFeature::Country;
+                    // Subnets restricted to a set of countries are expected to be controlled by those countries
+                    let allowed_countries_exemption = rules.allowed_countries.is_some() && feature == &NodeFeature::Country;
 
-                    if score == 1.0 && controlled_nodes > nodes.len() * 2 / 3 && !european_subnet_penalty {
+                    if score == 1.0 && controlled_nodes > nodes.len() * 2 / 3 && !allowed_countries_exemption {
                         checks.push(format!(
                             "NodeFeature {} controls {} of nodes, which is > {} (2/3 of all) nodes",
                             feature,
                             controlled_nodes,
                             nodes.len() * 2 / 3
                         ));
-                        penalties += (controlled_nodes - nodes.len() * 2 / 3) * 1000;
+                        penalties += (controlled_nodes - nodes.len() * 2 / 3) * weights.feature_above_two_thirds;
                     }
                 }
                 (score, controlled_nodes) => {
@@ -305,9 +318,10 @@ impl DecentralizedSubnet {
         }
 
         let mut cluster_counter = AHashMap::new();
+        let linked_providers = business_rules.linked_providers();
         // Count how many nodes in the subnet could be controlled by linked providers
         for provider_id in nodes.iter().map(|n| n.operator.provider.principal).collect_vec() {
-            for (pl_name, pl_providers) in get_linked_providers() {
+            for (pl_name, pl_providers) in linked_providers.iter() {
                 if pl_providers.contains(&provider_id) {
                     *cluster_counter.entry(pl_name.clone()).or_insert(0) += 1;
                 }
@@ -317,7 +331,7 @@ impl DecentralizedSubnet {
         for (pl_name, count) in cluster_counter {
             if count > 1 {
                 checks.push(format!("{} has {} nodes in the subnet", pl_name, count));
-                penalties += 10 * (count - 1);
+                penalties += weights.linked_providers * (count - 1);
             }
         }
 
@@ -505,7 +519,7 @@ impl DecentralizedSubnet {
         touched_node: &Node,
         err_log: &mut Vec<String>,
     ) -> Option<ReplacementCandidate> {
-        match Self::check_business_rules_for_subnet_with_nodes(&self.id, subnet_nodes) {
+        match Self::check_business_rules_for_subnet_with_nodes(&self.id, subnet_nodes, &self.business_rules) {
             Ok((penalty, business_rules_log)) => {
                 let new_score = Self::_calc_nakamoto_score(subnet_nodes);
                 Some(ReplacementCandidate {
@@ -612,6 +626,7 @@ impl DecentralizedSubnet {
             removed_nodes: self.removed_nodes,
             comment,
             run_log,
+            business_rules: self.business_rules,
         })
     }
 
@@ -697,6 +712,7 @@ impl DecentralizedSubnet {
             removed_nodes: self.removed_nodes,
             comment,
             run_log,
+            business_rules: self.business_rules,
         })
     }
 
@@ -735,6 +751,7 @@ impl DecentralizedSubnet {
                 removed_nodes: removed_nodes_desc,
                 comment: self.comment.clone(),
                 run_log: self.run_log.clone(),
+                business_rules: self.business_rules,
             }
         } else {
             self
@@ -769,6 +786,7 @@ impl From<&ic_management_types::Subnet> for DecentralizedSubnet {
             removed_nodes: Vec::new(),
             comment: None,
             run_log: Vec::new(),
+            business_rules: BusinessRules::default(),
         }
     }
 }
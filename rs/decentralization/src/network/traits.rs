@@ -1,6 +1,7 @@
 use log::info;
 
 use super::*;
+use crate::business_rules::BusinessRules;
 
 pub trait AvailableNodesQuerier {
     fn available_nodes(&self) -> BoxFuture<'_, Result<Vec<Node>, NetworkError>>;
@@ -40,6 +41,7 @@ pub trait TopologyManager: SubnetQuerier + AvailableNodesQuerier + Sync {
         health_of_nodes: &'a IndexMap<PrincipalId, HealthStatus>,
         cordoned_features: Vec<CordonedFeature>,
         all_nodes: &'a [Node],
+        business_rules: BusinessRules,
     ) -> BoxFuture<'a, Result<SubnetChange, NetworkError>> {
         Box::pin(async move {
             let mut available_nodes = self.available_nodes().await?;
@@ -83,6 +85,7 @@ pub trait TopologyManager: SubnetQuerier + AvailableNodesQuerier + Sync {
                 available_nodes,
                 ..Default::default()
             }
+            .with_business_rules(business_rules)
             .adding_from_available(add_nodes)
             .excluding_from_available(exclude_nodes)
             .resize(size, 0, 0, health_of_nodes, cordoned_features, all_nodes)
@@ -1,36 +1,247 @@
 use ahash::{AHashMap, AHashSet};
 use ic_base_types::PrincipalId;
-use lazy_static::lazy_static;
+use ic_management_types::Subnet;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
-// List of providers known to be linked. Having them grouped reduces the risk of sybil attacks.
-// To add a new provider cluster, include the cluster name and its associated Principal IDs in the map.
-// Always add a reference link in the comment, preferably pointing directly to the provider's explanation.
-lazy_static! {
-    static ref LINKED_PROVIDERS: AHashMap<String, AHashSet<PrincipalId>> = AHashMap::from_iter([
-        (
-            // https://forum.dfinity.org/t/sybiling-nodes-exploiting-ic-network-community-attention-required/40690/59
-            "Node provider cluster 1 (6sq7t, vegae, eatbv)".to_string(),
-            AHashSet::from_iter(vec![
-                PrincipalId::from_str("6sq7t-knkul-fko6h-xzvnf-ktbvr-jhx7r-hapzr-kjlek-whugy-zt6ip-xqe").unwrap(),
-                PrincipalId::from_str("vegae-c4chr-aetfj-7gzuh-c23sx-u2paz-vmvbn-bcage-pu7lu-mptnn-eqe").unwrap(),
-                PrincipalId::from_str("eatbv-nlydd-n655c-g7j7p-gnmpz-pszdg-6e6et-veobv-ftz2y-4m752-vqe").unwrap(),
-            ]),
-        ),
-        (
-            // https://forum.dfinity.org/t/enhancing-network-decentralization-proposals-for-node-provider-standards/43053/99
-            "Providers that share multiple dcs 1".to_string(),
-            AHashSet::from_iter([
-                PrincipalId::from_str("4r6qy-tljxg-slziw-zoteo-pboxh-vlctz-hkv2d-7zior-u3pxm-mmuxb-cae").unwrap(),
-                PrincipalId::from_str("ivf2y-crxj4-y6ewo-un35q-a7pum-wqmbw-pkepy-d6uew-bfmff-g5yxe-eae").unwrap(),
-                PrincipalId::from_str("3oqw6-vmpk2-mlwlx-52z5x-e3p7u-fjlcw-yxc34-lf2zq-6ub2f-v63hk-lae").unwrap(),
-                PrincipalId::from_str("dhywe-eouw6-hstpj-ahsnw-xnjxq-cmqks-47mrg-nnncb-3sr5d-rac6m-nae").unwrap(),
-                PrincipalId::from_str("diyay-s4rfq-xnx23-zczwi-nptra-5254n-e4zn6-p7tqe-vqhzr-sd4gd-bqe").unwrap(),
-            ]),
-        )
-    ]);
+/// The only provider clusters schema version understood by this release.
+pub const PROVIDER_CLUSTERS_VERSION: u32 = 1;
+
+const BUNDLED_PROVIDER_CLUSTERS: &str = include_str!("../../../provider_clusters.yaml");
+
+/// Node providers known to be linked. Having them grouped reduces the risk of sybil attacks.
+///
+/// The clusters are kept in `provider_clusters.yaml` at the root of the repository, so that they
+/// can follow ownership disclosures without releasing a new `dre`.
+/// The `Default` implementation is the copy of that file bundled with this release.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderClusters {
+    pub version: u32,
+    #[serde(default)]
+    pub clusters: Vec<ProviderCluster>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderCluster {
+    pub name: String,
+    /// Why the providers are considered linked, preferably a link to the providers' own explanation
+    pub source: String,
+    pub providers: Vec<String>,
+}
+
+impl ProviderCluster {
+    /// Principals of the providers, skipping the invalid ones which `validate` reports.
+    pub fn provider_ids(&self) -> AHashSet<PrincipalId> {
+        self.providers.iter().filter_map(|p| PrincipalId::from_str(p).ok()).collect()
+    }
+}
+
+impl Default for ProviderClusters {
+    fn default() -> Self {
+        Self::from_yaml(BUNDLED_PROVIDER_CLUSTERS.as_bytes()).expect("bundled provider clusters are valid")
+    }
+}
+
+impl ProviderClusters {
+    pub fn from_yaml(contents: &[u8]) -> anyhow::Result<Self> {
+        let clusters: Self = serde_yaml::from_slice(contents).map_err(|e| anyhow::anyhow!("Failed to parse provider clusters: {}", e))?;
+        clusters.validate()?;
+        Ok(clusters)
+    }
+
+    pub fn from_json(contents: &[u8]) -> anyhow::Result<Self> {
+        let clusters: Self = serde_json::from_slice(contents).map_err(|e| anyhow::anyhow!("Failed to parse provider clusters: {}", e))?;
+        clusters.validate()?;
+        Ok(clusters)
+    }
+
+    /// Parses the clusters as JSON if the contents look like JSON, otherwise as YAML.
+    pub fn parse(contents: &[u8]) -> anyhow::Result<Self> {
+        match contents.iter().find(|c| !c.is_ascii_whitespace()) {
+            Some(b'{') => Self::from_json(contents),
+            _ => Self::from_yaml(contents),
+        }
+    }
+
+    pub fn to_yaml(&self) -> anyhow::Result<String> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    /// Checks the clusters for mistakes which the deserialization alone cannot catch.
+    /// All problems are collected and reported at once.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut errors = vec![];
+        if self.version != PROVIDER_CLUSTERS_VERSION {
+            errors.push(format!(
+                "Unsupported provider clusters version {}, expected {}",
+                self.version, PROVIDER_CLUSTERS_VERSION
+            ));
+        }
+        for name in self.clusters.iter().map(|c| &c.name).duplicates() {
+            errors.push(format!("Cluster `{}` is listed more than once", name));
+        }
+        for cluster in &self.clusters {
+            if cluster.name.trim().is_empty() {
+                errors.push("A cluster has an empty name".to_string());
+            }
+            if cluster.source.trim().is_empty() {
+                errors.push(format!("Cluster `{}` must have a source", cluster.name));
+            }
+            if cluster.providers.len() < 2 {
+                errors.push(format!("Cluster `{}` must have at least 2 providers", cluster.name));
+            }
+            for provider in &cluster.providers {
+                if PrincipalId::from_str(provider).is_err() {
+                    errors.push(format!("Provider `{}` of cluster `{}` is not a valid principal", provider, cluster.name));
+                }
+            }
+        }
+        if !errors.is_empty() {
+            anyhow::bail!("Invalid provider clusters:\n  {}", errors.join("\n  "));
+        }
+        Ok(())
+    }
+
+    pub fn linked_providers(&self) -> AHashMap<String, AHashSet<PrincipalId>> {
+        self.clusters.iter().map(|c| (c.name.clone(), c.provider_ids())).collect()
+    }
+
+    /// Subnets with more than one node of a cluster, which the business rules penalize.
+    pub fn subnets_with_several_members<'a>(&self, subnets: impl IntoIterator<Item = &'a Subnet>) -> Vec<ClusterMembersInSubnet> {
+        let clusters = self.clusters.iter().map(|c| (c, c.provider_ids())).collect_vec();
+        subnets
+            .into_iter()
+            .flat_map(|subnet| {
+                clusters.iter().filter_map(|(cluster, providers)| {
+                    let nodes = subnet
+                        .nodes
+                        .iter()
+                        .filter(|n| providers.contains(&n.operator.provider.principal))
+                        .map(|n| n.principal)
+                        .collect_vec();
+                    (nodes.len() > 1).then(|| ClusterMembersInSubnet {
+                        cluster: cluster.name.clone(),
+                        subnet_id: subnet.principal,
+                        nodes,
+                    })
+                })
+            })
+            .collect()
+    }
 }
 
-pub fn get_linked_providers() -> AHashMap<String, AHashSet<PrincipalId>> {
-    LINKED_PROVIDERS.clone()
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClusterMembersInSubnet {
+    pub cluster: String,
+    pub subnet_id: PrincipalId,
+    pub nodes: Vec<PrincipalId>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_management_types::{Node, NodeFeatures};
+
+    #[test]
+    fn bundled_clusters_are_valid() {
+        let clusters = ProviderClusters::parse(BUNDLED_PROVIDER_CLUSTERS.as_bytes()).unwrap();
+        assert!(!clusters.clusters.is_empty());
+    }
+
+    #[test]
+    fn clusters_are_parsed_from_yaml_and_json() {
+        let yaml = br#"
+version: 1
+clusters:
+  - name: Shared owner
+    source: https://forum.dfinity.org/t/example/1
+    providers:
+      - 6sq7t-knkul-fko6h-xzvnf-ktbvr-jhx7r-hapzr-kjlek-whugy-zt6ip-xqe
+      - bvcsg-3od6r-jnydw-eysln-aql7w-td5zn-ay5m6-sibd2-jzojt-anwag-mqe
+"#;
+        let clusters = ProviderClusters::parse(yaml).unwrap();
+        assert_eq!(clusters.linked_providers()["Shared owner"].len(), 2);
+
+        let json = serde_json::to_vec(&clusters).unwrap();
+        assert_eq!(ProviderClusters::parse(&json).unwrap(), clusters);
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(ProviderClusters::parse(b"version: 1\nfeatures: []").is_err());
+    }
+
+    #[test]
+    fn validation_reports_all_errors() {
+        let contents = br#"
+version: 2
+clusters:
+  - name: cluster
+    source: ""
+    providers:
+      - not-a-principal
+  - name: cluster
+    source: https://forum.dfinity.org
+    providers:
+      - 6sq7t-knkul-fko6h-xzvnf-ktbvr-jhx7r-hapzr-kjlek-whugy-zt6ip-xqe
+      - vegae-c4chr-aetfj-7gzuh-c23sx-u2paz-vmvbn-bcage-pu7lu-mptnn-eqe
+"#;
+        let err = ProviderClusters::from_yaml(contents).unwrap_err().to_string();
+        for expected in [
+            "Unsupported provider clusters version 2",
+            "Cluster `cluster` is listed more than once",
+            "Cluster `cluster` must have a source",
+            "Cluster `cluster` must have at least 2 providers",
+            "Provider `not-a-principal` of cluster `cluster` is not a valid principal",
+        ] {
+            assert!(err.contains(expected), "`{}` not found in:\n{}", expected, err);
+        }
+    }
+
+    #[test]
+    fn subnets_with_several_members_of_a_cluster() {
+        let clusters = ProviderClusters::default();
+        let providers = clusters.clusters[0].provider_ids().into_iter().collect_vec();
+        let node = |number: u64, provider: PrincipalId| {
+            let mut node = Node::new_test_node(number, NodeFeatures::new_test_feature_set(&number.to_string()), false);
+            node.operator.provider.principal = provider;
+            node
+        };
+        let subnet = |number: u64, nodes: Vec<Node>| Subnet {
+            principal: PrincipalId::new_subnet_test_id(number),
+            nodes,
+            ..Default::default()
+        };
+        let subnets = [
+            subnet(
+                1,
+                vec![node(1, providers[0]), node(2, providers[1]), node(3, PrincipalId::new_user_test_id(1))],
+            ),
+            subnet(2, vec![node(4, providers[0]), node(5, PrincipalId::new_user_test_id(1))]),
+        ];
+
+        let members = clusters.subnets_with_several_members(&subnets);
+        assert_eq!(
+            members,
+            vec![ClusterMembersInSubnet {
+                cluster: clusters.clusters[0].name.clone(),
+                subnet_id: PrincipalId::new_subnet_test_id(1),
+                nodes: vec![PrincipalId::new_node_test_id(1), PrincipalId::new_node_test_id(2)],
+            }]
+        );
+    }
+
+    #[test]
+    fn clusters_without_source_are_rejected() {
+        let contents = br#"
+version: 1
+clusters:
+  - name: cluster
+    providers: []
+"#;
+        assert!(ProviderClusters::from_yaml(contents).is_err());
+    }
 }
@@ -0,0 +1,731 @@
+//! Renders a [`SubnetChangeResponse`] for the people and tools reviewing a subnet membership change.
+//!
+//! The CLI, the backend and the proposal and forum post texts all use this renderer, so the
+//! report reads the same wherever it is shown.
+use std::fmt::{Display, Formatter, Write};
+use std::str::FromStr;
+
+use ic_base_types::PrincipalId;
+use ic_management_types::{Node, NodeFeature};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::SubnetChangeResponse;
+use crate::nakamoto::NakamotoThreshold;
+
+/// Version of the JSON report, increased on every change that is not backwards compatible.
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, strum_macros::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ReportFormat {
+    /// Summary used in proposals and forum posts
+    #[default]
+    Markdown,
+    /// Self-contained page with the full diff
+    Html,
+    /// Stable schema for tooling, see [`SubnetChangeReportJson`]
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(anyhow::anyhow!("Unknown report format `{}`, expected markdown, html or json", s)),
+        }
+    }
+}
+
+impl ReportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ReportFormat::Markdown => "text/markdown; charset=utf-8",
+            ReportFormat::Html => "text/html; charset=utf-8",
+            ReportFormat::Json => "application/json",
+        }
+    }
+}
+
+pub struct SubnetChangeReport<'a> {
+    change: &'a SubnetChangeResponse,
+}
+
+impl<'a> From<&'a SubnetChangeResponse> for SubnetChangeReport<'a> {
+    fn from(change: &'a SubnetChangeResponse) -> Self {
+        Self { change }
+    }
+}
+
+/// JSON report of a subnet membership change.
+///
+/// Fields are only added to this schema, anything else increases [`REPORT_SCHEMA_VERSION`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SubnetChangeReportJson {
+    pub schema_version: u32,
+    pub subnet_id: Option<PrincipalId>,
+    pub proposal_id: Option<u64>,
+    pub motivation: Option<String>,
+    pub comment: Option<String>,
+    pub nakamoto: Vec<ThresholdReport>,
+    pub penalties: PenaltiesReport,
+    pub features: Vec<FeatureReport>,
+    pub nodes_removed: Vec<NodeReport>,
+    pub nodes_added: Vec<NodeReport>,
+    pub nodes_before: Vec<NodeReport>,
+    pub nodes_after: Vec<NodeReport>,
+}
+
+/// Nakamoto coefficients before and after the change, at one threshold.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ThresholdReport {
+    pub threshold: NakamotoThreshold,
+    pub description: String,
+    pub mean_before: f64,
+    pub mean_after: f64,
+    pub coefficients: Vec<CoefficientReport>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CoefficientReport {
+    pub feature: String,
+    pub before: f64,
+    pub after: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PenaltiesReport {
+    pub before: usize,
+    pub after: usize,
+    pub details_before: Vec<String>,
+    pub details_after: Vec<String>,
+}
+
+/// Number of subnet nodes with each value of a feature.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FeatureReport {
+    pub feature: String,
+    pub values: Vec<FeatureValueReport>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FeatureValueReport {
+    pub value: String,
+    pub nodes_before: usize,
+    pub nodes_after: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeReport {
+    pub node_id: PrincipalId,
+    pub health: Option<String>,
+    /// Feature values of the node, e.g. its provider, data center and country, if the node is known
+    pub features: IndexMap<String, String>,
+}
+
+/// One line per feature with the change of its Nakamoto coefficient.
+fn feature_changes(before: &IndexMap<NodeFeature, f64>, after: &IndexMap<NodeFeature, f64>) -> Vec<String> {
+    before
+        .keys()
+        .sorted()
+        .filter(|f| *f != &NodeFeature::NodeOperator)
+        .map(|k| {
+            let before = before.get(k).unwrap();
+            let after = after.get(k).unwrap();
+            format!(
+                "{}: {:.2} -> {:.2}  {:>7}",
+                k,
+                before,
+                after,
+                format_args!("({:+.0}%)", ((after - before) / before) * 100.).to_string()
+            )
+        })
+        .collect()
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin:0.5em 2em 1.5em 0;display:inline-table;vertical-align:top}\
+th,td{border:1px solid #ccc;padding:0.2em 0.6em;text-align:left}\
+td.num{text-align:right}\
+tr.added{background:#e6ffed}tr.removed{background:#ffeef0}\
+.better{color:#22863a}.worse{color:#cb2431}\
+pre{white-space:pre-wrap}";
+
+impl SubnetChangeReport<'_> {
+    pub fn render(&self, format: ReportFormat) -> anyhow::Result<String> {
+        Ok(match format {
+            ReportFormat::Markdown => self.markdown(),
+            ReportFormat::Html => self.html(),
+            ReportFormat::Json => serde_json::to_string_pretty(&self.json())?,
+        })
+    }
+
+    /// Summary included in the proposal and the forum post.
+    pub fn markdown(&self) -> String {
+        self.to_string()
+    }
+
+    /// Thresholds at which the coefficients were computed, starting with 1/3.
+    fn thresholds(&self) -> Vec<NakamotoThreshold> {
+        self.change
+            .score_before
+            .thresholds()
+            .into_iter()
+            .chain(self.change.score_after.thresholds())
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    fn health(&self, node_id: &PrincipalId) -> Option<String> {
+        self.change.health_of_nodes.get(node_id).map(|h| h.to_string().to_lowercase())
+    }
+
+    fn node_report(&self, node_id: &PrincipalId) -> NodeReport {
+        let node = self
+            .change
+            .nodes_old
+            .iter()
+            .chain(self.change.nodes_new.iter())
+            .find(|n| n.principal == *node_id);
+        NodeReport {
+            node_id: *node_id,
+            health: self.health(node_id),
+            features: node.map(node_locations).unwrap_or_default(),
+        }
+    }
+
+    pub fn json(&self) -> SubnetChangeReportJson {
+        let change = self.change;
+        SubnetChangeReportJson {
+            schema_version: REPORT_SCHEMA_VERSION,
+            subnet_id: change.subnet_id,
+            proposal_id: change.proposal_id,
+            motivation: change.motivation.clone(),
+            comment: change.comment.clone(),
+            nakamoto: self
+                .thresholds()
+                .into_iter()
+                .map(|threshold| {
+                    let before = change.score_before.scores_individual_at(&threshold);
+                    let after = change.score_after.scores_individual_at(&threshold);
+                    ThresholdReport {
+                        threshold,
+                        description: threshold.description().to_string(),
+                        mean_before: change.score_before.score_avg_linear_at(&threshold),
+                        mean_after: change.score_after.score_avg_linear_at(&threshold),
+                        coefficients: before
+                            .keys()
+                            .sorted()
+                            .filter(|f| *f != &NodeFeature::NodeOperator)
+                            .map(|feature| CoefficientReport {
+                                feature: feature.to_string(),
+                                before: before.get(feature).copied().unwrap_or_default(),
+                                after: after.get(feature).copied().unwrap_or_default(),
+                            })
+                            .collect(),
+                    }
+                })
+                .collect(),
+            penalties: PenaltiesReport {
+                before: change.penalties_before_change.0,
+                after: change.penalties_after_change.0,
+                details_before: change.penalties_before_change.1.clone(),
+                details_after: change.penalties_after_change.1.clone(),
+            },
+            features: change
+                .feature_diff
+                .iter()
+                .map(|(feature, diff)| FeatureReport {
+                    feature: feature.to_string(),
+                    values: diff
+                        .iter()
+                        .sorted()
+                        .map(|(value, (before, after))| FeatureValueReport {
+                            value: value.clone(),
+                            nodes_before: *before,
+                            nodes_after: *after,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            nodes_removed: change.node_ids_removed.iter().map(|n| self.node_report(n)).collect(),
+            nodes_added: change.node_ids_added.iter().map(|n| self.node_report(n)).collect(),
+            nodes_before: change.nodes_old.iter().map(|n| self.node_report(&n.principal)).collect(),
+            nodes_after: change.nodes_new.iter().map(|n| self.node_report(&n.principal)).collect(),
+        }
+    }
+
+    /// Self-contained HTML page with the Nakamoto coefficients, the before and after
+    /// node count of every feature value, and the location of the subnet nodes.
+    pub fn html(&self) -> String {
+        let report = self.json();
+        let title = match report.subnet_id {
+            Some(subnet_id) => format!("Membership change of subnet {}", subnet_id),
+            None => "Subnet membership change".to_string(),
+        };
+        let mut out = String::new();
+        // Writing to a String cannot fail
+        let _ = self.write_html(&mut out, &title, &report);
+        out
+    }
+
+    fn write_html(&self, out: &mut String, title: &str, report: &SubnetChangeReportJson) -> std::fmt::Result {
+        writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">")?;
+        writeln!(
+            out,
+            "<title>{}</title>\n<style>{}</style>\n</head>\n<body>",
+            escape_html(title),
+            HTML_STYLE
+        )?;
+        writeln!(out, "<h1>{}</h1>", escape_html(title))?;
+        if let Some(proposal_id) = report.proposal_id {
+            writeln!(out, "<p>Proposal {}</p>", proposal_id)?;
+        }
+        if let Some(motivation) = &report.motivation {
+            writeln!(out, "<h2>Motivation</h2>\n<pre>{}</pre>", escape_html(motivation))?;
+        }
+
+        writeln!(out, "<h2>Nakamoto coefficients</h2>")?;
+        for threshold in &report.nakamoto {
+            writeln!(
+                out,
+                "<table>\n<caption>{} ({}) threshold, mean {:.2} &rarr; {:.2}</caption>\n<tr><th>Feature</th><th>Before</th><th>After</th></tr>",
+                threshold.threshold, threshold.description, threshold.mean_before, threshold.mean_after
+            )?;
+            for c in &threshold.coefficients {
+                let class = match c.after.partial_cmp(&c.before) {
+                    Some(std::cmp::Ordering::Greater) => "better",
+                    Some(std::cmp::Ordering::Less) => "worse",
+                    _ => "",
+                };
+                writeln!(
+                    out,
+                    "<tr><td>{}</td><td class=\"num\">{:.2}</td><td class=\"num {}\">{:.2}</td></tr>",
+                    escape_html(&c.feature),
+                    c.before,
+                    class,
+                    c.after
+                )?;
+            }
+            writeln!(out, "</table>")?;
+        }
+
+        writeln!(
+            out,
+            "<h2>Business rules penalties: {} &rarr; {}</h2>",
+            report.penalties.before, report.penalties.after
+        )?;
+        for (when, details) in [("before", &report.penalties.details_before), ("after", &report.penalties.details_after)] {
+            if !details.is_empty() {
+                writeln!(out, "<p>Check results {} the change:</p>\n<ul>", when)?;
+                for line in details {
+                    writeln!(out, "<li>{}</li>", escape_html(line))?;
+                }
+                writeln!(out, "</ul>")?;
+            }
+        }
+
+        writeln!(out, "<h2>Nodes per feature value</h2>")?;
+        for feature in &report.features {
+            writeln!(
+                out,
+                "<table>\n<caption>{}</caption>\n<tr><th>Value</th><th>Before</th><th>After</th></tr>",
+                escape_html(&feature.feature)
+            )?;
+            for value in &feature.values {
+                let class = match value.nodes_after.cmp(&value.nodes_before) {
+                    std::cmp::Ordering::Greater => " class=\"added\"",
+                    std::cmp::Ordering::Less => " class=\"removed\"",
+                    std::cmp::Ordering::Equal => "",
+                };
+                writeln!(
+                    out,
+                    "<tr{}><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                    class,
+                    escape_html(&value.value),
+                    value.nodes_before,
+                    value.nodes_after
+                )?;
+            }
+            writeln!(out, "</table>")?;
+        }
+
+        for (heading, nodes) in [
+            ("Nodes before the change", &report.nodes_before),
+            ("Nodes after the change", &report.nodes_after),
+        ] {
+            writeln!(out, "<h2>{}</h2>", heading)?;
+            let features = nodes.iter().flat_map(|n| n.features.keys()).unique().collect_vec();
+            write!(out, "<table>\n<tr><th>Node</th><th>Health</th>")?;
+            for feature in &features {
+                write!(out, "<th>{}</th>", escape_html(feature))?;
+            }
+            writeln!(out, "</tr>")?;
+            for node in nodes {
+                let class = if self.change.node_ids_added.contains(&node.node_id) {
+                    " class=\"added\""
+                } else if self.change.node_ids_removed.contains(&node.node_id) {
+                    " class=\"removed\""
+                } else {
+                    ""
+                };
+                write!(
+                    out,
+                    "<tr{}><td><code>{}</code></td><td>{}</td>",
+                    class,
+                    node.node_id,
+                    node.health.as_deref().unwrap_or("unknown")
+                )?;
+                for feature in &features {
+                    write!(
+                        out,
+                        "<td>{}</td>",
+                        escape_html(node.features.get(*feature).map(String::as_str).unwrap_or_default())
+                    )?;
+                }
+                writeln!(out, "</tr>")?;
+            }
+            writeln!(out, "</table>")?;
+        }
+        writeln!(out, "</body>\n</html>")
+    }
+}
+
+/// Features of a node that place it, leaving out the node id itself.
+fn node_locations(node: &Node) -> IndexMap<String, String> {
+    node.get_features()
+        .feature_map
+        .into_iter()
+        .filter(|(feature, _)| *feature != NodeFeature::NodeId)
+        .map(|(feature, value)| (feature.to_string(), value))
+        .collect()
+}
+
+impl Display for SubnetChangeReport<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let change = self.change;
+        writeln!(
+            f,
+            "Decentralization Nakamoto coefficient changes for subnet `{}`:\n```",
+            change.subnet_id.unwrap_or_default()
+        )?;
+        for line in feature_changes(&change.score_before.scores_individual(), &change.score_after.scores_individual()) {
+            writeln!(f, "{: >40}", line)?;
+        }
+
+        let total_before = change.score_before.score_avg_linear();
+        let total_after = change.score_after.score_avg_linear();
+        writeln!(
+            f,
+            "```\n\n**Mean Nakamoto comparison:** {:.2} -> {:.2}  ({:+.0}%)\n\nOverall replacement impact: {}",
+            total_before,
+            total_after,
+            ((total_after - total_before) / total_before) * 100.,
+            change.score_after.describe_difference_from(&change.score_before).1
+        )?;
+
+        let liveness_before = change.score_before.scores_individual();
+        let liveness_after = change.score_after.scores_individual();
+        let mut opposite_effects = vec![];
+        for threshold in self.thresholds().into_iter().filter(|t| *t != NakamotoThreshold::ONE_THIRD) {
+            let before = change.score_before.scores_individual_at(&threshold);
+            let after = change.score_after.scores_individual_at(&threshold);
+            writeln!(
+                f,
+                "\nNakamoto coefficient changes at the {} ({}) threshold:\n```",
+                threshold,
+                threshold.description()
+            )?;
+            for line in feature_changes(&before, &after) {
+                writeln!(f, "{: >40}", line)?;
+            }
+            writeln!(f, "```")?;
+
+            for feature in before.keys().sorted().filter(|f| *f != &NodeFeature::NodeOperator) {
+                let liveness_diff = liveness_after.get(feature).unwrap_or(&0.) - liveness_before.get(feature).unwrap_or(&0.);
+                let threshold_diff = after.get(feature).unwrap_or(&0.) - before.get(feature).unwrap_or(&0.);
+                if liveness_diff * threshold_diff < 0. {
+                    opposite_effects.push(format!(
+                        "- {}: {} ({}) {:+.0}, {} ({}) {:+.0}",
+                        feature,
+                        NakamotoThreshold::ONE_THIRD,
+                        NakamotoThreshold::ONE_THIRD.description(),
+                        liveness_diff,
+                        threshold,
+                        threshold.description(),
+                        threshold_diff
+                    ));
+                }
+            }
+        }
+        if !opposite_effects.is_empty() {
+            writeln!(
+                f,
+                "\n**Note:** this change moves the Nakamoto coefficients in opposite directions at different thresholds:\n{}",
+                opposite_effects.join("\n")
+            )?;
+        }
+
+        if (change.penalties_before_change.0 != change.penalties_after_change.0) || (change.penalties_after_change.0 > 0) {
+            writeln!(
+                f,
+                "\nImpact on business rules penalties: {} -> {}",
+                change.penalties_before_change.0, change.penalties_after_change.0
+            )?;
+
+            if !change.penalties_before_change.1.is_empty() {
+                writeln!(
+                    f,
+                    "\nBusiness rules check results *before* the membership change:\n{}",
+                    change.penalties_before_change.1.iter().map(|l| format!("- {}", l)).join("\n")
+                )?;
+            }
+
+            if !change.penalties_after_change.1.is_empty() {
+                writeln!(
+                    f,
+                    "\nBusiness rules check results *after* the membership change:\n{}",
+                    change.penalties_after_change.1.iter().map(|l| format!("- {}", l)).join("\n")
+                )?;
+            }
+        }
+
+        writeln!(f, "\n\n# Details\n\nNodes removed:")?;
+        for node_id in &change.node_ids_removed {
+            writeln!(f, "- `{}` [health: {}]", node_id, self.health(node_id).as_deref().unwrap_or("unknown"))?;
+        }
+        writeln!(f, "\nNodes added:")?;
+        for node_id in &change.node_ids_added {
+            writeln!(f, "- `{}` [health: {}]", node_id, self.health(node_id).as_deref().unwrap_or("unknown"))?;
+        }
+
+        let rows = change.feature_diff.values().map(|diff| diff.len()).max().unwrap_or(0);
+        let mut table = tabular::Table::new(&change.feature_diff.keys().map(|_| "    {:<}  {:>}").collect::<Vec<_>>().join(""));
+        table.add_row(
+            change
+                .feature_diff
+                .keys()
+                .fold(tabular::Row::new(), |acc, k| acc.with_cell(k.to_string()).with_cell("")),
+        );
+        table.add_row(
+            change
+                .feature_diff
+                .keys()
+                .fold(tabular::Row::new(), |acc, k| acc.with_cell("-".repeat(k.to_string().len())).with_cell("")),
+        );
+        for i in 0..rows {
+            table.add_row(change.feature_diff.values().fold(tabular::Row::new(), |acc, v| {
+                let (value, value_change) = v
+                    .iter()
+                    .sorted()
+                    .nth(i)
+                    .map(|(k, (before, after))| {
+                        (
+                            k.to_string(),
+                            match before.cmp(after) {
+                                std::cmp::Ordering::Equal => format!("{}", before),
+                                std::cmp::Ordering::Greater => format!("{} -> {}", before, after),
+                                std::cmp::Ordering::Less => format!("{} -> {}", before, after),
+                            },
+                        )
+                    })
+                    .unwrap_or_default();
+                acc.with_cell(value).with_cell(value_change)
+            }));
+        }
+
+        writeln!(f, "\n\n```\n{}```", table)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_management_types::{HealthStatus, NodeFeatures};
+
+    use super::*;
+    use crate::network::SubnetChange;
+
+    fn change() -> SubnetChangeResponse {
+        let nodes = (0..4)
+            .map(|i| Node::new_test_node(i, NodeFeatures::new_test_feature_set(&format!("<dc{}>", i % 2)), false))
+            .collect_vec();
+        let added = Node::new_test_node(4, NodeFeatures::new_test_feature_set("dc4"), false);
+        let change = SubnetChange {
+            subnet_id: PrincipalId::new_subnet_test_id(1),
+            old_nodes: nodes.clone(),
+            new_nodes: nodes[1..].iter().cloned().chain(std::iter::once(added.clone())).collect(),
+            removed_nodes: vec![nodes[0].clone()],
+            added_nodes: vec![added],
+            ..Default::default()
+        };
+        SubnetChangeResponse::new(&change, &IndexMap::new(), Some("Replace a node".to_string()))
+    }
+
+    #[test]
+    fn json_report_lists_nodes_and_feature_counts() {
+        let change = change();
+        let report = SubnetChangeReport::from(&change).json();
+
+        assert_eq!(report.schema_version, REPORT_SCHEMA_VERSION);
+        assert_eq!(
+            report.nodes_removed.iter().map(|n| n.node_id).collect_vec(),
+            vec![PrincipalId::new_node_test_id(0)]
+        );
+        assert_eq!(report.nodes_added[0].features.get("country").map(String::as_str), Some("dc4"));
+        assert_eq!(report.nodes_after.len(), 4);
+        let countries = report.features.iter().find(|f| f.feature == "country").unwrap();
+        assert_eq!(
+            countries
+                .values
+                .iter()
+                .map(|v| (v.value.as_str(), v.nodes_before, v.nodes_after))
+                .collect_vec(),
+            vec![("<dc0>", 2, 1), ("<dc1>", 2, 2), ("dc4", 0, 1)]
+        );
+        assert_eq!(report.nakamoto[0].threshold, NakamotoThreshold::ONE_THIRD);
+
+        let json: serde_json::Value = serde_json::from_str(&SubnetChangeReport::from(&change).render(ReportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["schema_version"], REPORT_SCHEMA_VERSION);
+        assert_eq!(json["nakamoto"][0]["threshold"], "1/3");
+    }
+
+    // The Markdown that the `Display` of [SubnetChangeResponse] rendered before the report renderer
+    // replaced it, so that the Markdown posted in proposals and on the forum does not change by accident.
+    #[test]
+    fn markdown_matches_the_legacy_display() {
+        let change = change();
+        assert_eq!(
+            SubnetChangeReport::from(&change).render(ReportFormat::Markdown).unwrap(),
+            r#"Decentralization Nakamoto coefficient changes for subnet `yndj2-3ybaa-aaaaa-aaaap-yai`:
+```
+    node_provider: 1.00 -> 1.00    (+0%)
+      data_center: 1.00 -> 1.00    (+0%)
+data_center_owner: 1.00 -> 1.00    (+0%)
+             area: 1.00 -> 1.00    (+0%)
+          country: 1.00 -> 1.00    (+0%)
+```
+
+**Mean Nakamoto comparison:** 1.00 -> 1.00  (+0%)
+
+Overall replacement impact: (gets better) the number of different NP actors increases from 2 to 3
+
+Nakamoto coefficient changes at the 2/3 (safety) threshold:
+```
+    node_provider: 2.00 -> 2.00    (+0%)
+      data_center: 2.00 -> 2.00    (+0%)
+data_center_owner: 2.00 -> 2.00    (+0%)
+             area: 2.00 -> 2.00    (+0%)
+          country: 2.00 -> 2.00    (+0%)
+```
+
+
+# Details
+
+Nodes removed:
+- `gwp4o-eaaaa-aaaaa-aaaap-2ai` [health: unknown]
+
+Nodes added:
+- `hr2go-2qeaa-aaaaa-aaaap-2ai` [health: unknown]
+
+
+```
+    node_provider            data_center            data_center_owner            area             country        
+    -------------            -----------            -----------------            ----             -------        
+    <dc0>          2 -> 1    <dc0>        2 -> 1    <dc0>              2 -> 1    <dc0>  2 -> 1    <dc0>    2 -> 1
+    <dc1>               2    <dc1>             2    <dc1>                   2    <dc1>       2    <dc1>         2
+    dc4            0 -> 1    dc4          0 -> 1    dc4                0 -> 1    dc4    0 -> 1    dc4      0 -> 1
+```
+"#
+        );
+
+        let with_penalties_and_health = SubnetChangeResponse {
+            penalties_before_change: (1, vec!["Too many nodes in <dc0>".to_string()]),
+            penalties_after_change: (2, vec!["Too many nodes in dc4".to_string(), "Node operator limit".to_string()]),
+            health_of_nodes: IndexMap::from([
+                (PrincipalId::new_node_test_id(0), HealthStatus::Dead),
+                (PrincipalId::new_node_test_id(4), HealthStatus::Healthy),
+            ]),
+            ..change
+        };
+        assert_eq!(
+            SubnetChangeReport::from(&with_penalties_and_health)
+                .render(ReportFormat::Markdown)
+                .unwrap(),
+            r#"Decentralization Nakamoto coefficient changes for subnet `yndj2-3ybaa-aaaaa-aaaap-yai`:
+```
+    node_provider: 1.00 -> 1.00    (+0%)
+      data_center: 1.00 -> 1.00    (+0%)
+data_center_owner: 1.00 -> 1.00    (+0%)
+             area: 1.00 -> 1.00    (+0%)
+          country: 1.00 -> 1.00    (+0%)
+```
+
+**Mean Nakamoto comparison:** 1.00 -> 1.00  (+0%)
+
+Overall replacement impact: (gets better) the number of different NP actors increases from 2 to 3
+
+Nakamoto coefficient changes at the 2/3 (safety) threshold:
+```
+    node_provider: 2.00 -> 2.00    (+0%)
+      data_center: 2.00 -> 2.00    (+0%)
+data_center_owner: 2.00 -> 2.00    (+0%)
+             area: 2.00 -> 2.00    (+0%)
+          country: 2.00 -> 2.00    (+0%)
+```
+
+Impact on business rules penalties: 1 -> 2
+
+Business rules check results *before* the membership change:
+- Too many nodes in <dc0>
+
+Business rules check results *after* the membership change:
+- Too many nodes in dc4
+- Node operator limit
+
+
+# Details
+
+Nodes removed:
+- `gwp4o-eaaaa-aaaaa-aaaap-2ai` [health: dead]
+
+Nodes added:
+- `hr2go-2qeaa-aaaaa-aaaap-2ai` [health: healthy]
+
+
+```
+    node_provider            data_center            data_center_owner            area             country        
+    -------------            -----------            -----------------            ----             -------        
+    <dc0>          2 -> 1    <dc0>        2 -> 1    <dc0>              2 -> 1    <dc0>  2 -> 1    <dc0>    2 -> 1
+    <dc1>               2    <dc1>             2    <dc1>                   2    <dc1>       2    <dc1>         2
+    dc4            0 -> 1    dc4          0 -> 1    dc4                0 -> 1    dc4    0 -> 1    dc4      0 -> 1
+```
+"#
+        );
+    }
+
+    #[test]
+    fn html_escapes_the_change() {
+        let change = change();
+        let report = SubnetChangeReport::from(&change);
+
+        let html = report.render(ReportFormat::Html).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("&lt;dc0&gt;"));
+        assert!(!html.contains("<dc0>"));
+        assert!(html.contains(&format!("<tr class=\"removed\"><td><code>{}</code>", PrincipalId::new_node_test_id(0))));
+        assert_eq!("html".parse::<ReportFormat>().unwrap(), ReportFormat::Html);
+    }
+}
@@ -7,6 +7,7 @@ use indexmap::IndexMap;
 use itertools::Itertools;
 use std::sync::Arc;
 
+use crate::business_rules::BusinessRules;
 use crate::network::DecentralizedSubnet;
 
 pub fn unhealthy_with_nodes(
@@ -31,11 +32,11 @@ pub fn unhealthy_with_nodes(
         .collect::<IndexMap<_, _>>()
 }
 
-pub fn subnets_with_business_rules_violations(subnets: &[Subnet]) -> Vec<Subnet> {
+pub fn subnets_with_business_rules_violations(subnets: &[Subnet], business_rules: &BusinessRules) -> Vec<Subnet> {
     subnets
         .iter()
         .filter_map(|subnet| {
-            let decentralized_subnet = DecentralizedSubnet::from(subnet.clone());
+            let decentralized_subnet = DecentralizedSubnet::from(subnet).with_business_rules(business_rules.clone());
 
             if decentralized_subnet
                 .check_business_rules()
@@ -1,4 +1,6 @@
 use candid::Decode;
+use candid::Principal;
+use ic_agent::agent::CallResponse;
 use ic_nns_common::pb::v1::NeuronId;
 use ic_nns_common::pb::v1::ProposalId;
 use ic_nns_constants::GOVERNANCE_CANISTER_ID;
@@ -6,6 +8,7 @@ use ic_nns_governance::pb::v1::GovernanceError;
 use ic_nns_governance::pb::v1::ManageNeuron;
 use ic_nns_governance::pb::v1::NodeProvider as PbNodeProvider;
 use ic_nns_governance::pb::v1::Proposal;
+use ic_nns_governance::pb::v1::Vote;
 use ic_nns_governance::pb::v1::manage_neuron::ClaimOrRefresh;
 use ic_nns_governance::pb::v1::manage_neuron::Command;
 use ic_nns_governance::pb::v1::manage_neuron::Command::ClaimOrRefresh as CoR;
@@ -23,6 +26,8 @@ use ic_nns_governance_api::manage_neuron_response::Command as CommandResponse;
 use ic_nns_governance_api::manage_neuron_response::MakeProposalResponse;
 use ic_nns_governance_api::{ListNeurons, ListProposalInfoResponse, NeuronInfo, ProposalInfo};
 use ic_nns_governance_api::{ListNeuronsResponse, Neuron};
+use ic_transport_types::{Envelope, EnvelopeContent, request_id::to_request_id};
+use std::time::Duration;
 use url::Url;
 
 use crate::CanisterVersion;
@@ -86,7 +91,7 @@ impl GovernanceCanisterWrapper {
         .await
     }
 
-    pub async fn register_vote(&self, neuron_id: u64, proposal_id: u64) -> anyhow::Result<String> {
+    pub async fn register_vote(&self, neuron_id: u64, proposal_id: u64, vote: Vote) -> anyhow::Result<String> {
         let mut retries = 0;
         let response = backoff::future::retry(backoff::ExponentialBackoff::default(), || async move {
             retries += 1;
@@ -98,7 +103,7 @@ impl GovernanceCanisterWrapper {
                 neuron_id_or_subaccount: None,
                 command: Some(ic_nns_governance::pb::v1::manage_neuron::Command::RegisterVote(RegisterVote {
                     proposal: Some(ProposalId { id: proposal_id }),
-                    vote: ic_nns_governance::pb::v1::Vote::Yes.into(),
+                    vote: vote.into(),
                 })),
             })
             .await
@@ -108,7 +113,7 @@ impl GovernanceCanisterWrapper {
 
         match response.command {
             None => Err(anyhow::anyhow!("No command in response")),
-            Some(CommandResponse::RegisterVote(response)) => Ok(format!("Successfully voted on proposal {} {:?}", proposal_id, response)),
+            Some(CommandResponse::RegisterVote(response)) => Ok(format!("Successfully voted {:?} on proposal {} {:?}", vote, proposal_id, response)),
             Some(CommandResponse::Error(err))
                 if err
                     == ic_nns_governance_api::GovernanceError {
@@ -133,6 +138,35 @@ impl GovernanceCanisterWrapper {
         .await
     }
 
+    /// Resets the voting power of the neuron, which starts decaying if it was not refreshed for 6 months.
+    pub async fn refresh_voting_power(&self, neuron_id: u64) -> anyhow::Result<CommandResponse> {
+        self.execute_neuron_command(&Self::neuron_command(
+            neuron_id,
+            Command::RefreshVotingPower(ic_nns_governance::pb::v1::manage_neuron::RefreshVotingPower {}),
+        ))
+        .await
+    }
+
+    /// Builds a `manage_neuron` call running the command on the neuron.
+    pub fn neuron_command(neuron_id: u64, command: Command) -> ManageNeuron {
+        ManageNeuron {
+            id: None,
+            neuron_id_or_subaccount: Some(NeuronIdOrSubaccount::NeuronId(NeuronId { id: neuron_id })),
+            command: Some(command),
+        }
+    }
+
+    /// Sends a `manage_neuron` call, such as one built with `neuron_command`, and returns the response
+    /// to its command. A governance error in the response is returned as an error.
+    pub async fn execute_neuron_command(&self, manage_neuron: &ManageNeuron) -> anyhow::Result<CommandResponse> {
+        let resp = self.manage_neuron(manage_neuron).await?;
+        match resp.command {
+            None => Err(anyhow::anyhow!("No command in response")),
+            Some(CommandResponse::Error(err)) => Err(anyhow::anyhow!("Governance error: {:?}", err)),
+            Some(command) => Ok(command),
+        }
+    }
+
     async fn manage_neuron(&self, manage_neuron: &ManageNeuron) -> anyhow::Result<ManageNeuronResponse> {
         let resp = self
             .client
@@ -148,12 +182,19 @@ impl GovernanceCanisterWrapper {
     }
 
     pub async fn make_proposal(&self, proposer_id: NeuronId, proposal: Proposal) -> anyhow::Result<MakeProposalResponse> {
-        let mng = ManageNeuron {
+        let resp = self.manage_neuron(&Self::make_proposal_command(proposer_id, proposal)).await?;
+        Self::make_proposal_response(resp)
+    }
+
+    pub fn make_proposal_command(proposer_id: NeuronId, proposal: Proposal) -> ManageNeuron {
+        ManageNeuron {
             id: None,
             neuron_id_or_subaccount: Some(NeuronIdOrSubaccount::NeuronId(proposer_id)),
             command: Some(Command::MakeProposal(proposal.into())),
-        };
-        let resp = self.manage_neuron(&mng).await?;
+        }
+    }
+
+    pub fn make_proposal_response(resp: ManageNeuronResponse) -> anyhow::Result<MakeProposalResponse> {
         match resp.command {
             None => Err(anyhow::anyhow!("No command in response")),
             Some(CommandResponse::MakeProposal(resp)) => Ok(resp),
@@ -162,6 +203,52 @@ impl GovernanceCanisterWrapper {
         }
     }
 
+    /// Signs a `manage_neuron` call without sending it, so that it can be submitted later from
+    /// another machine with `submit_signed_manage_neuron`. The IC rejects the call once `expire_after`
+    /// has elapsed, which can be at most 5 minutes.
+    pub fn sign_manage_neuron(&self, manage_neuron: &ManageNeuron, expire_after: Duration) -> anyhow::Result<SignedManageNeuron> {
+        let signed = self
+            .client
+            .agent
+            .update(&GOVERNANCE_CANISTER_ID.into(), "manage_neuron")
+            .with_effective_canister_id(GOVERNANCE_CANISTER_ID.into())
+            .with_arg(candid::encode_one(manage_neuron)?)
+            .expire_after(expire_after)
+            .sign()?;
+        let signed_request_status = self.client.agent.sign_request_status(GOVERNANCE_CANISTER_ID.into(), signed.request_id)?;
+
+        Ok(SignedManageNeuron {
+            sender: signed.sender,
+            ingress_expiry: signed.ingress_expiry,
+            nonce: signed.nonce,
+            arg: signed.arg,
+            request_id: signed.request_id.as_slice().to_vec(),
+            signed_update: signed.signed_update,
+            signed_request_status: signed_request_status.signed_request_status,
+        })
+    }
+
+    /// Sends a call signed with `sign_manage_neuron`. The client does not need the signing identity.
+    pub async fn submit_signed_manage_neuron(&self, signed: &SignedManageNeuron) -> anyhow::Result<ManageNeuronResponse> {
+        let effective_canister_id = GOVERNANCE_CANISTER_ID.into();
+        let resp = match self
+            .client
+            .agent
+            .update_signed(effective_canister_id, signed.signed_update.clone())
+            .await?
+        {
+            CallResponse::Response(resp) => resp,
+            CallResponse::Poll(request_id) => {
+                self.client
+                    .agent
+                    .wait_signed(&request_id, effective_canister_id, signed.signed_request_status.clone())
+                    .await?
+            }
+        };
+
+        Decode!(resp.as_slice(), ManageNeuronResponse).map_err(anyhow::Error::from)
+    }
+
     pub async fn list_proposals(&self, contract: ListProposalInfoRequest) -> anyhow::Result<Vec<ProposalInfo>> {
         self.query::<ListProposalInfoResponse>("list_proposals", candid::encode_one(&contract)?)
             .await
@@ -254,3 +341,71 @@ impl GovernanceCanisterWrapper {
         self.client.query(&GOVERNANCE_CANISTER_ID.into(), method_name, args).await
     }
 }
+
+/// A `manage_neuron` call to the governance canister, signed but not yet submitted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedManageNeuron {
+    pub sender: Principal,
+    /// Nanoseconds since the UNIX epoch after which the IC rejects the call.
+    pub ingress_expiry: u64,
+    pub nonce: Option<Vec<u8>>,
+    /// Candid encoded `ManageNeuron`.
+    pub arg: Vec<u8>,
+    pub request_id: Vec<u8>,
+    pub signed_update: Vec<u8>,
+    pub signed_request_status: Vec<u8>,
+}
+
+impl SignedManageNeuron {
+    /// Request ID of the call content, which the signature in `signed_update` covers.
+    pub fn computed_request_id(&self) -> anyhow::Result<Vec<u8>> {
+        let content = EnvelopeContent::Call {
+            nonce: self.nonce.clone(),
+            ingress_expiry: self.ingress_expiry,
+            sender: self.sender,
+            canister_id: GOVERNANCE_CANISTER_ID.into(),
+            method_name: "manage_neuron".to_string(),
+            arg: self.arg.clone(),
+        };
+        Ok(to_request_id(&content)?.as_slice().to_vec())
+    }
+
+    /// Checks that `signed_update`, which is what `submit_signed_manage_neuron` sends, is a call to
+    /// `manage_neuron` with exactly the fields of this struct, and that it is for `request_id`.
+    /// This does not check the signature itself, which is left to the IC.
+    pub fn verify_signed_update(&self) -> anyhow::Result<()> {
+        let envelope: Envelope =
+            serde_cbor::from_slice(&self.signed_update).map_err(|e| anyhow::anyhow!("Failed to decode the signed update: {}", e))?;
+        let EnvelopeContent::Call {
+            nonce,
+            ingress_expiry,
+            sender,
+            canister_id,
+            method_name,
+            arg,
+        } = envelope.content.as_ref()
+        else {
+            anyhow::bail!("The signed update is not a call");
+        };
+        if *canister_id != Principal::from(GOVERNANCE_CANISTER_ID) || method_name != "manage_neuron" {
+            anyhow::bail!(
+                "The signed update calls `{}` on {} instead of `manage_neuron` on governance",
+                method_name,
+                canister_id
+            );
+        }
+        if *arg != self.arg || *sender != self.sender || *ingress_expiry != self.ingress_expiry || *nonce != self.nonce {
+            anyhow::bail!("The signed update does not match the call it was signed for");
+        }
+
+        let request_id = self.computed_request_id()?;
+        if to_request_id(envelope.content.as_ref())?.as_slice() != request_id.as_slice() || request_id != self.request_id {
+            anyhow::bail!("The signed update is for a different request ID");
+        }
+        Ok(())
+    }
+
+    pub fn manage_neuron(&self) -> anyhow::Result<ManageNeuron> {
+        Decode!(self.arg.as_slice(), ManageNeuron).map_err(anyhow::Error::from)
+    }
+}
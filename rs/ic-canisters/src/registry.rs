@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use crate::{CanisterVersion, IcAgentCanisterClient};
 use candid::{Decode, Encode};
 use ic_agent::Agent;
@@ -13,7 +15,10 @@ use ic_registry_keys::make_crypto_threshold_signing_pubkey_key;
 use ic_registry_nns_data_provider::registry::RegistryCanister;
 use ic_registry_transport::{
     GetChunk, dechunkify_get_value_response_content,
-    pb::v1::{HighCapacityRegistryGetValueResponse, RegistryGetLatestVersionResponse, RegistryGetValueRequest},
+    pb::v1::{
+        HighCapacityRegistryGetChangesSinceResponse, HighCapacityRegistryGetValueResponse, RegistryGetChangesSinceRequest,
+        RegistryGetLatestVersionResponse, RegistryGetValueRequest,
+    },
 };
 use ic_types::crypto::threshold_sig::ThresholdSigPublicKey;
 use prost::Message;
@@ -86,6 +91,51 @@ impl RegistryCanisterWrapper {
             .map(|(res, _, _)| res)
     }
 
+    /// Timestamps in nanoseconds of the registry versions after `version`, as far as the registry returns them in one response.
+    /// Versions recorded before the registry kept timestamps have a timestamp of 0.
+    pub async fn get_version_timestamps_page(&self, version: u64) -> anyhow::Result<BTreeMap<u64, u64>> {
+        let request = RegistryGetChangesSinceRequest { version };
+
+        let mut buf = vec![];
+        request.encode(&mut buf)?;
+        let response = self
+            .agent
+            .query(&REGISTRY_CANISTER_ID.into(), "get_changes_since")
+            .with_arg(buf)
+            .call()
+            .await?;
+
+        let decoded_resp = HighCapacityRegistryGetChangesSinceResponse::decode(&response[..])?;
+        if let Some(error) = decoded_resp.error {
+            return Err(anyhow::anyhow!(error.reason));
+        }
+
+        Ok(decoded_resp
+            .deltas
+            .iter()
+            .flat_map(|delta| &delta.values)
+            .map(|value| (value.version, value.timestamp_nanoseconds))
+            .collect())
+    }
+
+    /// Timestamps in nanoseconds of all the registry versions after `version`, up to the latest one.
+    /// Versions recorded before the registry kept timestamps are left out.
+    pub async fn get_version_timestamps_since(&self, version: u64) -> anyhow::Result<BTreeMap<u64, u64>> {
+        let latest = self.get_latest_version().await?;
+        let mut timestamps = BTreeMap::new();
+        let mut since = version;
+        while since < latest {
+            let page = self.get_version_timestamps_page(since).await?;
+            let Some(last) = page.keys().last().copied() else {
+                break;
+            };
+            timestamps.extend(page);
+            since = last;
+        }
+        timestamps.retain(|_, timestamp| *timestamp > 0);
+        Ok(timestamps)
+    }
+
     async fn get_value(&self, request: String) -> anyhow::Result<Vec<u8>> {
         let request = RegistryGetValueRequest {
             key: request.as_bytes().to_vec(),
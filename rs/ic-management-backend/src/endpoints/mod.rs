@@ -8,6 +8,7 @@ use crate::health::HealthStatusQuerier;
 use crate::{health, prometheus, proposal, registry, registry::RegistryState, release::RolloutBuilder, release::list_subnets_release_statuses};
 use actix_web::dev::Service;
 use actix_web::{App, Error, HttpResponse, HttpServer, Responder, Result, get, web};
+use decentralization::business_rules::BusinessRulesPolicy;
 use decentralization::network::AvailableNodesQuerier;
 use ic_management_types::Network;
 use ic_registry_nns_data_provider::registry::RegistryCanister;
@@ -27,6 +28,7 @@ pub async fn run_backend(
     listen_port: u16,
     run_from_cli: bool,
     mpsc_tx: Option<std::sync::mpsc::Sender<actix_web::dev::ServerHandle>>,
+    business_rules: BusinessRulesPolicy,
 ) -> std::io::Result<()> {
     debug!("Starting backend");
     let registry_state = Arc::new(RwLock::new(
@@ -39,11 +41,11 @@ pub async fn run_backend(
     ));
 
     if run_from_cli {
-        registry::update_node_details(&registry_state).await;
+        registry::update_node_details(&registry_state, &business_rules).await;
     } else {
         let closure_target_network = target_network.clone();
         let registry_state_poll = registry_state.clone();
-        tokio::spawn(async { registry::poll(registry_state_poll, closure_target_network).await });
+        tokio::spawn(async { registry::poll(registry_state_poll, closure_target_network, business_rules).await });
     }
 
     let num_workers = if run_from_cli { 1 } else { 8 };
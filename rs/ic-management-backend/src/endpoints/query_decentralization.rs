@@ -1,6 +1,8 @@
 use super::*;
 use decentralization::SubnetChangeResponse;
+use decentralization::nakamoto::NakamotoThreshold;
 use decentralization::network::{DecentralizedSubnet, SubnetChange};
+use decentralization::report::{ReportFormat, SubnetChangeReport};
 use ic_base_types::PrincipalId;
 use serde::{Deserialize, Serialize};
 
@@ -9,6 +11,23 @@ struct SubnetRequest {
     subnet: PrincipalId,
 }
 
+#[derive(Deserialize)]
+struct ThresholdsQuery {
+    /// Comma separated Nakamoto thresholds in addition to 1/3 and 2/3, e.g. `1/2,3/4`
+    thresholds: Option<String>,
+}
+
+impl ThresholdsQuery {
+    fn thresholds(&self) -> Result<Vec<NakamotoThreshold>, Error> {
+        self.thresholds
+            .iter()
+            .flat_map(|t| t.split(','))
+            .filter(|t| !t.trim().is_empty())
+            .map(|t| t.parse().map_err(|e: anyhow::Error| actix_web::error::ErrorBadRequest(e.to_string())))
+            .collect()
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct DecentralizedSubnetResponse {
     id: PrincipalId,
@@ -21,9 +40,10 @@ struct DecentralizedSubnetResponse {
 #[get("/decentralization/subnet/{subnet}")]
 pub(crate) async fn decentralization_subnet_query(
     request: web::Path<SubnetRequest>,
+    query: web::Query<ThresholdsQuery>,
     registry: web::Data<Arc<RwLock<RegistryState>>>,
 ) -> Result<HttpResponse, Error> {
-    get_decentralization_analysis(registry, Some(request.subnet), None, None).await
+    get_decentralization_analysis(registry, Some(request.subnet), None, None, query.thresholds()?, None).await
 }
 
 #[derive(Deserialize)]
@@ -31,6 +51,11 @@ struct SubnetWhatIfRequest {
     subnet: Option<PrincipalId>,
     nodes_to_add: Option<Vec<PrincipalId>>,
     nodes_to_remove: Option<Vec<PrincipalId>>,
+    /// Nakamoto thresholds in addition to 1/3 and 2/3, e.g. `["1/2"]`
+    #[serde(default)]
+    thresholds: Vec<NakamotoThreshold>,
+    /// If set, respond with the change report in this format (`markdown`, `html` or `json`)
+    format: Option<ReportFormat>,
 }
 
 /// Get the decentralization coefficients for a subnet
@@ -39,7 +64,15 @@ pub(crate) async fn decentralization_whatif_query(
     request: web::Json<SubnetWhatIfRequest>,
     registry: web::Data<Arc<RwLock<RegistryState>>>,
 ) -> Result<HttpResponse, Error> {
-    get_decentralization_analysis(registry, request.subnet, request.nodes_to_add.clone(), request.nodes_to_remove.clone()).await
+    get_decentralization_analysis(
+        registry,
+        request.subnet,
+        request.nodes_to_add.clone(),
+        request.nodes_to_remove.clone(),
+        request.thresholds.clone(),
+        request.format,
+    )
+    .await
 }
 
 async fn get_decentralization_analysis(
@@ -47,9 +80,12 @@ async fn get_decentralization_analysis(
     subnet: Option<PrincipalId>,
     node_ids_to_add: Option<Vec<PrincipalId>>,
     node_ids_to_remove: Option<Vec<PrincipalId>>,
+    thresholds: Vec<NakamotoThreshold>,
+    format: Option<ReportFormat>,
 ) -> Result<HttpResponse, Error> {
     let subnets = registry.read().await.subnets();
     let registry_nodes = registry.read().await.nodes();
+    let business_rules = registry.read().await.business_rules();
 
     let original_subnet = subnet
         .map(|subnet_id| match subnets.get(&subnet_id) {
@@ -60,6 +96,7 @@ async fn get_decentralization_analysis(
                 removed_nodes: Vec::new(),
                 comment: None,
                 run_log: Vec::new(),
+                business_rules: business_rules.clone(),
             },
             None => DecentralizedSubnet {
                 id: PrincipalId::new_subnet_test_id(0),
@@ -68,6 +105,7 @@ async fn get_decentralization_analysis(
                 removed_nodes: Vec::new(),
                 comment: None,
                 run_log: Vec::new(),
+                business_rules: business_rules.clone(),
             },
         })
         .unwrap_or_else(|| DecentralizedSubnet {
@@ -77,6 +115,7 @@ async fn get_decentralization_analysis(
             removed_nodes: Vec::new(),
             comment: None,
             run_log: Vec::new(),
+            business_rules: business_rules.clone(),
         });
 
     let nodes_to_remove = node_ids_to_remove.map(|node_ids_to_remove| {
@@ -98,10 +137,12 @@ async fn get_decentralization_analysis(
         }
         None => updated_subnet,
     };
-    let penalties_before_change = DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&original_subnet.id, &original_subnet.nodes)
-        .expect("Business rules check before should succeed");
-    let penalties_after_change = DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&original_subnet.id, &updated_subnet.nodes)
-        .expect("Business rules check after should succeed");
+    let penalties_before_change =
+        DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&original_subnet.id, &original_subnet.nodes, &business_rules)
+            .expect("Business rules check before should succeed");
+    let penalties_after_change =
+        DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&original_subnet.id, &updated_subnet.nodes, &business_rules)
+            .expect("Business rules check after should succeed");
 
     let subnet_change = SubnetChange {
         subnet_id: original_subnet.id,
@@ -113,12 +154,21 @@ async fn get_decentralization_analysis(
         penalties_after_change,
         comment: updated_subnet.comment.clone(),
         run_log: updated_subnet.run_log.clone(),
+        business_rules,
     };
 
+    let change = SubnetChangeResponse::new(&subnet_change, &IndexMap::new(), None).with_thresholds(&thresholds);
+    if let Some(format) = format {
+        let report = SubnetChangeReport::from(&change)
+            .render(format)
+            .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+        return Ok(HttpResponse::Ok().content_type(format.content_type()).body(report));
+    }
+
     let response = DecentralizedSubnetResponse {
         id: subnet.unwrap_or_else(|| PrincipalId::new_subnet_test_id(0)),
-        message: format!("{}", SubnetChangeResponse::new(&subnet_change, &IndexMap::new(), None)),
-        nakamoto: updated_subnet.nakamoto_score(),
+        message: SubnetChangeReport::from(&change).markdown(),
+        nakamoto: updated_subnet.nakamoto_score().with_thresholds(&thresholds),
         run_log: subnet_change.run_log.join("\n"),
     };
     Ok(HttpResponse::Ok().json(&response))
@@ -22,12 +22,13 @@ pub(crate) async fn change_preview(
                 .get(&request.subnet)
                 .ok_or_else(|| actix_web::error::ErrorNotFound(anyhow::format_err!("subnet {} not found", request.subnet)))?;
             let registry_nodes: IndexMap<PrincipalId, Node> = registry.read().await.nodes();
+            let business_rules = registry.read().await.business_rules();
             let health_of_nodes = HealthClient::new(registry.read().await.network(), None, false)
                 .nodes()
                 .await
                 .unwrap_or_default();
 
-            get_proposed_subnet_changes(&registry_nodes, subnet, &health_of_nodes)
+            get_proposed_subnet_changes(&registry_nodes, subnet, &health_of_nodes, &business_rules)
                 .map_err(actix_web::error::ErrorBadRequest)
                 .map(|r| HttpResponse::Ok().json(r))
         }
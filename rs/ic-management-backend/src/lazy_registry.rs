@@ -5,10 +5,12 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::{Arc, OnceLock};
 
+use decentralization::business_rules::BusinessRules;
 use decentralization::network::{AvailableNodesQuerier, DecentralizedSubnet, NodesConverter, SubnetQuerier, SubnetQueryBy};
 use futures::future::BoxFuture;
 use ic_interfaces_registry::RegistryClient;
 use ic_interfaces_registry::{RegistryClientVersionedResult, RegistryValue};
+use ic_management_types::custom_features::CustomNodeFeatures;
 use ic_management_types::{
     Datacenter, DatacenterOwner, Guest, Network, NetworkError, Node, NodeProvidersResponse, Operator, Provider, Subnet, SubnetMetadata,
 };
@@ -176,6 +178,7 @@ where
     guest_labels_cache_path: PathBuf,
     health_client: Arc<dyn HealthStatusQuerier>,
     version_height: Option<u64>,
+    custom_node_features: Arc<CustomNodeFeatures>,
 }
 
 pub trait LazyRegistryEntry: RegistryValue {
@@ -278,6 +281,7 @@ impl LazyRegistryImpl {
         guest_labels_cache_path: PathBuf,
         health_client: Arc<dyn HealthStatusQuerier>,
         version_height: Option<u64>,
+        custom_node_features: Arc<CustomNodeFeatures>,
     ) -> Self {
         Self {
             local_registry,
@@ -295,6 +299,7 @@ impl LazyRegistryImpl {
             guest_labels_cache_path,
             health_client,
             version_height,
+            custom_node_features,
         }
     }
 
@@ -549,7 +554,8 @@ impl LazyRegistry for LazyRegistryImpl {
                             chip_id: nr.chip_id.clone(),
                             public_ipv4_config: nr.public_ipv4_config.clone(),
                             node_reward_type: NodeRewardType::try_from(nr.node_reward_type.unwrap_or_default()).ok(),
-                        },
+                        }
+                        .with_custom_features(&self.custom_node_features),
                     )
                 })
                 .collect();
@@ -837,6 +843,7 @@ impl SubnetQuerier for LazyRegistryImpl {
                         removed_nodes: vec![],
                         comment: None,
                         run_log: vec![],
+                        business_rules: BusinessRules::default(),
                     })
                     .ok_or(ic_management_types::NetworkError::SubnetNotFound(id)),
                 SubnetQueryBy::NodeList(nodes) => {
@@ -863,6 +870,7 @@ impl SubnetQuerier for LazyRegistryImpl {
                             removed_nodes: vec![],
                             comment: None,
                             run_log: vec![],
+                            business_rules: BusinessRules::default(),
                         })
                     } else {
                         Err(NetworkError::IllegalRequest("no subnets found".to_string()))
@@ -10,7 +10,10 @@ mod release;
 mod subnets;
 
 use clap::Parser;
+use decentralization::business_rules::BusinessRulesPolicy;
 use dotenv::dotenv;
+use log::info;
+use std::path::PathBuf;
 use url::Url;
 
 #[actix_web::main]
@@ -25,10 +28,18 @@ async fn main() -> std::io::Result<()> {
         .await
         .expect("Failed to create network");
 
+    let business_rules = match &args.business_rules_file {
+        Some(path) => BusinessRulesPolicy::parse(&fs_err::read(path)?).expect("Failed to load the business rules policy"),
+        None => {
+            info!("No business rules file provided, using the built-in policy");
+            BusinessRulesPolicy::default()
+        }
+    };
+
     let listen_port = std::env::var("BACKEND_PORT")
         .map(|p| p.parse().expect("Unable to parse BACKEND_PORT environment variable as a valid port"))
         .unwrap_or(8080);
-    endpoints::run_backend(&target_network, "0.0.0.0", listen_port, false, None).await
+    endpoints::run_backend(&target_network, "0.0.0.0", listen_port, false, None, business_rules).await
 }
 
 #[derive(Parser, Debug)]
@@ -42,4 +53,9 @@ struct Cli {
     // The argument is mandatory for testnets, and is optional for mainnet and staging
     #[clap(long, env = "NNS_URLS", aliases = &["registry-url", "nns-url"], value_delimiter = ',')]
     pub nns_urls: Vec<Url>,
+
+    // Business rules policy, in the format of `business_rules.yaml` at the root of the repository.
+    // The built-in policy is used if not provided
+    #[clap(long, env = "BUSINESS_RULES_FILE")]
+    business_rules_file: Option<PathBuf>,
 }
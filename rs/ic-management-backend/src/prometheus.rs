@@ -1,6 +1,61 @@
+use std::str::FromStr;
+
 use ic_management_types::Network;
+use ic_types::PrincipalId;
+use indexmap::IndexMap;
 use prometheus_http_query::Client;
 
 pub fn client(network: &Network) -> Client {
     Client::try_from(network.get_prometheus_endpoint().as_str()).unwrap()
 }
+
+/// Finalized blocks per second of each subnet, averaged over the replicas of
+/// the subnet for the last 10 minutes.
+pub async fn finalization_rates(client: &Client, network: &Network) -> anyhow::Result<IndexMap<PrincipalId, f64>> {
+    let query = format!(
+        r#"avg by (ic_subnet) (rate(artifact_pool_consensus_height_stat{{ic="{network}", type="finalization", pool_type="validated", stat="max"}}[10m]))"#,
+        network = network.legacy_name(),
+    );
+    let response = client.query(query).get().await?;
+    let results = response.data().as_vector().ok_or_else(|| anyhow::anyhow!("Expected instant vector"))?;
+    Ok(results
+        .iter()
+        .filter_map(|r| {
+            r.metric()
+                .get("ic_subnet")
+                .and_then(|s| PrincipalId::from_str(s).ok())
+                .map(|s| (s, r.sample().value()))
+        })
+        .collect())
+}
+
+/// Whether the node exporter running on the host of each node is reachable.
+pub async fn hosts_up(client: &Client, network: &Network) -> anyhow::Result<IndexMap<PrincipalId, bool>> {
+    let query = format!(
+        r#"min by (ic_node) (up{{ic="{network}", job="host_node_exporter"}})"#,
+        network = network.legacy_name()
+    );
+    let response = client.query(query).get().await?;
+    let results = response.data().as_vector().ok_or_else(|| anyhow::anyhow!("Expected instant vector"))?;
+    Ok(results
+        .iter()
+        .filter_map(|r| {
+            r.metric()
+                .get("ic_node")
+                .and_then(|n| PrincipalId::from_str(n).ok())
+                .map(|n| (n, r.sample().value() == 1.0))
+        })
+        .collect())
+}
+
+/// Share of the scrapes in which the replica of the node was up, during the day before `end_timestamp`.
+/// None if Prometheus has no samples for the node in that day.
+pub async fn replica_daily_availability(client: &Client, network: &Network, node: PrincipalId, end_timestamp: i64) -> anyhow::Result<Option<f64>> {
+    let query = format!(
+        r#"min by (ic_node) (avg_over_time(up{{ic="{network}", job="replica", ic_node="{node}"}}[1d]))"#,
+        network = network.legacy_name(),
+    );
+    let response = client.query(query).at(end_timestamp).get().await?;
+    let results = response.data().as_vector().ok_or_else(|| anyhow::anyhow!("Expected instant vector"))?;
+    Ok(results.first().map(|r| r.sample().value()))
+}
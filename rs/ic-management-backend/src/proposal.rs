@@ -10,9 +10,9 @@ use ic_agent::Agent;
 use ic_management_types::UpdateElectedHostosVersionsProposal;
 use ic_management_types::UpdateElectedReplicaVersionsProposal;
 use ic_management_types::UpdateNodesHostosVersionsProposal;
-use ic_management_types::filter_map_nns_function_proposals;
 use ic_management_types::{TopologyChangePayload, TopologyChangeProposal};
-use ic_nns_governance::pb::v1::{ProposalStatus, Topic};
+use ic_management_types::{filter_map_nns_function_proposals, filter_map_nns_functions_proposals};
+use ic_nns_governance::pb::v1::{NnsFunction, ProposalStatus, Topic};
 use ic_nns_governance_api::ListProposalInfoRequest;
 use ic_nns_governance_api::proposal::Action;
 use ic_nns_governance_api::{ListProposalInfoResponse, ProposalInfo};
@@ -31,20 +31,36 @@ use registry_canister::mutations::node_management::do_remove_nodes::RemoveNodesP
 use serde::Serialize;
 use url::Url;
 
+/// Longest time a proposal can stay open, with its voting period extended by wait-for-quiet.
+/// Proposals executed after a point in time were made at most this long before it.
+pub const MAX_PROPOSAL_OPEN_TIME: Duration = Duration::from_secs(8 * 24 * 60 * 60);
+
 #[allow(dead_code)]
 #[automock]
 pub trait ProposalAgent: Send + Sync {
     fn list_open_topology_proposals(&self) -> BoxFuture<'_, Result<Vec<TopologyChangeProposal>>>;
 
+    /// Executed topology proposals, made at or after the provided timestamp
+    fn list_executed_topology_proposals(&self, since_timestamp_seconds: u64) -> BoxFuture<'_, Result<Vec<TopologyChangeProposal>>>;
+
     fn list_open_elect_replica_proposals(&self) -> BoxFuture<'_, Result<Vec<UpdateElectedReplicaVersionsProposal>>>;
 
     fn list_open_elect_hostos_proposals(&self) -> BoxFuture<'_, Result<Vec<UpdateElectedHostosVersionsProposal>>>;
 
     fn list_open_update_nodes_hostos_versions_proposals(&self) -> BoxFuture<'_, Result<Vec<UpdateNodesHostosVersionsProposal>>>;
 
+    /// HostOS deployments to nodes, under their current and former NNS function, made at or after the provided timestamp
+    fn list_update_nodes_hostos_versions_proposals(&self, since_timestamp_seconds: u64) -> BoxFuture<'_, Result<Vec<HostosUpdateProposal>>>;
+
     fn list_update_subnet_version_proposals(&self) -> BoxFuture<'_, Result<Vec<SubnetUpdateProposal>>>;
 
+    /// Subnet GuestOS version updates, made at or after the provided timestamp
+    fn list_update_subnet_version_proposals_since(&self, since_timestamp_seconds: u64) -> BoxFuture<'_, Result<Vec<SubnetUpdateProposal>>>;
+
     fn list_update_unassigned_nodes_version_proposals(&self) -> BoxFuture<'_, Result<Vec<UpdateUnassignedNodesProposal>>>;
+
+    /// IDs of the proposals made by the neuron with exactly this summary, at or after the provided timestamp
+    fn find_proposals_by_summary(&self, proposer: u64, summary: String, since_timestamp_seconds: u64) -> BoxFuture<'_, Result<Vec<u64>>>;
 }
 
 #[derive(Clone)]
@@ -99,6 +115,12 @@ pub struct SubnetUpdateProposal {
     pub payload: DeployGuestosToAllSubnetNodesPayload,
 }
 
+#[derive(Clone, Serialize)]
+pub struct HostosUpdateProposal {
+    pub info: ProposalInfoInternal,
+    pub payload: UpdateNodesHostosVersionPayload,
+}
+
 #[derive(Clone, Serialize)]
 pub struct UpdateUnassignedNodesProposal {
     pub info: ProposalInfoInternal,
@@ -107,31 +129,28 @@ pub struct UpdateUnassignedNodesProposal {
 
 impl ProposalAgent for ProposalAgentImpl {
     fn list_open_topology_proposals(&self) -> BoxFuture<'_, Result<Vec<TopologyChangeProposal>>> {
-        Box::pin(async {
-            let proposals = &self.list_proposals(vec![ProposalStatus::Open]).await?;
-            let create_subnet_proposals = Self::nodes_proposals(filter_map_nns_function_proposals::<CreateSubnetPayload>(proposals)).into_iter();
-
-            let add_nodes_to_subnet_proposals =
-                Self::nodes_proposals(filter_map_nns_function_proposals::<AddNodesToSubnetPayload>(proposals)).into_iter();
-
-            let remove_nodes_from_subnet_proposals =
-                Self::nodes_proposals(filter_map_nns_function_proposals::<RemoveNodesFromSubnetPayload>(proposals)).into_iter();
-
-            let remove_nodes_proposals = Self::nodes_proposals(filter_map_nns_function_proposals::<RemoveNodesPayload>(proposals)).into_iter();
-
-            let membership_change_proposals =
-                Self::nodes_proposals(filter_map_nns_function_proposals::<ChangeSubnetMembershipPayload>(proposals)).into_iter();
+        Box::pin(async { Ok(Self::topology_proposals(&self.list_proposals(vec![ProposalStatus::Open]).await?)) })
+    }
 
-            let mut result = create_subnet_proposals
-                .chain(add_nodes_to_subnet_proposals)
-                .chain(remove_nodes_from_subnet_proposals)
-                .chain(membership_change_proposals)
-                .chain(remove_nodes_proposals)
-                .collect::<Vec<_>>();
-            result.sort_by_key(|p| p.id);
-            result.reverse();
+    fn list_executed_topology_proposals(&self, since_timestamp_seconds: u64) -> BoxFuture<'_, Result<Vec<TopologyChangeProposal>>> {
+        Box::pin(async move {
+            Ok(Self::topology_proposals(
+                &self
+                    .list_proposals_since(vec![ProposalStatus::Executed], Some(since_timestamp_seconds))
+                    .await?,
+            ))
+        })
+    }
 
-            Ok(result)
+    fn find_proposals_by_summary(&self, proposer: u64, summary: String, since_timestamp_seconds: u64) -> BoxFuture<'_, Result<Vec<u64>>> {
+        Box::pin(async move {
+            Ok(self
+                .list_proposals_since(vec![], Some(since_timestamp_seconds))
+                .await?
+                .into_iter()
+                .filter(|p| p.proposer.is_some_and(|n| n.id == proposer) && p.proposal.as_ref().is_some_and(|p| p.summary == summary))
+                .filter_map(|p| p.id.map(|id| id.id))
+                .collect())
         })
     }
 
@@ -198,12 +217,28 @@ impl ProposalAgent for ProposalAgentImpl {
         })
     }
 
+    fn list_update_nodes_hostos_versions_proposals(&self, since_timestamp_seconds: u64) -> BoxFuture<'_, Result<Vec<HostosUpdateProposal>>> {
+        Box::pin(async move {
+            let proposals = self.list_proposals_since(vec![], Some(since_timestamp_seconds)).await?;
+            Ok(filter_map_nns_functions_proposals::<UpdateNodesHostosVersionPayload>(
+                &proposals,
+                &[NnsFunction::UpdateNodesHostosVersion, NnsFunction::DeployHostosToSomeNodes],
+            )
+            .into_iter()
+            .map(|(info, payload)| HostosUpdateProposal { info: info.into(), payload })
+            .collect::<Vec<_>>())
+        })
+    }
+
     fn list_update_subnet_version_proposals(&self) -> BoxFuture<'_, Result<Vec<SubnetUpdateProposal>>> {
-        Box::pin(async {
-            Ok(filter_map_nns_function_proposals(&self.list_proposals(vec![]).await?)
-                .into_iter()
-                .map(|(info, payload)| SubnetUpdateProposal { info: info.into(), payload })
-                .collect::<Vec<_>>())
+        Box::pin(async { Ok(Self::subnet_update_proposals(&self.list_proposals(vec![]).await?)) })
+    }
+
+    fn list_update_subnet_version_proposals_since(&self, since_timestamp_seconds: u64) -> BoxFuture<'_, Result<Vec<SubnetUpdateProposal>>> {
+        Box::pin(async move {
+            Ok(Self::subnet_update_proposals(
+                &self.list_proposals_since(vec![], Some(since_timestamp_seconds)).await?,
+            ))
         })
     }
 
@@ -239,7 +274,46 @@ impl ProposalAgentImpl {
         proposals.into_iter().map(TopologyChangeProposal::from).collect()
     }
 
+    fn topology_proposals(proposals: &[ProposalInfo]) -> Vec<TopologyChangeProposal> {
+        let create_subnet_proposals = Self::nodes_proposals(filter_map_nns_function_proposals::<CreateSubnetPayload>(proposals)).into_iter();
+
+        let add_nodes_to_subnet_proposals =
+            Self::nodes_proposals(filter_map_nns_function_proposals::<AddNodesToSubnetPayload>(proposals)).into_iter();
+
+        let remove_nodes_from_subnet_proposals =
+            Self::nodes_proposals(filter_map_nns_function_proposals::<RemoveNodesFromSubnetPayload>(proposals)).into_iter();
+
+        let remove_nodes_proposals = Self::nodes_proposals(filter_map_nns_function_proposals::<RemoveNodesPayload>(proposals)).into_iter();
+
+        let membership_change_proposals =
+            Self::nodes_proposals(filter_map_nns_function_proposals::<ChangeSubnetMembershipPayload>(proposals)).into_iter();
+
+        let mut result = create_subnet_proposals
+            .chain(add_nodes_to_subnet_proposals)
+            .chain(remove_nodes_from_subnet_proposals)
+            .chain(membership_change_proposals)
+            .chain(remove_nodes_proposals)
+            .collect::<Vec<_>>();
+        result.sort_by_key(|p| p.id);
+        result.reverse();
+
+        result
+    }
+
+    fn subnet_update_proposals(proposals: &[ProposalInfo]) -> Vec<SubnetUpdateProposal> {
+        filter_map_nns_function_proposals(proposals)
+            .into_iter()
+            .map(|(info, payload)| SubnetUpdateProposal { info: info.into(), payload })
+            .collect()
+    }
+
     async fn list_proposals(&self, include_status: Vec<ProposalStatus>) -> Result<Vec<ProposalInfo>> {
+        self.list_proposals_since(include_status, None).await
+    }
+
+    /// Lists the proposals newest first, and stops paging once the proposals are older than `since_timestamp_seconds`
+    async fn list_proposals_since(&self, include_status: Vec<ProposalStatus>, since_timestamp_seconds: Option<u64>) -> Result<Vec<ProposalInfo>> {
+        let is_too_old = |p: &ProposalInfo| since_timestamp_seconds.is_some_and(|since| p.proposal_timestamp_seconds < since);
         let mut proposals = vec![];
         loop {
             let fetch_partial_results = || async {
@@ -287,8 +361,12 @@ impl ProposalAgentImpl {
             let partial_result = fetch_partial_results.retry(ExponentialBuilder::default()).await?;
             if partial_result.is_empty() {
                 break;
-            } else {
-                proposals.extend(partial_result);
+            }
+            let reached_since = partial_result.last().is_some_and(is_too_old);
+            proposals.extend(partial_result);
+            if reached_since {
+                proposals.retain(|p| !is_too_old(p));
+                break;
             }
         }
         let (empty_payload_proposals, full_payload_proposals): (_, Vec<_>) = proposals.into_iter().partition(|p| {
@@ -4,7 +4,9 @@ use crate::node_labels;
 use crate::proposal::{self, ProposalAgent, SubnetUpdateProposal, UpdateUnassignedNodesProposal};
 use crate::public_dashboard::query_ic_dashboard_list;
 use candid::Principal;
+use decentralization::business_rules::{BusinessRules, BusinessRulesPolicy};
 use decentralization::network::{AvailableNodesQuerier, NodesConverter, SubnetQuerier, SubnetQueryBy};
+use decentralization::provider_clusters::ProviderClusters;
 use futures::TryFutureExt;
 use futures::future::BoxFuture;
 use ic_base_types::NodeId;
@@ -73,6 +75,7 @@ pub struct RegistryState {
     operators: IndexMap<PrincipalId, Operator>,
     node_labels_guests: Vec<Guest>,
     known_subnets: IndexMap<PrincipalId, String>,
+    business_rules: BusinessRules,
 
     guestos_releases: ArtifactReleases,
     hostos_releases: ArtifactReleases,
@@ -225,6 +228,7 @@ impl RegistryState {
             guestos_releases: ArtifactReleases::new(Artifact::GuestOs),
             hostos_releases: ArtifactReleases::new(Artifact::HostOs),
             ic_repo,
+            business_rules: BusinessRules::default(),
             known_subnets: [
                 (
                     "uzr34-akd3s-xrdag-3ql62-ocgoh-ld2ao-tamcv-54e7j-krwgb-2gm4z-oqe",
@@ -604,6 +608,14 @@ impl RegistryState {
         self.network.clone()
     }
 
+    pub fn business_rules(&self) -> BusinessRules {
+        self.business_rules.clone()
+    }
+
+    pub fn set_business_rules(&mut self, business_rules: BusinessRules) {
+        self.business_rules = business_rules;
+    }
+
     pub fn version(&self) -> u64 {
         self.version
     }
@@ -831,6 +843,7 @@ impl SubnetQuerier for RegistryState {
                         removed_nodes: Vec::new(),
                         comment: None,
                         run_log: Vec::new(),
+                        business_rules: self.business_rules.clone(),
                     })
                     .ok_or(NetworkError::SubnetNotFound(id)),
                 SubnetQueryBy::NodeList(nodes) => {
@@ -850,6 +863,7 @@ impl SubnetQuerier for RegistryState {
                             removed_nodes: Vec::new(),
                             comment: None,
                             run_log: Vec::new(),
+                            business_rules: self.business_rules.clone(),
                         })
                     } else {
                         Err(NetworkError::IllegalRequest("no subnet found".to_string()))
@@ -1057,7 +1071,7 @@ pub async fn sync_local_store_with_path(target_network: &Network, local_registry
     Ok(())
 }
 
-pub async fn poll(registry_state: Arc<RwLock<RegistryState>>, target_network: Network) {
+pub async fn poll(registry_state: Arc<RwLock<RegistryState>>, target_network: Network, business_rules: BusinessRulesPolicy) {
     let nns_urls = target_network.get_nns_urls().clone();
     let registry_canister = RegistryCanister::new(nns_urls);
     loop {
@@ -1069,7 +1083,7 @@ pub async fn poll(registry_state: Arc<RwLock<RegistryState>>, target_network: Ne
         };
         if latest_version != registry_state.read().await.version() {
             fetch_and_add_node_labels_guests_to_registry(&target_network, &mut *registry_state.write().await).await;
-            update_node_details(&registry_state).await;
+            update_node_details(&registry_state, &business_rules).await;
         } else {
             debug!(
                 "Skipping update. Registry already on latest version: {}",
@@ -1093,7 +1107,8 @@ pub async fn fetch_and_add_node_labels_guests_to_registry(target_network: &Netwo
     }
 }
 
-pub async fn update_node_details(registry_state: &Arc<RwLock<RegistryState>>) {
+/// Updates the registry state and its business rules policy with the subnet types of the updated state.
+pub async fn update_node_details(registry_state: &Arc<RwLock<RegistryState>>, business_rules: &BusinessRulesPolicy) {
     let network = registry_state.read().await.network();
     match query_ic_dashboard_list::<NodeProvidersResponse>(&network, "v3/node-providers").await {
         Ok(node_providers_response) => {
@@ -1107,4 +1122,7 @@ pub async fn update_node_details(registry_state: &Arc<RwLock<RegistryState>>) {
             warn!("Failed querying IC dashboard {}", e);
         }
     }
+    let subnets = registry_state.read().await.subnets();
+    let business_rules = BusinessRules::new(business_rules.clone().with_subnet_types(subnets.values()), ProviderClusters::default());
+    registry_state.write().await.set_business_rules(business_rules);
 }
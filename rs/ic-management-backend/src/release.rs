@@ -120,8 +120,14 @@ mod iso_8601_date_format {
 }
 
 impl RolloutConfig {
+    /// Config bundled with the backend, listing the days on which no rollout
+    /// should happen.
+    pub fn bundled() -> Self {
+        serde_yaml::from_slice(include_bytes!("../config/releases/default.yaml")).expect("invalid config")
+    }
+
     // Returns available rollout days. Always return at least 2 available days
-    fn rollout_days(&self, start: NaiveDate) -> Vec<NaiveDate> {
+    pub fn rollout_days(&self, start: NaiveDate) -> Vec<NaiveDate> {
         let candidates = (0..14)
             .map(|i| {
                 let mut d = start;
@@ -187,7 +193,7 @@ impl RolloutBuilder {
     }
 
     async fn new_for_release(&self, release: Release, subnet_update_proposals: Vec<SubnetUpdateProposal>) -> Result<Rollout> {
-        let config: RolloutConfig = serde_yaml::from_slice(include_bytes!("../config/releases/default.yaml")).expect("invalid config");
+        let config = RolloutConfig::bundled();
         let submitted_stages = self.stages_from_proposals(&release, subnet_update_proposals).await?;
         let today = Utc::now().date_naive();
         let rollout_days = config.rollout_days(submitted_stages.first().map(|s| s.start_date_time.date_naive()).unwrap_or(today));
@@ -1,5 +1,6 @@
 use decentralization::{
     SubnetChangeResponse,
+    business_rules::BusinessRules,
     network::{DecentralizedSubnet, SubnetChange},
 };
 use ic_base_types::PrincipalId;
@@ -10,14 +11,17 @@ pub fn get_proposed_subnet_changes(
     all_nodes: &IndexMap<PrincipalId, Node>,
     subnet: &ic_management_types::Subnet,
     health_of_nodes: &IndexMap<PrincipalId, ic_management_types::HealthStatus>,
+    business_rules: &BusinessRules,
 ) -> Result<SubnetChangeResponse, anyhow::Error> {
     if let Some(proposal) = &subnet.proposal {
         let proposal: &TopologyChangeProposal = proposal;
 
-        let penalties_before_change = DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&subnet.principal, &subnet.nodes)
-            .expect("Business rules check should succeed");
-        let penalties_after_change = DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&subnet.principal, &subnet.nodes)
-            .expect("Business rules check should succeed");
+        let penalties_before_change =
+            DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&subnet.principal, &subnet.nodes, business_rules)
+                .expect("Business rules check should succeed");
+        let penalties_after_change =
+            DecentralizedSubnet::check_business_rules_for_subnet_with_nodes(&subnet.principal, &subnet.nodes, business_rules)
+                .expect("Business rules check should succeed");
 
         let change = SubnetChange {
             subnet_id: subnet.principal,
@@ -29,6 +33,7 @@ pub fn get_proposed_subnet_changes(
             penalties_after_change,
             comment: None,
             run_log: vec![],
+            business_rules: business_rules.clone(),
         }
         .with_nodes(
             &proposal
@@ -74,7 +79,7 @@ mod tests {
             nodes: all_nodes.values().take(13).cloned().collect(),
             ..Default::default()
         };
-        let err = get_proposed_subnet_changes(&all_nodes, &subnet, &IndexMap::new())
+        let err = get_proposed_subnet_changes(&all_nodes, &subnet, &IndexMap::new(), &BusinessRules::default())
             .unwrap_err()
             .to_string();
         assert_eq!(err, "subnet fscpm-uiaaa-aaaaa-aaaap-yai does not have open membership change proposals");
@@ -98,7 +103,7 @@ mod tests {
             proposal: Some(proposal_replace),
             ..Default::default()
         };
-        let change = get_proposed_subnet_changes(&all_nodes, &subnet, &IndexMap::new()).unwrap();
+        let change = get_proposed_subnet_changes(&all_nodes, &subnet, &IndexMap::new(), &BusinessRules::default()).unwrap();
         assert_eq!(change.node_ids_added, node_ids_added);
         assert_eq!(change.node_ids_removed, vec![]);
     }
@@ -122,7 +127,7 @@ mod tests {
             proposal: Some(proposal_replace),
             ..Default::default()
         };
-        let change = get_proposed_subnet_changes(&all_nodes, &subnet, &IndexMap::new()).unwrap();
+        let change = get_proposed_subnet_changes(&all_nodes, &subnet, &IndexMap::new(), &BusinessRules::default()).unwrap();
         assert_eq!(change.node_ids_added, node_ids_added);
         assert_eq!(change.node_ids_removed, node_ids_removed);
     }
@@ -0,0 +1,176 @@
+use crate::{NodeFeature, NodeFeatures};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// Extra decentralization dimensions, such as the autonomous system number, the jurisdiction
+/// bloc or the hardware generation of a node, which are not part of the registry.
+///
+/// Every feature maps values of a built-in feature to a label, for example:
+/// ```yaml
+/// features:
+///   asn:
+///     data_center:
+///       zh2: AS1234
+///     node_id:
+///       <node principal>: AS5678
+///   jurisdiction_group:
+///     country:
+///       DE: EU
+///       FR: EU
+/// ```
+/// A node gets the label of its most specific matching built-in feature (`node_id` first,
+/// `continent` last), and `unknown` if none matches.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CustomNodeFeatures {
+    #[serde(default)]
+    pub features: IndexMap<String, IndexMap<String, IndexMap<String, String>>>,
+}
+
+impl CustomNodeFeatures {
+    /// Checks that the features do not shadow the built-in ones and are only keyed by built-in features.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut errors = vec![];
+        for (name, labels) in &self.features {
+            if NodeFeature::from_builtin_str(name).is_some() {
+                errors.push(format!("`{}` is a built-in node feature", name));
+            }
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+                errors.push(format!("`{}` is not a valid feature name, expected snake_case", name));
+            }
+            for key in labels.keys() {
+                if NodeFeature::from_builtin_str(key).is_none() {
+                    errors.push(format!(
+                        "`features.{}.{}` is not a built-in node feature, expected one of: [{}]",
+                        name,
+                        key,
+                        NodeFeature::VARIANTS.join(", ")
+                    ));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Invalid node features file:\n{}",
+                errors.iter().map(|e| format!("- {}", e)).collect::<Vec<_>>().join("\n")
+            ))
+        }
+    }
+
+    /// Custom features of the file, in the order in which they are defined.
+    pub fn names(&self) -> impl Iterator<Item = NodeFeature> + '_ {
+        self.features.keys().map(|name| NodeFeature::Custom(name.clone()))
+    }
+
+    /// Adds the custom features to the built-in features of a node, labelled `unknown` if no
+    /// built-in feature matches.
+    pub fn apply(&self, builtin: NodeFeatures) -> NodeFeatures {
+        let mut features = builtin.clone();
+        for name in self.features.keys() {
+            let label = self.label(name, &builtin).unwrap_or_else(|| "unknown".to_string());
+            features.feature_map.insert(NodeFeature::Custom(name.clone()), label);
+        }
+        features
+    }
+
+    /// Checks that the deserialized features, e.g. of a saved plan, are built-in or defined in this
+    /// file, so that a typo or a plan computed with another node features file is rejected.
+    pub fn check_known<'a>(&self, features: impl IntoIterator<Item = &'a NodeFeature>) -> anyhow::Result<()> {
+        let known = self.features.keys().cloned().collect::<Vec<_>>();
+        for feature in features {
+            NodeFeature::parse_with_custom(&feature.to_string(), &known)?;
+        }
+        Ok(())
+    }
+
+    /// Label of the custom feature for a node with the provided built-in features.
+    pub fn label(&self, feature: &str, builtin: &NodeFeatures) -> Option<String> {
+        let labels = self.features.get(feature)?;
+        NodeFeature::VARIANTS.iter().find_map(|key| {
+            let value = builtin.get(&NodeFeature::from_builtin_str(key)?)?;
+            labels.get(*key)?.get(&value).cloned()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom_features() -> CustomNodeFeatures {
+        CustomNodeFeatures {
+            features: IndexMap::from([(
+                "asn".to_string(),
+                IndexMap::from([
+                    ("data_center".to_string(), IndexMap::from([("zh2".to_string(), "AS1".to_string())])),
+                    ("node_id".to_string(), IndexMap::from([("node-a".to_string(), "AS2".to_string())])),
+                ]),
+            )]),
+        }
+    }
+
+    #[test]
+    fn most_specific_label_wins() {
+        let custom = custom_features();
+        let node = |id: &str, dc: &str| NodeFeatures::from_iter([(NodeFeature::NodeId, id.to_string()), (NodeFeature::DataCenter, dc.to_string())]);
+
+        assert_eq!(custom.label("asn", &node("node-a", "zh2")), Some("AS2".to_string()));
+        assert_eq!(custom.label("asn", &node("node-b", "zh2")), Some("AS1".to_string()));
+        assert_eq!(custom.label("asn", &node("node-b", "zh3")), None);
+        assert_eq!(custom.label("hardware_generation", &node("node-a", "zh2")), None);
+    }
+
+    #[test]
+    fn validation_rejects_builtin_names_and_keys() {
+        assert!(custom_features().validate().is_ok());
+
+        let mut custom = custom_features();
+        custom.features.insert("country".to_string(), IndexMap::new());
+        custom
+            .features
+            .insert("isp".to_string(), IndexMap::from([("asn".to_string(), IndexMap::new())]));
+        let err = custom.validate().unwrap_err().to_string();
+        assert!(err.contains("`country` is a built-in node feature"));
+        assert!(err.contains("`features.isp.asn` is not a built-in node feature"));
+    }
+
+    #[test]
+    fn node_feature_string_roundtrip() {
+        for feature in NodeFeature::variants_all() {
+            assert_eq!(feature.to_string().parse::<NodeFeature>().unwrap(), feature);
+        }
+        assert_eq!(serde_json::to_string(&NodeFeature::DataCenterOwner).unwrap(), "\"data_center_owner\"");
+        assert_eq!(
+            serde_json::from_str::<NodeFeature>("\"data_center_owner\"").unwrap(),
+            NodeFeature::DataCenterOwner
+        );
+    }
+
+    #[test]
+    fn only_registered_custom_features_are_known() {
+        let features: Vec<NodeFeature> = serde_json::from_str(r#"["country", "asn"]"#).unwrap();
+        assert_eq!(features, vec![NodeFeature::Country, NodeFeature::Custom("asn".to_string())]);
+        assert!("asn".parse::<NodeFeature>().is_err());
+
+        let err = CustomNodeFeatures::default().check_known(&features).unwrap_err().to_string();
+        assert!(err.contains("Unknown node feature `asn`"), "{}", err);
+        custom_features().check_known(&features).unwrap();
+        assert!(custom_features().check_known(&[NodeFeature::Custom("contry".to_string())]).is_err());
+    }
+
+    #[test]
+    fn custom_features_are_attached_to_the_node() {
+        let custom = custom_features();
+        let node = crate::Node::new_test_node(1, NodeFeatures::new_test_feature_set("zh2"), false).with_custom_features(&custom);
+
+        assert_eq!(node.get_feature(&NodeFeature::Custom("asn".to_string())), Some("AS1".to_string()));
+        assert!(node.matches_feature_value("asn=as1"));
+        assert!(!node.matches_feature_value("asn=AS2"));
+        assert_eq!(
+            NodeFeature::variants_for([node.get_features()]).last(),
+            Some(&NodeFeature::Custom("asn".to_string()))
+        );
+    }
+}
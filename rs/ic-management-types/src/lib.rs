@@ -1,8 +1,8 @@
+pub mod custom_features;
 pub mod errors;
 pub mod requests;
 pub use crate::errors::*;
 
-use ahash::AHashMap;
 use candid::{CandidType, Decode};
 use core::hash::Hash;
 use ic_base_types::NodeId;
@@ -15,7 +15,7 @@ use ic_protobuf::registry::subnet::v1::ChainKeyConfig;
 use ic_protobuf::registry::subnet::v1::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
 use ic_types::PrincipalId;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use registry_canister::mutations::do_add_nodes_to_subnet::AddNodesToSubnetPayload;
 use registry_canister::mutations::do_change_subnet_membership::ChangeSubnetMembershipPayload;
 use registry_canister::mutations::do_create_subnet::CreateSubnetPayload;
@@ -27,6 +27,7 @@ use registry_canister::mutations::do_update_nodes_hostos_version::UpdateNodesHos
 use registry_canister::mutations::do_update_unassigned_nodes_config::UpdateUnassignedNodesConfigPayload;
 use registry_canister::mutations::node_management::do_remove_nodes::RemoveNodesPayload;
 use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
 use std::cmp::{Eq, Ord, PartialEq, PartialOrd};
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
@@ -35,11 +36,18 @@ use std::net::Ipv6Addr;
 use std::ops::Deref;
 use std::str::FromStr;
 use std::sync::OnceLock;
-use strum::VariantNames;
 use strum_macros::EnumString;
 use url::Url;
 
 pub fn filter_map_nns_function_proposals<T: NnsFunctionProposal + candid::CandidType>(proposals: &[ProposalInfo]) -> Vec<(ProposalInfo, T)> {
+    filter_map_nns_functions_proposals(proposals, &[T::TYPE])
+}
+
+/// Like [filter_map_nns_function_proposals], for several NNS functions taking the same payload, such as renamed functions.
+pub fn filter_map_nns_functions_proposals<T: NnsFunctionProposal + candid::CandidType>(
+    proposals: &[ProposalInfo],
+    functions: &[NnsFunction],
+) -> Vec<(ProposalInfo, T)> {
     proposals
         .iter()
         .filter(|p| ProposalStatus::try_from(p.status).expect("unknown proposal status") != ProposalStatus::Rejected)
@@ -56,7 +64,7 @@ pub fn filter_map_nns_function_proposals<T: NnsFunctionProposal + candid::Candid
                     _ => Err(anyhow::format_err!("not an NNS function")),
                 })
                 .and_then(|(function_type, function_payload)| {
-                    if function_type == T::TYPE {
+                    if functions.contains(&function_type) {
                         Decode!(function_payload, T).map_err(|e| anyhow::format_err!("failed decoding candid: {}", e))
                     } else {
                         Err(anyhow::format_err!("unsupported NNS function"))
@@ -393,11 +401,32 @@ impl Node {
         features
     }
 
+    /// Adds the custom features to the features of the node.
+    pub fn with_custom_features(self, custom: &custom_features::CustomNodeFeatures) -> Self {
+        if custom.features.is_empty() {
+            return self;
+        }
+        let features = custom.apply(self.get_features());
+        Node {
+            cached_features: OnceLock::from(features),
+            ..self
+        }
+    }
+
     pub fn get_feature(&self, feature: &NodeFeature) -> Option<String> {
         self.get_features().get(feature)
     }
 
+    /// Matches the node principal or any feature value, or a single feature with `feature=value`, e.g. `asn=AS1234`.
     pub fn matches_feature_value(&self, value: &str) -> bool {
+        if let Some((feature, feature_value)) = value.split_once('=') {
+            let features = self.get_features();
+            if let Ok(feature) = NodeFeature::parse_known(feature.trim(), features.feature_map.keys()) {
+                return self
+                    .get_feature(&feature)
+                    .is_some_and(|v| v.to_lowercase() == feature_value.trim().to_lowercase());
+            }
+        }
         self.principal.to_string() == *value.to_lowercase()
             || self
                 .get_features()
@@ -405,40 +434,6 @@ impl Node {
                 .values()
                 .any(|v| *v.to_lowercase() == *value.to_lowercase())
     }
-
-    pub fn is_country_from_eu(country: &str) -> bool {
-        // (As of 2024) the EU countries are not properly marked in the registry, so we check membership separately.
-        let eu_countries: AHashMap<&str, &str> = AHashMap::from_iter([
-            ("AT", "Austria"),
-            ("BE", "Belgium"),
-            ("BG", "Bulgaria"),
-            ("CY", "Cyprus"),
-            ("CZ", "Czechia"),
-            ("DE", "Germany"),
-            ("DK", "Denmark"),
-            ("EE", "Estonia"),
-            ("ES", "Spain"),
-            ("FI", "Finland"),
-            ("FR", "France"),
-            ("GR", "Greece"),
-            ("HR", "Croatia"),
-            ("HU", "Hungary"),
-            ("IE", "Ireland"),
-            ("IT", "Italy"),
-            ("LT", "Lithuania"),
-            ("LU", "Luxembourg"),
-            ("LV", "Latvia"),
-            ("MT", "Malta"),
-            ("NL", "Netherlands"),
-            ("PL", "Poland"),
-            ("PT", "Portugal"),
-            ("RO", "Romania"),
-            ("SE", "Sweden"),
-            ("SI", "Slovenia"),
-            ("SK", "Slovakia"),
-        ]);
-        eu_countries.contains_key(country)
-    }
 }
 
 impl std::fmt::Display for Node {
@@ -467,9 +462,11 @@ impl PartialEq for Node {
 
 impl Eq for Node {}
 
-#[derive(strum_macros::Display, EnumString, VariantNames, Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Serialize, Deserialize, Debug)]
-#[strum(serialize_all = "snake_case")]
-#[serde(rename_all = "snake_case")]
+/// A dimension along which the decentralization of a subnet is measured.
+///
+/// Besides the built-in features, which are derived from the registry, extra features
+/// can be loaded from a node features file, see [`custom_features::CustomNodeFeatures`].
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Debug)]
 pub enum NodeFeature {
     NodeId,
     NodeOperator,
@@ -479,19 +476,129 @@ pub enum NodeFeature {
     Area,    // Represents smaller geographic entities like cities and states
     Country, // Covers larger contexts, like countries or broader regions under shared legal jurisdiction
     Continent,
+    /// Feature loaded from the node features file, e.g. `asn` or `hardware_generation`
+    Custom(String),
 }
 
 impl NodeFeature {
+    /// Names of the built-in features, ordered from the most to the least specific one.
+    pub const VARIANTS: &'static [&'static str] = &[
+        "node_id",
+        "node_operator",
+        "node_provider",
+        "data_center",
+        "data_center_owner",
+        "area",
+        "country",
+        "continent",
+    ];
+
     pub fn variants() -> Vec<Self> {
         // Generally skip the continent feature as it is not used in the Nakamoto score calculation
-        NodeFeature::VARIANTS
-            .iter()
-            .filter(|f| **f != "continent" && **f != "node_id")
-            .map(|f| NodeFeature::from_str(f).unwrap())
+        Self::builtin()
+            .filter(|f| *f != NodeFeature::Continent && *f != NodeFeature::NodeId)
             .collect()
     }
     pub fn variants_all() -> Vec<Self> {
-        NodeFeature::VARIANTS.iter().map(|f| NodeFeature::from_str(f).unwrap()).collect()
+        Self::builtin().collect()
+    }
+
+    /// Same as [`NodeFeature::variants`], followed by the custom features of the provided nodes.
+    pub fn variants_for<F: Borrow<NodeFeatures>>(features: impl IntoIterator<Item = F>) -> Vec<Self> {
+        let mut custom = IndexSet::new();
+        for node_features in features {
+            custom.extend(node_features.borrow().feature_map.keys().filter(|f| f.is_custom()).cloned());
+        }
+        Self::variants().into_iter().chain(custom).collect()
+    }
+
+    /// Parses a built-in feature or one of the `known` custom features.
+    pub fn parse_known<'a>(s: &str, known: impl IntoIterator<Item = &'a NodeFeature>) -> anyhow::Result<Self> {
+        let custom = known.into_iter().filter(|f| f.is_custom()).map(|f| f.to_string()).collect::<Vec<_>>();
+        Self::parse_with_custom(s, &custom)
+    }
+
+    fn parse_with_custom(s: &str, custom: &[String]) -> anyhow::Result<Self> {
+        if let Some(feature) = Self::from_builtin_str(s) {
+            return Ok(feature);
+        }
+        if custom.iter().any(|c| c == s) {
+            return Ok(NodeFeature::Custom(s.to_string()));
+        }
+        Err(anyhow::anyhow!(
+            "Unknown node feature `{}`, expected one of: [{}]",
+            s,
+            Self::VARIANTS
+                .iter()
+                .map(|v| v.to_string())
+                .chain(custom.iter().cloned())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+
+    fn builtin() -> impl Iterator<Item = Self> {
+        Self::VARIANTS.iter().map(|f| Self::from_builtin_str(f).unwrap())
+    }
+
+    /// Parses only the names of the built-in features.
+    pub fn from_builtin_str(s: &str) -> Option<Self> {
+        match s {
+            "node_id" => Some(NodeFeature::NodeId),
+            "node_operator" => Some(NodeFeature::NodeOperator),
+            "node_provider" => Some(NodeFeature::NodeProvider),
+            "data_center" => Some(NodeFeature::DataCenter),
+            "data_center_owner" => Some(NodeFeature::DataCenterOwner),
+            "area" => Some(NodeFeature::Area),
+            "country" => Some(NodeFeature::Country),
+            "continent" => Some(NodeFeature::Continent),
+            _ => None,
+        }
+    }
+
+    pub fn is_custom(&self) -> bool {
+        matches!(self, NodeFeature::Custom(_))
+    }
+}
+
+impl std::fmt::Display for NodeFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            NodeFeature::NodeId => "node_id",
+            NodeFeature::NodeOperator => "node_operator",
+            NodeFeature::NodeProvider => "node_provider",
+            NodeFeature::DataCenter => "data_center",
+            NodeFeature::DataCenterOwner => "data_center_owner",
+            NodeFeature::Area => "area",
+            NodeFeature::Country => "country",
+            NodeFeature::Continent => "continent",
+            NodeFeature::Custom(name) => name,
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Accepts only the built-in features, see [`NodeFeature::parse_known`] for the custom ones.
+impl FromStr for NodeFeature {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with_custom(s, &[])
+    }
+}
+
+impl Serialize for NodeFeature {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// Any name which is not built-in is kept as a custom feature. Check the parsed features with
+// `CustomNodeFeatures::check_known`, so that a typo is an error instead of a new feature.
+impl<'de> Deserialize<'de> for NodeFeature {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Self::from_builtin_str(&name).unwrap_or(NodeFeature::Custom(name)))
     }
 }
 